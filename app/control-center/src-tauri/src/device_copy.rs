@@ -0,0 +1,483 @@
+use russh_sftp::protocol::OpenFlags;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::State;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+use crate::sftp::{
+    self, shell_quote, SftpConnection, SftpState, SshEndpoint, TransferDirection, TransferEvent,
+};
+use crate::tree_transfer;
+
+/// Bytes read from the source per round trip when streaming through the app.
+const STREAM_CHUNK_SIZE: usize = 256 * 1024;
+
+/// How often streamed progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Options for the `ssh` the source host runs to reach the destination. It
+/// must not stop to ask anything, and only trusts host keys it already knows.
+const SOURCE_SSH_OPTIONS: &str =
+    "-o BatchMode=yes -o ConnectTimeout=5 -o StrictHostKeyChecking=yes";
+
+// ----- Data Structures -----
+
+/// How the bytes get from one device to the other.
+enum Route {
+    /// `rsync` on the source host, pushing straight to the destination.
+    /// Only 3.1 and later report progress for the whole copy.
+    Rsync { progress2: bool },
+    /// `scp` on the source host, for when the destination has no `rsync`.
+    Scp,
+    /// Read from one SFTP channel and write to the other, through the app.
+    Stream,
+}
+
+/// A copy whose source has been looked at.
+struct DeviceCopy<'a> {
+    source: Arc<SftpConnection>,
+    dest: Arc<SftpConnection>,
+    source_path: &'a str,
+    /// Where the copy itself lands, not the folder it goes into.
+    dest_path: &'a str,
+    is_dir: bool,
+    /// Zero for a folder, whose size isn't known up front.
+    total_bytes: u64,
+}
+
+// ----- Helper Functions -----
+
+/// `user@host` for the source host's `ssh` to reach the destination by, as
+/// this app resolved it from `~/.ssh/config`.
+fn ssh_destination(dest: &SshEndpoint) -> String {
+    if dest.host.contains(':') {
+        format!("{}@[{}]", dest.user, dest.host)
+    } else {
+        format!("{}@{}", dest.user, dest.host)
+    }
+}
+
+/// Options for the source host's `ssh` to take the app's way to the
+/// destination: its port and ProxyJump hops. `scp` spells the port `-P`.
+fn ssh_options(dest: &SshEndpoint, port_flag: &str) -> String {
+    let mut options = format!("{} {} {}", SOURCE_SSH_OPTIONS, port_flag, dest.port);
+    if !dest.jumps.is_empty() {
+        options.push_str(" -J ");
+        options.push_str(&shell_quote(&dest.jumps.join(",")));
+    }
+    options
+}
+
+/// The major and minor version from the first line of `rsync --version`,
+/// such as `rsync  version 3.2.7  protocol version 31`.
+fn rsync_version(line: &str) -> Option<(u32, u32)> {
+    let mut words = line.split_whitespace();
+    if words.next()? != "rsync" || words.next()? != "version" {
+        return None;
+    }
+    let mut numbers = words.next()?.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Whether `path` reads the same to a remote shell as it does literally,
+/// which older `scp` needs since it hands the path to one.
+fn shell_safe(path: &str) -> bool {
+    path.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+,@%=".contains(c))
+}
+
+/// Picks the direct route when the source host can log in to the
+/// destination on its own, otherwise streaming.
+async fn choose_route(source: &SftpConnection, dest: &SftpConnection, dest_path: &str) -> Route {
+    let Ok(tools) = source
+        .exec("rsync --version 2>/dev/null | head -n 1; command -v scp >/dev/null && echo scp; true")
+        .await
+    else {
+        return Route::Stream;
+    };
+    let tools = String::from_utf8_lossy(&tools);
+    let has = |tool: &str| tools.lines().any(|line| line.trim() == tool);
+    let rsync = tools.lines().find_map(rsync_version);
+
+    // ssh exits with the remote command's status, or 255 if it couldn't
+    // connect; so 0 means reachable with rsync there, 1 reachable without
+    let probe = format!(
+        "ssh {} {} 'command -v rsync >/dev/null' </dev/null",
+        ssh_options(dest.endpoint(), "-p"),
+        shell_quote(&ssh_destination(dest.endpoint()))
+    );
+    let mut discard = |_: &[u8]| {};
+    let status = match source
+        .exec_streaming(&probe, &CancellationToken::new(), &mut discard)
+        .await
+    {
+        Ok(exit) => exit.status,
+        Err(_) => None,
+    };
+
+    match status {
+        Some(0) if rsync.is_some() => Route::Rsync {
+            progress2: rsync >= Some((3, 1)),
+        },
+        Some(0) | Some(1) if has("scp") && shell_safe(dest_path) => Route::Scp,
+        _ => Route::Stream,
+    }
+}
+
+/// Bytes sent so far and the percentage done, from a line of rsync's
+/// `--info=progress2` output such as `  1,234,567  45%  1.23MB/s  0:00:10`.
+fn parse_rsync_progress(line: &str) -> Option<(u64, u64)> {
+    let mut fields = line.split_whitespace();
+    let bytes = fields.next()?.replace(',', "").parse().ok()?;
+    let percent = fields.next()?.strip_suffix('%')?.parse().ok()?;
+    Some((bytes, percent))
+}
+
+// ----- Copying -----
+
+/// Runs rsync or scp on the source host, reporting rsync's progress.
+async fn copy_direct(
+    copy: &DeviceCopy<'_>,
+    route: &Route,
+    cancel_token: &CancellationToken,
+    report: &(dyn Fn(u64, u64) + Send + Sync),
+) -> Result<(), String> {
+    let dest = copy.dest.endpoint();
+    let destination = format!("{}:{}", ssh_destination(dest), copy.dest_path);
+    let command = match route {
+        // -s keeps the remote shell from interpreting the destination path.
+        // A folder goes as `source/` to `dest/`, so rsync fills in `dest`
+        // whether or not it exists yet, as the other routes do.
+        Route::Rsync { progress2 } => {
+            let (source, destination) = if copy.is_dir {
+                (format!("{}/", copy.source_path), format!("{}/", destination))
+            } else {
+                (copy.source_path.to_string(), destination)
+            };
+            format!(
+                "rsync -a -s --partial {}-e {} {} {}",
+                if *progress2 { "--info=progress2 " } else { "" },
+                shell_quote(&format!("ssh {}", ssh_options(dest, "-p"))),
+                shell_quote(&source),
+                shell_quote(&destination)
+            )
+        }
+        _ => format!(
+            "scp -p -r {} {} {}",
+            ssh_options(dest, "-P"),
+            shell_quote(copy.source_path),
+            shell_quote(&destination)
+        ),
+    };
+
+    // rsync redraws its progress line with carriage returns
+    let mut pending = String::new();
+    let mut on_stdout = |data: &[u8]| {
+        pending.push_str(&String::from_utf8_lossy(data));
+        while let Some(end) = pending.find(['\r', '\n']) {
+            let line: String = pending.drain(..=end).collect();
+            if let Some((bytes, percent)) = parse_rsync_progress(&line) {
+                let total = match copy.total_bytes {
+                    0 if percent > 0 => bytes * 100 / percent,
+                    0 => bytes,
+                    total => total,
+                };
+                report(bytes, total);
+            }
+        }
+    };
+
+    let exit = copy
+        .source
+        .exec_streaming(&command, cancel_token, &mut on_stdout)
+        .await?;
+    match exit.status {
+        Some(0) => Ok(()),
+        Some(status) => Err(format!(
+            "Copy exited with status {}: {}",
+            status, exit.stderr
+        )),
+        None => Err("Copy was interrupted".to_string()),
+    }
+}
+
+/// Reads the file over one device's SFTP channel and writes it over the
+/// other's, into a `.part` file renamed into place at the end.
+async fn copy_streamed(
+    copy: &DeviceCopy<'_>,
+    cancel_token: &CancellationToken,
+    report: &(dyn Fn(u64, u64) + Send + Sync),
+) -> Result<(), String> {
+    let DeviceCopy {
+        source_path,
+        dest_path,
+        total_bytes,
+        ..
+    } = *copy;
+    let source_sftp = copy.source.sftp().await?;
+    let dest_sftp = copy.dest.sftp().await?;
+    let part_path = format!("{}.part", dest_path);
+
+    let mut reader = source_sftp
+        .open(source_path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", source_path, e))?;
+    let mut writer = dest_sftp
+        .open_with_flags(
+            part_path.as_str(),
+            OpenFlags::CREATE | OpenFlags::TRUNCATE | OpenFlags::WRITE,
+        )
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", part_path, e))?;
+
+    let mut buffer = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut copied = 0;
+    let mut last_report = Instant::now();
+    let result: Result<(), String> = async {
+        loop {
+            let read = tokio::select! {
+                _ = cancel_token.cancelled() => return Err("Transfer cancelled".to_string()),
+                read = reader.read(&mut buffer) => read,
+            }
+            .map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+            if read == 0 {
+                break;
+            }
+            writer
+                .write_all(&buffer[..read])
+                .await
+                .map_err(|e| format!("Failed to write {}: {}", part_path, e))?;
+            copied += read as u64;
+            if last_report.elapsed() >= PROGRESS_INTERVAL {
+                report(copied, total_bytes);
+                last_report = Instant::now();
+            }
+        }
+        writer
+            .shutdown()
+            .await
+            .map_err(|e| format!("Failed to finish {}: {}", part_path, e))
+    }
+    .await;
+    drop(writer);
+
+    if let Err(e) = result {
+        dest_sftp.remove_file(part_path.as_str()).await.ok();
+        return Err(e);
+    }
+    if copied != total_bytes {
+        dest_sftp.remove_file(part_path.as_str()).await.ok();
+        return Err(format!(
+            "Copied {} of {} bytes; the source changed during the copy",
+            copied, total_bytes
+        ));
+    }
+
+    // Not every server renames over an existing file
+    if dest_sftp
+        .rename(part_path.as_str(), dest_path)
+        .await
+        .is_err()
+    {
+        dest_sftp.remove_file(dest_path).await.ok();
+        dest_sftp
+            .rename(part_path.as_str(), dest_path)
+            .await
+            .map_err(|e| format!("Failed to move {} into place: {}", dest_path, e))?;
+    }
+    report(copied, total_bytes);
+    Ok(())
+}
+
+/// Where a copy of `source_path` to `dest_path` lands, the same for every
+/// route: inside `dest_path` if that is an existing folder, as `cp` and
+/// `rsync` do, otherwise at `dest_path` itself. Trailing slashes on either
+/// path change nothing.
+async fn landing_path(
+    dest: &Arc<SftpConnection>,
+    source_path: &str,
+    dest_path: &str,
+) -> Result<String, String> {
+    let dest_path = trim_slashes(dest_path);
+    let sftp = dest.sftp().await?;
+    match sftp.metadata(dest_path).await {
+        Ok(metadata) if metadata.is_dir() => {
+            let name = file_name(source_path);
+            Ok(format!("{}/{}", dest_path.trim_end_matches('/'), name))
+        }
+        _ => Ok(dest_path.to_string()),
+    }
+}
+
+/// `path` without trailing slashes, unless it is the root.
+fn trim_slashes(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/",
+        trimmed => trimmed,
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    let path = trim_slashes(path);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+async fn copy_between(
+    state: &SftpState,
+    source_ip: &str,
+    source_path: &str,
+    dest_ip: &str,
+    dest_path: &str,
+    cancel_token: &CancellationToken,
+    on_progress: &Channel<TransferEvent>,
+) -> Result<(), String> {
+    let source = sftp::get_connection(state, source_ip).await?;
+    let dest = sftp::get_connection(state, dest_ip).await?;
+    let source_path = trim_slashes(source_path);
+
+    let metadata = {
+        let sftp = source.sftp().await?;
+        sftp.metadata(source_path)
+            .await
+            .map_err(|e| format!("Failed to stat {}: {}", source_path, e))?
+    };
+    let is_dir = metadata.is_dir();
+    let total_bytes = if is_dir {
+        0
+    } else {
+        metadata.size.unwrap_or(0)
+    };
+
+    let dest_path = landing_path(&dest, source_path, dest_path).await?;
+    let existing = {
+        let sftp = dest.sftp().await?;
+        sftp.metadata(dest_path.as_str()).await.ok()
+    };
+    if !is_dir && existing.as_ref().is_some_and(|m| m.is_dir()) {
+        return Err(format!("{} is a folder on {}", dest_path, dest_ip));
+    }
+
+    let filename = file_name(source_path).to_string();
+    let report = |bytes_transferred, total_bytes| {
+        let _ = on_progress.send(TransferEvent::Progress {
+            bytes_transferred,
+            total_bytes,
+            filename: filename.clone(),
+        });
+    };
+    report(0, total_bytes);
+
+    let route = choose_route(&source, &dest, &dest_path).await;
+    let copy = DeviceCopy {
+        source,
+        dest,
+        source_path,
+        dest_path: &dest_path,
+        is_dir,
+        total_bytes,
+    };
+    match route {
+        Route::Stream if is_dir => Err(format!(
+            "{} is a folder, which can only be copied when {} can reach {} over SSH",
+            source_path, source_ip, dest_ip
+        )),
+        // scp would put the folder inside the existing one instead
+        Route::Scp if is_dir && existing.is_some() => Err(format!(
+            "{} already exists on {}; adding to a folder needs rsync on both devices",
+            dest_path, dest_ip
+        )),
+        Route::Stream => copy_streamed(&copy, cancel_token, &report).await,
+        route => {
+            copy_direct(&copy, &route, cancel_token, &report).await?;
+            report(total_bytes, total_bytes);
+            Ok(())
+        }
+    }
+}
+
+// ----- Tauri Commands -----
+
+/// Copies a file, or with a direct route a folder, from one connected device
+/// to another without staging it on this machine.
+#[tauri::command]
+pub async fn sftp_copy_between_devices(
+    state: State<'_, SftpState>,
+    source_ip: String,
+    source_path: String,
+    dest_ip: String,
+    dest_path: String,
+    transfer_id: String,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    // Reads from the source like a download would
+    let cancel_token = state
+        .begin_transfer(&transfer_id, TransferDirection::Download)
+        .await;
+    let result = copy_between(
+        &state,
+        &source_ip,
+        &source_path,
+        &dest_ip,
+        &dest_path,
+        &cancel_token,
+        &on_progress,
+    )
+    .await;
+    state.end_transfer(&transfer_id).await;
+
+    let filename = file_name(&source_path).to_string();
+    tree_transfer::report_outcome(&on_progress, filename, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(port: u16, jumps: &[&str]) -> SshEndpoint {
+        SshEndpoint {
+            host: "nas.lan".to_string(),
+            port,
+            user: "admin".to_string(),
+            jumps: jumps.iter().map(|j| j.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn ssh_options_carry_port_and_jumps() {
+        let direct = endpoint(22, &[]);
+        assert_eq!(ssh_options(&direct, "-p"), format!("{} -p 22", SOURCE_SSH_OPTIONS));
+
+        let jumped = endpoint(2222, &["me@bastion:22", "me@[fd00::1]:2200"]);
+        assert_eq!(
+            ssh_options(&jumped, "-P"),
+            format!(
+                "{} -P 2222 -J 'me@bastion:22,me@[fd00::1]:2200'",
+                SOURCE_SSH_OPTIONS
+            )
+        );
+        assert_eq!(ssh_destination(&jumped), "admin@nas.lan");
+    }
+
+    #[test]
+    fn rsync_versions() {
+        let modern = rsync_version("rsync  version 3.2.7  protocol version 31");
+        let old = rsync_version("rsync  version 3.0.9  protocol version 30");
+        assert_eq!(modern, Some((3, 2)));
+        assert_eq!(old, Some((3, 0)));
+        assert!(old < Some((3, 1)));
+        assert_eq!(rsync_version("scp"), None);
+    }
+
+    #[test]
+    fn trailing_slashes_are_ignored() {
+        assert_eq!(trim_slashes("/srv/media/"), "/srv/media");
+        assert_eq!(trim_slashes("/srv/media"), "/srv/media");
+        assert_eq!(trim_slashes("/"), "/");
+        assert_eq!(file_name("/srv/media//"), "media");
+        assert_eq!(file_name("notes.txt"), "notes.txt");
+    }
+}
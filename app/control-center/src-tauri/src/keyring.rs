@@ -0,0 +1,120 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Service name every entry is filed under. The keyring is reached through
+/// its command-line front end, `secret-tool` (libsecret) on Linux and
+/// `security` on macOS, with secrets passed on stdin so they never show up
+/// in the process list.
+const SERVICE: &str = "control-center";
+
+// ----- Helper Functions -----
+
+/// Runs `program` with `stdin` piped in and returns its stdout.
+fn run(program: &str, args: &[&str], stdin: &str) -> Result<String, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run `{}`: {}", program, e))?;
+
+    if let Some(mut pipe) = child.stdin.take() {
+        pipe.write_all(stdin.as_bytes())
+            .map_err(|e| format!("Failed to write to `{}`: {}", program, e))?;
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run `{}`: {}", program, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} exited with error: {}", program, stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Quotes an argument for a `security -i` command line.
+#[cfg(target_os = "macos")]
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// ----- Keyring Access -----
+
+/// The secret stored for `account`, if any. A missing keyring reads as empty.
+#[cfg(target_os = "linux")]
+pub fn get(account: &str) -> Option<String> {
+    let secret = run(
+        "secret-tool",
+        &["lookup", "service", SERVICE, "account", account],
+        "",
+    )
+    .ok()?;
+    // secret-tool prints the secret without a newline when stdout is a pipe
+    (!secret.is_empty()).then_some(secret)
+}
+
+#[cfg(target_os = "linux")]
+pub fn set(account: &str, secret: &str) -> Result<(), String> {
+    let label = format!("{} ({})", SERVICE, account);
+    run(
+        "secret-tool",
+        &["store", "--label", &label, "service", SERVICE, "account", account],
+        secret,
+    )
+    .map(|_| ())
+}
+
+#[cfg(target_os = "linux")]
+pub fn delete(account: &str) -> Result<(), String> {
+    run("secret-tool", &["clear", "service", SERVICE, "account", account], "").map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn get(account: &str) -> Option<String> {
+    let secret = run(
+        "security",
+        &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+        "",
+    )
+    .ok()?;
+    let secret = secret.strip_suffix('\n').unwrap_or(&secret);
+    (!secret.is_empty()).then(|| secret.to_string())
+}
+
+#[cfg(target_os = "macos")]
+pub fn set(account: &str, secret: &str) -> Result<(), String> {
+    let command = format!(
+        "add-generic-password -U -s {} -a {} -w {}\n",
+        quote(SERVICE),
+        quote(account),
+        quote(secret)
+    );
+    run("security", &["-i"], &command).map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn delete(account: &str) -> Result<(), String> {
+    run(
+        "security",
+        &["delete-generic-password", "-s", SERVICE, "-a", account],
+        "",
+    )
+    .map(|_| ())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn get(_account: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn set(_account: &str, _secret: &str) -> Result<(), String> {
+    Err("Storing credentials is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn delete(_account: &str) -> Result<(), String> {
+    Ok(())
+}
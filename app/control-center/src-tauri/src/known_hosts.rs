@@ -0,0 +1,361 @@
+use russh::keys::known_hosts::learn_known_hosts_path;
+use russh::keys::ssh_key::{HashAlg, PublicKey};
+use russh::keys::check_known_hosts_path;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::sftp::SftpError;
+
+// ----- Data Structures -----
+
+pub enum HostKeyCheck {
+    Trusted,
+    Unknown,
+    Changed { path: PathBuf, line: usize },
+}
+
+/// Where host keys are looked up: `~/.ssh/known_hosts`, then the app's own
+/// trust store, which is also where accepted keys are saved.
+#[derive(Clone)]
+pub struct TrustStores {
+    user: Option<PathBuf>,
+    app: PathBuf,
+}
+
+impl Default for TrustStores {
+    fn default() -> Self {
+        TrustStores {
+            user: get_user_known_hosts_path(),
+            app: get_known_hosts_path(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedHost {
+    /// As written in the trust store: `host`, or `[host]:port` off port 22.
+    pub host: String,
+    pub key_type: String,
+    pub fingerprint: String,
+}
+
+// ----- Helper Functions -----
+
+/// The app's own trust store, in `known_hosts` format so it can be merged
+/// into `~/.ssh/known_hosts` by hand.
+fn get_known_hosts_path() -> PathBuf {
+    let proj_dirs = directories::ProjectDirs::from("com", "homelab", "control-center")
+        .expect("Failed to get project directories");
+    let data_dir = proj_dirs.data_dir();
+    fs::create_dir_all(data_dir).ok();
+    data_dir.join("known_hosts")
+}
+
+fn get_user_known_hosts_path() -> Option<PathBuf> {
+    std::env::var("HOME")
+        .ok()
+        .map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+pub fn fingerprint(key: &PublicKey) -> String {
+    key.fingerprint(HashAlg::Sha256).to_string()
+}
+
+impl TrustStores {
+    #[cfg(test)]
+    pub(crate) fn new(user: Option<PathBuf>, app: PathBuf) -> Self {
+        TrustStores { user, app }
+    }
+
+    /// Looks `key` up in both stores, hashed entries included. A mismatch in
+    /// either wins over a match in the other: a changed key must never be
+    /// papered over.
+    pub fn check(&self, host: &str, port: u16, key: &PublicKey) -> Result<HostKeyCheck, SftpError> {
+        let stores: Vec<PathBuf> = self
+            .user
+            .iter()
+            .chain(std::iter::once(&self.app))
+            .cloned()
+            .collect();
+        check_stores(&stores, host, port, key)
+    }
+
+    /// Adds `key` to the app's trust store.
+    pub fn remember(&self, host: &str, port: u16, key: &PublicKey) -> Result<(), SftpError> {
+        learn_known_hosts_path(host, port, key, &self.app)
+            .map_err(|e| SftpError::HostKey(format!("Failed to save host key: {}", e)))
+    }
+}
+
+fn check_stores(
+    stores: &[PathBuf],
+    host: &str,
+    port: u16,
+    key: &PublicKey,
+) -> Result<HostKeyCheck, SftpError> {
+    let mut trusted = false;
+    for path in stores {
+        match check_known_hosts_path(host, port, key, path) {
+            Ok(found) => trusted |= found,
+            Err(russh::keys::Error::KeyChanged { line }) => {
+                return Ok(HostKeyCheck::Changed {
+                    path: path.clone(),
+                    line,
+                });
+            }
+            Err(e) => {
+                return Err(SftpError::HostKey(format!(
+                    "Failed to read {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        }
+    }
+    Ok(if trusted {
+        HostKeyCheck::Trusted
+    } else {
+        HostKeyCheck::Unknown
+    })
+}
+
+// ----- Tauri Commands -----
+
+#[tauri::command]
+pub async fn sftp_list_trusted_hosts() -> Result<Vec<TrustedHost>, String> {
+    let path = get_known_hosts_path();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read trust store: {}", e))?;
+
+    let hosts = content
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (host, key) = line.split_once(' ')?;
+            let key = PublicKey::from_openssh(key).ok()?;
+            Some(TrustedHost {
+                host: host.to_string(),
+                key_type: key.algorithm().to_string(),
+                fingerprint: fingerprint(&key),
+            })
+        })
+        .collect();
+
+    Ok(hosts)
+}
+
+/// Drops every key the app trusts for `host`, e.g. after it was reinstalled.
+/// `host` may carry a port as `[host]:port`; without one every port goes.
+/// Entries in `~/.ssh/known_hosts` are left for the user to edit.
+#[tauri::command]
+pub async fn sftp_forget_host(host: String) -> Result<(), String> {
+    forget_in(&get_known_hosts_path(), &host)
+}
+
+fn forget_in(path: &Path, host: &str) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read trust store: {}", e))?;
+
+    let (name, port) = split_host(host);
+    let forgotten = |pattern: &str| {
+        let (n, p) = split_host(pattern);
+        n == name && (port.is_none() || p.unwrap_or(22) == port.unwrap_or(22))
+    };
+
+    // A line may list several names (`host,10.0.0.5 ssh-ed25519 ...`); only
+    // the forgotten ones go, and the line with them once none are left
+    let mut kept = String::new();
+    for line in content.lines() {
+        match line.split_once(' ') {
+            Some((names, key)) if !line.starts_with('#') => {
+                let names: Vec<&str> = names.split(',').filter(|n| !forgotten(n)).collect();
+                if !names.is_empty() {
+                    kept.push_str(&format!("{} {}\n", names.join(","), key));
+                }
+            }
+            _ => kept.push_str(&format!("{}\n", line)),
+        }
+    }
+
+    fs::write(path, kept).map_err(|e| format!("Failed to write trust store: {}", e))?;
+
+    Ok(())
+}
+
+/// Splits a `known_hosts` name into host and port: `[host]:port`, or a bare
+/// `host` with no port of its own (so 22).
+fn split_host(pattern: &str) -> (&str, Option<u16>) {
+    pattern
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("]:"))
+        .and_then(|(host, port)| Some((host, Some(port.parse().ok()?))))
+        .unwrap_or((pattern, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server;
+    use russh::client;
+    use russh::keys::ssh_key::rand_core::OsRng;
+    use russh::keys::ssh_key::Algorithm;
+    use russh::keys::PrivateKey;
+    use std::sync::{Arc, Mutex};
+
+    /// Trusts the way `SshHandler` does, with the user accepting every
+    /// unknown key: known keys pass, unknown ones are learned into the last
+    /// store, changed ones are refused.
+    struct Client {
+        stores: Vec<PathBuf>,
+        port: u16,
+        seen: Arc<Mutex<Option<HostKeyCheck>>>,
+    }
+
+    impl client::Handler for Client {
+        type Error = SftpError;
+
+        async fn check_server_key(&mut self, key: &PublicKey) -> Result<bool, Self::Error> {
+            let check = check_stores(&self.stores, "127.0.0.1", self.port, key)?;
+            let accept = match check {
+                HostKeyCheck::Trusted => true,
+                HostKeyCheck::Unknown => {
+                    let store = self.stores.last().unwrap();
+                    learn_known_hosts_path("127.0.0.1", self.port, key, store).unwrap();
+                    true
+                }
+                HostKeyCheck::Changed { .. } => false,
+            };
+            *self.seen.lock().unwrap() = Some(check);
+            Ok(accept)
+        }
+    }
+
+    async fn connect(stores: &[PathBuf], port: u16) -> (bool, HostKeyCheck) {
+        let seen = Arc::new(Mutex::new(None));
+        let client = Client {
+            stores: stores.to_vec(),
+            port,
+            seen: seen.clone(),
+        };
+        let config = Arc::new(client::Config::default());
+        let connected = client::connect(config, ("127.0.0.1", port), client)
+            .await
+            .is_ok();
+        let check = seen.lock().unwrap().take().expect("server key was not checked");
+        (connected, check)
+    }
+
+    fn stores(dir: &tempfile::TempDir) -> Vec<PathBuf> {
+        vec![dir.path().join("user"), dir.path().join("app")]
+    }
+
+    #[tokio::test]
+    async fn unknown_host_is_learned_then_trusted() {
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores(&dir);
+        let server = test_server::start().await;
+        let port = server.addr.port();
+
+        let (connected, check) = connect(&stores, port).await;
+        assert!(connected);
+        assert!(matches!(check, HostKeyCheck::Unknown));
+        let learned = fs::read_to_string(&stores[1]).unwrap();
+        assert!(learned.trim_start().starts_with(&format!("[127.0.0.1]:{} ssh-ed25519 ", port)));
+
+        let (connected, check) = connect(&stores, port).await;
+        assert!(connected);
+        assert!(matches!(check, HostKeyCheck::Trusted));
+    }
+
+    #[tokio::test]
+    async fn key_in_user_store_is_trusted() {
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores(&dir);
+        let server = test_server::start().await;
+        let port = server.addr.port();
+        learn_known_hosts_path("127.0.0.1", port, &server.key, &stores[0]).unwrap();
+
+        let (connected, check) = connect(&stores, port).await;
+        assert!(connected);
+        assert!(matches!(check, HostKeyCheck::Trusted));
+        assert!(!stores[1].exists());
+    }
+
+    #[tokio::test]
+    async fn changed_key_is_refused_even_if_the_other_store_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let stores = stores(&dir);
+        let server = test_server::start().await;
+        let port = server.addr.port();
+        let old = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        learn_known_hosts_path("127.0.0.1", port, old.public_key(), &stores[0]).unwrap();
+        learn_known_hosts_path("127.0.0.1", port, &server.key, &stores[1]).unwrap();
+
+        let (connected, check) = connect(&stores, port).await;
+        assert!(!connected);
+        match check {
+            HostKeyCheck::Changed { path, line } => {
+                assert_eq!(path, stores[0]);
+                // russh starts a new file with an empty line
+                assert_eq!(line, 2);
+            }
+            _ => panic!("changed key was not reported"),
+        }
+    }
+
+    #[test]
+    fn forget_removes_only_the_matching_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("known_hosts");
+        fs::write(
+            &path,
+            "# comment\n\
+             nas,10.0.0.5 ssh-ed25519 AAAA1\n\
+             [nas]:2222 ssh-ed25519 AAAA2\n\
+             router ssh-ed25519 AAAA3\n",
+        )
+        .unwrap();
+
+        forget_in(&path, "[nas]:2222").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# comment\nnas,10.0.0.5 ssh-ed25519 AAAA1\nrouter ssh-ed25519 AAAA3\n"
+        );
+
+        forget_in(&path, "nas").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# comment\n10.0.0.5 ssh-ed25519 AAAA1\nrouter ssh-ed25519 AAAA3\n"
+        );
+    }
+
+    #[test]
+    fn forget_without_a_port_covers_every_port() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("known_hosts");
+        fs::write(
+            &path,
+            "nas ssh-ed25519 AAAA1\n[nas]:2222 ssh-ed25519 AAAA2\n[nas]:22 ssh-rsa AAAA3\n",
+        )
+        .unwrap();
+
+        forget_in(&path, "[nas]:22").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[nas]:2222 ssh-ed25519 AAAA2\n"
+        );
+
+        forget_in(&path, "nas").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+}
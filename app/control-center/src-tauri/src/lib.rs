@@ -1,5 +1,16 @@
+mod device_copy;
+mod keyring;
+mod known_hosts;
+mod remote_edit;
 mod sftp;
+mod ssh_config;
+mod sync;
 mod tailscale;
+#[cfg(test)]
+mod test_server;
+mod transfer_queue;
+mod tree_transfer;
+mod wol;
 
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use serde::Serialize;
@@ -7,7 +18,7 @@ use std::collections::HashMap;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 use tauri::ipc::Channel;
-use tauri::State;
+use tauri::{Manager, State};
 
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "event", content = "data")]
@@ -121,6 +132,13 @@ pub fn run() {
             sessions: Arc::new(Mutex::new(HashMap::new())),
         })
         .manage(sftp::SftpState::default())
+        .manage(transfer_queue::TransferQueue::default())
+        .manage(remote_edit::RemoteEditState::default())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window.state::<remote_edit::RemoteEditState>().close_all();
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             spawn_shell,
             write_to_pty,
@@ -129,7 +147,9 @@ pub fn run() {
             tailscale::get_tailscale_status,
             tailscale::tailscale_up,
             tailscale::tailscale_down,
+            wol::wake_on_lan,
             sftp::sftp_connect,
+            sftp::sftp_answer_prompt,
             sftp::sftp_disconnect,
             sftp::sftp_list_dir,
             sftp::sftp_mkdir,
@@ -139,9 +159,31 @@ pub fn run() {
             sftp::sftp_download,
             sftp::sftp_upload,
             sftp::sftp_cancel_transfer,
+            tree_transfer::sftp_download_dir,
+            tree_transfer::sftp_upload_dir,
+            sync::sync_plan,
+            sync::sync_run,
+            device_copy::sftp_copy_between_devices,
+            remote_edit::remote_edit_open,
+            remote_edit::remote_edit_read,
+            remote_edit::remote_edit_write,
+            remote_edit::remote_edit_diff,
+            remote_edit::remote_edit_resolve,
+            remote_edit::remote_edit_close,
             sftp::sftp_get_bookmarks,
             sftp::sftp_save_bookmark,
             sftp::sftp_delete_bookmark,
+            transfer_queue::transfer_queue_subscribe,
+            transfer_queue::transfer_queue_list,
+            transfer_queue::transfer_queue_enqueue,
+            transfer_queue::transfer_queue_pause,
+            transfer_queue::transfer_queue_resume,
+            transfer_queue::transfer_queue_move,
+            transfer_queue::transfer_queue_remove,
+            transfer_queue::transfer_queue_clear_finished,
+            transfer_queue::transfer_queue_set_limits,
+            known_hosts::sftp_list_trusted_hosts,
+            known_hosts::sftp_forget_host,
             sftp::local_list_dir,
             sftp::local_read_file,
             sftp::local_mkdir,
@@ -0,0 +1,708 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::AsyncReadExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::sftp::{self, FileTransfer, SftpState, TransferDirection};
+use crate::tree_transfer::is_plain_name;
+
+/// How often the local copy is checked for saves.
+const POLL_INTERVAL: Duration = Duration::from_millis(750);
+
+/// Largest file the in-app editor and the conflict diff will load.
+const MAX_TEXT_BYTES: u64 = 5 * 1024 * 1024;
+
+// ----- Data Structures -----
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum EditorChoice {
+    /// Whatever the OS opens text files with.
+    System,
+    /// The app's own editor, through `remote_edit_read` and `remote_edit_write`.
+    InApp,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictResolution {
+    /// Upload the local copy over the device's.
+    Overwrite,
+    /// Throw the local edits away and reload the device's file.
+    Discard,
+}
+
+/// The device's file as it was at the last download or upload. A
+/// different one at upload time means someone else changed it. mtimes only
+/// have whole seconds, so the content's hash is what catches a same-size
+/// edit within the same second.
+#[derive(Clone, Debug, PartialEq)]
+struct RemoteStamp {
+    size: u64,
+    modified: Option<u64>,
+    sha256: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditSessionInfo {
+    pub id: String,
+    pub device_ip: String,
+    pub remote_path: String,
+    pub local_path: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum EditEvent {
+    /// A save is on its way to the device.
+    Uploading,
+    /// The device has the latest save.
+    Saved { size: u64 },
+    /// The file changed on the device since it was opened. Saves wait until
+    /// `remote_edit_resolve`.
+    Conflict {
+        local_size: u64,
+        remote_size: u64,
+        remote_modified: Option<u64>,
+    },
+    /// The local copy was replaced with the device's.
+    Reloaded,
+    /// The upload failed; the next poll tries again.
+    Error { message: String },
+}
+
+/// Both sides of a conflict, for showing a diff.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditDiff {
+    pub local: String,
+    pub remote: String,
+}
+
+struct EditSession {
+    device_ip: String,
+    /// With symlinks resolved, so a save replaces the file and not the link.
+    remote_path: String,
+    /// Inside the session's own directory in the workspace.
+    local_path: PathBuf,
+    base: RemoteStamp,
+    /// Size and mtime of the local copy when last synced, to notice saves.
+    local_seen: Option<(u64, SystemTime)>,
+    in_conflict: bool,
+    on_event: Channel<EditEvent>,
+    cancel_token: CancellationToken,
+}
+
+pub struct RemoteEditState {
+    sessions: Mutex<HashMap<String, Arc<tokio::sync::Mutex<EditSession>>>>,
+    /// Parent of every session's cancellation token.
+    shutdown: CancellationToken,
+    next_id: AtomicU64,
+}
+
+impl Default for RemoteEditState {
+    /// Clears out sessions a previous run left behind when it crashed.
+    fn default() -> Self {
+        fs::remove_dir_all(get_workspace_dir()).ok();
+        RemoteEditState {
+            sessions: Mutex::new(HashMap::new()),
+            shutdown: CancellationToken::new(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RemoteEditState {
+    /// Stops every session and deletes the local copies, for when the
+    /// window goes away without closing them.
+    pub fn close_all(&self) {
+        self.shutdown.cancel();
+        self.sessions.lock().unwrap().clear();
+        fs::remove_dir_all(get_workspace_dir()).ok();
+    }
+
+    fn session(&self, session_id: &str) -> Result<Arc<tokio::sync::Mutex<EditSession>>, String> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| "No such edit session".to_string())
+    }
+}
+
+// ----- Helper Functions -----
+
+/// Where files being edited live, one directory per session.
+fn get_workspace_dir() -> PathBuf {
+    let proj_dirs = directories::ProjectDirs::from("com", "homelab", "control-center")
+        .expect("Failed to get project directories");
+    proj_dirs.cache_dir().join("remote-edit")
+}
+
+fn local_state(path: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+async fn remote_stamp(
+    state: &SftpState,
+    device_ip: &str,
+    path: &str,
+) -> Result<RemoteStamp, String> {
+    let sftp = sftp::get_connection(state, device_ip).await?.sftp().await?;
+    let metadata = sftp
+        .metadata(path)
+        .await
+        .map_err(|e| format!("Failed to stat {}: {}", path, e))?;
+
+    let mut file = sftp
+        .open(path)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(RemoteStamp {
+        size: metadata.size.unwrap_or(0),
+        modified: metadata.mtime.map(u64::from),
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Moves the file either way with the transfer machinery, so a dropped
+/// connection is retried.
+async fn transfer(
+    state: &SftpState,
+    session: &EditSession,
+    direction: TransferDirection,
+) -> Result<(), String> {
+    let transfer = FileTransfer {
+        device_ip: session.device_ip.clone(),
+        direction,
+        local_path: session.local_path.to_string_lossy().to_string(),
+        remote_path: session.remote_path.clone(),
+        verify_checksum: false,
+    };
+    sftp::run_transfer(state, &transfer, &CancellationToken::new(), &|_| {}).await
+}
+
+/// Opens `path` with the OS's default application for it.
+fn open_with_system(path: &Path) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        // The default text editor, not whatever the extension maps to
+        command.arg("-t");
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+
+    command
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open an editor: {}", e))
+}
+
+/// Uploads the local copy, which `local` describes, if the device's file is
+/// still the one it came from, and flags a conflict if not. The check is
+/// just before the upload starts, so a change landing while the upload runs
+/// is overwritten. The upload keeps the file's mode, and its owner where
+/// the server lets it. Only a finished upload marks `local` as seen, so a
+/// failed one is tried again on the next poll.
+async fn push_save(state: &SftpState, session: &mut EditSession, local: Option<(u64, SystemTime)>) {
+    let _ = session.on_event.send(EditEvent::Uploading);
+
+    let result = async {
+        let remote = remote_stamp(state, &session.device_ip, &session.remote_path).await?;
+        if remote != session.base {
+            return Ok(Some(remote));
+        }
+        transfer(state, session, TransferDirection::Upload).await?;
+        session.base = remote_stamp(state, &session.device_ip, &session.remote_path).await?;
+        Ok(None)
+    }
+    .await;
+
+    let event = match result {
+        Ok(None) => {
+            session.local_seen = local;
+            EditEvent::Saved {
+                size: session.base.size,
+            }
+        }
+        Ok(Some(remote)) => {
+            session.in_conflict = true;
+            EditEvent::Conflict {
+                local_size: local.map(|(size, _)| size).unwrap_or(0),
+                remote_size: remote.size,
+                remote_modified: remote.modified,
+            }
+        }
+        Err(message) => EditEvent::Error { message },
+    };
+    let _ = session.on_event.send(event);
+}
+
+/// Polls the local copy and pushes each save, until the session closes.
+async fn watch(app: AppHandle, session: Arc<tokio::sync::Mutex<EditSession>>) {
+    let cancel_token = session.lock().await.cancel_token.clone();
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+
+        let mut session = session.lock().await;
+        if session.in_conflict {
+            continue;
+        }
+        let current = local_state(&session.local_path);
+        if current.is_none() || current == session.local_seen {
+            continue;
+        }
+        push_save(&app.state::<SftpState>(), &mut session, current).await;
+    }
+}
+
+/// Downloads `remote_path`, already canonical, into `session_dir` under its
+/// own name. The name has to be a plain one so the copy stays in the
+/// directory.
+async fn open_session(
+    state: &SftpState,
+    session_dir: &Path,
+    device_ip: String,
+    remote_path: String,
+    on_event: Channel<EditEvent>,
+    cancel_token: CancellationToken,
+) -> Result<EditSession, String> {
+    let filename = remote_path.rsplit('/').next().unwrap_or_default();
+    if !is_plain_name(filename) {
+        return Err(format!("{} is not a file that can be edited", remote_path));
+    }
+    fs::create_dir_all(session_dir)
+        .map_err(|e| format!("Failed to create {}: {}", session_dir.display(), e))?;
+
+    let mut session = EditSession {
+        base: remote_stamp(state, &device_ip, &remote_path).await?,
+        local_path: session_dir.join(filename),
+        device_ip,
+        remote_path,
+        local_seen: None,
+        in_conflict: false,
+        on_event,
+        cancel_token,
+    };
+    transfer(state, &session, TransferDirection::Download).await?;
+    session.local_seen = local_state(&session.local_path);
+    Ok(session)
+}
+
+/// Settles a conflict one way or the other, and starts watching for saves
+/// again.
+async fn resolve(
+    state: &SftpState,
+    session: &mut EditSession,
+    resolution: ConflictResolution,
+) -> Result<(), String> {
+    match resolution {
+        ConflictResolution::Overwrite => {
+            let current = local_state(&session.local_path);
+            transfer(state, session, TransferDirection::Upload).await?;
+            session.base = remote_stamp(state, &session.device_ip, &session.remote_path).await?;
+            session.local_seen = current;
+            session.in_conflict = false;
+            let _ = session.on_event.send(EditEvent::Saved {
+                size: session.base.size,
+            });
+        }
+        ConflictResolution::Discard => {
+            session.base = remote_stamp(state, &session.device_ip, &session.remote_path).await?;
+            transfer(state, session, TransferDirection::Download).await?;
+            session.local_seen = local_state(&session.local_path);
+            session.in_conflict = false;
+            let _ = session.on_event.send(EditEvent::Reloaded);
+        }
+    }
+    Ok(())
+}
+
+// ----- Tauri Commands -----
+
+/// Downloads the file into a fresh workspace directory, opens it in
+/// `editor`, and uploads it whenever it's saved.
+#[tauri::command]
+pub async fn remote_edit_open(
+    app: AppHandle,
+    state: State<'_, RemoteEditState>,
+    sftp: State<'_, SftpState>,
+    device_ip: String,
+    remote_path: String,
+    editor: EditorChoice,
+    on_event: Channel<EditEvent>,
+) -> Result<EditSessionInfo, String> {
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let id = format!("{}-{}", stamp, state.next_id.fetch_add(1, Ordering::SeqCst));
+
+    let canonical = sftp::get_connection(&sftp, &device_ip)
+        .await?
+        .sftp()
+        .await?
+        .canonicalize(remote_path.as_str())
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", remote_path, e))?;
+    let session_dir = get_workspace_dir().join(&id);
+    let opened = async {
+        let session = open_session(
+            &sftp,
+            &session_dir,
+            device_ip.clone(),
+            canonical,
+            on_event,
+            state.shutdown.child_token(),
+        )
+        .await?;
+        if editor == EditorChoice::System {
+            open_with_system(&session.local_path)?;
+        }
+        Ok::<EditSession, String>(session)
+    }
+    .await;
+    let session = match opened {
+        Ok(session) => session,
+        Err(e) => {
+            fs::remove_dir_all(&session_dir).ok();
+            return Err(e);
+        }
+    };
+
+    let info = EditSessionInfo {
+        id: id.clone(),
+        device_ip,
+        remote_path,
+        local_path: session.local_path.to_string_lossy().to_string(),
+    };
+    let session = Arc::new(tokio::sync::Mutex::new(session));
+    state
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(id, Arc::clone(&session));
+    tauri::async_runtime::spawn(watch(app, session));
+
+    Ok(info)
+}
+
+/// The local copy's text, for the in-app editor.
+#[tauri::command]
+pub async fn remote_edit_read(
+    state: State<'_, RemoteEditState>,
+    session_id: String,
+) -> Result<String, String> {
+    let session = state.session(&session_id)?;
+    let path = session.lock().await.local_path.clone();
+
+    let size = fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_TEXT_BYTES {
+        return Err(format!(
+            "File is too large to edit here ({} bytes); use the system editor",
+            size
+        ));
+    }
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    String::from_utf8(bytes).map_err(|_| "File is not UTF-8 text".to_string())
+}
+
+/// Saves the in-app editor's text and uploads it straight away.
+#[tauri::command]
+pub async fn remote_edit_write(
+    state: State<'_, RemoteEditState>,
+    sftp: State<'_, SftpState>,
+    session_id: String,
+    content: String,
+) -> Result<(), String> {
+    let session = state.session(&session_id)?;
+    let mut session = session.lock().await;
+    tokio::fs::write(&session.local_path, content)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", session.local_path.display(), e))?;
+    if !session.in_conflict {
+        let current = local_state(&session.local_path);
+        push_save(&sftp, &mut session, current).await;
+    }
+    Ok(())
+}
+
+/// The local copy and the device's file side by side, while in conflict.
+#[tauri::command]
+pub async fn remote_edit_diff(
+    state: State<'_, RemoteEditState>,
+    sftp: State<'_, SftpState>,
+    session_id: String,
+) -> Result<EditDiff, String> {
+    let session = state.session(&session_id)?;
+    let session = session.lock().await;
+
+    let local = tokio::fs::read(&session.local_path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", session.local_path.display(), e))?;
+
+    let connection = sftp::get_connection(&sftp, &session.device_ip).await?;
+    let remote_sftp = connection.sftp().await?;
+    let file = remote_sftp
+        .open(session.remote_path.as_str())
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", session.remote_path, e))?;
+    let mut remote = Vec::new();
+    file.take(MAX_TEXT_BYTES)
+        .read_to_end(&mut remote)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", session.remote_path, e))?;
+
+    let local_len = (local.len() as u64).min(MAX_TEXT_BYTES) as usize;
+    Ok(EditDiff {
+        local: String::from_utf8_lossy(&local[..local_len]).to_string(),
+        remote: String::from_utf8_lossy(&remote).to_string(),
+    })
+}
+
+#[tauri::command]
+pub async fn remote_edit_resolve(
+    state: State<'_, RemoteEditState>,
+    sftp: State<'_, SftpState>,
+    session_id: String,
+    resolution: ConflictResolution,
+) -> Result<(), String> {
+    let session = state.session(&session_id)?;
+    let mut session = session.lock().await;
+    resolve(&sftp, &mut session, resolution).await
+}
+
+/// Stops watching and deletes the local copy. Saves not yet uploaded, as
+/// while in conflict, are lost.
+#[tauri::command]
+pub async fn remote_edit_close(
+    state: State<'_, RemoteEditState>,
+    session_id: String,
+) -> Result<(), String> {
+    let Some(session) = state.sessions.lock().unwrap().remove(&session_id) else {
+        return Ok(());
+    };
+    let session = session.lock().await;
+    session.cancel_token.cancel();
+    if let Some(dir) = session.local_path.parent() {
+        tokio::fs::remove_dir_all(dir).await.ok();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sftp::tests::{connect, state_with};
+    use crate::test_server;
+    use tauri::ipc::InvokeResponseBody;
+
+    /// A session on `remote` through a fresh test server, and the events it
+    /// sends.
+    async fn open(
+        remote: &Path,
+        session_dir: &Path,
+    ) -> (SftpState, EditSession, Arc<Mutex<Vec<String>>>) {
+        let server = test_server::start().await;
+        let state = state_with("127.0.0.1", connect(&server).await).await;
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let on_event = Channel::new(move |body| {
+            if let InvokeResponseBody::Json(json) = body {
+                sink.lock().unwrap().push(json);
+            }
+            Ok(())
+        });
+        let session = open_session(
+            &state,
+            session_dir,
+            "127.0.0.1".to_string(),
+            remote.to_string_lossy().to_string(),
+            on_event,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        (state, session, events)
+    }
+
+    /// Replaces the file's content but keeps its mtime, as an edit within
+    /// the same second would.
+    fn rewrite_in_place(path: &Path, content: &str) {
+        let modified = fs::metadata(path).unwrap().modified().unwrap();
+        fs::write(path, content).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    fn saw(events: &Mutex<Vec<String>>, event: &str) -> bool {
+        let tag = format!("\"event\":\"{}\"", event);
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|json| json.contains(&tag))
+    }
+
+    /// Opens `config.txt` and has the device change it under the local
+    /// copy, with the same size and mtime, before a local save.
+    async fn conflicted(dir: &Path) -> (SftpState, EditSession, Arc<Mutex<Vec<String>>>, PathBuf) {
+        let remote = dir.join("config.txt");
+        fs::write(&remote, "alpha\n").unwrap();
+        let (state, mut session, events) = open(&remote, &dir.join("session")).await;
+        assert_eq!(fs::read_to_string(&session.local_path).unwrap(), "alpha\n");
+
+        rewrite_in_place(&remote, "bravo\n");
+        fs::write(&session.local_path, "local edit\n").unwrap();
+        let current = local_state(&session.local_path);
+        push_save(&state, &mut session, current).await;
+        (state, session, events, remote)
+    }
+
+    #[tokio::test]
+    async fn a_same_second_remote_edit_is_a_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_state, session, events, remote) = conflicted(dir.path()).await;
+
+        assert!(session.in_conflict);
+        assert!(saw(&events, "conflict"));
+        assert!(!saw(&events, "saved"));
+        assert_eq!(fs::read_to_string(&remote).unwrap(), "bravo\n");
+        // The save is still pending
+        assert_ne!(session.local_seen, local_state(&session.local_path));
+    }
+
+    #[tokio::test]
+    async fn overwrite_uploads_the_local_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state, mut session, events, remote) = conflicted(dir.path()).await;
+
+        resolve(&state, &mut session, ConflictResolution::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&remote).unwrap(), "local edit\n");
+        assert!(!session.in_conflict);
+        assert!(saw(&events, "saved"));
+        let stamp = remote_stamp(&state, "127.0.0.1", &session.remote_path)
+            .await
+            .unwrap();
+        assert_eq!(session.base, stamp);
+        assert_eq!(session.local_seen, local_state(&session.local_path));
+    }
+
+    #[tokio::test]
+    async fn discard_reloads_the_remote_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state, mut session, events, remote) = conflicted(dir.path()).await;
+
+        resolve(&state, &mut session, ConflictResolution::Discard)
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&session.local_path).unwrap(), "bravo\n");
+        assert_eq!(fs::read_to_string(&remote).unwrap(), "bravo\n");
+        assert!(!session.in_conflict);
+        assert!(saw(&events, "reloaded"));
+        let stamp = remote_stamp(&state, "127.0.0.1", &session.remote_path)
+            .await
+            .unwrap();
+        assert_eq!(session.base, stamp);
+        assert_eq!(session.local_seen, local_state(&session.local_path));
+    }
+
+    #[tokio::test]
+    async fn a_failed_save_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("config.txt");
+        fs::write(&remote, "alpha\n").unwrap();
+        let (state, mut session, events) = open(&remote, &dir.path().join("session")).await;
+        let opened = session.local_seen;
+
+        // The device's file is briefly gone, so the save can't go through
+        let hidden = dir.path().join("hidden.txt");
+        fs::rename(&remote, &hidden).unwrap();
+        fs::write(&session.local_path, "local edit\n").unwrap();
+        let current = local_state(&session.local_path);
+        push_save(&state, &mut session, current).await;
+        assert!(saw(&events, "error"));
+        assert_eq!(session.local_seen, opened);
+        assert!(!session.in_conflict);
+
+        fs::rename(&hidden, &remote).unwrap();
+        push_save(&state, &mut session, current).await;
+        assert!(saw(&events, "saved"));
+        assert_eq!(session.local_seen, current);
+        assert_eq!(fs::read_to_string(&remote).unwrap(), "local edit\n");
+    }
+
+    #[tokio::test]
+    async fn names_that_leave_the_session_dir_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server::start().await;
+        let state = state_with("127.0.0.1", connect(&server).await).await;
+        let session_dir = dir.path().join("session");
+
+        for remote in ["/", "/srv/..", "/srv/."] {
+            let opened = open_session(
+                &state,
+                &session_dir,
+                "127.0.0.1".to_string(),
+                remote.to_string(),
+                Channel::new(|_| Ok(())),
+                CancellationToken::new(),
+            )
+            .await;
+            assert!(opened.is_err(), "{} was opened", remote);
+        }
+        assert!(!session_dir.exists());
+    }
+}
@@ -1,27 +1,48 @@
-use russh::client::{self, Handle};
-use russh::keys::PrivateKeyWithHashAlg;
+use russh::client::{self, AuthResult, Handle, KeyboardInteractiveAuthResponse};
+use russh::keys::ssh_key::PublicKey;
+use russh::keys::{PrivateKey, PrivateKeyWithHashAlg};
+use russh::{ChannelMsg, MethodKind};
+use russh_sftp::client::error::Error as SftpClientError;
 use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::{FileAttributes, OpenFlags, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::Read as StdRead;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::io::{Read as StdRead, SeekFrom};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
 use tauri::ipc::Channel;
-use tauri::State;
+use tauri::{AppHandle, State};
 use thiserror::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::sync::RwLock;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::{oneshot, OwnedSemaphorePermit, RwLock, Semaphore};
 use tokio_util::sync::CancellationToken;
 
+use crate::keyring;
+use crate::known_hosts::{self, HostKeyCheck, TrustStores};
+use crate::ssh_config::{HostConfig, JumpHost, SshConfig};
+use crate::transfer_queue;
+
 // ----- Error Types -----
 
 #[derive(Error, Debug)]
 pub enum SftpError {
     #[error("Connection failed: {0}")]
     Connection(String),
-    #[error("Authentication failed: {0}")]
-    Authentication(String),
+    #[error(
+        "Authentication as {user} on {host} failed (tried: {}). Load a key into the SSH agent, set an IdentityFile in ~/.ssh/config (by default ~/.ssh/id_ed25519 or ~/.ssh/id_rsa is used), or check the password.",
+        if tried.is_empty() { "nothing the server accepts".to_string() } else { tried.join(", ") }
+    )]
+    Authentication {
+        user: String,
+        host: String,
+        /// Each method offered, e.g. `publickey (~/.ssh/id_ed25519)`.
+        tried: Vec<String>,
+    },
     #[error("SSH agent error: {0}")]
     SshAgent(String),
     #[error("SFTP operation failed: {0}")]
@@ -34,6 +55,28 @@ pub enum SftpError {
     Io(#[from] std::io::Error),
     #[error("Timeout: {0}")]
     Timeout(String),
+    #[error("SSH config error: {0}")]
+    Config(String),
+    #[error("Host key verification failed: {0}")]
+    HostKey(String),
+    #[error(
+        "Host key for {host} has changed (now {fingerprint}) and no longer matches line {line} of {path}. \
+         This could be a man-in-the-middle attack; if the host was reinstalled, remove that line and reconnect."
+    )]
+    HostKeyChanged {
+        host: String,
+        fingerprint: String,
+        path: String,
+        line: usize,
+    },
+    #[error("Host key for {host} ({fingerprint}) was not trusted")]
+    HostKeyRejected { host: String, fingerprint: String },
+}
+
+impl From<russh::Error> for SftpError {
+    fn from(err: russh::Error) -> SftpError {
+        SftpError::Connection(err.to_string())
+    }
 }
 
 impl From<SftpError> for String {
@@ -79,6 +122,95 @@ pub enum TransferEvent {
         filename: String,
         message: String,
     },
+    /// A transient failure; `attempt` starts after `delay_ms`.
+    Retrying {
+        filename: String,
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: u64,
+        message: String,
+    },
+    /// Overall progress of a directory transfer; `filename` is the file
+    /// moving right now.
+    TreeProgress {
+        filename: String,
+        files_done: u64,
+        files_total: u64,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+    /// A file left as it was because of the overwrite policy.
+    Skipped {
+        filename: String,
+        reason: String,
+    },
+}
+
+/// Questions `sftp_connect` needs the user to answer before it can go on,
+/// answered through `sftp_answer_prompt`, and warnings to show along the way.
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum ConnectPrompt {
+    UnknownHostKey {
+        prompt_id: String,
+        host: String,
+        key_type: String,
+        fingerprint: String,
+    },
+    /// An encrypted key file needs its passphrase.
+    KeyPassphrase {
+        prompt_id: String,
+        key_path: String,
+        /// Counts up from 1 as wrong passphrases are given.
+        attempt: u32,
+    },
+    Password {
+        prompt_id: String,
+        user: String,
+        host: String,
+        attempt: u32,
+    },
+    /// Keyboard-interactive questions from the server, such as an OTP code.
+    KeyboardInteractive {
+        prompt_id: String,
+        user: String,
+        host: String,
+        name: String,
+        instructions: String,
+        prompts: Vec<InteractivePrompt>,
+    },
+    /// A line of `~/.ssh/config` was skipped. Needs no answer.
+    ConfigWarning { message: String },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InteractivePrompt {
+    pub prompt: String,
+    /// Whether the answer may be shown while typed.
+    pub echo: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PromptAnswer {
+    /// Accept the host key until the app quits.
+    TrustOnce,
+    /// Accept the host key and add it to the app's trust store.
+    TrustAlways,
+    Reject,
+    /// A passphrase or password; `remember` saves it in the OS keyring once
+    /// it has worked.
+    Secret { value: String, remember: bool },
+    /// One answer per keyboard-interactive prompt, in order.
+    Responses(Vec<String>),
+    /// Skip this authentication method.
+    Cancel,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -97,41 +229,257 @@ pub struct BookmarkList {
     pub bookmarks: Vec<Bookmark>,
 }
 
+/// Where a device's SSH server was found once `~/.ssh/config` was applied,
+/// so another host can be pointed at it the same way.
+#[derive(Clone)]
+pub(crate) struct SshEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    /// ProxyJump hops as `user@host:port`, first hop first.
+    pub jumps: Vec<String>,
+}
+
 // ----- SSH Handler -----
 
-struct SshHandler;
+/// Keys accepted for this app session only, as host, port and fingerprint.
+type SessionTrust = Arc<Mutex<HashSet<(String, u16, String)>>>;
+
+struct SshHandler {
+    host: String,
+    port: u16,
+    trust_stores: TrustStores,
+    session_trust: SessionTrust,
+    /// Set when the server's key is in no trust store, so the caller can
+    /// ask the user about it and reconnect.
+    unknown_key: Arc<Mutex<Option<PublicKey>>>,
+}
 
 impl client::Handler for SshHandler {
-    type Error = russh::Error;
+    type Error = SftpError;
 
-    fn check_server_key(
+    async fn check_server_key(
         &mut self,
-        _server_public_key: &russh::keys::ssh_key::PublicKey,
-    ) -> impl std::future::Future<Output = Result<bool, Self::Error>> + Send {
-        // Accept all host keys for home lab environment
-        // In production, you'd want to verify against known_hosts
-        async { Ok(true) }
+        server_public_key: &PublicKey,
+    ) -> Result<bool, Self::Error> {
+        let fingerprint = known_hosts::fingerprint(server_public_key);
+        match self.trust_stores.check(&self.host, self.port, server_public_key)? {
+            HostKeyCheck::Trusted => Ok(true),
+            HostKeyCheck::Changed { path, line } => Err(SftpError::HostKeyChanged {
+                host: self.host.clone(),
+                fingerprint,
+                path: path.display().to_string(),
+                line,
+            }),
+            HostKeyCheck::Unknown => {
+                let trust_key = (self.host.clone(), self.port, fingerprint);
+                if self.session_trust.lock().unwrap().contains(&trust_key) {
+                    return Ok(true);
+                }
+                *self.unknown_key.lock().unwrap() = Some(server_public_key.clone());
+                Ok(false)
+            }
+        }
     }
 }
 
 // ----- State Management -----
 
-struct SftpConnection {
-    #[allow(dead_code)]
+/// Attempts a transfer gets before its error is reported.
+const MAX_TRANSFER_ATTEMPTS: u32 = 5;
+/// Wait before the first retry, doubled for each one after.
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Suffixes of an unfinished transfer's data and manifest.
+const PARTIAL_SUFFIX: &str = ".part";
+const MANIFEST_SUFFIX: &str = ".part.json";
+
+/// Where the manifest for a transfer to `path` goes: beside it, but hidden,
+/// so it stays out of the directory listings.
+fn manifest_path(path: &str) -> String {
+    match path.rfind(['/', std::path::MAIN_SEPARATOR]) {
+        Some(i) => format!("{}.{}{}", &path[..=i], &path[i + 1..], MANIFEST_SUFFIX),
+        None => format!(".{}{}", path, MANIFEST_SUFFIX),
+    }
+}
+
+/// Whether `path` is an unfinished transfer's data or manifest.
+pub(crate) fn is_partial_file(path: &str) -> bool {
+    path.ends_with(PARTIAL_SUFFIX) || path.ends_with(MANIFEST_SUFFIX)
+}
+
+/// SFTP channels one SSH connection may have open at once. OpenSSH allows
+/// ten sessions per connection by default (`MaxSessions`), so this leaves
+/// room for shells and exec channels.
+const MAX_SFTP_CHANNELS: usize = 4;
+
+pub(crate) struct SftpConnection {
     ssh_handle: Handle<SshHandler>,
-    sftp: SftpSession,
+    /// ProxyJump sessions the connection is tunnelled through.
     #[allow(dead_code)]
+    jump_handles: Vec<Handle<SshHandler>>,
+    /// Open SFTP channels nobody is using right now.
+    idle: Mutex<Vec<SftpSession>>,
+    /// One permit per channel that may be in use.
+    channels: Arc<Semaphore>,
     device_ip: String,
-    #[allow(dead_code)]
-    username: String,
+    device_hostname: Option<String>,
+    endpoint: SshEndpoint,
+    /// Where to ask the user things when the connection has to be remade.
+    on_prompt: Channel<ConnectPrompt>,
 }
 
-#[derive(Clone)]
+impl SftpConnection {
+    fn new(
+        ssh_handle: Handle<SshHandler>,
+        jump_handles: Vec<Handle<SshHandler>>,
+        sftp: SftpSession,
+        device_ip: String,
+        device_hostname: Option<String>,
+        endpoint: SshEndpoint,
+        on_prompt: Channel<ConnectPrompt>,
+    ) -> SftpConnection {
+        SftpConnection {
+            ssh_handle,
+            jump_handles,
+            idle: Mutex::new(vec![sftp]),
+            channels: Arc::new(Semaphore::new(MAX_SFTP_CHANNELS)),
+            device_ip,
+            device_hostname,
+            endpoint,
+            on_prompt,
+        }
+    }
+
+    /// Borrows an SFTP channel: an idle one that still answers, a new one
+    /// while under `MAX_SFTP_CHANNELS`, or else the next one handed back.
+    pub(crate) async fn sftp(self: &Arc<Self>) -> Result<PooledSftp, String> {
+        let permit = Arc::clone(&self.channels)
+            .acquire_owned()
+            .await
+            .map_err(|_| "Connection is closing".to_string())?;
+        // Borrowers hand channels back whether or not they failed, so an
+        // idle one may be dead; it has to answer before it's lent again
+        let session = loop {
+            let idle = self.idle.lock().unwrap().pop();
+            match idle {
+                Some(session) if session.canonicalize(".").await.is_ok() => break session,
+                Some(_) => continue,
+                None => break open_sftp(&self.ssh_handle).await?,
+            }
+        };
+        Ok(PooledSftp {
+            session: Some(session),
+            connection: Arc::clone(self),
+            _permit: permit,
+        })
+    }
+
+    /// Runs `command` on the device and returns its stdout.
+    pub(crate) async fn exec(&self, command: &str) -> Result<Vec<u8>, String> {
+        exec_command(&self.ssh_handle, command).await
+    }
+
+    pub(crate) async fn exec_streaming(
+        &self,
+        command: &str,
+        cancel_token: &CancellationToken,
+        on_stdout: &mut (dyn FnMut(&[u8]) + Send),
+    ) -> Result<ExecExit, String> {
+        exec_streaming(&self.ssh_handle, command, cancel_token, on_stdout).await
+    }
+
+    pub(crate) fn endpoint(&self) -> &SshEndpoint {
+        &self.endpoint
+    }
+}
+
+/// An SFTP channel on loan from its connection, returned when dropped. Keep
+/// it alive for as long as files opened through it are in use.
+pub(crate) struct PooledSftp {
+    session: Option<SftpSession>,
+    connection: Arc<SftpConnection>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledSftp {
+    type Target = SftpSession;
+
+    fn deref(&self) -> &SftpSession {
+        self.session.as_ref().expect("session is only taken on drop")
+    }
+}
+
+impl Drop for PooledSftp {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            self.connection.idle.lock().unwrap().push(session);
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum TransferDirection {
     Download,
     Upload,
 }
 
+/// One file moving between this machine and a device.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTransfer {
+    pub device_ip: String,
+    pub direction: TransferDirection,
+    pub local_path: String,
+    pub remote_path: String,
+    /// Compare SHA-256 sums of both ends once the bytes are across.
+    pub verify_checksum: bool,
+}
+
+impl FileTransfer {
+    /// Name of the file being sent, for progress events.
+    pub fn filename(&self) -> String {
+        let source = match self.direction {
+            TransferDirection::Download => &self.remote_path,
+            TransferDirection::Upload => &self.local_path,
+        };
+        Path::new(source)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| source.clone())
+    }
+}
+
+/// Kept beside a `.part` file, so an interrupted transfer only resumes
+/// while the source is still the file it started from.
+#[derive(Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct PartialManifest {
+    source: String,
+    size: u64,
+    /// Seconds since the epoch.
+    modified: Option<u64>,
+}
+
+impl PartialManifest {
+    fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("manifest serializes")
+    }
+}
+
+/// Receives a transfer's events, whoever is listening for them.
+pub type ProgressFn<'a> = dyn Fn(TransferEvent) + Send + Sync + 'a;
+
+/// Why a transfer attempt stopped short.
+enum TransferFailure {
+    Cancelled,
+    /// The connection dropped or stalled; another attempt may get through.
+    Transient(String),
+    Fatal(String),
+}
+
 pub struct TransferJob {
     pub cancel_token: CancellationToken,
     #[allow(dead_code)]
@@ -139,8 +487,42 @@ pub struct TransferJob {
 }
 
 pub struct SftpState {
-    connections: Arc<RwLock<HashMap<String, SftpConnection>>>,
+    connections: Arc<RwLock<HashMap<String, Arc<SftpConnection>>>>,
     transfers: Arc<RwLock<HashMap<String, TransferJob>>>,
+    prompts: Mutex<HashMap<String, oneshot::Sender<PromptAnswer>>>,
+    next_prompt_id: AtomicU64,
+    trust_stores: TrustStores,
+    session_trust: SessionTrust,
+    /// Key files decrypted this session, so each passphrase is asked once.
+    key_cache: Mutex<HashMap<PathBuf, Arc<PrivateKey>>>,
+}
+
+impl SftpState {
+    pub async fn is_connected(&self, device_ip: &str) -> bool {
+        self.connections.read().await.contains_key(device_ip)
+    }
+
+    /// Makes `transfer_id` cancellable through `sftp_cancel_transfer` until
+    /// `end_transfer`.
+    pub(crate) async fn begin_transfer(
+        &self,
+        transfer_id: &str,
+        direction: TransferDirection,
+    ) -> CancellationToken {
+        let cancel_token = CancellationToken::new();
+        self.transfers.write().await.insert(
+            transfer_id.to_string(),
+            TransferJob {
+                cancel_token: cancel_token.clone(),
+                direction,
+            },
+        );
+        cancel_token
+    }
+
+    pub(crate) async fn end_transfer(&self, transfer_id: &str) {
+        self.transfers.write().await.remove(transfer_id);
+    }
 }
 
 impl Default for SftpState {
@@ -148,10 +530,18 @@ impl Default for SftpState {
         SftpState {
             connections: Arc::new(RwLock::new(HashMap::new())),
             transfers: Arc::new(RwLock::new(HashMap::new())),
+            prompts: Mutex::new(HashMap::new()),
+            next_prompt_id: AtomicU64::new(0),
+            trust_stores: TrustStores::default(),
+            session_trust: Arc::new(Mutex::new(HashSet::new())),
+            key_cache: Mutex::new(HashMap::new()),
         }
     }
 }
 
+/// Wrong passphrases or passwords accepted before moving on, as in OpenSSH.
+const SECRET_ATTEMPTS: u32 = 3;
+
 // ----- Helper Functions -----
 
 fn get_bookmarks_path() -> PathBuf {
@@ -162,6 +552,43 @@ fn get_bookmarks_path() -> PathBuf {
     data_dir.join("bookmarks.json")
 }
 
+/// Where and how to reach one SSH host once `~/.ssh/config` is applied.
+struct Target {
+    host: String,
+    port: u16,
+    user: String,
+    /// Empty means the default keys.
+    identity_files: Vec<PathBuf>,
+    identities_only: bool,
+    connect_timeout: Duration,
+}
+
+impl Target {
+    /// `host` is dialled unless the config names a HostName; an explicit
+    /// `user` wins over the config's, which wins over `$USER`.
+    fn new(config: &HostConfig, host: &str, port: Option<u16>, user: Option<String>) -> Target {
+        Target {
+            host: config.host_name.clone().unwrap_or_else(|| host.to_string()),
+            port: port.or(config.port).unwrap_or(22),
+            user: user.or_else(|| config.user.clone()).unwrap_or_else(|| {
+                std::env::var("USER").unwrap_or_else(|_| "root".to_string())
+            }),
+            identity_files: config.identity_files.clone(),
+            identities_only: config.identities_only,
+            connect_timeout: config.connect_timeout.unwrap_or(Duration::from_secs(30)),
+        }
+    }
+
+    /// `user@host:port`, as `ssh -J` takes it.
+    fn jump_spec(&self) -> String {
+        if self.host.contains(':') {
+            format!("{}@[{}]:{}", self.user, self.host, self.port)
+        } else {
+            format!("{}@{}:{}", self.user, self.host, self.port)
+        }
+    }
+}
+
 fn get_parent_path(path: &str) -> Option<String> {
     let path = path.trim_end_matches('/');
     if path.is_empty() || path == "/" {
@@ -173,39 +600,241 @@ fn get_parent_path(path: &str) -> Option<String> {
     parent.map(|p| if p.is_empty() { "/".to_string() } else { p })
 }
 
-async fn establish_connection(
-    device_ip: &str,
-    username: &str,
-) -> Result<(Handle<SshHandler>, SftpSession), SftpError> {
+/// Passes on what was skipped while resolving `config`. The connection goes
+/// ahead either way, so a closed channel is no reason to stop.
+fn warn_config(config: &HostConfig, on_prompt: &Channel<ConnectPrompt>) {
+    for message in &config.warnings {
+        let _ = on_prompt.send(ConnectPrompt::ConfigWarning {
+            message: message.clone(),
+        });
+    }
+}
+
+/// Sends `prompt` to the frontend and waits for `sftp_answer_prompt`.
+async fn ask(
+    state: &SftpState,
+    on_prompt: &Channel<ConnectPrompt>,
+    prompt: impl FnOnce(String) -> ConnectPrompt,
+) -> Result<PromptAnswer, SftpError> {
+    let prompt_id = format!("prompt-{}", state.next_prompt_id.fetch_add(1, Ordering::Relaxed));
+    let (tx, rx) = oneshot::channel();
+    state.prompts.lock().unwrap().insert(prompt_id.clone(), tx);
+
+    if let Err(e) = on_prompt.send(prompt(prompt_id.clone())) {
+        state.prompts.lock().unwrap().remove(&prompt_id);
+        return Err(SftpError::Connection(format!("Failed to ask the user: {}", e)));
+    }
+
+    rx.await
+        .map_err(|_| SftpError::Connection("Prompt was dismissed".to_string()))
+}
+
+/// Runs the SSH handshake with `target`, directly or tunnelled through the
+/// `via` session.
+async fn connect_ssh(
+    state: &SftpState,
+    target: &Target,
+    via: Option<&Handle<SshHandler>>,
+    unknown_key: &Arc<Mutex<Option<PublicKey>>>,
+) -> Result<Handle<SshHandler>, SftpError> {
     // Configure SSH client
-    let config = client::Config {
-        inactivity_timeout: Some(std::time::Duration::from_secs(300)),
-        keepalive_interval: Some(std::time::Duration::from_secs(30)),
+    let config = Arc::new(client::Config {
+        inactivity_timeout: Some(Duration::from_secs(300)),
+        keepalive_interval: Some(Duration::from_secs(30)),
         keepalive_max: 3,
         ..Default::default()
+    });
+
+    let handler = SshHandler {
+        host: target.host.clone(),
+        port: target.port,
+        trust_stores: state.trust_stores.clone(),
+        session_trust: Arc::clone(&state.session_trust),
+        unknown_key: Arc::clone(unknown_key),
     };
 
-    let addr = format!("{}:22", device_ip);
+    let connect = async {
+        match via {
+            Some(jump) => {
+                let channel = jump
+                    .channel_open_direct_tcpip(&target.host, target.port.into(), "127.0.0.1", 0)
+                    .await?;
+                client::connect_stream(config, channel.into_stream(), handler).await
+            }
+            None => client::connect(config, (target.host.as_str(), target.port), handler).await,
+        }
+    };
 
     // Connect with timeout
-    let mut ssh_handle = tokio::time::timeout(
-        std::time::Duration::from_secs(30),
-        client::connect(Arc::new(config), &addr, SshHandler),
-    )
-    .await
-    .map_err(|_| SftpError::Timeout("SSH connection timed out".to_string()))?
-    .map_err(|e| SftpError::Connection(e.to_string()))?;
+    tokio::time::timeout(target.connect_timeout, connect)
+        .await
+        .map_err(|_| {
+            SftpError::Timeout(format!("SSH connection to {} timed out", target.host))
+        })?
+}
 
-    // Try SSH agent authentication first, then key file auth
-    let authenticated = try_ssh_agent_auth(&mut ssh_handle, username).await
-        || try_key_file_auth(&mut ssh_handle, username).await;
+/// Connects, asking the user about a host key no trust store knows. The
+/// handshake can't wait on the user, so an accepted key is recorded and the
+/// connection made again.
+async fn connect_verified(
+    state: &SftpState,
+    target: &Target,
+    via: Option<&Handle<SshHandler>>,
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<Handle<SshHandler>, SftpError> {
+    let (host, port) = (target.host.as_str(), target.port);
+    let unknown_key = Arc::new(Mutex::new(None));
+    let err = match connect_ssh(state, target, via, &unknown_key).await {
+        Ok(handle) => return Ok(handle),
+        Err(err) => err,
+    };
+    let key = unknown_key.lock().unwrap().take();
+    let Some(key) = key else {
+        return Err(err);
+    };
+
+    let fingerprint = known_hosts::fingerprint(&key);
+    let answer = ask(state, on_prompt, |prompt_id| ConnectPrompt::UnknownHostKey {
+        prompt_id,
+        host: host.to_string(),
+        key_type: key.algorithm().to_string(),
+        fingerprint: fingerprint.clone(),
+    })
+    .await?;
+
+    match answer {
+        PromptAnswer::TrustAlways => state.trust_stores.remember(host, port, &key)?,
+        PromptAnswer::TrustOnce => {
+            state
+                .session_trust
+                .lock()
+                .unwrap()
+                .insert((host.to_string(), port, fingerprint.clone()));
+        }
+        // Anything but trust is taken as a no
+        _ => {
+            return Err(SftpError::HostKeyRejected {
+                host: host.to_string(),
+                fingerprint,
+            });
+        }
+    }
 
-    if !authenticated {
-        return Err(SftpError::Authentication(
-            "Authentication failed. Ensure SSH agent is running with your keys loaded, or that ~/.ssh/id_ed25519 or ~/.ssh/id_rsa exists.".to_string(),
-        ));
+    // Still unknown means the host answered with another key this time
+    let result = connect_ssh(state, target, via, &unknown_key).await;
+    if unknown_key.lock().unwrap().is_some() {
+        return Err(SftpError::HostKey(format!(
+            "{} presented a different key after {} was accepted",
+            host, fingerprint
+        )));
     }
+    result
+}
+
+/// Tries public keys (agent, then key files), keyboard-interactive and
+/// password in OpenSSH's order, skipping what the server doesn't offer. A
+/// method that only partly succeeds narrows the list for the next one.
+async fn authenticate(
+    state: &SftpState,
+    handle: &mut Handle<SshHandler>,
+    target: &Target,
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<(), SftpError> {
+    let mut tried = Vec::new();
+
+    // The "none" request tells us which methods the server accepts
+    let offered = match handle.authenticate_none(&target.user).await? {
+        AuthResult::Success => return Ok(()),
+        AuthResult::Failure {
+            remaining_methods, ..
+        } => remaining_methods,
+    };
+    let offers = |kind: MethodKind| offered.contains(&kind);
 
+    if offers(MethodKind::PublicKey)
+        && (try_ssh_agent_auth(handle, target, &mut tried).await
+            || try_key_file_auth(state, handle, target, on_prompt, &mut tried).await?)
+    {
+        return Ok(());
+    }
+    if offers(MethodKind::KeyboardInteractive)
+        && try_keyboard_interactive_auth(state, handle, target, on_prompt, &mut tried).await?
+    {
+        return Ok(());
+    }
+    if offers(MethodKind::Password)
+        && try_password_auth(state, handle, target, on_prompt, &mut tried).await?
+    {
+        return Ok(());
+    }
+
+    Err(SftpError::Authentication {
+        user: target.user.clone(),
+        host: target.host.clone(),
+        tried,
+    })
+}
+
+/// Connects and authenticates to `target`, hopping through `jumps` in order,
+/// and through any ProxyJump the first hop's own config names before it.
+/// The jump sessions are returned too, as the tunnel closes with them, and
+/// the hops they went through.
+async fn open_session(
+    state: &SftpState,
+    ssh_config: &SshConfig,
+    target: &Target,
+    jumps: &[JumpHost],
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<(Handle<SshHandler>, Vec<Handle<SshHandler>>, Vec<String>), SftpError> {
+    let hops = ssh_config.jump_chain(jumps).map_err(SftpError::Config)?;
+    let mut chain: Vec<Handle<SshHandler>> = Vec::new();
+    let mut specs = Vec::new();
+    for (jump, config) in hops {
+        warn_config(&config, on_prompt);
+        let jump_target = Target::new(&config, &jump.host, jump.port, jump.user.clone());
+        let mut handle = connect_verified(state, &jump_target, chain.last(), on_prompt).await?;
+        authenticate(state, &mut handle, &jump_target, on_prompt).await?;
+        chain.push(handle);
+        specs.push(jump_target.jump_spec());
+    }
+
+    let mut handle = connect_verified(state, target, chain.last(), on_prompt).await?;
+    authenticate(state, &mut handle, target, on_prompt).await?;
+    Ok((handle, chain, specs))
+}
+
+/// Looks the device up in `~/.ssh/config` by hostname (or by IP when there is
+/// none), then opens the SFTP session.
+async fn establish_connection(
+    state: &SftpState,
+    device_ip: &str,
+    device_hostname: Option<&str>,
+    username: Option<String>,
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<(Handle<SshHandler>, Vec<Handle<SshHandler>>, SshEndpoint, SftpSession), SftpError> {
+    let ssh_config = SshConfig::load().map_err(SftpError::Config)?;
+    let config = ssh_config
+        .resolve(device_hostname.unwrap_or(device_ip))
+        .map_err(SftpError::Config)?;
+    warn_config(&config, on_prompt);
+    let target = Target::new(&config, device_ip, None, username);
+
+    let (ssh_handle, jump_handles, jumps) =
+        open_session(state, &ssh_config, &target, &config.proxy_jump, on_prompt).await?;
+
+    let sftp = open_sftp(&ssh_handle).await?;
+
+    let endpoint = SshEndpoint {
+        host: target.host,
+        port: target.port,
+        user: target.user,
+        jumps,
+    };
+    Ok((ssh_handle, jump_handles, endpoint, sftp))
+}
+
+/// Opens a channel on `ssh_handle` and starts the SFTP subsystem on it.
+async fn open_sftp(ssh_handle: &Handle<SshHandler>) -> Result<SftpSession, SftpError> {
     // Open channel and request SFTP subsystem
     let channel = ssh_handle
         .channel_open_session()
@@ -218,14 +847,226 @@ async fn establish_connection(
         .map_err(|e| SftpError::Connection(format!("Failed to request SFTP subsystem: {}", e)))?;
 
     // Create SFTP session from channel
-    let sftp = SftpSession::new(channel.into_stream())
+    SftpSession::new(channel.into_stream())
+        .await
+        .map_err(|e| SftpError::SftpOperation(format!("Failed to create SFTP session: {}", e)))
+}
+
+/// The connection to `device_ip`. The map is only locked for the lookup, so
+/// a long transfer doesn't hold up connecting or disconnecting.
+pub(crate) async fn get_connection(
+    state: &SftpState,
+    device_ip: &str,
+) -> Result<Arc<SftpConnection>, String> {
+    state
+        .connections
+        .read()
+        .await
+        .get(device_ip)
+        .cloned()
+        .ok_or_else(|| "Not connected to this device".to_string())
+}
+
+/// Replaces the dropped connection `stale` with one made the same way,
+/// unless another task already did or the device was disconnected since.
+async fn reconnect(
+    state: &SftpState,
+    stale: &Arc<SftpConnection>,
+) -> Result<Arc<SftpConnection>, SftpError> {
+    let (ssh_handle, jump_handles, endpoint, sftp) = establish_connection(
+        state,
+        &stale.device_ip,
+        stale.device_hostname.as_deref(),
+        Some(stale.endpoint.user.clone()),
+        &stale.on_prompt,
+    )
+    .await?;
+    let fresh = Arc::new(SftpConnection::new(
+        ssh_handle,
+        jump_handles,
+        sftp,
+        stale.device_ip.clone(),
+        stale.device_hostname.clone(),
+        endpoint,
+        stale.on_prompt.clone(),
+    ));
+
+    let mut connections = state.connections.write().await;
+    match connections.get(&stale.device_ip) {
+        Some(current) if Arc::ptr_eq(current, stale) => {
+            connections.insert(stale.device_ip.clone(), Arc::clone(&fresh));
+            Ok(fresh)
+        }
+        Some(current) => Ok(Arc::clone(current)),
+        None => Err(SftpError::NotConnected(stale.device_ip.clone())),
+    }
+}
+
+/// Whether an SFTP error is worth another attempt: an I/O error, the
+/// channel or session going away, or the server not answering in time.
+fn is_transient(err: &SftpClientError) -> bool {
+    match err {
+        SftpClientError::Status(status) => matches!(
+            status.status_code,
+            StatusCode::NoConnection | StatusCode::ConnectionLost
+        ),
+        SftpClientError::IO(_) | SftpClientError::Timeout => true,
+        // How russh-sftp reports its channel closing under a request; any
+        // other unexpected behaviour is the server's and will happen again
+        SftpClientError::UnexpectedBehavior(message) => {
+            message == "session closed" || message == "recv none message"
+        }
+        SftpClientError::Limited(_) | SftpClientError::UnexpectedPacket => false,
+    }
+}
+
+fn sftp_failure(context: &str, err: SftpClientError) -> TransferFailure {
+    let message = format!("{}: {}", context, err);
+    if is_transient(&err) {
+        TransferFailure::Transient(message)
+    } else {
+        TransferFailure::Fatal(message)
+    }
+}
+
+/// Quotes `text` as a single POSIX shell word.
+pub(crate) fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// How a command run over an exec channel ended.
+pub(crate) struct ExecExit {
+    /// `None` if the channel closed without one, as when the command is
+    /// killed by a signal.
+    pub status: Option<u32>,
+    pub stderr: String,
+}
+
+/// Runs `command` on the device, handing its stdout to `on_stdout` as it
+/// arrives. Cancelling sends the command SIGTERM and closes the channel.
+async fn exec_streaming(
+    ssh_handle: &Handle<SshHandler>,
+    command: &str,
+    cancel_token: &CancellationToken,
+    on_stdout: &mut (dyn FnMut(&[u8]) + Send),
+) -> Result<ExecExit, String> {
+    let mut channel = ssh_handle
+        .channel_open_session()
+        .await
+        .map_err(|e| format!("Failed to open channel: {}", e))?;
+    channel
+        .exec(true, command)
         .await
-        .map_err(|e| SftpError::SftpOperation(format!("Failed to create SFTP session: {}", e)))?;
+        .map_err(|e| format!("Failed to run `{}`: {}", command, e))?;
+
+    let mut stderr = Vec::new();
+    let mut status = None;
+    loop {
+        let msg = tokio::select! {
+            _ = cancel_token.cancelled() => {
+                channel.signal(russh::Sig::TERM).await.ok();
+                channel.close().await.ok();
+                return Err("Transfer cancelled".to_string());
+            }
+            msg = channel.wait() => msg,
+        };
+        match msg {
+            Some(ChannelMsg::Data { data }) => on_stdout(&data),
+            Some(ChannelMsg::ExtendedData { data, ext: 1 }) => stderr.extend_from_slice(&data),
+            Some(ChannelMsg::ExitStatus { exit_status }) => status = Some(exit_status),
+            Some(_) => {}
+            None => break,
+        }
+    }
+
+    Ok(ExecExit {
+        status,
+        stderr: String::from_utf8_lossy(&stderr).trim().to_string(),
+    })
+}
+
+/// Runs `command` on the device over an exec channel and returns its stdout.
+async fn exec_command(ssh_handle: &Handle<SshHandler>, command: &str) -> Result<Vec<u8>, String> {
+    let mut stdout = Vec::new();
+    let exit = exec_streaming(
+        ssh_handle,
+        command,
+        &CancellationToken::new(),
+        &mut |data| stdout.extend_from_slice(data),
+    )
+    .await?;
+
+    match exit.status {
+        Some(0) => Ok(stdout),
+        Some(status) => Err(format!(
+            "`{}` exited with status {}: {}",
+            command, status, exit.stderr
+        )),
+        None => Err(format!("`{}` ended without an exit status", command)),
+    }
+}
 
-    Ok((ssh_handle, sftp))
+/// SHA-256 of a file on the device, with `sha256sum` or, on macOS and the
+/// BSDs, `shasum`.
+async fn remote_sha256(ssh_handle: &Handle<SshHandler>, path: &str) -> Result<String, String> {
+    let path = shell_quote(path);
+    let command = format!(
+        "sha256sum {} 2>/dev/null || shasum -a 256 {}",
+        path, path
+    );
+    let output = exec_command(ssh_handle, &command).await?;
+    String::from_utf8_lossy(&output)
+        .split_whitespace()
+        .next()
+        .map(|sum| sum.to_lowercase())
+        .ok_or_else(|| "Remote checksum command printed nothing".to_string())
 }
 
-async fn try_ssh_agent_auth(handle: &mut Handle<SshHandler>, username: &str) -> bool {
+pub(crate) async fn local_sha256(path: &str) -> Result<String, String> {
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut file =
+            File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Ok(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .map_err(|e| format!("Checksum task failed: {}", e))?
+}
+
+/// Key files to offer: the configured IdentityFiles, or the usual defaults.
+fn identity_files(target: &Target) -> Vec<PathBuf> {
+    if !target.identity_files.is_empty() {
+        return target.identity_files.clone();
+    }
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
+    vec![
+        PathBuf::from(format!("{}/.ssh/id_ed25519", home)),
+        PathBuf::from(format!("{}/.ssh/id_rsa", home)),
+    ]
+}
+
+/// Public halves of the identity files, from `<file>.pub` or the key itself.
+fn identity_public_keys(target: &Target) -> Vec<PublicKey> {
+    identity_files(target)
+        .iter()
+        .filter_map(|path| {
+            let mut pub_path = path.clone().into_os_string();
+            pub_path.push(".pub");
+            russh::keys::load_public_key(Path::new(&pub_path))
+                .or_else(|_| russh::keys::load_secret_key(path, None).map(|k| k.public_key().clone()))
+                .ok()
+        })
+        .collect()
+}
+
+async fn try_ssh_agent_auth(
+    handle: &mut Handle<SshHandler>,
+    target: &Target,
+    tried: &mut Vec<String>,
+) -> bool {
     // Check if SSH_AUTH_SOCK is set
     let auth_sock = match std::env::var("SSH_AUTH_SOCK") {
         Ok(sock) => sock,
@@ -239,15 +1080,24 @@ async fn try_ssh_agent_auth(handle: &mut Handle<SshHandler>, username: &str) ->
     };
 
     // Get identities from agent
-    let identities = match agent.request_identities().await {
+    let mut identities = match agent.request_identities().await {
         Ok(ids) => ids,
         Err(_) => return false,
     };
 
+    // IdentitiesOnly: only agent keys that match a configured IdentityFile
+    if target.identities_only {
+        let allowed = identity_public_keys(target);
+        identities.retain(|identity| allowed.contains(identity));
+    }
+    if !identities.is_empty() {
+        tried.push("publickey (agent)".to_string());
+    }
+
     // Try each identity with the agent as signer
     for identity in identities {
         match handle
-            .authenticate_publickey_with(username, identity, None, &mut agent)
+            .authenticate_publickey_with(&target.user, identity, None, &mut agent)
             .await
         {
             Ok(result) => {
@@ -262,48 +1112,243 @@ async fn try_ssh_agent_auth(handle: &mut Handle<SshHandler>, username: &str) ->
     false
 }
 
-async fn try_key_file_auth(handle: &mut Handle<SshHandler>, username: &str) -> bool {
-    let home = std::env::var("HOME").unwrap_or_else(|_| "/root".to_string());
-    let key_paths = [
-        format!("{}/.ssh/id_ed25519", home),
-        format!("{}/.ssh/id_rsa", home),
-    ];
-
-    for key_path in key_paths {
-        let path = std::path::Path::new(&key_path);
-        if !path.exists() {
+async fn try_key_file_auth(
+    state: &SftpState,
+    handle: &mut Handle<SshHandler>,
+    target: &Target,
+    on_prompt: &Channel<ConnectPrompt>,
+    tried: &mut Vec<String>,
+) -> Result<bool, SftpError> {
+    for key_path in identity_files(target) {
+        if !key_path.exists() {
             continue;
         }
 
-        // Try to load the key (without passphrase first) using russh's key loader
-        let key_pair = match russh::keys::load_secret_key(&key_path, None) {
-            Ok(key) => key,
-            Err(_) => continue,
+        let Some(key_pair) = load_identity(state, &key_path, on_prompt).await? else {
+            continue;
         };
+        tried.push(format!("publickey ({})", key_path.display()));
 
         // Create a key with the default hash algorithm for the key type
-        let key_with_alg = PrivateKeyWithHashAlg::new(Arc::new(key_pair), None);
+        let key_with_alg = PrivateKeyWithHashAlg::new(key_pair, None);
 
-        match handle.authenticate_publickey(username, key_with_alg).await {
+        match handle.authenticate_publickey(&target.user, key_with_alg).await {
             Ok(result) => {
                 if result.success() {
-                    return true;
+                    return Ok(true);
                 }
             }
             Err(_) => continue,
         }
     }
 
-    false
+    Ok(false)
+}
+
+/// Loads a key file, unlocking it first if it is encrypted. `None` when the
+/// file can't be used or the user skipped it.
+async fn load_identity(
+    state: &SftpState,
+    key_path: &Path,
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<Option<Arc<PrivateKey>>, SftpError> {
+    if let Some(key) = state.key_cache.lock().unwrap().get(key_path) {
+        return Ok(Some(Arc::clone(key)));
+    }
+
+    let key = match load_key(key_path, None).await {
+        Ok(key) => key,
+        Err(russh::keys::Error::KeyIsEncrypted) => {
+            match unlock_key(state, key_path, on_prompt).await? {
+                Some(key) => key,
+                None => return Ok(None),
+            }
+        }
+        Err(_) => return Ok(None),
+    };
+
+    let key = Arc::new(key);
+    state
+        .key_cache
+        .lock()
+        .unwrap()
+        .insert(key_path.to_path_buf(), Arc::clone(&key));
+    Ok(Some(key))
+}
+
+/// Decrypting runs the key's KDF, which can take a second, so it is kept off
+/// the async workers.
+async fn load_key(
+    key_path: &Path,
+    passphrase: Option<String>,
+) -> Result<PrivateKey, russh::keys::Error> {
+    let key_path = key_path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        russh::keys::load_secret_key(key_path, passphrase.as_deref())
+    })
+    .await
+    .unwrap_or(Err(russh::keys::Error::CouldNotReadKey))
+}
+
+/// Tries the passphrase saved in the keyring, then asks the user.
+async fn unlock_key(
+    state: &SftpState,
+    key_path: &Path,
+    on_prompt: &Channel<ConnectPrompt>,
+) -> Result<Option<PrivateKey>, SftpError> {
+    let account = format!("passphrase:{}", key_path.display());
+    if let Some(passphrase) = keyring::get(&account) {
+        if let Ok(key) = load_key(key_path, Some(passphrase)).await {
+            return Ok(Some(key));
+        }
+        // The key was re-encrypted since; forget the stale passphrase
+        keyring::delete(&account).ok();
+    }
+
+    for attempt in 1..=SECRET_ATTEMPTS {
+        let answer = ask(state, on_prompt, |prompt_id| ConnectPrompt::KeyPassphrase {
+            prompt_id,
+            key_path: key_path.display().to_string(),
+            attempt,
+        })
+        .await?;
+        let PromptAnswer::Secret { value, remember } = answer else {
+            return Ok(None);
+        };
+
+        if let Ok(key) = load_key(key_path, Some(value.clone())).await {
+            // Not being able to save it only means being asked again
+            if remember {
+                keyring::set(&account, &value).ok();
+            }
+            return Ok(Some(key));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn try_password_auth(
+    state: &SftpState,
+    handle: &mut Handle<SshHandler>,
+    target: &Target,
+    on_prompt: &Channel<ConnectPrompt>,
+    tried: &mut Vec<String>,
+) -> Result<bool, SftpError> {
+    let account = format!("password:{}@{}:{}", target.user, target.host, target.port);
+    if let Some(password) = keyring::get(&account) {
+        tried.push("password (keyring)".to_string());
+        if handle
+            .authenticate_password(&target.user, password)
+            .await?
+            .success()
+        {
+            return Ok(true);
+        }
+        keyring::delete(&account).ok();
+    }
+
+    for attempt in 1..=SECRET_ATTEMPTS {
+        let answer = ask(state, on_prompt, |prompt_id| ConnectPrompt::Password {
+            prompt_id,
+            user: target.user.clone(),
+            host: target.host.clone(),
+            attempt,
+        })
+        .await?;
+        let PromptAnswer::Secret { value, remember } = answer else {
+            return Ok(false);
+        };
+        if attempt == 1 {
+            tried.push("password".to_string());
+        }
+
+        if handle
+            .authenticate_password(&target.user, value.clone())
+            .await?
+            .success()
+        {
+            if remember {
+                keyring::set(&account, &value).ok();
+            }
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Relays the server's questions (password, OTP code, ...) to the user until
+/// it accepts or rejects the answers. Nothing here is saved, as the answers
+/// are usually one-time codes.
+async fn try_keyboard_interactive_auth(
+    state: &SftpState,
+    handle: &mut Handle<SshHandler>,
+    target: &Target,
+    on_prompt: &Channel<ConnectPrompt>,
+    tried: &mut Vec<String>,
+) -> Result<bool, SftpError> {
+    tried.push("keyboard-interactive".to_string());
+    let mut response = handle
+        .authenticate_keyboard_interactive_start(&target.user, None::<String>)
+        .await?;
+
+    loop {
+        let (name, instructions, prompts) = match response {
+            KeyboardInteractiveAuthResponse::Success => return Ok(true),
+            KeyboardInteractiveAuthResponse::Failure { .. } => return Ok(false),
+            KeyboardInteractiveAuthResponse::InfoRequest {
+                name,
+                instructions,
+                prompts,
+            } => (name, instructions, prompts),
+        };
+
+        // Servers may send a round with no questions, e.g. just a banner
+        let responses = if prompts.is_empty() {
+            Vec::new()
+        } else {
+            let answer = ask(state, on_prompt, |prompt_id| {
+                ConnectPrompt::KeyboardInteractive {
+                    prompt_id,
+                    user: target.user.clone(),
+                    host: target.host.clone(),
+                    name,
+                    instructions,
+                    prompts: prompts
+                        .iter()
+                        .map(|p| InteractivePrompt {
+                            prompt: p.prompt.clone(),
+                            echo: p.echo,
+                        })
+                        .collect(),
+                }
+            })
+            .await?;
+            match answer {
+                PromptAnswer::Responses(responses) if responses.len() == prompts.len() => {
+                    responses
+                }
+                _ => return Ok(false),
+            }
+        };
+
+        response = handle
+            .authenticate_keyboard_interactive_respond(responses)
+            .await?;
+    }
 }
 
 // ----- Tauri Commands -----
 
 #[tauri::command]
 pub async fn sftp_connect(
+    app: AppHandle,
     state: State<'_, SftpState>,
     device_ip: String,
+    device_hostname: Option<String>,
     username: Option<String>,
+    on_prompt: Channel<ConnectPrompt>,
 ) -> Result<(), String> {
     // Check if already connected
     {
@@ -313,20 +1358,50 @@ pub async fn sftp_connect(
         }
     }
 
-    let user = username
-        .unwrap_or_else(|| std::env::var("USER").unwrap_or_else(|_| "root".to_string()));
-
-    let (ssh_handle, sftp) = establish_connection(&device_ip, &user).await?;
+    let (ssh_handle, jump_handles, endpoint, sftp) = establish_connection(
+        &state,
+        &device_ip,
+        device_hostname.as_deref(),
+        username,
+        &on_prompt,
+    )
+    .await?;
 
-    let connection = SftpConnection {
+    let connection = SftpConnection::new(
         ssh_handle,
+        jump_handles,
         sftp,
-        device_ip: device_ip.clone(),
-        username: user,
-    };
+        device_ip.clone(),
+        device_hostname,
+        endpoint,
+        on_prompt,
+    );
+
+    state
+        .connections
+        .write()
+        .await
+        .insert(device_ip, Arc::new(connection));
 
-    state.connections.write().await.insert(device_ip, connection);
+    // Queued transfers for this device can start now
+    transfer_queue::pump(&app);
+
+    Ok(())
+}
 
+#[tauri::command]
+pub async fn sftp_answer_prompt(
+    state: State<'_, SftpState>,
+    prompt_id: String,
+    answer: PromptAnswer,
+) -> Result<(), String> {
+    let sender = state
+        .prompts
+        .lock()
+        .unwrap()
+        .remove(&prompt_id)
+        .ok_or_else(|| "This prompt is no longer waiting for an answer".to_string())?;
+    let _ = sender.send(answer);
     Ok(())
 }
 
@@ -342,10 +1417,7 @@ pub async fn sftp_list_dir(
     device_ip: String,
     path: String,
 ) -> Result<DirectoryListing, String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(&device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = get_connection(&state, &device_ip).await?.sftp().await?;
 
     let normalized_path = if path.is_empty() {
         "/".to_string()
@@ -353,8 +1425,7 @@ pub async fn sftp_list_dir(
         path
     };
 
-    let dir_entries = connection
-        .sftp
+    let dir_entries = sftp
         .read_dir(&normalized_path)
         .await
         .map_err(|e| format!("Failed to read directory: {}", e))?;
@@ -414,13 +1485,9 @@ pub async fn sftp_mkdir(
     device_ip: String,
     path: String,
 ) -> Result<(), String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(&device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = get_connection(&state, &device_ip).await?.sftp().await?;
 
-    connection
-        .sftp
+    sftp
         .create_dir(&path)
         .await
         .map_err(|e| format!("Failed to create directory: {}", e))?;
@@ -435,13 +1502,9 @@ pub async fn sftp_read_file(
     path: String,
     max_bytes: Option<usize>,
 ) -> Result<String, String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(&device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = get_connection(&state, &device_ip).await?.sftp().await?;
 
-    let mut file = connection
-        .sftp
+    let mut file = sftp
         .open(&path)
         .await
         .map_err(|e| format!("Failed to open file: {}", e))?;
@@ -465,13 +1528,9 @@ pub async fn sftp_delete(
     path: String,
     recursive: bool,
 ) -> Result<(), String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(&device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = get_connection(&state, &device_ip).await?.sftp().await?;
 
-    let metadata = connection
-        .sftp
+    let metadata = sftp
         .metadata(&path)
         .await
         .map_err(|e| format!("Failed to stat path: {}", e))?;
@@ -479,17 +1538,15 @@ pub async fn sftp_delete(
     if metadata.is_dir() {
         if recursive {
             // Recursively delete directory contents
-            delete_recursive(&connection.sftp, &path).await?;
+            delete_recursive(&sftp, &path).await?;
         } else {
-            connection
-                .sftp
+            sftp
                 .remove_dir(&path)
                 .await
                 .map_err(|e| format!("Failed to remove directory (may not be empty): {}", e))?;
         }
     } else {
-        connection
-            .sftp
+        sftp
             .remove_file(&path)
             .await
             .map_err(|e| format!("Failed to delete file: {}", e))?;
@@ -498,7 +1555,7 @@ pub async fn sftp_delete(
     Ok(())
 }
 
-async fn delete_recursive(sftp: &SftpSession, path: &str) -> Result<(), String> {
+pub(crate) async fn delete_recursive(sftp: &SftpSession, path: &str) -> Result<(), String> {
     let entries = sftp
         .read_dir(path)
         .await
@@ -537,13 +1594,9 @@ pub async fn sftp_rename(
     old_path: String,
     new_path: String,
 ) -> Result<(), String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(&device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = get_connection(&state, &device_ip).await?.sftp().await?;
 
-    connection
-        .sftp
+    sftp
         .rename(&old_path, &new_path)
         .await
         .map_err(|e| format!("Failed to rename: {}", e))?;
@@ -558,45 +1611,65 @@ pub async fn sftp_download(
     remote_path: String,
     local_path: String,
     transfer_id: String,
+    verify_checksum: Option<bool>,
     on_progress: Channel<TransferEvent>,
 ) -> Result<(), String> {
-    // Create cancellation token and register transfer
-    let cancel_token = CancellationToken::new();
-    {
-        let mut transfers = state.transfers.write().await;
-        transfers.insert(
-            transfer_id.clone(),
-            TransferJob {
-                cancel_token: cancel_token.clone(),
-                direction: TransferDirection::Download,
-            },
-        );
-    }
+    let transfer = FileTransfer {
+        device_ip,
+        direction: TransferDirection::Download,
+        local_path,
+        remote_path,
+        verify_checksum: verify_checksum.unwrap_or(false),
+    };
+    track_transfer(&state, transfer_id, &transfer, &on_progress).await
+}
+
+#[tauri::command]
+pub async fn sftp_upload(
+    state: State<'_, SftpState>,
+    device_ip: String,
+    local_path: String,
+    remote_path: String,
+    transfer_id: String,
+    verify_checksum: Option<bool>,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    let transfer = FileTransfer {
+        device_ip,
+        direction: TransferDirection::Upload,
+        local_path,
+        remote_path,
+        verify_checksum: verify_checksum.unwrap_or(false),
+    };
+    track_transfer(&state, transfer_id, &transfer, &on_progress).await
+}
 
-    let filename = std::path::Path::new(&remote_path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| remote_path.clone());
+/// Runs `transfer` under `transfer_id` so it can be cancelled, and reports
+/// how it ended.
+async fn track_transfer(
+    state: &SftpState,
+    transfer_id: String,
+    transfer: &FileTransfer,
+    on_progress: &Channel<TransferEvent>,
+) -> Result<(), String> {
+    let cancel_token = state
+        .begin_transfer(&transfer_id, transfer.direction.clone())
+        .await;
 
-    let result = download_file(
-        &state,
-        &device_ip,
-        &remote_path,
-        &local_path,
-        &filename,
-        &cancel_token,
-        &on_progress,
-    )
-    .await;
+    let report = |event| {
+        let _ = on_progress.send(event);
+    };
+    let result = run_transfer(state, transfer, &cancel_token, &report).await;
+    if result.is_err() && cancel_token.is_cancelled() {
+        discard_partial(state, transfer).await;
+    }
 
-    // Clean up transfer tracking
-    state.transfers.write().await.remove(&transfer_id);
+    state.end_transfer(&transfer_id).await;
 
+    let filename = transfer.filename();
     match result {
         Ok(_) => {
-            let _ = on_progress.send(TransferEvent::Complete {
-                filename: filename.clone(),
-            });
+            let _ = on_progress.send(TransferEvent::Complete { filename });
             Ok(())
         }
         Err(e) => {
@@ -609,52 +1682,163 @@ pub async fn sftp_download(
     }
 }
 
-async fn download_file(
-    state: &State<'_, SftpState>,
+/// Moves the file, retrying transient failures with exponential backoff.
+/// Each attempt resumes from what the previous ones left behind; a failure
+/// that ends the transfer removes it. A cancelled transfer leaves it, as a
+/// pause does too, for the caller to `discard_partial` if it wants.
+pub(crate) async fn run_transfer(
+    state: &SftpState,
+    transfer: &FileTransfer,
+    cancel_token: &CancellationToken,
+    on_progress: &ProgressFn<'_>,
+) -> Result<(), String> {
+    let filename = transfer.filename();
+    let mut attempt = 1;
+    loop {
+        let result = match live_connection(state, &transfer.device_ip).await {
+            Ok(connection) => match transfer.direction {
+                TransferDirection::Download => {
+                    download_attempt(&connection, transfer, &filename, cancel_token, on_progress)
+                        .await
+                }
+                TransferDirection::Upload => {
+                    upload_attempt(&connection, transfer, &filename, cancel_token, on_progress)
+                        .await
+                }
+            },
+            Err(failure) => Err(failure),
+        };
+
+        let message = match result {
+            Ok(()) => return Ok(()),
+            Err(TransferFailure::Cancelled) => return Err("Transfer cancelled".to_string()),
+            Err(TransferFailure::Fatal(message)) => {
+                discard_partial(state, transfer).await;
+                return Err(message);
+            }
+            Err(TransferFailure::Transient(message)) if attempt >= MAX_TRANSFER_ATTEMPTS => {
+                discard_partial(state, transfer).await;
+                return Err(format!("{} (gave up after {} attempts)", message, attempt));
+            }
+            Err(TransferFailure::Transient(message)) => message,
+        };
+
+        let delay = RETRY_BASE_DELAY
+            .saturating_mul(1 << (attempt - 1))
+            .min(RETRY_MAX_DELAY);
+        attempt += 1;
+        on_progress(TransferEvent::Retrying {
+            filename: filename.clone(),
+            attempt,
+            max_attempts: MAX_TRANSFER_ATTEMPTS,
+            delay_ms: delay.as_millis() as u64,
+            message,
+        });
+
+        tokio::select! {
+            _ = cancel_token.cancelled() => return Err("Transfer cancelled".to_string()),
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+/// The device's connection, made again first if its SSH session dropped.
+async fn live_connection(
+    state: &SftpState,
     device_ip: &str,
-    remote_path: &str,
-    local_path: &str,
+) -> Result<Arc<SftpConnection>, TransferFailure> {
+    let connection = get_connection(state, device_ip)
+        .await
+        .map_err(TransferFailure::Fatal)?;
+    if !connection.ssh_handle.is_closed() {
+        return Ok(connection);
+    }
+    match reconnect(state, &connection).await {
+        Ok(connection) => Ok(connection),
+        Err(e @ SftpError::NotConnected(_)) => Err(TransferFailure::Fatal(e.to_string())),
+        Err(e) => Err(TransferFailure::Transient(format!("Failed to reconnect: {}", e))),
+    }
+}
+
+async fn download_attempt(
+    connection: &Arc<SftpConnection>,
+    transfer: &FileTransfer,
     filename: &str,
     cancel_token: &CancellationToken,
-    on_progress: &Channel<TransferEvent>,
-) -> Result<(), String> {
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    on_progress: &ProgressFn<'_>,
+) -> Result<(), TransferFailure> {
+    let sftp = connection.sftp().await.map_err(TransferFailure::Transient)?;
 
-    let metadata = connection
-        .sftp
-        .metadata(remote_path)
+    let metadata = sftp
+        .metadata(&transfer.remote_path)
         .await
-        .map_err(|e| format!("Failed to stat file: {}", e))?;
+        .map_err(|e| sftp_failure("Failed to stat file", e))?;
 
     let total_bytes = metadata.size.unwrap_or(0);
+    let source = PartialManifest {
+        source: transfer.remote_path.clone(),
+        size: total_bytes,
+        modified: metadata.mtime.map(u64::from),
+    };
+
+    // Pick up a partial download of this same file where it stopped
+    let part_path = format!("{}{}", transfer.local_path, PARTIAL_SUFFIX);
+    let manifest_path = manifest_path(&transfer.local_path);
+    let offset = match tokio::fs::read(&manifest_path).await {
+        Ok(json) if serde_json::from_slice(&json).ok().as_ref() == Some(&source) => {
+            tokio::fs::metadata(&part_path)
+                .await
+                .map_or(0, |m| m.len().min(total_bytes))
+        }
+        _ => 0,
+    };
+    if offset == 0 {
+        tokio::fs::write(&manifest_path, source.to_json())
+            .await
+            .map_err(|e| TransferFailure::Fatal(format!("Failed to write {}: {}", manifest_path, e)))?;
+    }
 
-    let mut remote_file = connection
-        .sftp
-        .open(remote_path)
+    let mut local_file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        // Cut back to `offset` below instead, which keeps a resumed part
+        .truncate(false)
+        .open(&part_path)
+        .await
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to create local file: {}", e)))?;
+    local_file
+        .set_len(offset)
+        .await
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to resize local file: {}", e)))?;
+    local_file
+        .seek(SeekFrom::Start(offset))
         .await
-        .map_err(|e| format!("Failed to open remote file: {}", e))?;
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to seek local file: {}", e)))?;
 
-    let mut local_file =
-        tokio::fs::File::create(local_path)
-            .await
-            .map_err(|e| format!("Failed to create local file: {}", e))?;
+    let mut remote_file = sftp
+        .open(&transfer.remote_path)
+        .await
+        .map_err(|e| sftp_failure("Failed to open remote file", e))?;
+    remote_file
+        .seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|e| TransferFailure::Transient(format!("Failed to seek remote file: {}", e)))?;
 
     let mut buffer = vec![0u8; 65536]; // 64KB buffer
-    let mut bytes_transferred: u64 = 0;
+    let mut bytes_transferred = offset;
 
     loop {
         tokio::select! {
             _ = cancel_token.cancelled() => {
-                drop(local_file);
-                tokio::fs::remove_file(local_path).await.ok();
-                return Err("Transfer cancelled".to_string());
+                // The part file and manifest stay for a pause to resume from;
+                // a cancel clears them up through `discard_partial`
+                local_file.flush().await.ok();
+                return Err(TransferFailure::Cancelled);
             }
             read_result = remote_file.read(&mut buffer) => {
-                let bytes_read = read_result
-                    .map_err(|e| format!("Failed to read from remote: {}", e))?;
+                let bytes_read = read_result.map_err(|e| {
+                    TransferFailure::Transient(format!("Failed to read from remote: {}", e))
+                })?;
 
                 if bytes_read == 0 {
                     break;
@@ -662,11 +1846,11 @@ async fn download_file(
 
                 local_file.write_all(&buffer[..bytes_read])
                     .await
-                    .map_err(|e| format!("Failed to write to local file: {}", e))?;
+                    .map_err(|e| TransferFailure::Fatal(format!("Failed to write to local file: {}", e)))?;
 
                 bytes_transferred += bytes_read as u64;
 
-                let _ = on_progress.send(TransferEvent::Progress {
+                on_progress(TransferEvent::Progress {
                     bytes_transferred,
                     total_bytes,
                     filename: filename.to_string(),
@@ -678,113 +1862,121 @@ async fn download_file(
     local_file
         .flush()
         .await
-        .map_err(|e| format!("Failed to flush local file: {}", e))?;
-
-    Ok(())
-}
-
-#[tauri::command]
-pub async fn sftp_upload(
-    state: State<'_, SftpState>,
-    device_ip: String,
-    local_path: String,
-    remote_path: String,
-    transfer_id: String,
-    on_progress: Channel<TransferEvent>,
-) -> Result<(), String> {
-    // Create cancellation token and register transfer
-    let cancel_token = CancellationToken::new();
-    {
-        let mut transfers = state.transfers.write().await;
-        transfers.insert(
-            transfer_id.clone(),
-            TransferJob {
-                cancel_token: cancel_token.clone(),
-                direction: TransferDirection::Upload,
-            },
-        );
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to flush local file: {}", e)))?;
+    drop(local_file);
+
+    // A mismatch means the file changed underneath us: start over
+    let written = tokio::fs::metadata(&part_path).await.map_or(0, |m| m.len());
+    if written != total_bytes {
+        discard_local_partial(&part_path, &manifest_path).await;
+        return Err(TransferFailure::Transient(format!(
+            "Size mismatch: expected {} bytes, got {}",
+            total_bytes, written
+        )));
     }
-
-    let filename = std::path::Path::new(&local_path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| local_path.clone());
-
-    let result = upload_file(
-        &state,
-        &device_ip,
-        &local_path,
-        &remote_path,
-        &filename,
-        &cancel_token,
-        &on_progress,
-    )
-    .await;
-
-    // Clean up transfer tracking
-    state.transfers.write().await.remove(&transfer_id);
-
-    match result {
-        Ok(_) => {
-            let _ = on_progress.send(TransferEvent::Complete {
-                filename: filename.clone(),
-            });
-            Ok(())
-        }
-        Err(e) => {
-            let _ = on_progress.send(TransferEvent::Error {
-                filename,
-                message: e.clone(),
-            });
-            Err(e)
+    if transfer.verify_checksum {
+        let remote_sum = remote_sha256(&connection.ssh_handle, &transfer.remote_path)
+            .await
+            .map_err(TransferFailure::Transient)?;
+        let local_sum = local_sha256(&part_path).await.map_err(TransferFailure::Fatal)?;
+        if remote_sum != local_sum {
+            discard_local_partial(&part_path, &manifest_path).await;
+            return Err(TransferFailure::Transient(format!(
+                "Checksum mismatch: remote {}, local {}",
+                remote_sum, local_sum
+            )));
         }
     }
+
+    tokio::fs::rename(&part_path, &transfer.local_path)
+        .await
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to move download into place: {}", e)))?;
+    tokio::fs::remove_file(&manifest_path).await.ok();
+
+    Ok(())
 }
 
-async fn upload_file(
-    state: &State<'_, SftpState>,
-    device_ip: &str,
-    local_path: &str,
-    remote_path: &str,
+async fn upload_attempt(
+    connection: &Arc<SftpConnection>,
+    transfer: &FileTransfer,
     filename: &str,
     cancel_token: &CancellationToken,
-    on_progress: &Channel<TransferEvent>,
-) -> Result<(), String> {
-    let metadata = tokio::fs::metadata(local_path)
+    on_progress: &ProgressFn<'_>,
+) -> Result<(), TransferFailure> {
+    let metadata = tokio::fs::metadata(&transfer.local_path)
         .await
-        .map_err(|e| format!("Failed to get local file info: {}", e))?;
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to get local file info: {}", e)))?;
 
     let total_bytes = metadata.len();
+    let source = PartialManifest {
+        source: transfer.local_path.clone(),
+        size: total_bytes,
+        modified: metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs()),
+    };
 
-    let mut local_file = tokio::fs::File::open(local_path)
+    let mut local_file = tokio::fs::File::open(&transfer.local_path)
         .await
-        .map_err(|e| format!("Failed to open local file: {}", e))?;
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to open local file: {}", e)))?;
 
-    let connections = state.connections.read().await;
-    let connection = connections
-        .get(device_ip)
-        .ok_or_else(|| "Not connected to this device".to_string())?;
+    let sftp = connection.sftp().await.map_err(TransferFailure::Transient)?;
+
+    // Pick up a partial upload of this same file where it stopped
+    let part_path = format!("{}{}", transfer.remote_path, PARTIAL_SUFFIX);
+    let manifest_path = manifest_path(&transfer.remote_path);
+    let offset = match sftp.read(&manifest_path).await {
+        Ok(json) if serde_json::from_slice(&json).ok().as_ref() == Some(&source) => sftp
+            .metadata(&part_path)
+            .await
+            .map_or(0, |m| m.size.unwrap_or(0).min(total_bytes)),
+        _ => 0,
+    };
+
+    let mut remote_file = if offset == 0 {
+        let mut manifest = sftp
+            .create(&manifest_path)
+            .await
+            .map_err(|e| sftp_failure("Failed to create upload manifest", e))?;
+        manifest
+            .write_all(&source.to_json())
+            .await
+            .map_err(|e| TransferFailure::Transient(format!("Failed to write upload manifest: {}", e)))?;
+        manifest.shutdown().await.ok();
 
-    let mut remote_file = connection
-        .sftp
-        .create(remote_path)
+        sftp.create(&part_path)
+            .await
+            .map_err(|e| sftp_failure("Failed to create remote file", e))?
+    } else {
+        sftp.open_with_flags(&part_path, OpenFlags::WRITE)
+            .await
+            .map_err(|e| sftp_failure("Failed to open remote file", e))?
+    };
+    remote_file
+        .seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|e| TransferFailure::Transient(format!("Failed to seek remote file: {}", e)))?;
+    local_file
+        .seek(SeekFrom::Start(offset))
         .await
-        .map_err(|e| format!("Failed to create remote file: {}", e))?;
+        .map_err(|e| TransferFailure::Fatal(format!("Failed to seek local file: {}", e)))?;
 
     let mut buffer = vec![0u8; 65536]; // 64KB buffer
-    let mut bytes_transferred: u64 = 0;
+    let mut bytes_transferred = offset;
 
     loop {
         tokio::select! {
             _ = cancel_token.cancelled() => {
-                drop(remote_file);
-                // Try to delete partial remote file
-                connection.sftp.remove_file(remote_path).await.ok();
-                return Err("Transfer cancelled".to_string());
+                // The part file and manifest stay for a pause to resume from;
+                // a cancel clears them up through `discard_partial`
+                return Err(TransferFailure::Cancelled);
             }
             read_result = local_file.read(&mut buffer) => {
-                let bytes_read = read_result
-                    .map_err(|e| format!("Failed to read from local file: {}", e))?;
+                let bytes_read = read_result.map_err(|e| {
+                    TransferFailure::Fatal(format!("Failed to read from local file: {}", e))
+                })?;
 
                 if bytes_read == 0 {
                     break;
@@ -792,11 +1984,11 @@ async fn upload_file(
 
                 remote_file.write_all(&buffer[..bytes_read])
                     .await
-                    .map_err(|e| format!("Failed to write to remote file: {}", e))?;
+                    .map_err(|e| TransferFailure::Transient(format!("Failed to write to remote file: {}", e)))?;
 
                 bytes_transferred += bytes_read as u64;
 
-                let _ = on_progress.send(TransferEvent::Progress {
+                on_progress(TransferEvent::Progress {
                     bytes_transferred,
                     total_bytes,
                     filename: filename.to_string(),
@@ -808,11 +2000,114 @@ async fn upload_file(
     remote_file
         .flush()
         .await
-        .map_err(|e| format!("Failed to flush remote file: {}", e))?;
+        .map_err(|e| TransferFailure::Transient(format!("Failed to flush remote file: {}", e)))?;
+    remote_file.shutdown().await.ok();
+
+    // A mismatch means the file changed underneath us: start over
+    let written = sftp
+        .metadata(&part_path)
+        .await
+        .map_err(|e| sftp_failure("Failed to stat uploaded file", e))?
+        .size
+        .unwrap_or(0);
+    if written != total_bytes {
+        discard_remote_partial(&sftp, &part_path, &manifest_path).await;
+        return Err(TransferFailure::Transient(format!(
+            "Size mismatch: expected {} bytes, got {}",
+            total_bytes, written
+        )));
+    }
+    if transfer.verify_checksum {
+        let remote_sum = remote_sha256(&connection.ssh_handle, &part_path)
+            .await
+            .map_err(TransferFailure::Transient)?;
+        let local_sum = local_sha256(&transfer.local_path)
+            .await
+            .map_err(TransferFailure::Fatal)?;
+        if remote_sum != local_sum {
+            discard_remote_partial(&sftp, &part_path, &manifest_path).await;
+            return Err(TransferFailure::Transient(format!(
+                "Checksum mismatch: remote {}, local {}",
+                remote_sum, local_sum
+            )));
+        }
+    }
+
+    if let Ok(existing) = sftp.metadata(&transfer.remote_path).await {
+        keep_attributes(&sftp, &part_path, &existing).await?;
+    }
+    if sftp.rename(&part_path, &transfer.remote_path).await.is_err() {
+        // SFTP v3 servers won't rename over an existing file
+        sftp.remove_file(&transfer.remote_path).await.ok();
+        sftp.rename(&part_path, &transfer.remote_path)
+            .await
+            .map_err(|e| sftp_failure("Failed to move upload into place", e))?;
+    }
+    sftp.remove_file(&manifest_path).await.ok();
+
+    Ok(())
+}
 
+/// Gives the upload at `part_path` the mode of the file it replaces, and
+/// its owner too if the server allows it, which it usually only does for
+/// root.
+async fn keep_attributes(
+    sftp: &SftpSession,
+    part_path: &str,
+    existing: &FileAttributes,
+) -> Result<(), TransferFailure> {
+    if let Some(permissions) = existing.permissions {
+        let attrs = FileAttributes {
+            permissions: Some(permissions & 0o7777),
+            ..FileAttributes::empty()
+        };
+        sftp.set_metadata(part_path, attrs)
+            .await
+            .map_err(|e| sftp_failure("Failed to set permissions on upload", e))?;
+    }
+    if existing.uid.is_some() && existing.gid.is_some() {
+        let attrs = FileAttributes {
+            uid: existing.uid,
+            gid: existing.gid,
+            ..FileAttributes::empty()
+        };
+        sftp.set_metadata(part_path, attrs).await.ok();
+    }
     Ok(())
 }
 
+/// Removes what an unfinished `transfer` left at its destination, as far as
+/// the connection allows.
+pub(crate) async fn discard_partial(state: &SftpState, transfer: &FileTransfer) {
+    match transfer.direction {
+        TransferDirection::Download => {
+            let part_path = format!("{}{}", transfer.local_path, PARTIAL_SUFFIX);
+            discard_local_partial(&part_path, &manifest_path(&transfer.local_path)).await;
+        }
+        TransferDirection::Upload => {
+            let Ok(connection) = get_connection(state, &transfer.device_ip).await else {
+                return;
+            };
+            let Ok(sftp) = connection.sftp().await else {
+                return;
+            };
+            let part_path = format!("{}{}", transfer.remote_path, PARTIAL_SUFFIX);
+            discard_remote_partial(&sftp, &part_path, &manifest_path(&transfer.remote_path))
+                .await;
+        }
+    }
+}
+
+async fn discard_local_partial(part_path: &str, manifest_path: &str) {
+    tokio::fs::remove_file(part_path).await.ok();
+    tokio::fs::remove_file(manifest_path).await.ok();
+}
+
+async fn discard_remote_partial(sftp: &SftpSession, part_path: &str, manifest_path: &str) {
+    sftp.remove_file(part_path).await.ok();
+    sftp.remove_file(manifest_path).await.ok();
+}
+
 #[tauri::command]
 pub async fn sftp_cancel_transfer(
     state: State<'_, SftpState>,
@@ -991,3 +2286,519 @@ pub async fn local_delete(path: String, recursive: bool) -> Result<(), String> {
 pub async fn local_rename(old_path: String, new_path: String) -> Result<(), String> {
     fs::rename(&old_path, &new_path).map_err(|e| format!("Failed to rename: {}", e))
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_server::{self, TestServer, CODE_PROMPT};
+    use russh::keys::known_hosts::learn_known_hosts_path;
+    use russh::keys::ssh_key::rand_core::OsRng;
+    use russh::keys::ssh_key::Algorithm;
+    use serde_json::Value;
+    use std::collections::VecDeque;
+    use tauri::ipc::InvokeResponseBody;
+
+    /// Connects to `server` as the app would, with its key trusted for the
+    /// session only.
+    pub(crate) async fn connect(server: &TestServer) -> Arc<SftpConnection> {
+        let port = server.addr.port();
+        let trust = (
+            "127.0.0.1".to_string(),
+            port,
+            known_hosts::fingerprint(&server.key),
+        );
+        let handler = SshHandler {
+            host: "127.0.0.1".to_string(),
+            port,
+            trust_stores: TrustStores::default(),
+            session_trust: Arc::new(Mutex::new(HashSet::from([trust]))),
+            unknown_key: Arc::new(Mutex::new(None)),
+        };
+        let config = Arc::new(client::Config::default());
+        let mut handle = client::connect(config, server.addr, handler).await.unwrap();
+        let auth = handle.authenticate_none("test").await.unwrap();
+        assert!(matches!(auth, AuthResult::Success));
+        let sftp = open_sftp(&handle).await.unwrap();
+        Arc::new(SftpConnection::new(
+            handle,
+            vec![],
+            sftp,
+            "127.0.0.1".to_string(),
+            None,
+            SshEndpoint {
+                host: "127.0.0.1".to_string(),
+                port,
+                user: "test".to_string(),
+                jumps: vec![],
+            },
+            Channel::new(|_| Ok(())),
+        ))
+    }
+
+    #[tokio::test]
+    async fn pool_shares_a_bounded_number_of_channels() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = test_server::start().await;
+        let connection = connect(&server).await;
+
+        let mut tasks = Vec::new();
+        for i in 0..(MAX_SFTP_CHANNELS * 3) {
+            let path = dir.path().join(format!("{}.txt", i));
+            fs::write(&path, format!("file {}", i)).unwrap();
+            let connection = Arc::clone(&connection);
+            tasks.push(tokio::spawn(async move {
+                let sftp = connection.sftp().await.unwrap();
+                let content = sftp.read(path.to_string_lossy()).await.unwrap();
+                // Hold the channel so the borrowers overlap
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                String::from_utf8(content).unwrap()
+            }));
+        }
+        for (i, task) in tasks.into_iter().enumerate() {
+            assert_eq!(task.await.unwrap(), format!("file {}", i));
+        }
+
+        let max = server.stats.max_channels.load(Ordering::SeqCst);
+        assert!(max > 1, "borrowers never ran in parallel");
+        assert!(
+            max <= MAX_SFTP_CHANNELS,
+            "{} channels were open at once",
+            max
+        );
+    }
+
+    #[tokio::test]
+    async fn dead_channels_are_not_lent_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "alive").unwrap();
+        let server = test_server::start().await;
+        let connection = connect(&server).await;
+
+        {
+            let sftp = connection.sftp().await.unwrap();
+            // The borrower's channel dies, and it is handed back anyway
+            sftp.close().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        let sftp = connection.sftp().await.unwrap();
+        let content = sftp.read(path.to_string_lossy()).await.unwrap();
+        assert_eq!(content, b"alive");
+    }
+
+    /// A fresh `SftpState` with `connection` in it as `device_ip`.
+    pub(crate) async fn state_with(device_ip: &str, connection: Arc<SftpConnection>) -> SftpState {
+        let state = SftpState::default();
+        state
+            .connections
+            .write()
+            .await
+            .insert(device_ip.to_string(), connection);
+        state
+    }
+
+    /// A transfer through a fresh `SftpState` with `connection` in it,
+    /// returning its result and events.
+    async fn transfer(
+        connection: Arc<SftpConnection>,
+        transfer: &FileTransfer,
+    ) -> (Result<(), String>, Vec<TransferEvent>) {
+        let state = state_with(&transfer.device_ip, connection).await;
+        let events = Mutex::new(Vec::new());
+        let report = |event| events.lock().unwrap().push(event);
+        let result = run_transfer(&state, transfer, &CancellationToken::new(), &report).await;
+        (result, events.into_inner().unwrap())
+    }
+
+    /// Where the progress picked up after the first retry.
+    fn resumed_at(events: &[TransferEvent]) -> u64 {
+        let retry = events
+            .iter()
+            .position(|e| matches!(e, TransferEvent::Retrying { .. }))
+            .expect("transfer was not retried");
+        events[retry..]
+            .iter()
+            .find_map(|e| match e {
+                TransferEvent::Progress {
+                    bytes_transferred, ..
+                } => Some(*bytes_transferred),
+                _ => None,
+            })
+            .expect("no progress after the retry")
+    }
+
+    fn content() -> Vec<u8> {
+        (0..512 * 1024).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn leftovers(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| is_partial_file(name))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn interrupted_download_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.bin");
+        let local = dir.path().join("local.bin");
+        fs::write(&remote, content()).unwrap();
+        let server = test_server::start().await;
+        *server.stats.fail_read_at.lock().unwrap() = Some(200_000);
+
+        let download = FileTransfer {
+            device_ip: "127.0.0.1".to_string(),
+            direction: TransferDirection::Download,
+            local_path: local.to_string_lossy().to_string(),
+            remote_path: remote.to_string_lossy().to_string(),
+            verify_checksum: false,
+        };
+        let (result, events) = transfer(connect(&server).await, &download).await;
+
+        assert_eq!(result, Ok(()));
+        // Starting over would report one 64 KiB read first
+        assert!(resumed_at(&events) > 65536);
+        assert_eq!(fs::read(&local).unwrap(), content());
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn interrupted_upload_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("local.bin");
+        let remote = dir.path().join("remote.bin");
+        fs::write(&local, content()).unwrap();
+        let server = test_server::start().await;
+        *server.stats.fail_write_at.lock().unwrap() = Some(200_000);
+
+        let upload = FileTransfer {
+            device_ip: "127.0.0.1".to_string(),
+            direction: TransferDirection::Upload,
+            local_path: local.to_string_lossy().to_string(),
+            remote_path: remote.to_string_lossy().to_string(),
+            verify_checksum: false,
+        };
+        let (result, events) = transfer(connect(&server).await, &upload).await;
+
+        assert_eq!(result, Ok(()));
+        assert!(resumed_at(&events) > 65536);
+        assert_eq!(fs::read(&remote).unwrap(), content());
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_transfer_leaves_nothing_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.bin");
+        fs::write(&remote, content()).unwrap();
+        // The download arrives, but can't replace a directory
+        let local = dir.path().join("local.bin");
+        fs::create_dir_all(local.join("inside")).unwrap();
+        let server = test_server::start().await;
+
+        let download = FileTransfer {
+            device_ip: "127.0.0.1".to_string(),
+            direction: TransferDirection::Download,
+            local_path: local.to_string_lossy().to_string(),
+            remote_path: remote.to_string_lossy().to_string(),
+            verify_checksum: false,
+        };
+        let (result, _) = transfer(connect(&server).await, &download).await;
+
+        assert!(result
+            .unwrap_err()
+            .starts_with("Failed to move download into place"));
+        assert!(leftovers(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn manifests_are_hidden() {
+        assert_eq!(manifest_path("/srv/a.iso"), "/srv/.a.iso.part.json");
+        assert_eq!(manifest_path("a.iso"), ".a.iso.part.json");
+        assert!(is_partial_file(&manifest_path("/srv/a.iso")));
+    }
+
+    #[test]
+    fn only_connection_trouble_is_transient() {
+        let closed = SftpClientError::UnexpectedBehavior("session closed".to_string());
+        let odd = SftpClientError::UnexpectedBehavior("unexpected handle".to_string());
+        assert!(is_transient(&SftpClientError::Timeout));
+        assert!(is_transient(&SftpClientError::IO("reset".to_string())));
+        assert!(is_transient(&closed));
+        assert!(!is_transient(&odd));
+        assert!(!is_transient(&SftpClientError::UnexpectedPacket));
+    }
+
+    /// A state whose trust stores live in `dir`.
+    fn state_trusting(dir: &Path) -> Arc<SftpState> {
+        Arc::new(SftpState {
+            trust_stores: TrustStores::new(Some(dir.join("user")), dir.join("app")),
+            ..SftpState::default()
+        })
+    }
+
+    fn target(server: &TestServer) -> Target {
+        Target {
+            host: "127.0.0.1".to_string(),
+            port: server.addr.port(),
+            user: "test".to_string(),
+            identity_files: vec![],
+            identities_only: false,
+            connect_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// A prompt channel that gives every question `answer`, counting them.
+    fn answering(
+        state: &Arc<SftpState>,
+        answer: fn() -> PromptAnswer,
+    ) -> (Channel<ConnectPrompt>, Arc<AtomicU64>) {
+        let asked = Arc::new(AtomicU64::new(0));
+        let (state, count) = (Arc::clone(state), Arc::clone(&asked));
+        let channel = Channel::new(move |body| {
+            let InvokeResponseBody::Json(json) = body else {
+                panic!("prompt was not JSON");
+            };
+            let prompt: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let prompt_id = prompt["data"]["promptId"].as_str().unwrap();
+            count.fetch_add(1, Ordering::SeqCst);
+            let sender = state.prompts.lock().unwrap().remove(prompt_id).unwrap();
+            let _ = sender.send(answer());
+            Ok(())
+        });
+        (channel, asked)
+    }
+
+    async fn connect_answering(
+        state: &Arc<SftpState>,
+        server: &TestServer,
+        answer: fn() -> PromptAnswer,
+    ) -> (Result<Handle<SshHandler>, SftpError>, u64) {
+        let (on_prompt, asked) = answering(state, answer);
+        let result = connect_verified(state, &target(server), None, &on_prompt).await;
+        (result, asked.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn a_key_trusted_always_is_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let server = test_server::start().await;
+        let port = server.addr.port();
+
+        let (result, asked) =
+            connect_answering(&state, &server, || PromptAnswer::TrustAlways).await;
+        assert!(result.is_ok());
+        assert_eq!(asked, 1);
+        let saved = fs::read_to_string(dir.path().join("app")).unwrap();
+        assert!(saved.contains(&format!("[127.0.0.1]:{} ssh-ed25519 ", port)));
+        assert!(!dir.path().join("user").exists());
+
+        // A new session finds it without asking
+        let state = state_trusting(dir.path());
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::Reject).await;
+        assert!(result.is_ok());
+        assert_eq!(asked, 0);
+    }
+
+    #[tokio::test]
+    async fn a_key_trusted_once_lasts_the_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let server = test_server::start().await;
+
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::TrustOnce).await;
+        assert!(result.is_ok());
+        assert_eq!(asked, 1);
+        assert!(!dir.path().join("app").exists());
+
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::Reject).await;
+        assert!(result.is_ok());
+        assert_eq!(asked, 0);
+
+        let state = state_trusting(dir.path());
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::Reject).await;
+        assert!(result.is_err());
+        assert_eq!(asked, 1);
+    }
+
+    #[tokio::test]
+    async fn a_rejected_key_fails_the_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let server = test_server::start().await;
+
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::Reject).await;
+        assert_eq!(asked, 1);
+        match result {
+            Err(SftpError::HostKeyRejected { host, fingerprint }) => {
+                assert_eq!(host, "127.0.0.1");
+                assert_eq!(fingerprint, known_hosts::fingerprint(&server.key));
+            }
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("connected with a rejected key"),
+        }
+        assert!(!dir.path().join("app").exists());
+        assert!(state.session_trust.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_different_key_after_accepting_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let server = test_server::start().await;
+        server.stats.rekey.store(true, Ordering::SeqCst);
+
+        // Trusting always would read the second key as a changed one
+        let (result, asked) = connect_answering(&state, &server, || PromptAnswer::TrustOnce).await;
+        assert_eq!(asked, 1);
+        match result {
+            Err(SftpError::HostKey(message)) => {
+                assert!(message.contains("presented a different key"), "{}", message)
+            }
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("connected with a different key"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_changed_key_is_refused_without_asking() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let server = test_server::start().await;
+        let old = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).unwrap();
+        let user_store = dir.path().join("user");
+        learn_known_hosts_path(
+            "127.0.0.1",
+            server.addr.port(),
+            old.public_key(),
+            &user_store,
+        )
+        .unwrap();
+
+        let (result, asked) =
+            connect_answering(&state, &server, || PromptAnswer::TrustAlways).await;
+        assert_eq!(asked, 0);
+        match result {
+            Err(SftpError::HostKeyChanged { path, .. }) => {
+                assert_eq!(path, user_store.display().to_string())
+            }
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(_) => panic!("connected with a changed key"),
+        }
+        assert!(!dir.path().join("app").exists());
+    }
+
+    fn secret(value: &str) -> PromptAnswer {
+        PromptAnswer::Secret {
+            value: value.to_string(),
+            remember: false,
+        }
+    }
+
+    /// Authenticates to `server`, whose key is trusted first, answering its
+    /// prompts with `answers` in turn and cancelling once they run out.
+    /// Returns the prompts that were asked.
+    async fn authenticate_answering(
+        server: &TestServer,
+        answers: Vec<PromptAnswer>,
+    ) -> (Result<(), SftpError>, Vec<Value>) {
+        let dir = tempfile::tempdir().unwrap();
+        let state = state_trusting(dir.path());
+        let (trusting, _) = answering(&state, || PromptAnswer::TrustOnce);
+        let mut handle = connect_verified(&state, &target(server), None, &trusting)
+            .await
+            .unwrap();
+
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let answers = Mutex::new(VecDeque::from(answers));
+        let on_prompt = Channel::new({
+            let (state, asked) = (Arc::clone(&state), Arc::clone(&asked));
+            move |body| {
+                let InvokeResponseBody::Json(json) = body else {
+                    panic!("prompt was not JSON");
+                };
+                let prompt: Value = serde_json::from_str(&json).unwrap();
+                let prompt_id = prompt["data"]["promptId"].as_str().unwrap().to_string();
+                asked.lock().unwrap().push(prompt);
+                let answer = answers
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .unwrap_or(PromptAnswer::Cancel);
+                let sender = state.prompts.lock().unwrap().remove(&prompt_id).unwrap();
+                let _ = sender.send(answer);
+                Ok(())
+            }
+        });
+        let result = authenticate(&state, &mut handle, &target(server), &on_prompt).await;
+        let asked = asked.lock().unwrap().clone();
+        (result, asked)
+    }
+
+    fn tried(result: Result<(), SftpError>) -> Vec<String> {
+        match result {
+            Err(SftpError::Authentication { tried, .. }) => tried,
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(()) => panic!("authenticated"),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_password_is_asked_for_until_it_is_right() {
+        let server = test_server::start().await;
+        *server.stats.password.lock().unwrap() = Some("hunter2".to_string());
+
+        let answers = vec![secret("wrong"), secret("hunter2")];
+        let (result, asked) = authenticate_answering(&server, answers).await;
+        assert!(result.is_ok());
+        let attempts: Vec<&Value> = asked.iter().map(|p| &p["data"]["attempt"]).collect();
+        assert_eq!(attempts, [1, 2]);
+        assert_eq!(asked[0]["event"], "password");
+        assert_eq!(asked[0]["data"]["user"], "test");
+    }
+
+    #[tokio::test]
+    async fn wrong_passwords_give_up_after_the_last_attempt() {
+        let server = test_server::start().await;
+        *server.stats.password.lock().unwrap() = Some("hunter2".to_string());
+
+        let answers = (0..=SECRET_ATTEMPTS).map(|_| secret("wrong")).collect();
+        let (result, asked) = authenticate_answering(&server, answers).await;
+        assert_eq!(asked.len(), SECRET_ATTEMPTS as usize);
+        assert_eq!(tried(result), ["password"]);
+    }
+
+    #[tokio::test]
+    async fn keyboard_interactive_answers_reach_the_server() {
+        let server = test_server::start().await;
+        *server.stats.code.lock().unwrap() = Some("123456".to_string());
+
+        let answers = vec![PromptAnswer::Responses(vec!["123456".to_string()])];
+        let (result, asked) = authenticate_answering(&server, answers).await;
+        assert!(result.is_ok());
+        assert_eq!(asked.len(), 1);
+        assert_eq!(asked[0]["event"], "keyboardInteractive");
+        assert_eq!(asked[0]["data"]["prompts"][0]["prompt"], CODE_PROMPT);
+        assert_eq!(asked[0]["data"]["prompts"][0]["echo"], false);
+    }
+
+    #[tokio::test]
+    async fn a_failure_lists_every_method_tried() {
+        let server = test_server::start().await;
+        *server.stats.code.lock().unwrap() = Some("123456".to_string());
+        *server.stats.password.lock().unwrap() = Some("hunter2".to_string());
+
+        let answers = vec![
+            PromptAnswer::Responses(vec!["000000".to_string()]),
+            secret("wrong"),
+        ];
+        let (result, asked) = authenticate_answering(&server, answers).await;
+        let events: Vec<&Value> = asked.iter().map(|p| &p["event"]).collect();
+        assert_eq!(events, ["keyboardInteractive", "password", "password"]);
+        assert_eq!(tried(result), ["keyboard-interactive", "password"]);
+    }
+}
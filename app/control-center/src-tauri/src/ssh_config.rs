@@ -0,0 +1,638 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Include nesting allowed before giving up, as in OpenSSH.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Jump hosts reached through their own ProxyJump before giving up, which
+/// also stops a loop of them.
+const MAX_JUMP_DEPTH: usize = 8;
+
+// ----- Data Structures -----
+
+/// What `~/.ssh/config` says about one host. As in ssh_config(5), the first
+/// value found for a keyword wins, except IdentityFile which accumulates.
+#[derive(Clone, Debug, Default)]
+pub struct HostConfig {
+    pub host_name: Option<String>,
+    pub port: Option<u16>,
+    pub user: Option<String>,
+    pub identity_files: Vec<PathBuf>,
+    pub identities_only: bool,
+    /// Hops in the order they are dialled; empty connects directly.
+    pub proxy_jump: Vec<JumpHost>,
+    pub connect_timeout: Option<Duration>,
+    /// Lines that were skipped rather than failing the lookup, for the UI
+    /// to show.
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+/// Values as they are found, before `%` tokens and `~` are expanded.
+#[derive(Default)]
+struct Collected {
+    host_name: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    identity_files: Vec<String>,
+    identities_only: Option<bool>,
+    proxy_jump: Option<Vec<JumpHost>>,
+    connect_timeout: Option<Duration>,
+    warnings: Vec<String>,
+}
+
+/// The user's `~/.ssh/config`, read once for any number of lookups.
+pub struct SshConfig {
+    path: PathBuf,
+    /// `None` when there is no config file.
+    content: Option<String>,
+    home: PathBuf,
+    local_user: String,
+}
+
+struct Resolver<'a> {
+    config: &'a SshConfig,
+    /// The name being looked up, as given.
+    original: &'a str,
+    found: Collected,
+}
+
+// ----- Lookup -----
+
+impl SshConfig {
+    pub fn load() -> Result<SshConfig, String> {
+        let home = PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "/root".to_string()));
+        let path = home.join(".ssh").join("config");
+        let content = if path.exists() {
+            Some(
+                fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?,
+            )
+        } else {
+            None
+        };
+        Ok(SshConfig {
+            path,
+            content,
+            home,
+            local_user: std::env::var("USER").unwrap_or_else(|_| "root".to_string()),
+        })
+    }
+
+    /// Looks `host` up. A missing file is no error: every field is simply
+    /// left unset.
+    pub fn resolve(&self, host: &str) -> Result<HostConfig, String> {
+        let mut resolver = Resolver {
+            config: self,
+            original: host,
+            found: Collected::default(),
+        };
+        if let Some(content) = &self.content {
+            resolver.read_config(&self.path, content, true, 0)?;
+        }
+        Ok(resolver.finish())
+    }
+
+    /// Every hop to dial for `jumps`, in order, with its own config. The
+    /// first hop is reached however its config says, so a ProxyJump there
+    /// goes in front of it; later hops are reached through the one before,
+    /// as with OpenSSH.
+    pub fn jump_chain(&self, jumps: &[JumpHost]) -> Result<Vec<(JumpHost, HostConfig)>, String> {
+        let mut chain = Vec::new();
+        self.push_jumps(jumps, &mut chain, 0)?;
+        Ok(chain)
+    }
+
+    fn push_jumps(
+        &self,
+        jumps: &[JumpHost],
+        chain: &mut Vec<(JumpHost, HostConfig)>,
+        depth: usize,
+    ) -> Result<(), String> {
+        if depth > MAX_JUMP_DEPTH {
+            return Err("ProxyJump nested too deeply; do the jump hosts loop?".to_string());
+        }
+        for (index, jump) in jumps.iter().enumerate() {
+            let config = self.resolve(&jump.host)?;
+            if index == 0 {
+                self.push_jumps(&config.proxy_jump, chain, depth + 1)?;
+            }
+            chain.push((jump.clone(), config));
+        }
+        Ok(())
+    }
+}
+
+impl Resolver<'_> {
+    fn read_file(&mut self, path: &Path, depth: usize) -> Result<(), String> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(format!("{}: Include nested too deeply", path.display()));
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        self.read_config(path, &content, true, depth)
+    }
+
+    fn read_config(
+        &mut self,
+        path: &Path,
+        content: &str,
+        active: bool,
+        depth: usize,
+    ) -> Result<(), String> {
+        // Lines before the first Host or Match apply to every host
+        let mut active = active;
+        let outer = active;
+        for (index, line) in content.lines().enumerate() {
+            let at = || format!("{}:{}", path.display(), index + 1);
+            let Some((keyword, args)) = split_line(line) else {
+                continue;
+            };
+            if args.is_empty() {
+                return Err(format!("{}: {} needs a value", at(), keyword));
+            }
+
+            match keyword.to_ascii_lowercase().as_str() {
+                "host" => active = outer && host_matches(&args, self.original),
+                "match" => {
+                    let matched = self.match_matches(&args).unwrap_or_else(|e| {
+                        // One Match we can't evaluate shouldn't stop every
+                        // connection; it just doesn't apply
+                        let warning = format!("{}: {}; skipping this Match block", at(), e);
+                        self.found.warnings.push(warning);
+                        false
+                    });
+                    active = outer && matched;
+                }
+                "include" if active => {
+                    for pattern in &args {
+                        for included in self.include_paths(pattern) {
+                            self.read_file(&included, depth + 1)?;
+                        }
+                    }
+                }
+                _ if !active => {}
+                keyword => self
+                    .set(keyword, &args)
+                    .map_err(|e| format!("{}: {}", at(), e))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, keyword: &str, args: &[String]) -> Result<(), String> {
+        let found = &mut self.found;
+        let value = args[0].as_str();
+        match keyword {
+            "hostname" if found.host_name.is_none() => found.host_name = Some(value.to_string()),
+            "port" if found.port.is_none() => found.port = Some(parse_port(value)?),
+            "user" if found.user.is_none() => found.user = Some(value.to_string()),
+            "identityfile" if !found.identity_files.iter().any(|f| f == value) => {
+                found.identity_files.push(value.to_string());
+            }
+            "identitiesonly" if found.identities_only.is_none() => {
+                found.identities_only = Some(parse_yes_no(value)?);
+            }
+            "proxyjump" if found.proxy_jump.is_none() => {
+                found.proxy_jump = Some(if value.eq_ignore_ascii_case("none") {
+                    vec![]
+                } else {
+                    value
+                        .split(',')
+                        .map(parse_jump_host)
+                        .collect::<Result<_, _>>()?
+                });
+            }
+            "connecttimeout" if found.connect_timeout.is_none() => {
+                let seconds: u64 = value
+                    .parse()
+                    .map_err(|_| format!("bad ConnectTimeout `{}`", value))?;
+                found.connect_timeout = Some(Duration::from_secs(seconds));
+            }
+            // Keywords we don't use, and ones already set earlier
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Evaluates a `Match` line. Criteria like `exec` and `canonical` can't
+    /// be honoured here, so blocks using them never apply; ones we don't
+    /// know at all are an error.
+    fn match_matches(&self, args: &[String]) -> Result<bool, String> {
+        let mut args = args.iter();
+        let mut matched = true;
+        while let Some(criterion) = args.next() {
+            let (negate, criterion) = match criterion.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, criterion.as_str()),
+            };
+            let result = match criterion.to_ascii_lowercase().as_str() {
+                "all" | "final" => true,
+                "canonical" => false,
+                name @ ("host" | "originalhost" | "user" | "localuser" | "exec"
+                | "localnetwork" | "tagged" | "command" | "version" | "sessiontype") => {
+                    let list = args
+                        .next()
+                        .ok_or_else(|| format!("Match {} needs a value", name))?;
+                    match name {
+                        "host" => list_matches(list, &self.current_host_name()),
+                        "originalhost" => list_matches(list, self.original),
+                        "user" => list_matches(list, self.current_user()),
+                        "localuser" => list_matches(list, &self.config.local_user),
+                        _ => false,
+                    }
+                }
+                other => return Err(format!("unsupported Match criterion `{}`", other)),
+            };
+            matched &= result != negate;
+        }
+        Ok(matched)
+    }
+
+    fn current_host_name(&self) -> String {
+        match &self.found.host_name {
+            Some(host_name) => expand_host_name(host_name, self.original),
+            None => self.original.to_string(),
+        }
+    }
+
+    fn current_user(&self) -> &str {
+        self.found
+            .user
+            .as_deref()
+            .unwrap_or(&self.config.local_user)
+    }
+
+    /// Relative Include paths are under `~/.ssh`; globs expand in sorted
+    /// order and may match nothing.
+    fn include_paths(&self, pattern: &str) -> Vec<PathBuf> {
+        let expanded = expand_tilde(pattern, &self.config.home);
+        let full = if Path::new(&expanded).is_absolute() {
+            expanded
+        } else {
+            self.config
+                .home
+                .join(".ssh")
+                .join(expanded)
+                .to_string_lossy()
+                .to_string()
+        };
+        let mut paths: Vec<PathBuf> = match glob::glob(&full) {
+            Ok(paths) => paths.filter_map(Result::ok).collect(),
+            Err(_) => vec![PathBuf::from(full)],
+        };
+        paths.sort();
+        paths.retain(|path| path.is_file());
+        paths
+    }
+
+    fn finish(self) -> HostConfig {
+        let host_name = self.current_host_name();
+        let user = self.current_user().to_string();
+        let port = self.found.port.unwrap_or(22);
+        let identity_files = self
+            .found
+            .identity_files
+            .iter()
+            .map(|file| {
+                let file = expand_tokens(file, |token| match token {
+                    'd' => Some(self.config.home.to_string_lossy().to_string()),
+                    'u' => Some(self.config.local_user.clone()),
+                    'h' => Some(host_name.clone()),
+                    'n' => Some(self.original.to_string()),
+                    'r' => Some(user.clone()),
+                    'p' => Some(port.to_string()),
+                    _ => None,
+                });
+                PathBuf::from(expand_tilde(&file, &self.config.home))
+            })
+            .collect();
+
+        HostConfig {
+            host_name: self.found.host_name.as_ref().map(|_| host_name.clone()),
+            port: self.found.port,
+            user: self.found.user,
+            identity_files,
+            identities_only: self.found.identities_only.unwrap_or(false),
+            proxy_jump: self.found.proxy_jump.unwrap_or_default(),
+            connect_timeout: self.found.connect_timeout,
+            warnings: self.found.warnings,
+        }
+    }
+}
+
+// ----- Parsing Helpers -----
+
+/// Splits `Keyword value...` or `Keyword=value...` into its parts, honouring
+/// double quotes. Blank lines and comments give `None`.
+fn split_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let keyword = line[..end].to_string();
+    let rest = line[end..].trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in rest.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    Some((keyword, args))
+}
+
+fn parse_port(value: &str) -> Result<u16, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|&port| port != 0)
+        .ok_or_else(|| format!("bad port `{}`", value))
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" => Ok(true),
+        "no" | "false" => Ok(false),
+        _ => Err(format!("expected yes or no, got `{}`", value)),
+    }
+}
+
+/// `[user@]host[:port]`, with `[addr]:port` for IPv6 literals.
+fn parse_jump_host(spec: &str) -> Result<JumpHost, String> {
+    let spec = spec.strip_prefix("ssh://").unwrap_or(spec);
+    let (user, rest) = match spec.rsplit_once('@') {
+        Some((user, rest)) => (Some(user.to_string()), rest),
+        None => (None, spec),
+    };
+    let (host, port) = if let Some(bracketed) = rest.strip_prefix('[') {
+        let (host, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| format!("bad ProxyJump host `{}`", spec))?;
+        let port = after.strip_prefix(':').map(parse_port).transpose()?;
+        (host, port)
+    } else {
+        match rest.rsplit_once(':') {
+            Some((host, port)) => (host, Some(parse_port(port)?)),
+            None => (rest, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(format!("bad ProxyJump host `{}`", spec));
+    }
+    Ok(JumpHost {
+        user,
+        host: host.to_string(),
+        port,
+    })
+}
+
+// ----- Matching -----
+
+/// A `Host` line: any pattern matches and no negated pattern does.
+fn host_matches(patterns: &[String], host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, host) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, host),
+        }
+    }
+    matched
+}
+
+/// A comma-separated `Match` list, with the same negation rules.
+fn list_matches(list: &str, value: &str) -> bool {
+    let patterns: Vec<String> = list.split(',').map(str::to_string).collect();
+    host_matches(&patterns, value)
+}
+
+/// `*` and `?` globbing, case-insensitive as host names are.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// ----- Expansion -----
+
+fn expand_tilde(path: &str, home: &Path) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => home.join(rest).to_string_lossy().to_string(),
+        None if path == "~" => home.to_string_lossy().to_string(),
+        None => path.to_string(),
+    }
+}
+
+fn expand_host_name(host_name: &str, original: &str) -> String {
+    expand_tokens(host_name, |token| {
+        (token == 'h').then(|| original.to_string())
+    })
+}
+
+/// Replaces `%x` tokens through `lookup`; `%%` is a literal percent and
+/// unknown tokens are kept as written.
+fn expand_tokens(text: &str, lookup: impl Fn(char) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some(token) => match lookup(token) {
+                Some(value) => out.push_str(&value),
+                None => {
+                    out.push('%');
+                    out.push(token);
+                }
+            },
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_text(text: &str) -> SshConfig {
+        SshConfig {
+            path: PathBuf::from("config"),
+            content: Some(text.to_string()),
+            home: PathBuf::from("/home/me"),
+            local_user: "me".to_string(),
+        }
+    }
+
+    fn jump(host: &str) -> JumpHost {
+        JumpHost {
+            user: None,
+            host: host.to_string(),
+            port: None,
+        }
+    }
+
+    #[test]
+    fn first_value_wins() {
+        let config = from_text(
+            "Host web\n  Port 2222\n  User deploy\nHost *\n  Port 22\n  User root\n  ConnectTimeout 5\n",
+        );
+        let web = config.resolve("web").unwrap();
+        assert_eq!(web.port, Some(2222));
+        assert_eq!(web.user.as_deref(), Some("deploy"));
+        assert_eq!(web.connect_timeout, Some(Duration::from_secs(5)));
+
+        let other = config.resolve("db").unwrap();
+        assert_eq!(other.port, Some(22));
+        assert_eq!(other.user.as_deref(), Some("root"));
+    }
+
+    #[test]
+    fn identity_files_accumulate() {
+        let config = from_text(
+            "Host web\n  IdentityFile ~/.ssh/web\nHost *\n  IdentityFile ~/.ssh/id_%r\n  User ops\n",
+        );
+        let web = config.resolve("web").unwrap();
+        assert_eq!(
+            web.identity_files,
+            [
+                PathBuf::from("/home/me/.ssh/web"),
+                PathBuf::from("/home/me/.ssh/id_ops")
+            ]
+        );
+    }
+
+    #[test]
+    fn host_and_match_apply_in_file_order() {
+        // Match host sees the HostName set by the Host block above it
+        let config = from_text(
+            "Host web\n  HostName web.internal\nMatch host *.internal\n  User internal\nHost web\n  User later\n",
+        );
+        let web = config.resolve("web").unwrap();
+        assert_eq!(web.host_name.as_deref(), Some("web.internal"));
+        assert_eq!(web.user.as_deref(), Some("internal"));
+
+        // A Match first takes precedence over a Host after it
+        let config = from_text("Match originalhost web\n  Port 1\nHost web\n  Port 2\n");
+        assert_eq!(config.resolve("web").unwrap().port, Some(1));
+    }
+
+    #[test]
+    fn wildcards_and_negation() {
+        let config = from_text("Host *.lan !printer.lan\n  User lan\nHost db?\n  User db\n");
+        assert_eq!(
+            config.resolve("NAS.lan").unwrap().user.as_deref(),
+            Some("lan")
+        );
+        assert_eq!(config.resolve("printer.lan").unwrap().user, None);
+        assert_eq!(config.resolve("db1").unwrap().user.as_deref(), Some("db"));
+        assert_eq!(config.resolve("db12").unwrap().user, None);
+        assert_eq!(config.resolve("lan").unwrap().user, None);
+
+        let config = from_text("Match !host web,api\n  User other\n");
+        assert_eq!(config.resolve("web").unwrap().user, None);
+        assert_eq!(config.resolve("db").unwrap().user.as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn unknown_match_criteria_never_match() {
+        let config = from_text("Match frobnicate yes\n  User wrong\nHost *\n  User right\n");
+        let resolved = config.resolve("web").unwrap();
+        assert_eq!(resolved.user.as_deref(), Some("right"));
+        assert_eq!(
+            resolved.warnings,
+            ["config:1: unsupported Match criterion `frobnicate`; skipping this Match block"]
+        );
+    }
+
+    #[test]
+    fn jump_hosts_use_their_own_config() {
+        let config = from_text(
+            "Host inner\n  ProxyJump outer\n  Port 2200\nHost outer\n  HostName outer.example.com\n  User gate\nHost target\n  ProxyJump inner,last\n",
+        );
+        let target = config.resolve("target").unwrap();
+        let chain = config.jump_chain(&target.proxy_jump).unwrap();
+        let hosts: Vec<&str> = chain.iter().map(|(jump, _)| jump.host.as_str()).collect();
+        assert_eq!(hosts, ["outer", "inner", "last"]);
+        assert_eq!(chain[0].1.host_name.as_deref(), Some("outer.example.com"));
+        assert_eq!(chain[0].1.user.as_deref(), Some("gate"));
+        assert_eq!(chain[1].1.port, Some(2200));
+    }
+
+    #[test]
+    fn looping_jump_hosts_are_refused() {
+        let config = from_text("Host a\n  ProxyJump b\nHost b\n  ProxyJump a\n");
+        assert!(config.jump_chain(&[jump("a")]).is_err());
+    }
+
+    #[test]
+    fn jump_host_specs() {
+        assert_eq!(
+            parse_jump_host("ops@[fe80::1]:2022").unwrap(),
+            JumpHost {
+                user: Some("ops".to_string()),
+                host: "fe80::1".to_string(),
+                port: Some(2022),
+            }
+        );
+        assert_eq!(parse_jump_host("gate").unwrap(), jump("gate"));
+        assert!(parse_jump_host("gate:0").is_err());
+    }
+}
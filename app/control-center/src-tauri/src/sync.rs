@@ -0,0 +1,818 @@
+use russh_sftp::protocol::FileAttributes;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::State;
+use tokio_util::sync::CancellationToken;
+
+use crate::sftp::{
+    self, FileTransfer, SftpConnection, SftpState, TransferDirection, TransferEvent,
+};
+use crate::tree_transfer::{
+    self, is_contained, join_local, join_remote, local_mtime, walk_local, walk_remote, EntryKind,
+    SymlinkPolicy, TreeEntry, TreeFilter, TreeProgress,
+};
+
+/// Paths per remote checksum command, to stay well under the argument limit.
+const CHECKSUM_BATCH: usize = 100;
+
+// ----- Data Structures -----
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncMode {
+    /// Make the device's tree match the local one.
+    #[default]
+    Push,
+    /// Make the local tree match the device's.
+    Pull,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncOptions {
+    pub mode: SyncMode,
+    /// Delete what's only at the destination, so it ends up a mirror.
+    pub delete: bool,
+    /// Compare same-size files by SHA-256 rather than modification time.
+    pub checksum: bool,
+    /// Globs as for directory transfers. Excluded paths are neither copied
+    /// nor deleted.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+/// The two trees a sync works between.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncTarget {
+    pub device_ip: String,
+    pub local_path: String,
+    pub remote_path: String,
+    #[serde(default)]
+    pub options: SyncOptions,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateReason {
+    Size,
+    Modified,
+    Checksum,
+}
+
+/// One step of a sync, by path relative to the roots.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "action"
+)]
+pub enum SyncAction {
+    CreateDir {
+        path: String,
+    },
+    Create {
+        path: String,
+        size: u64,
+    },
+    Update {
+        path: String,
+        size: u64,
+        reason: UpdateReason,
+    },
+    /// Only at the destination, or in the way of something of another kind.
+    /// A directory goes with everything in it.
+    Delete {
+        path: String,
+        is_dir: bool,
+    },
+}
+
+impl SyncAction {
+    fn path(&self) -> &str {
+        match self {
+            SyncAction::CreateDir { path }
+            | SyncAction::Create { path, .. }
+            | SyncAction::Update { path, .. }
+            | SyncAction::Delete { path, .. } => path,
+        }
+    }
+}
+
+/// What a sync would do, for showing before it's run. Deletes come first,
+/// then directories, parents before children, then files.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+    pub actions: Vec<SyncAction>,
+    /// Bytes the creates and updates will copy.
+    pub bytes_total: u64,
+}
+
+// ----- Planning -----
+
+fn is_under(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// What it takes to make `dest` match `source`. With `same_content`, files
+/// of equal size are compared by whether they're in it instead of by
+/// modification time. Unfinished transfers' partial files are left alone on
+/// both sides. Links are never copied, and one at the destination is only
+/// deleted when something from the source needs its place.
+pub(crate) fn plan_sync(
+    source: &[TreeEntry],
+    dest: &[TreeEntry],
+    delete: bool,
+    same_content: Option<&HashSet<String>>,
+) -> SyncPlan {
+    let settled = |entry: &&TreeEntry| !sftp::is_partial_file(&entry.relative);
+    let source: Vec<&TreeEntry> = source.iter().filter(settled).collect();
+    let dest: Vec<&TreeEntry> = dest.iter().filter(settled).collect();
+
+    let source_kinds: HashMap<&str, &EntryKind> = source
+        .iter()
+        .map(|entry| (entry.relative.as_str(), &entry.kind))
+        .collect();
+    let dest_kinds: HashMap<&str, &EntryKind> = dest
+        .iter()
+        .map(|entry| (entry.relative.as_str(), &entry.kind))
+        .collect();
+
+    let mut actions = Vec::new();
+
+    // Walk order puts parents first, so a deleted directory's contents are
+    // skipped rather than deleted one by one
+    let mut deleted_dirs: Vec<&str> = Vec::new();
+    for entry in &dest {
+        if deleted_dirs
+            .iter()
+            .any(|dir| is_under(&entry.relative, dir))
+        {
+            continue;
+        }
+        let remove = match (source_kinds.get(entry.relative.as_str()), &entry.kind) {
+            (None, EntryKind::Symlink { .. }) => false,
+            (None, _) => delete,
+            (Some(EntryKind::Dir), EntryKind::Dir) => false,
+            (Some(EntryKind::File { .. }), EntryKind::File { .. }) => false,
+            (Some(_), _) => true,
+        };
+        if remove {
+            let is_dir = matches!(entry.kind, EntryKind::Dir);
+            if is_dir {
+                deleted_dirs.push(&entry.relative);
+            }
+            actions.push(SyncAction::Delete {
+                path: entry.relative.clone(),
+                is_dir,
+            });
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut bytes_total = 0;
+    for entry in &source {
+        let path = entry.relative.clone();
+        let existing = dest_kinds.get(entry.relative.as_str());
+        match (&entry.kind, existing) {
+            (EntryKind::Dir, Some(EntryKind::Dir)) => {}
+            (EntryKind::Dir, _) => actions.push(SyncAction::CreateDir { path }),
+            (
+                EntryKind::File { size, modified },
+                Some(EntryKind::File {
+                    size: dest_size,
+                    modified: dest_modified,
+                }),
+            ) => {
+                let reason = if size != dest_size {
+                    Some(UpdateReason::Size)
+                } else if let Some(same_content) = same_content {
+                    (!same_content.contains(&entry.relative)).then_some(UpdateReason::Checksum)
+                } else {
+                    (modified.is_some() && modified != dest_modified)
+                        .then_some(UpdateReason::Modified)
+                };
+                if let Some(reason) = reason {
+                    bytes_total += size;
+                    files.push(SyncAction::Update {
+                        path,
+                        size: *size,
+                        reason,
+                    });
+                }
+            }
+            (EntryKind::File { size, .. }, _) => {
+                bytes_total += size;
+                files.push(SyncAction::Create { path, size: *size });
+            }
+            // Sources are walked without their links
+            (EntryKind::Symlink { .. }, _) => {}
+        }
+    }
+    actions.extend(files);
+
+    SyncPlan {
+        actions,
+        bytes_total,
+    }
+}
+
+// ----- Scanning -----
+
+/// Files present on both sides with the same size; only these need summing.
+fn checksum_candidates(local: &[TreeEntry], remote: &[TreeEntry]) -> Vec<String> {
+    let remote_sizes: HashMap<&str, u64> = remote
+        .iter()
+        .filter_map(|entry| match entry.kind {
+            EntryKind::File { size, .. } => Some((entry.relative.as_str(), size)),
+            _ => None,
+        })
+        .collect();
+    local
+        .iter()
+        .filter_map(|entry| match entry.kind {
+            EntryKind::File { size, .. }
+                if remote_sizes.get(entry.relative.as_str()) == Some(&size) =>
+            {
+                Some(entry.relative.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// SHA-256 sums of `paths` under the device's `root`, computed there in
+/// batches. Files that couldn't be summed are left out.
+async fn remote_checksums(
+    connection: &SftpConnection,
+    root: &str,
+    paths: &[String],
+) -> HashMap<String, String> {
+    let mut sums = HashMap::new();
+    for batch in paths.chunks(CHECKSUM_BATCH) {
+        let files = batch
+            .iter()
+            .map(|path| sftp::shell_quote(path))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let command = format!(
+            "cd {} && {{ sha256sum -- {} 2>/dev/null || shasum -a 256 -- {}; }}",
+            sftp::shell_quote(root),
+            files,
+            files
+        );
+        // One unreadable file fails the whole batch; its files then count
+        // as changed, which only costs a copy
+        let Ok(output) = connection.exec(&command).await else {
+            continue;
+        };
+        for line in String::from_utf8_lossy(&output).lines() {
+            if let Some((path, sum)) = parse_checksum_line(line) {
+                sums.insert(path, sum);
+            }
+        }
+    }
+    sums
+}
+
+/// The path and sum in a line of `sha256sum` output. A line starting with
+/// `\` has a name with a backslash or line break in it, escaped.
+fn parse_checksum_line(line: &str) -> Option<(String, String)> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (sum, path) = line.split_once(' ')?;
+    // One mode character, ` ` or `*`, before the name
+    let path = path.strip_prefix([' ', '*']).unwrap_or(path);
+    if !escaped {
+        return Some((path.to_string(), sum.to_lowercase()));
+    }
+
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => unescaped.push('\\'),
+            'n' => unescaped.push('\n'),
+            'r' => unescaped.push('\r'),
+            _ => return None,
+        }
+    }
+    Some((unescaped, sum.to_lowercase()))
+}
+
+/// Candidates whose local and remote sums agree.
+async fn same_content(
+    connection: &SftpConnection,
+    target: &SyncTarget,
+    local: &[TreeEntry],
+    remote: &[TreeEntry],
+) -> Result<HashSet<String>, String> {
+    let candidates = checksum_candidates(local, remote);
+    let remote_sums = remote_checksums(connection, &target.remote_path, &candidates).await;
+
+    let mut same = HashSet::new();
+    for path in candidates {
+        let Some(remote_sum) = remote_sums.get(&path) else {
+            continue;
+        };
+        let local_sum = sftp::local_sha256(&join_local(&target.local_path, &path)).await?;
+        if &local_sum == remote_sum {
+            same.insert(path);
+        }
+    }
+    Ok(same)
+}
+
+/// Walks both trees and plans the sync. A root that doesn't exist yet reads
+/// as empty.
+async fn scan(connection: &Arc<SftpConnection>, target: &SyncTarget) -> Result<SyncPlan, String> {
+    let options = &target.options;
+    let filter = TreeFilter::new(&options.include, &options.exclude)?;
+    // The destination's links are listed so whatever replaces one deletes
+    // it first, rather than being written through it
+    let (local_links, remote_links) = match options.mode {
+        SyncMode::Push => (SymlinkPolicy::Skip, SymlinkPolicy::Preserve),
+        SyncMode::Pull => (SymlinkPolicy::Preserve, SymlinkPolicy::Skip),
+    };
+
+    let remote = {
+        let sftp = connection.sftp().await?;
+        match sftp.try_exists(target.remote_path.as_str()).await {
+            Ok(true) => walk_remote(&sftp, &target.remote_path, &filter, remote_links).await?,
+            _ => Vec::new(),
+        }
+    };
+
+    let local = if Path::new(&target.local_path).exists() {
+        let root = PathBuf::from(&target.local_path);
+        tokio::task::spawn_blocking(move || walk_local(&root, &filter, local_links))
+            .await
+            .map_err(|e| format!("Failed to walk {}: {}", target.local_path, e))??
+    } else {
+        Vec::new()
+    };
+
+    let same = if options.checksum {
+        Some(same_content(connection, target, &local, &remote).await?)
+    } else {
+        None
+    };
+
+    let (source, dest) = match options.mode {
+        SyncMode::Push => (&local, &remote),
+        SyncMode::Pull => (&remote, &local),
+    };
+    Ok(plan_sync(source, dest, options.delete, same.as_ref()))
+}
+
+// ----- Execution -----
+
+/// Gives the copy the source's modification time, so the next scan sees
+/// the pair as in sync.
+async fn copy_mtime(
+    connection: &Arc<SftpConnection>,
+    mode: SyncMode,
+    local_path: &str,
+    remote_path: &str,
+) -> Result<(), String> {
+    match mode {
+        SyncMode::Push => {
+            let modified = fs::metadata(local_path)
+                .ok()
+                .and_then(|metadata| local_mtime(&metadata))
+                .and_then(|secs| u32::try_from(secs).ok());
+            let Some(modified) = modified else {
+                return Ok(());
+            };
+            let attrs = FileAttributes {
+                atime: Some(modified),
+                mtime: Some(modified),
+                ..FileAttributes::empty()
+            };
+            let sftp = connection.sftp().await?;
+            sftp.set_metadata(remote_path, attrs)
+                .await
+                .map_err(|e| format!("Failed to set times on {}: {}", remote_path, e))
+        }
+        SyncMode::Pull => {
+            let sftp = connection.sftp().await?;
+            let Some(modified) = sftp
+                .metadata(remote_path)
+                .await
+                .ok()
+                .and_then(|metadata| metadata.mtime)
+            else {
+                return Ok(());
+            };
+            let time = UNIX_EPOCH + Duration::from_secs(u64::from(modified));
+            fs::File::options()
+                .write(true)
+                .open(local_path)
+                .and_then(|file| file.set_modified(time))
+                .map_err(|e| format!("Failed to set times on {}: {}", local_path, e))
+        }
+    }
+}
+
+async fn delete_path(
+    connection: &Arc<SftpConnection>,
+    mode: SyncMode,
+    path: &str,
+    is_dir: bool,
+) -> Result<(), String> {
+    match mode {
+        SyncMode::Push => {
+            let sftp = connection.sftp().await?;
+            if is_dir {
+                sftp::delete_recursive(&sftp, path).await
+            } else {
+                sftp.remove_file(path)
+                    .await
+                    .map_err(|e| format!("Failed to delete {}: {}", path, e))
+            }
+        }
+        SyncMode::Pull => {
+            let result = if is_dir {
+                tokio::fs::remove_dir_all(path).await
+            } else {
+                tokio::fs::remove_file(path).await
+            };
+            result.map_err(|e| format!("Failed to delete {}: {}", path, e))
+        }
+    }
+}
+
+/// Carries out `plan`. Deletes and directories have to work for the files
+/// to land, so they stop the sync; a file that fails is reported and the
+/// rest carry on.
+async fn run_sync(
+    state: &SftpState,
+    connection: &Arc<SftpConnection>,
+    target: &SyncTarget,
+    plan: SyncPlan,
+    cancel_token: &CancellationToken,
+    on_progress: &Channel<TransferEvent>,
+) -> Result<(), String> {
+    let mode = target.options.mode;
+    let (direction, dest) = match mode {
+        SyncMode::Push => (TransferDirection::Upload, &target.remote_path),
+        SyncMode::Pull => (TransferDirection::Download, &target.local_path),
+    };
+    let dest_path = |relative: &str| match mode {
+        SyncMode::Push => join_remote(dest, relative),
+        SyncMode::Pull => join_local(dest, relative),
+    };
+
+    match mode {
+        SyncMode::Push => {
+            let sftp = connection.sftp().await?;
+            tree_transfer::ensure_remote_root(&sftp, dest).await?;
+        }
+        SyncMode::Pull => tree_transfer::ensure_local_root(dest).await?,
+    }
+
+    let mut files = Vec::new();
+    for action in plan.actions {
+        if cancel_token.is_cancelled() {
+            return Err("Sync cancelled".to_string());
+        }
+        match action {
+            SyncAction::Delete { path, is_dir } => {
+                delete_path(connection, mode, &dest_path(&path), is_dir).await?
+            }
+            SyncAction::CreateDir { path } => match mode {
+                SyncMode::Push => {
+                    let sftp = connection.sftp().await?;
+                    tree_transfer::ensure_remote_dir(&sftp, &dest_path(&path)).await?
+                }
+                SyncMode::Pull => tree_transfer::ensure_local_dir(&dest_path(&path)).await?,
+            },
+            SyncAction::Create { path, size } | SyncAction::Update { path, size, .. } => {
+                files.push((path, size))
+            }
+        }
+    }
+
+    let mut progress = TreeProgress::new(on_progress, files.len() as u64, plan.bytes_total);
+    for (relative, size) in files {
+        if cancel_token.is_cancelled() {
+            return Err("Sync cancelled".to_string());
+        }
+
+        let transfer = FileTransfer {
+            device_ip: target.device_ip.clone(),
+            direction: direction.clone(),
+            local_path: join_local(&target.local_path, &relative),
+            remote_path: join_remote(&target.remote_path, &relative),
+            verify_checksum: target.options.checksum,
+        };
+        let mut result = progress
+            .transfer(state, &transfer, &relative, cancel_token)
+            .await;
+        if result.is_err() && cancel_token.is_cancelled() {
+            return Err("Sync cancelled".to_string());
+        }
+        if result.is_ok() {
+            result = copy_mtime(
+                connection,
+                mode,
+                &transfer.local_path,
+                &transfer.remote_path,
+            )
+            .await;
+        }
+        progress.file_done(relative, size, result);
+    }
+
+    progress.finish()
+}
+
+// ----- Tauri Commands -----
+
+/// Compares the trees and returns what `sync_run` would do, changing nothing.
+#[tauri::command]
+pub async fn sync_plan(
+    state: State<'_, SftpState>,
+    target: SyncTarget,
+) -> Result<SyncPlan, String> {
+    let connection = sftp::get_connection(&state, &target.device_ip).await?;
+    scan(&connection, &target).await
+}
+
+/// Runs `plan`, as shown to the user, or a fresh one when it's left out.
+#[tauri::command]
+pub async fn sync_run(
+    state: State<'_, SftpState>,
+    target: SyncTarget,
+    plan: Option<SyncPlan>,
+    transfer_id: String,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    let connection = sftp::get_connection(&state, &target.device_ip).await?;
+    let direction = match target.options.mode {
+        SyncMode::Push => TransferDirection::Upload,
+        SyncMode::Pull => TransferDirection::Download,
+    };
+    let cancel_token = state.begin_transfer(&transfer_id, direction).await;
+
+    let result = match plan {
+        // The plan comes from the frontend, so nothing in it may reach
+        // outside the roots
+        Some(plan) => match plan.actions.iter().find(|a| !is_contained(a.path())) {
+            Some(action) => Err(format!("Sync plan has an unsafe path {:?}", action.path())),
+            None => Ok(plan),
+        },
+        None => scan(&connection, &target).await,
+    };
+    let result = match result {
+        Ok(plan) => {
+            run_sync(
+                &state,
+                &connection,
+                &target,
+                plan,
+                &cancel_token,
+                &on_progress,
+            )
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    state.end_transfer(&transfer_id).await;
+
+    let filename = Path::new(&target.local_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| target.local_path.clone());
+    tree_transfer::report_outcome(&on_progress, filename, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(relative: &str) -> TreeEntry {
+        TreeEntry {
+            relative: relative.to_string(),
+            kind: EntryKind::Dir,
+        }
+    }
+
+    fn file(relative: &str, size: u64, modified: u64) -> TreeEntry {
+        TreeEntry {
+            relative: relative.to_string(),
+            kind: EntryKind::File {
+                size,
+                modified: Some(modified),
+            },
+        }
+    }
+
+    fn link(relative: &str) -> TreeEntry {
+        TreeEntry {
+            relative: relative.to_string(),
+            kind: EntryKind::Symlink {
+                target: "/elsewhere".to_string(),
+            },
+        }
+    }
+
+    /// Each action as `kind path`, for comparing plans at a glance.
+    fn summary(plan: &SyncPlan) -> Vec<String> {
+        plan.actions
+            .iter()
+            .map(|action| match action {
+                SyncAction::CreateDir { path } => format!("mkdir {}", path),
+                SyncAction::Create { path, .. } => format!("create {}", path),
+                SyncAction::Update { path, .. } => format!("update {}", path),
+                SyncAction::Delete { path, .. } => format!("delete {}", path),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn push_copies_what_is_missing_or_changed() {
+        let local = vec![
+            dir("docs"),
+            file("docs/a.txt", 10, 100),
+            file("docs/b.txt", 20, 200),
+            file("c.txt", 30, 300),
+        ];
+        let remote = vec![
+            dir("docs"),
+            file("docs/a.txt", 10, 100),
+            file("c.txt", 31, 300),
+        ];
+
+        let plan = plan_sync(&local, &remote, false, None);
+        assert_eq!(summary(&plan), ["create docs/b.txt", "update c.txt"]);
+        assert_eq!(plan.bytes_total, 50);
+    }
+
+    #[test]
+    fn pull_plans_the_other_way() {
+        let local = vec![file("a.txt", 10, 100)];
+        let remote = vec![dir("new"), file("new/b.txt", 5, 50), file("a.txt", 10, 150)];
+
+        // For a pull the device's tree is the source
+        let plan = plan_sync(&remote, &local, false, None);
+        assert_eq!(
+            summary(&plan),
+            ["mkdir new", "create new/b.txt", "update a.txt"]
+        );
+        assert!(matches!(
+            plan.actions[2],
+            SyncAction::Update {
+                reason: UpdateReason::Modified,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn extra_files_stay_without_delete() {
+        let source = vec![file("a.txt", 1, 1)];
+        let dest = vec![file("a.txt", 1, 1), file("extra.txt", 2, 2), dir("old")];
+
+        assert!(plan_sync(&source, &dest, false, None).actions.is_empty());
+    }
+
+    #[test]
+    fn mirror_deletes_extras_and_whole_directories() {
+        let source = vec![file("a.txt", 1, 1)];
+        let dest = vec![
+            file("a.txt", 1, 1),
+            file("extra.txt", 2, 2),
+            dir("old"),
+            file("old/inside.txt", 3, 3),
+        ];
+
+        let plan = plan_sync(&source, &dest, true, None);
+        assert_eq!(summary(&plan), ["delete extra.txt", "delete old"]);
+        assert!(matches!(
+            plan.actions[1],
+            SyncAction::Delete { is_dir: true, .. }
+        ));
+    }
+
+    #[test]
+    fn a_kind_change_deletes_first_even_without_delete() {
+        let source = vec![dir("thing"), file("thing/a.txt", 1, 1)];
+        let dest = vec![file("thing", 5, 5)];
+
+        let plan = plan_sync(&source, &dest, false, None);
+        assert_eq!(
+            summary(&plan),
+            ["delete thing", "mkdir thing", "create thing/a.txt"]
+        );
+    }
+
+    #[test]
+    fn a_link_in_the_way_is_deleted_first() {
+        let source = vec![dir("thing"), file("thing/a.txt", 1, 1), file("other", 2, 2)];
+        let dest = vec![link("thing"), link("other"), link("extra")];
+
+        let plan = plan_sync(&source, &dest, false, None);
+        assert_eq!(
+            summary(&plan),
+            [
+                "delete thing",
+                "delete other",
+                "mkdir thing",
+                "create thing/a.txt",
+                "create other"
+            ]
+        );
+        assert!(matches!(
+            plan.actions[0],
+            SyncAction::Delete { is_dir: false, .. }
+        ));
+        // Links are left out of a sync, so a mirror doesn't delete them either
+        assert!(plan_sync(&[], &[link("extra")], true, None)
+            .actions
+            .is_empty());
+    }
+
+    #[test]
+    fn checksum_lines_are_parsed() {
+        let sum = "E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855";
+        let lower = sum.to_lowercase();
+        let parse = |line: String| parse_checksum_line(&line);
+
+        assert_eq!(
+            parse(format!("{}  docs/a b.txt", sum)),
+            Some(("docs/a b.txt".to_string(), lower.clone()))
+        );
+        assert_eq!(
+            parse(format!("{} *binary.bin", sum)),
+            Some(("binary.bin".to_string(), lower.clone()))
+        );
+        assert_eq!(
+            parse(format!("\\{}  dir/new\\nline\\\\slash", sum)),
+            Some(("dir/new\nline\\slash".to_string(), lower.clone()))
+        );
+        assert_eq!(parse(format!("\\{}  bad\\q", sum)), None);
+        assert_eq!(
+            parse(format!("{}   leading space", sum)),
+            Some((" leading space".to_string(), lower.clone()))
+        );
+        assert_eq!(parse("garbage".to_string()), None);
+    }
+
+    #[test]
+    fn same_content_skips_files_whatever_their_times() {
+        let source = vec![file("same.txt", 4, 100), file("changed.txt", 4, 100)];
+        let dest = vec![file("same.txt", 4, 999), file("changed.txt", 4, 100)];
+        let same: HashSet<String> = ["same.txt".to_string()].into();
+
+        let plan = plan_sync(&source, &dest, true, Some(&same));
+        assert_eq!(summary(&plan), ["update changed.txt"]);
+        assert!(matches!(
+            plan.actions[0],
+            SyncAction::Update {
+                reason: UpdateReason::Checksum,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn partial_transfers_are_left_alone() {
+        let source = vec![file("a.txt", 1, 1), file("b.bin.part", 9, 9)];
+        let dest = vec![
+            file("a.txt", 1, 1),
+            file("big.iso.part", 100, 5),
+            file("big.iso.part.json", 50, 5),
+        ];
+
+        assert!(plan_sync(&source, &dest, true, None).actions.is_empty());
+    }
+
+    #[test]
+    fn plans_with_escaping_paths_are_refused() {
+        assert!(is_contained("docs/a.txt"));
+        for path in [
+            "../etc/passwd",
+            "/etc/passwd",
+            "a/../../b",
+            "a//b",
+            "",
+            "./a",
+        ] {
+            assert!(!is_contained(path), "{:?} should be refused", path);
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use russh::keys::ssh_key::rand_core::OsRng;
+use russh::keys::ssh_key::{Algorithm, PublicKey};
+use russh::keys::PrivateKey;
+use russh::server::{Auth, Msg, Response, Session};
+use russh::{Channel, ChannelId, MethodKind, MethodSet};
+use russh_sftp::protocol::{
+    Attrs, Data, File, FileAttributes, Handle, Name, OpenFlags, Status, StatusCode, Version,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::net::SocketAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+// ----- Data Structures -----
+
+/// An SSH server on a loopback port that lets anyone in, unless a password
+/// or code is set in its stats, and serves SFTP from the local filesystem,
+/// for tests that need the real protocol. It runs until the test's runtime
+/// shuts down.
+pub struct TestServer {
+    pub addr: SocketAddr,
+    pub key: PublicKey,
+    pub stats: Arc<Stats>,
+}
+
+/// What the server has seen, for tests to check.
+#[derive(Default)]
+pub struct Stats {
+    /// Session channels open right now.
+    pub channels: AtomicUsize,
+    /// The most session channels that were ever open at once.
+    pub max_channels: AtomicUsize,
+    /// Fails the first SFTP read at or past this offset, then clears.
+    pub fail_read_at: Mutex<Option<u64>>,
+    /// Likewise for writes.
+    pub fail_write_at: Mutex<Option<u64>>,
+    /// Gives each connection a new host key, as a host whose key changes
+    /// between two connections would.
+    pub rekey: AtomicBool,
+    /// Offers password authentication and accepts only this one.
+    pub password: Mutex<Option<String>>,
+    /// Offers keyboard-interactive authentication, asking [`CODE_PROMPT`]
+    /// once and accepting only this answer.
+    pub code: Mutex<Option<String>>,
+}
+
+pub const CODE_PROMPT: &str = "Verification code: ";
+
+struct Connection {
+    stats: Arc<Stats>,
+    /// Channels opened but not yet handed to a subsystem.
+    pending: HashMap<ChannelId, Channel<Msg>>,
+}
+
+/// One SFTP channel's open files and directories.
+struct Sftp {
+    stats: Arc<Stats>,
+    files: HashMap<String, std::fs::File>,
+    /// Each open directory's listing, handed out whole on the first read.
+    dirs: HashMap<String, Vec<File>>,
+    next_handle: u64,
+}
+
+// ----- Server -----
+
+pub async fn start() -> TestServer {
+    let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).expect("Failed to generate key");
+    let public = key.public_key().clone();
+    let config = Arc::new(server_config(key));
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("Failed to bind test server");
+    let addr = listener.local_addr().expect("Failed to read test server address");
+    let stats = Arc::new(Stats::default());
+
+    let server_stats = Arc::clone(&stats);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let config = if server_stats.rekey.load(Ordering::SeqCst) {
+                let key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)
+                    .expect("Failed to generate key");
+                Arc::new(server_config(key))
+            } else {
+                config.clone()
+            };
+            let connection = Connection {
+                stats: Arc::clone(&server_stats),
+                pending: HashMap::new(),
+            };
+            tokio::spawn(async move {
+                if let Ok(session) = russh::server::run_stream(config, stream, connection).await {
+                    let _ = session.await;
+                }
+            });
+        }
+    });
+
+    TestServer {
+        addr,
+        key: public,
+        stats,
+    }
+}
+
+/// Wrong answers are answered quickly, so retries don't slow the tests.
+fn server_config(key: PrivateKey) -> russh::server::Config {
+    russh::server::Config {
+        keys: vec![key],
+        auth_rejection_time: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+impl Connection {
+    /// Everything is let in when there is neither a password nor a code.
+    fn methods(&self) -> Vec<MethodKind> {
+        let mut methods = Vec::new();
+        if self.stats.code.lock().unwrap().is_some() {
+            methods.push(MethodKind::KeyboardInteractive);
+        }
+        if self.stats.password.lock().unwrap().is_some() {
+            methods.push(MethodKind::Password);
+        }
+        methods
+    }
+
+    fn reject(&self) -> Auth {
+        Auth::Reject {
+            proceed_with_methods: Some(MethodSet::from(&self.methods()[..])),
+            partial_success: false,
+        }
+    }
+}
+
+impl russh::server::Handler for Connection {
+    type Error = russh::Error;
+
+    async fn auth_none(&mut self, _user: &str) -> Result<Auth, Self::Error> {
+        if self.methods().is_empty() {
+            Ok(Auth::Accept)
+        } else {
+            Ok(self.reject())
+        }
+    }
+
+    async fn auth_password(&mut self, _user: &str, password: &str) -> Result<Auth, Self::Error> {
+        if self.stats.password.lock().unwrap().as_deref() == Some(password) {
+            Ok(Auth::Accept)
+        } else {
+            Ok(self.reject())
+        }
+    }
+
+    async fn auth_keyboard_interactive<'a>(
+        &'a mut self,
+        _user: &str,
+        _submethods: &str,
+        response: Option<Response<'a>>,
+    ) -> Result<Auth, Self::Error> {
+        let Some(code) = self.stats.code.lock().unwrap().clone() else {
+            return Ok(self.reject());
+        };
+        let Some(mut response) = response else {
+            return Ok(Auth::Partial {
+                name: Cow::Borrowed("Two-factor"),
+                instructions: Cow::Borrowed(""),
+                prompts: Cow::Owned(vec![(Cow::Borrowed(CODE_PROMPT), false)]),
+            });
+        };
+        if response.next().as_deref() == Some(code.as_bytes()) {
+            Ok(Auth::Accept)
+        } else {
+            Ok(self.reject())
+        }
+    }
+
+    async fn channel_open_session(
+        &mut self,
+        channel: Channel<Msg>,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        let open = self.stats.channels.fetch_add(1, Ordering::SeqCst) + 1;
+        self.stats.max_channels.fetch_max(open, Ordering::SeqCst);
+        self.pending.insert(channel.id(), channel);
+        Ok(true)
+    }
+
+    async fn channel_close(
+        &mut self,
+        channel: ChannelId,
+        _session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        self.pending.remove(&channel);
+        self.stats.channels.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn subsystem_request(
+        &mut self,
+        channel_id: ChannelId,
+        name: &str,
+        session: &mut Session,
+    ) -> Result<(), Self::Error> {
+        match self.pending.remove(&channel_id) {
+            Some(channel) if name == "sftp" => {
+                session.channel_success(channel_id)?;
+                let sftp = Sftp {
+                    stats: Arc::clone(&self.stats),
+                    files: HashMap::new(),
+                    dirs: HashMap::new(),
+                    next_handle: 0,
+                };
+                russh_sftp::server::run(channel.into_stream(), sftp).await;
+            }
+            _ => session.channel_failure(channel_id)?,
+        }
+        Ok(())
+    }
+}
+
+// ----- SFTP -----
+
+fn status(err: std::io::Error) -> StatusCode {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => StatusCode::NoSuchFile,
+        std::io::ErrorKind::PermissionDenied => StatusCode::PermissionDenied,
+        _ => StatusCode::Failure,
+    }
+}
+
+fn ok(id: u32) -> Status {
+    Status {
+        id,
+        status_code: StatusCode::Ok,
+        error_message: "Ok".to_string(),
+        language_tag: "en-US".to_string(),
+    }
+}
+
+/// Whether `offset` trips the failure set in `at`, which it clears.
+fn trips(at: &Mutex<Option<u64>>, offset: u64) -> bool {
+    let mut at = at.lock().unwrap();
+    match *at {
+        Some(limit) if offset >= limit => {
+            *at = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+impl Sftp {
+    fn file(&mut self, handle: &str) -> Result<&mut std::fs::File, StatusCode> {
+        self.files.get_mut(handle).ok_or(StatusCode::Failure)
+    }
+
+    fn next_handle(&mut self) -> String {
+        self.next_handle += 1;
+        self.next_handle.to_string()
+    }
+}
+
+impl russh_sftp::server::Handler for Sftp {
+    type Error = StatusCode;
+
+    fn unimplemented(&self) -> Self::Error {
+        StatusCode::OpUnsupported
+    }
+
+    async fn init(
+        &mut self,
+        _version: u32,
+        _extensions: HashMap<String, String>,
+    ) -> Result<Version, Self::Error> {
+        Ok(Version::new())
+    }
+
+    async fn open(
+        &mut self,
+        id: u32,
+        filename: String,
+        pflags: OpenFlags,
+        _attrs: FileAttributes,
+    ) -> Result<Handle, Self::Error> {
+        let file = std::fs::OpenOptions::from(pflags)
+            .open(&filename)
+            .map_err(status)?;
+        let handle = self.next_handle();
+        self.files.insert(handle.clone(), file);
+        Ok(Handle { id, handle })
+    }
+
+    async fn close(&mut self, id: u32, handle: String) -> Result<Status, Self::Error> {
+        self.files.remove(&handle);
+        self.dirs.remove(&handle);
+        Ok(ok(id))
+    }
+
+    async fn opendir(&mut self, id: u32, path: String) -> Result<Handle, Self::Error> {
+        let mut listing = Vec::new();
+        for entry in std::fs::read_dir(&path).map_err(status)? {
+            let entry = entry.map_err(status)?;
+            let metadata = std::fs::symlink_metadata(entry.path()).map_err(status)?;
+            listing.push(File::new(
+                entry.file_name().to_string_lossy(),
+                FileAttributes::from(&metadata),
+            ));
+        }
+        let handle = self.next_handle();
+        self.dirs.insert(handle.clone(), listing);
+        Ok(Handle { id, handle })
+    }
+
+    async fn readdir(&mut self, id: u32, handle: String) -> Result<Name, Self::Error> {
+        let listing = self.dirs.get_mut(&handle).ok_or(StatusCode::Failure)?;
+        if listing.is_empty() {
+            return Err(StatusCode::Eof);
+        }
+        Ok(Name {
+            id,
+            files: std::mem::take(listing),
+        })
+    }
+
+    async fn mkdir(
+        &mut self,
+        id: u32,
+        path: String,
+        _attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        std::fs::create_dir(&path).map_err(status)?;
+        Ok(ok(id))
+    }
+
+    async fn read(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        len: u32,
+    ) -> Result<Data, Self::Error> {
+        if trips(&self.stats.fail_read_at, offset) {
+            return Err(StatusCode::Failure);
+        }
+        let file = self.file(&handle)?;
+        file.seek(SeekFrom::Start(offset)).map_err(status)?;
+        let mut data = vec![0; len as usize];
+        let read = file.read(&mut data).map_err(status)?;
+        if read == 0 {
+            return Err(StatusCode::Eof);
+        }
+        data.truncate(read);
+        Ok(Data { id, data })
+    }
+
+    async fn write(
+        &mut self,
+        id: u32,
+        handle: String,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<Status, Self::Error> {
+        if trips(&self.stats.fail_write_at, offset) {
+            return Err(StatusCode::Failure);
+        }
+        let file = self.file(&handle)?;
+        file.seek(SeekFrom::Start(offset)).map_err(status)?;
+        file.write_all(&data).map_err(status)?;
+        Ok(ok(id))
+    }
+
+    async fn fstat(&mut self, id: u32, handle: String) -> Result<Attrs, Self::Error> {
+        let metadata = self.file(&handle)?.metadata().map_err(status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn stat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = std::fs::metadata(&path).map_err(status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn lstat(&mut self, id: u32, path: String) -> Result<Attrs, Self::Error> {
+        let metadata = std::fs::symlink_metadata(&path).map_err(status)?;
+        Ok(Attrs {
+            id,
+            attrs: FileAttributes::from(&metadata),
+        })
+    }
+
+    async fn setstat(
+        &mut self,
+        id: u32,
+        path: String,
+        attrs: FileAttributes,
+    ) -> Result<Status, Self::Error> {
+        if let Some(permissions) = attrs.permissions {
+            let permissions = std::fs::Permissions::from_mode(permissions & 0o7777);
+            std::fs::set_permissions(&path, permissions).map_err(status)?;
+        }
+        Ok(ok(id))
+    }
+
+    async fn realpath(&mut self, id: u32, path: String) -> Result<Name, Self::Error> {
+        let path = std::fs::canonicalize(&path).map_err(status)?;
+        Ok(Name {
+            id,
+            files: vec![File::dummy(path.to_string_lossy())],
+        })
+    }
+
+    async fn rename(
+        &mut self,
+        id: u32,
+        oldpath: String,
+        newpath: String,
+    ) -> Result<Status, Self::Error> {
+        // As in SFTP v3, which won't replace an existing file
+        if std::fs::symlink_metadata(&newpath).is_ok() {
+            return Err(StatusCode::Failure);
+        }
+        std::fs::rename(&oldpath, &newpath).map_err(status)?;
+        Ok(ok(id))
+    }
+
+    async fn remove(&mut self, id: u32, filename: String) -> Result<Status, Self::Error> {
+        std::fs::remove_file(&filename).map_err(status)?;
+        Ok(ok(id))
+    }
+}
@@ -0,0 +1,747 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State};
+use tokio_util::sync::CancellationToken;
+
+use crate::sftp::{self, FileTransfer, SftpState, TransferDirection, TransferEvent};
+
+// ----- Data Structures -----
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "state", content = "message")]
+pub enum QueueStatus {
+    Queued,
+    Running,
+    Paused,
+    Complete,
+    Failed(String),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedTransfer {
+    pub id: String,
+    #[serde(flatten)]
+    pub transfer: FileTransfer,
+    pub status: QueueStatus,
+    pub bytes_transferred: u64,
+    pub total_bytes: u64,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueLimits {
+    /// Transfers running at once across all devices.
+    pub global: usize,
+    /// Transfers running at once to or from any one device.
+    pub per_device: usize,
+}
+
+impl Default for QueueLimits {
+    fn default() -> Self {
+        QueueLimits {
+            global: 4,
+            per_device: 2,
+        }
+    }
+}
+
+/// The queue in order, as shown to the frontend and saved to disk.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueSnapshot {
+    pub jobs: Vec<QueuedTransfer>,
+    #[serde(default)]
+    pub limits: QueueLimits,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum QueueEvent {
+    /// A job was added or changed status.
+    Updated(QueuedTransfer),
+    Progress {
+        id: String,
+        bytes_transferred: u64,
+        total_bytes: u64,
+    },
+    Retrying {
+        id: String,
+        attempt: u32,
+        max_attempts: u32,
+        delay_ms: u64,
+        message: String,
+    },
+    Removed {
+        id: String,
+    },
+    /// Every job id in the new order.
+    Reordered {
+        ids: Vec<String>,
+    },
+    LimitsChanged(QueueLimits),
+}
+
+/// A job's task, from when it starts until it has fully exited.
+struct RunningJob {
+    /// Tells this run apart from a later one of the same job.
+    run: u64,
+    cancel_token: CancellationToken,
+}
+
+// ----- State Management -----
+
+pub struct TransferQueue {
+    queue: Mutex<QueueSnapshot>,
+    /// Jobs whose task hasn't exited yet, including ones paused or removed
+    /// that are still unwinding.
+    running: Mutex<HashMap<String, RunningJob>>,
+    subscribers: Mutex<Vec<Channel<QueueEvent>>>,
+    next_id: AtomicU64,
+    next_run: AtomicU64,
+}
+
+impl Default for TransferQueue {
+    /// Picks up the queue saved by the last run. Jobs that were running go
+    /// back to queued; they resume from their partial files.
+    fn default() -> Self {
+        TransferQueue {
+            queue: Mutex::new(load(&get_queue_path())),
+            running: Mutex::new(HashMap::new()),
+            subscribers: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            next_run: AtomicU64::new(0),
+        }
+    }
+}
+
+impl TransferQueue {
+    fn snapshot(&self) -> QueueSnapshot {
+        self.queue.lock().unwrap().clone()
+    }
+
+    /// Sends `event` to every subscriber, dropping those that went away.
+    fn broadcast(&self, event: QueueEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Applies `change` to job `id` and saves the queue. Fails if there is
+    /// no such job or `change` refuses.
+    fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut QueuedTransfer) -> Result<(), String>,
+    ) -> Result<QueuedTransfer, String> {
+        let job = {
+            let mut queue = self.queue.lock().unwrap();
+            let job = queue
+                .jobs
+                .iter_mut()
+                .find(|job| job.id == id)
+                .ok_or_else(|| "No such transfer in the queue".to_string())?;
+            change(job)?;
+            let job = job.clone();
+            save(&queue);
+            job
+        };
+        self.broadcast(QueueEvent::Updated(job.clone()));
+        Ok(job)
+    }
+
+    fn on_transfer_event(&self, id: &str, event: TransferEvent) {
+        match event {
+            TransferEvent::Progress {
+                bytes_transferred,
+                total_bytes,
+                ..
+            } => {
+                // Not saved: the partial file is what a resume goes by
+                if let Some(job) = self
+                    .queue
+                    .lock()
+                    .unwrap()
+                    .jobs
+                    .iter_mut()
+                    .find(|job| job.id == id)
+                {
+                    job.bytes_transferred = bytes_transferred;
+                    job.total_bytes = total_bytes;
+                }
+                self.broadcast(QueueEvent::Progress {
+                    id: id.to_string(),
+                    bytes_transferred,
+                    total_bytes,
+                });
+            }
+            TransferEvent::Retrying {
+                attempt,
+                max_attempts,
+                delay_ms,
+                message,
+                ..
+            } => self.broadcast(QueueEvent::Retrying {
+                id: id.to_string(),
+                attempt,
+                max_attempts,
+                delay_ms,
+                message,
+            }),
+            _ => {}
+        }
+    }
+
+    /// Cancels job `id`'s task, if it has one.
+    fn cancel(&self, id: &str) {
+        if let Some(running) = self.running.lock().unwrap().get(id) {
+            running.cancel_token.cancel();
+        }
+    }
+
+    /// Records how run `run` of job `id` ended, unless the job was paused or
+    /// removed while running, which is what cancelled it.
+    fn finish(&self, id: &str, run: u64, result: Result<(), String>) {
+        {
+            let mut running = self.running.lock().unwrap();
+            if running.get(id).map(|running| running.run) != Some(run) {
+                return;
+            }
+            running.remove(id);
+        }
+        let _ = self.update(id, |job| {
+            if job.status != QueueStatus::Running {
+                return Err("no longer running".to_string());
+            }
+            job.status = match result {
+                Ok(()) => QueueStatus::Complete,
+                Err(message) => QueueStatus::Failed(message),
+            };
+            Ok(())
+        });
+    }
+}
+
+// ----- Helper Functions -----
+
+fn get_queue_path() -> PathBuf {
+    let proj_dirs = directories::ProjectDirs::from("com", "homelab", "control-center")
+        .expect("Failed to get project directories");
+    let data_dir = proj_dirs.data_dir();
+    fs::create_dir_all(data_dir).ok();
+    data_dir.join("transfer_queue.json")
+}
+
+/// Reads the queue saved at `path`, putting jobs that were running back in
+/// line. A file that doesn't parse is set aside as `.corrupt` rather than
+/// overwritten by the next save.
+fn load(path: &Path) -> QueueSnapshot {
+    let Ok(content) = fs::read_to_string(path) else {
+        return QueueSnapshot::default();
+    };
+    let mut queue: QueueSnapshot = match serde_json::from_str(&content) {
+        Ok(queue) => queue,
+        Err(_) => {
+            fs::rename(path, path.with_extension("json.corrupt")).ok();
+            return QueueSnapshot::default();
+        }
+    };
+    for job in &mut queue.jobs {
+        if job.status == QueueStatus::Running {
+            job.status = QueueStatus::Queued;
+        }
+    }
+    queue
+}
+
+/// Best effort: a queue that fails to save still runs, it just won't
+/// survive a restart. Callers hold the queue lock, which keeps saves in
+/// order.
+fn save(queue: &QueueSnapshot) {
+    save_to(&get_queue_path(), queue).ok();
+}
+
+/// Writes beside `path` and renames into place, so a crash mid-write
+/// leaves the previous queue rather than half of this one.
+fn save_to(path: &Path, queue: &QueueSnapshot) -> std::io::Result<()> {
+    let content = serde_json::to_vec_pretty(queue)?;
+    let temp_path = path.with_extension("json.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(&content)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+/// Marks the jobs that may start now as running and returns them: queued
+/// ones, in queue order, on `connected` devices, within the limits. Jobs in
+/// `unwinding` still have a run exiting and wait for it.
+fn next_jobs(
+    queue: &mut QueueSnapshot,
+    connected: &HashSet<String>,
+    unwinding: &HashSet<String>,
+) -> Vec<QueuedTransfer> {
+    let limits = queue.limits;
+    let mut per_device: HashMap<String, usize> = HashMap::new();
+    for job in queue.jobs.iter().filter(|j| j.status == QueueStatus::Running) {
+        *per_device.entry(job.transfer.device_ip.clone()).or_default() += 1;
+    }
+    let mut total: usize = per_device.values().sum();
+
+    let mut started = Vec::new();
+    for job in queue.jobs.iter_mut() {
+        if total >= limits.global {
+            break;
+        }
+        if job.status != QueueStatus::Queued
+            || !connected.contains(&job.transfer.device_ip)
+            || unwinding.contains(&job.id)
+        {
+            continue;
+        }
+        let running = per_device.entry(job.transfer.device_ip.clone()).or_default();
+        if *running >= limits.per_device {
+            continue;
+        }
+        *running += 1;
+        total += 1;
+        job.status = QueueStatus::Running;
+        started.push(job.clone());
+    }
+    started
+}
+
+/// Moves job `id` to position `index` (or the end) and returns the new
+/// order of ids.
+fn move_job(queue: &mut QueueSnapshot, id: &str, index: usize) -> Result<Vec<String>, String> {
+    let from = queue
+        .jobs
+        .iter()
+        .position(|job| job.id == id)
+        .ok_or_else(|| "No such transfer in the queue".to_string())?;
+    let job = queue.jobs.remove(from);
+    let index = index.min(queue.jobs.len());
+    queue.jobs.insert(index, job);
+    Ok(queue.jobs.iter().map(|job| job.id.clone()).collect())
+}
+
+/// Starts as many queued jobs as the limits allow, in queue order. Jobs for
+/// devices that aren't connected wait until `sftp_connect` pumps again.
+pub fn pump(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let queue = app.state::<TransferQueue>();
+        let sftp = app.state::<SftpState>();
+
+        let devices: HashSet<String> = queue
+            .snapshot()
+            .jobs
+            .into_iter()
+            .filter(|job| job.status == QueueStatus::Queued)
+            .map(|job| job.transfer.device_ip)
+            .collect();
+        let mut connected = HashSet::new();
+        for device_ip in devices {
+            if sftp.is_connected(&device_ip).await {
+                connected.insert(device_ip);
+            }
+        }
+        // A job paused and resumed before its last run exited waits for it,
+        // so two runs never write the same partial file; the exit pumps again
+        let unwinding: HashSet<String> = queue.running.lock().unwrap().keys().cloned().collect();
+
+        let started = {
+            let mut queue = queue.queue.lock().unwrap();
+            let started = next_jobs(&mut queue, &connected, &unwinding);
+            if !started.is_empty() {
+                save(&queue);
+            }
+            started
+        };
+
+        for job in started {
+            queue.broadcast(QueueEvent::Updated(job.clone()));
+            run_job(&app, job);
+        }
+    });
+}
+
+fn run_job(app: &AppHandle, job: QueuedTransfer) {
+    let cancel_token = CancellationToken::new();
+    let queue = app.state::<TransferQueue>();
+    let run = queue.next_run.fetch_add(1, Ordering::Relaxed);
+    queue.running.lock().unwrap().insert(
+        job.id.clone(),
+        RunningJob {
+            run,
+            cancel_token: cancel_token.clone(),
+        },
+    );
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let queue = app.state::<TransferQueue>();
+        let sftp = app.state::<SftpState>();
+
+        let report = |event| queue.on_transfer_event(&job.id, event);
+        let result = sftp::run_transfer(&sftp, &job.transfer, &cancel_token, &report).await;
+
+        // Paused jobs keep their partial file to resume from; removed ones
+        // have nothing left to resume it
+        let removed = !queue.queue.lock().unwrap().jobs.iter().any(|j| j.id == job.id);
+        if result.is_err() && removed {
+            sftp::discard_partial(&sftp, &job.transfer).await;
+        }
+
+        queue.finish(&job.id, run, result);
+        pump(&app);
+    });
+}
+
+// ----- Tauri Commands -----
+
+/// Subscribes `on_event` to queue changes and returns the queue as it is,
+/// so nothing falls between the two.
+#[tauri::command]
+pub async fn transfer_queue_subscribe(
+    queue: State<'_, TransferQueue>,
+    on_event: Channel<QueueEvent>,
+) -> Result<QueueSnapshot, String> {
+    let mut subscribers = queue.subscribers.lock().unwrap();
+    subscribers.push(on_event);
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+pub async fn transfer_queue_list(queue: State<'_, TransferQueue>) -> Result<QueueSnapshot, String> {
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+pub async fn transfer_queue_enqueue(
+    app: AppHandle,
+    queue: State<'_, TransferQueue>,
+    device_ip: String,
+    direction: TransferDirection,
+    local_path: String,
+    remote_path: String,
+    verify_checksum: Option<bool>,
+) -> Result<QueuedTransfer, String> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let job = QueuedTransfer {
+        id: format!(
+            "queued-{}-{}",
+            millis,
+            queue.next_id.fetch_add(1, Ordering::Relaxed)
+        ),
+        transfer: FileTransfer {
+            device_ip,
+            direction,
+            local_path,
+            remote_path,
+            verify_checksum: verify_checksum.unwrap_or(false),
+        },
+        status: QueueStatus::Queued,
+        bytes_transferred: 0,
+        total_bytes: 0,
+    };
+
+    {
+        let mut snapshot = queue.queue.lock().unwrap();
+        snapshot.jobs.push(job.clone());
+        save(&snapshot);
+    }
+    queue.broadcast(QueueEvent::Updated(job.clone()));
+    pump(&app);
+
+    Ok(job)
+}
+
+/// Holds a queued job back, or stops a running one. Its partial file is
+/// kept, so resuming carries on from there.
+#[tauri::command]
+pub async fn transfer_queue_pause(
+    queue: State<'_, TransferQueue>,
+    id: String,
+) -> Result<QueuedTransfer, String> {
+    let job = queue.update(&id, |job| match job.status {
+        QueueStatus::Queued | QueueStatus::Running => {
+            job.status = QueueStatus::Paused;
+            Ok(())
+        }
+        _ => Err("Only queued or running transfers can be paused".to_string()),
+    })?;
+    queue.cancel(&id);
+    Ok(job)
+}
+
+/// Puts a paused or failed job back in line.
+#[tauri::command]
+pub async fn transfer_queue_resume(
+    app: AppHandle,
+    queue: State<'_, TransferQueue>,
+    id: String,
+) -> Result<QueuedTransfer, String> {
+    let job = queue.update(&id, |job| match job.status {
+        QueueStatus::Paused | QueueStatus::Failed(_) => {
+            job.status = QueueStatus::Queued;
+            Ok(())
+        }
+        _ => Err("Only paused or failed transfers can be resumed".to_string()),
+    })?;
+    pump(&app);
+    Ok(job)
+}
+
+/// Moves job `id` to position `index`, which decides what starts next.
+#[tauri::command]
+pub async fn transfer_queue_move(
+    app: AppHandle,
+    queue: State<'_, TransferQueue>,
+    id: String,
+    index: usize,
+) -> Result<(), String> {
+    let ids = {
+        let mut snapshot = queue.queue.lock().unwrap();
+        let ids = move_job(&mut snapshot, &id, index)?;
+        save(&snapshot);
+        ids
+    };
+    queue.broadcast(QueueEvent::Reordered { ids });
+    pump(&app);
+    Ok(())
+}
+
+/// Drops a job from the queue, cancelling it if it is running.
+#[tauri::command]
+pub async fn transfer_queue_remove(
+    queue: State<'_, TransferQueue>,
+    sftp: State<'_, SftpState>,
+    id: String,
+) -> Result<(), String> {
+    let removed = {
+        let mut snapshot = queue.queue.lock().unwrap();
+        let position = snapshot.jobs.iter().position(|job| job.id == id);
+        let removed = position.map(|i| snapshot.jobs.remove(i));
+        save(&snapshot);
+        removed
+    };
+    queue.cancel(&id);
+    queue.broadcast(QueueEvent::Removed { id: id.clone() });
+
+    // A running job clears up after itself as it exits; a paused one may
+    // have left a partial file behind
+    let running = queue.running.lock().unwrap().contains_key(&id);
+    if let Some(job) = removed.filter(|_| !running) {
+        sftp::discard_partial(&sftp, &job.transfer).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn transfer_queue_clear_finished(queue: State<'_, TransferQueue>) -> Result<(), String> {
+    let removed: Vec<String> = {
+        let mut snapshot = queue.queue.lock().unwrap();
+        let (finished, kept) = std::mem::take(&mut snapshot.jobs)
+            .into_iter()
+            .partition(|job| {
+                matches!(job.status, QueueStatus::Complete | QueueStatus::Failed(_))
+            });
+        snapshot.jobs = kept;
+        save(&snapshot);
+        finished.into_iter().map(|job: QueuedTransfer| job.id).collect()
+    };
+    for id in removed {
+        queue.broadcast(QueueEvent::Removed { id });
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn transfer_queue_set_limits(
+    app: AppHandle,
+    queue: State<'_, TransferQueue>,
+    limits: QueueLimits,
+) -> Result<(), String> {
+    if limits.global == 0 || limits.per_device == 0 {
+        return Err("Limits must be at least 1".to_string());
+    }
+    {
+        let mut snapshot = queue.queue.lock().unwrap();
+        snapshot.limits = limits;
+        save(&snapshot);
+    }
+    queue.broadcast(QueueEvent::LimitsChanged(limits));
+    // Lowering a limit lets running jobs finish; raising it starts more now
+    pump(&app);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, device_ip: &str, status: QueueStatus) -> QueuedTransfer {
+        QueuedTransfer {
+            id: id.to_string(),
+            transfer: FileTransfer {
+                device_ip: device_ip.to_string(),
+                direction: TransferDirection::Download,
+                local_path: format!("/tmp/{id}"),
+                remote_path: format!("/srv/{id}"),
+                verify_checksum: false,
+            },
+            status,
+            bytes_transferred: 0,
+            total_bytes: 0,
+        }
+    }
+
+    fn queue(jobs: Vec<QueuedTransfer>, global: usize, per_device: usize) -> QueueSnapshot {
+        QueueSnapshot {
+            jobs,
+            limits: QueueLimits { global, per_device },
+        }
+    }
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn ids(jobs: &[QueuedTransfer]) -> Vec<&str> {
+        jobs.iter().map(|job| job.id.as_str()).collect()
+    }
+
+    fn running(queue: &QueueSnapshot) -> Vec<&str> {
+        let running = queue
+            .jobs
+            .iter()
+            .filter(|job| job.status == QueueStatus::Running);
+        running.map(|job| job.id.as_str()).collect()
+    }
+
+    #[test]
+    fn starts_jobs_up_to_the_global_limit() {
+        let devices = ["a", "b", "c", "d"];
+        let jobs = devices
+            .iter()
+            .map(|d| job(d, d, QueueStatus::Queued))
+            .collect();
+        let mut queue = queue(jobs, 3, 2);
+
+        let started = next_jobs(&mut queue, &set(&devices), &set(&[]));
+        assert_eq!(ids(&started), ["a", "b", "c"]);
+        assert_eq!(running(&queue), ["a", "b", "c"]);
+
+        // Full until one of them finishes
+        assert!(next_jobs(&mut queue, &set(&devices), &set(&[])).is_empty());
+        queue.jobs[1].status = QueueStatus::Complete;
+        assert_eq!(
+            ids(&next_jobs(&mut queue, &set(&devices), &set(&[]))),
+            ["d"]
+        );
+    }
+
+    #[test]
+    fn starts_jobs_up_to_the_per_device_limit() {
+        let mut queue = queue(
+            vec![
+                job("1", "nas", QueueStatus::Running),
+                job("2", "nas", QueueStatus::Queued),
+                job("3", "nas", QueueStatus::Queued),
+                job("4", "pi", QueueStatus::Queued),
+            ],
+            4,
+            2,
+        );
+        let started = next_jobs(&mut queue, &set(&["nas", "pi"]), &set(&[]));
+        // The running job counts towards its device
+        assert_eq!(ids(&started), ["2", "4"]);
+        assert!(queue.jobs[2].status == QueueStatus::Queued);
+    }
+
+    #[test]
+    fn skips_paused_unwinding_and_disconnected_jobs() {
+        let mut queue = queue(
+            vec![
+                job("paused", "nas", QueueStatus::Paused),
+                job("unwinding", "nas", QueueStatus::Queued),
+                job("offline", "pi", QueueStatus::Queued),
+                job("failed", "nas", QueueStatus::Failed("boom".to_string())),
+                job("next", "nas", QueueStatus::Queued),
+            ],
+            4,
+            4,
+        );
+        let started = next_jobs(&mut queue, &set(&["nas"]), &set(&["unwinding"]));
+        assert_eq!(ids(&started), ["next"]);
+    }
+
+    #[test]
+    fn moving_a_job_changes_what_starts_next() {
+        let jobs = ["1", "2", "3"]
+            .iter()
+            .map(|id| job(id, "nas", QueueStatus::Queued))
+            .collect();
+        let mut queue = queue(jobs, 1, 1);
+
+        assert_eq!(move_job(&mut queue, "3", 0).unwrap(), ["3", "1", "2"]);
+        assert_eq!(
+            ids(&next_jobs(&mut queue, &set(&["nas"]), &set(&[]))),
+            ["3"]
+        );
+        // Past the end goes last
+        assert_eq!(move_job(&mut queue, "1", 99).unwrap(), ["3", "2", "1"]);
+        assert!(move_job(&mut queue, "missing", 0).is_err());
+    }
+
+    #[test]
+    fn reload_puts_running_jobs_back_in_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transfer_queue.json");
+        let saved = queue(
+            vec![
+                job("running", "nas", QueueStatus::Running),
+                job("paused", "nas", QueueStatus::Paused),
+                job("done", "nas", QueueStatus::Complete),
+            ],
+            3,
+            1,
+        );
+        save_to(&path, &saved).unwrap();
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let loaded = load(&path);
+        assert_eq!(ids(&loaded.jobs), ["running", "paused", "done"]);
+        assert!(loaded.jobs[0].status == QueueStatus::Queued);
+        assert!(loaded.jobs[1].status == QueueStatus::Paused);
+        assert!(loaded.jobs[2].status == QueueStatus::Complete);
+        assert_eq!((loaded.limits.global, loaded.limits.per_device), (3, 1));
+    }
+
+    #[test]
+    fn a_corrupt_queue_is_set_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transfer_queue.json");
+        assert!(load(&path).jobs.is_empty());
+
+        fs::write(&path, r#"{"jobs": [{"id": "trunc"#).unwrap();
+        assert!(load(&path).jobs.is_empty());
+        assert!(!path.exists());
+        assert!(path.with_extension("json.corrupt").exists());
+    }
+}
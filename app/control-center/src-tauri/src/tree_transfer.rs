@@ -0,0 +1,1113 @@
+use glob::{MatchOptions, Pattern};
+use russh_sftp::client::SftpSession;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::ipc::Channel;
+use tauri::State;
+use tokio_util::sync::CancellationToken;
+
+use crate::sftp::{self, FileTransfer, SftpState, TransferDirection, TransferEvent};
+
+/// Deepest a walk goes, so a followed link loop the canonical-path check
+/// misses still ends.
+const MAX_DEPTH: usize = 64;
+
+/// How many `name (n).ext` candidates to try before giving up on a free name.
+const MAX_RENAME_ATTEMPTS: u32 = 1000;
+
+// ----- Data Structures -----
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SymlinkPolicy {
+    /// Leave links out.
+    #[default]
+    Skip,
+    /// Copy whatever the link points to.
+    Follow,
+    /// Recreate the link itself.
+    Preserve,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum OverwritePolicy {
+    /// Keep files that already exist.
+    Skip,
+    #[default]
+    Overwrite,
+    /// Replace only files older than the source.
+    NewerOnly,
+    /// Keep both, saving the new one as `name (1).ext`.
+    Rename,
+}
+
+#[derive(Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TreeOptions {
+    pub symlinks: SymlinkPolicy,
+    /// Globs for files to send; empty sends them all. A pattern without a
+    /// `/` matches file names, one with a `/` the path under the root.
+    pub include: Vec<String>,
+    /// Globs for files and directories to leave out, matched the same way.
+    pub exclude: Vec<String>,
+    pub overwrite: OverwritePolicy,
+    pub verify_checksum: bool,
+}
+
+/// Something a walk found, by its `/`-separated path under the root.
+#[derive(Clone)]
+pub(crate) struct TreeEntry {
+    pub relative: String,
+    pub kind: EntryKind,
+}
+
+#[derive(Clone)]
+pub(crate) enum EntryKind {
+    Dir,
+    File {
+        size: u64,
+        /// Seconds since the epoch.
+        modified: Option<u64>,
+    },
+    Symlink {
+        target: String,
+    },
+}
+
+/// Compiled include and exclude globs.
+#[derive(Clone)]
+pub(crate) struct TreeFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl TreeFilter {
+    pub(crate) fn new(include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).map_err(|e| format!("Invalid pattern `{}`: {}", p, e)))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(TreeFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    fn matches(patterns: &[Pattern], relative: &str) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let name = relative.rsplit('/').next().unwrap_or(relative);
+        patterns.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(relative, options)
+            } else {
+                pattern.matches_with(name, options)
+            }
+        })
+    }
+
+    /// Excluded directories are not walked into at all.
+    pub(crate) fn excluded(&self, relative: &str) -> bool {
+        Self::matches(&self.exclude, relative)
+    }
+
+    pub(crate) fn included(&self, relative: &str) -> bool {
+        self.include.is_empty() || Self::matches(&self.include, relative)
+    }
+}
+
+/// Progress and failures across the files of a multi-file transfer.
+pub(crate) struct TreeProgress<'a> {
+    on_progress: &'a Channel<TransferEvent>,
+    files_done: u64,
+    files_total: u64,
+    bytes_done: u64,
+    bytes_total: u64,
+    failures: Vec<String>,
+}
+
+impl<'a> TreeProgress<'a> {
+    pub(crate) fn new(
+        on_progress: &'a Channel<TransferEvent>,
+        files_total: u64,
+        bytes_total: u64,
+    ) -> Self {
+        TreeProgress {
+            on_progress,
+            files_done: 0,
+            files_total,
+            bytes_done: 0,
+            bytes_total,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Moves one file, reporting its bytes as part of the whole.
+    pub(crate) async fn transfer(
+        &self,
+        state: &SftpState,
+        transfer: &FileTransfer,
+        relative: &str,
+        cancel_token: &CancellationToken,
+    ) -> Result<(), String> {
+        let report = |event| match event {
+            TransferEvent::Progress {
+                bytes_transferred, ..
+            } => {
+                let _ = self.on_progress.send(TransferEvent::TreeProgress {
+                    filename: relative.to_string(),
+                    files_done: self.files_done,
+                    files_total: self.files_total,
+                    bytes_done: self.bytes_done + bytes_transferred,
+                    bytes_total: self.bytes_total,
+                });
+            }
+            TransferEvent::Retrying { .. } => {
+                let _ = self.on_progress.send(event);
+            }
+            _ => {}
+        };
+        sftp::run_transfer(state, transfer, cancel_token, &report).await
+    }
+
+    /// Counts a file as dealt with, reporting it if it failed.
+    pub(crate) fn file_done(&mut self, relative: String, size: u64, result: Result<(), String>) {
+        if let Err(message) = result {
+            let _ = self.on_progress.send(TransferEvent::Error {
+                filename: relative.clone(),
+                message,
+            });
+            self.failures.push(relative.clone());
+        }
+        self.files_done += 1;
+        self.bytes_done += size;
+        let _ = self.on_progress.send(TransferEvent::TreeProgress {
+            filename: relative,
+            files_done: self.files_done,
+            files_total: self.files_total,
+            bytes_done: self.bytes_done,
+            bytes_total: self.bytes_total,
+        });
+    }
+
+    /// An error naming the files that failed, if any did.
+    pub(crate) fn finish(self) -> Result<(), String> {
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        let mut message = format!(
+            "{} of {} files failed: {}",
+            self.failures.len(),
+            self.files_total,
+            self.failures
+                .iter()
+                .take(5)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if self.failures.len() > 5 {
+            message.push_str(", ...");
+        }
+        Err(message)
+    }
+}
+
+/// A directory transfer, before it's been walked.
+struct TreeTransfer {
+    device_ip: String,
+    direction: TransferDirection,
+    local_root: String,
+    remote_root: String,
+    options: TreeOptions,
+}
+
+// ----- Helper Functions -----
+
+fn join_relative(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Whether `name` is a single ordinary path component: nothing a server
+/// sends back could climb out of, or reach past, the directory it's in.
+pub(crate) fn is_plain_name(name: &str) -> bool {
+    !name.contains('/')
+        && matches!(
+            Path::new(name).components().collect::<Vec<_>>().as_slice(),
+            [Component::Normal(_)]
+        )
+}
+
+/// Whether every part of the `/`-separated `relative` is a plain name, so
+/// joining it onto a root stays under that root.
+pub(crate) fn is_contained(relative: &str) -> bool {
+    relative.split('/').all(is_plain_name)
+}
+
+pub(crate) fn join_remote(root: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        return root.to_string();
+    }
+    format!("{}/{}", root.trim_end_matches('/'), relative)
+}
+
+pub(crate) fn join_local(root: &str, relative: &str) -> String {
+    if relative.is_empty() {
+        return root.to_string();
+    }
+    Path::new(root).join(relative).to_string_lossy().to_string()
+}
+
+pub(crate) fn local_mtime(metadata: &Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// `dir/name.ext` as `dir/name (n).ext`.
+fn numbered_path(path: &str, n: u32) -> String {
+    let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => {
+            let mut dot = name_start + dot;
+            // `.tar.gz` and the like stay together
+            if path[..dot].ends_with(".tar") && dot - 4 > name_start {
+                dot -= 4;
+            }
+            format!("{} ({}){}", &path[..dot], n, &path[dot..])
+        }
+        _ => format!("{} ({})", path, n),
+    }
+}
+
+// ----- Tree Walking -----
+
+struct LocalWalk<'a> {
+    filter: &'a TreeFilter,
+    symlinks: SymlinkPolicy,
+    visited: HashSet<PathBuf>,
+    entries: Vec<TreeEntry>,
+}
+
+impl LocalWalk<'_> {
+    fn dir(&mut self, dir: &Path, relative: &str, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("{} is nested too deeply", dir.display()));
+        }
+
+        let mut children: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+            .filter_map(Result::ok)
+            .collect();
+        children.sort_by_key(|child| child.file_name());
+
+        for child in children {
+            let name = child.file_name().to_string_lossy().to_string();
+            let child_relative = join_relative(relative, &name);
+            if self.filter.excluded(&child_relative) {
+                continue;
+            }
+
+            let path = child.path();
+            let mut metadata = fs::symlink_metadata(&path)
+                .map_err(|e| format!("Failed to stat {}: {}", path.display(), e))?;
+
+            if metadata.file_type().is_symlink() {
+                match self.symlinks {
+                    SymlinkPolicy::Skip => continue,
+                    SymlinkPolicy::Preserve => {
+                        let target = fs::read_link(&path).map_err(|e| {
+                            format!("Failed to read link {}: {}", path.display(), e)
+                        })?;
+                        if self.filter.included(&child_relative) {
+                            self.entries.push(TreeEntry {
+                                relative: child_relative,
+                                kind: EntryKind::Symlink {
+                                    target: target.to_string_lossy().to_string(),
+                                },
+                            });
+                        }
+                        continue;
+                    }
+                    SymlinkPolicy::Follow => match fs::metadata(&path) {
+                        Ok(target) => metadata = target,
+                        // Dangling link, nothing to copy
+                        Err(_) => continue,
+                    },
+                }
+            }
+
+            if metadata.is_dir() {
+                // A followed link back up the tree would otherwise recurse forever
+                let canonical = fs::canonicalize(&path)
+                    .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?;
+                if !self.visited.insert(canonical) {
+                    continue;
+                }
+                self.entries.push(TreeEntry {
+                    relative: child_relative.clone(),
+                    kind: EntryKind::Dir,
+                });
+                self.dir(&path, &child_relative, depth + 1)?;
+            } else if metadata.is_file() && self.filter.included(&child_relative) {
+                self.entries.push(TreeEntry {
+                    relative: child_relative,
+                    kind: EntryKind::File {
+                        size: metadata.len(),
+                        modified: local_mtime(&metadata),
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything under `root`, parents before their children. Blocks.
+pub(crate) fn walk_local(
+    root: &Path,
+    filter: &TreeFilter,
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<TreeEntry>, String> {
+    let mut walk = LocalWalk {
+        filter,
+        symlinks,
+        visited: HashSet::new(),
+        entries: Vec::new(),
+    };
+    if let Ok(canonical) = fs::canonicalize(root) {
+        walk.visited.insert(canonical);
+    }
+    walk.dir(root, "", 0)?;
+    Ok(walk.entries)
+}
+
+struct RemoteWalk<'a> {
+    sftp: &'a SftpSession,
+    filter: &'a TreeFilter,
+    symlinks: SymlinkPolicy,
+    visited: HashSet<String>,
+    entries: Vec<TreeEntry>,
+}
+
+impl RemoteWalk<'_> {
+    async fn dir(&mut self, dir: &str, relative: &str, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("{} is nested too deeply", dir));
+        }
+
+        let mut children: Vec<_> = self
+            .sftp
+            .read_dir(dir)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", dir, e))?
+            .collect();
+        children.sort_by_key(|child| child.file_name());
+
+        let mut seen = HashSet::new();
+        for child in children {
+            let name = child.file_name();
+            if name == "." || name == ".." {
+                continue;
+            }
+            // These names end up joined onto local paths on download
+            if !is_plain_name(&name) {
+                return Err(format!("{} lists an unusable name {:?}", dir, name));
+            }
+            // A name listed twice could come back as a link, then a directory
+            // whose files would be written through that link
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let child_relative = join_relative(relative, &name);
+            if self.filter.excluded(&child_relative) {
+                continue;
+            }
+
+            let path = join_remote(dir, &name);
+            let mut metadata = child.metadata();
+
+            if child.file_type().is_symlink() {
+                match self.symlinks {
+                    SymlinkPolicy::Skip => continue,
+                    SymlinkPolicy::Preserve => {
+                        let target = self
+                            .sftp
+                            .read_link(&path)
+                            .await
+                            .map_err(|e| format!("Failed to read link {}: {}", path, e))?;
+                        if self.filter.included(&child_relative) {
+                            self.entries.push(TreeEntry {
+                                relative: child_relative,
+                                kind: EntryKind::Symlink { target },
+                            });
+                        }
+                        continue;
+                    }
+                    SymlinkPolicy::Follow => match self.sftp.metadata(&path).await {
+                        Ok(target) => metadata = target,
+                        Err(_) => continue,
+                    },
+                }
+            }
+
+            if metadata.is_dir() {
+                let canonical = self
+                    .sftp
+                    .canonicalize(&path)
+                    .await
+                    .map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+                if !self.visited.insert(canonical) {
+                    continue;
+                }
+                self.entries.push(TreeEntry {
+                    relative: child_relative.clone(),
+                    kind: EntryKind::Dir,
+                });
+                Box::pin(self.dir(&path, &child_relative, depth + 1)).await?;
+            } else if metadata.is_regular() && self.filter.included(&child_relative) {
+                self.entries.push(TreeEntry {
+                    relative: child_relative,
+                    kind: EntryKind::File {
+                        size: metadata.size.unwrap_or(0),
+                        modified: metadata.mtime.map(u64::from),
+                    },
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything under the remote `root`, parents before their children.
+pub(crate) async fn walk_remote(
+    sftp: &SftpSession,
+    root: &str,
+    filter: &TreeFilter,
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<TreeEntry>, String> {
+    let mut walk = RemoteWalk {
+        sftp,
+        filter,
+        symlinks,
+        visited: HashSet::new(),
+        entries: Vec::new(),
+    };
+    if let Ok(canonical) = sftp.canonicalize(root).await {
+        walk.visited.insert(canonical);
+    }
+    walk.dir(root, "", 0).await?;
+    Ok(walk.entries)
+}
+
+// ----- Destination Handling -----
+
+/// Where a file should land locally under `policy`, or `None` to skip it.
+fn local_destination(
+    path: &str,
+    source_modified: Option<u64>,
+    policy: OverwritePolicy,
+) -> Result<Option<String>, String> {
+    let Ok(existing) = fs::metadata(path) else {
+        return Ok(Some(path.to_string()));
+    };
+    match policy {
+        OverwritePolicy::Overwrite => Ok(Some(path.to_string())),
+        OverwritePolicy::Skip => Ok(None),
+        OverwritePolicy::NewerOnly => {
+            Ok((source_modified > local_mtime(&existing)).then(|| path.to_string()))
+        }
+        OverwritePolicy::Rename => (1..=MAX_RENAME_ATTEMPTS)
+            .map(|n| numbered_path(path, n))
+            .find(|candidate| fs::symlink_metadata(candidate).is_err())
+            .map(Some)
+            .ok_or_else(|| format!("No free name left for {}", path)),
+    }
+}
+
+/// Where a file should land on the device under `policy`, or `None` to skip it.
+async fn remote_destination(
+    sftp: &SftpSession,
+    path: &str,
+    source_modified: Option<u64>,
+    policy: OverwritePolicy,
+) -> Result<Option<String>, String> {
+    let Ok(existing) = sftp.metadata(path).await else {
+        return Ok(Some(path.to_string()));
+    };
+    match policy {
+        OverwritePolicy::Overwrite => Ok(Some(path.to_string())),
+        OverwritePolicy::Skip => Ok(None),
+        OverwritePolicy::NewerOnly => {
+            Ok((source_modified > existing.mtime.map(u64::from)).then(|| path.to_string()))
+        }
+        OverwritePolicy::Rename => {
+            for n in 1..=MAX_RENAME_ATTEMPTS {
+                let candidate = numbered_path(path, n);
+                if sftp.symlink_metadata(&candidate).await.is_err() {
+                    return Ok(Some(candidate));
+                }
+            }
+            Err(format!("No free name left for {}", path))
+        }
+    }
+}
+
+/// The root the user picked, which may be a link to a directory.
+pub(crate) async fn ensure_remote_root(sftp: &SftpSession, path: &str) -> Result<(), String> {
+    match sftp.metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(_) => Err(format!("{} exists and is not a directory", path)),
+        Err(_) => sftp
+            .create_dir(path)
+            .await
+            .map_err(|e| format!("Failed to create directory {}: {}", path, e)),
+    }
+}
+
+/// A directory under the root. A link already there is refused rather than
+/// followed, as the files meant for it would land wherever it points.
+pub(crate) async fn ensure_remote_dir(sftp: &SftpSession, path: &str) -> Result<(), String> {
+    match sftp.symlink_metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(metadata) if metadata.is_symlink() => {
+            Err(format!("{} is a link, not a directory", path))
+        }
+        Ok(_) => Err(format!("{} exists and is not a directory", path)),
+        Err(_) => sftp
+            .create_dir(path)
+            .await
+            .map_err(|e| format!("Failed to create directory {}: {}", path, e)),
+    }
+}
+
+pub(crate) async fn ensure_local_root(path: &str) -> Result<(), String> {
+    tokio::fs::create_dir_all(path)
+        .await
+        .map_err(|e| format!("Failed to create directory {}: {}", path, e))
+}
+
+/// Likewise locally; the parent is already there, as walks list parents
+/// first.
+pub(crate) async fn ensure_local_dir(path: &str) -> Result<(), String> {
+    match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) if metadata.is_dir() => Ok(()),
+        Ok(metadata) if metadata.is_symlink() => {
+            Err(format!("{} is a link, not a directory", path))
+        }
+        Ok(_) => Err(format!("{} exists and is not a directory", path)),
+        Err(_) => tokio::fs::create_dir(path)
+            .await
+            .map_err(|e| format!("Failed to create directory {}: {}", path, e)),
+    }
+}
+
+/// Recreates a link locally. Existing files are only replaced under
+/// `Overwrite`; a link is never renamed.
+async fn make_local_symlink(
+    path: &str,
+    target: &str,
+    policy: OverwritePolicy,
+) -> Result<(), String> {
+    if fs::symlink_metadata(path).is_ok() {
+        if policy != OverwritePolicy::Overwrite {
+            return Ok(());
+        }
+        tokio::fs::remove_file(path)
+            .await
+            .map_err(|e| format!("Failed to replace {}: {}", path, e))?;
+    }
+
+    #[cfg(unix)]
+    {
+        tokio::fs::symlink(target, path)
+            .await
+            .map_err(|e| format!("Failed to create link {}: {}", path, e))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = target;
+        Err(format!("Cannot create link {} on this platform", path))
+    }
+}
+
+async fn make_remote_symlink(
+    sftp: &SftpSession,
+    path: &str,
+    target: &str,
+    policy: OverwritePolicy,
+) -> Result<(), String> {
+    if sftp.symlink_metadata(path).await.is_ok() {
+        if policy != OverwritePolicy::Overwrite {
+            return Ok(());
+        }
+        sftp.remove_file(path)
+            .await
+            .map_err(|e| format!("Failed to replace {}: {}", path, e))?;
+    }
+    // OpenSSH takes SSH_FXP_SYMLINK's arguments in the reverse of the order
+    // the draft gives them, and it's the server everything here talks to
+    sftp.symlink(target, path)
+        .await
+        .map_err(|e| format!("Failed to create link {}: {}", path, e))
+}
+
+// ----- Transfer -----
+
+/// Walks the source tree, recreates its directories and links, then moves
+/// each file with the single-file machinery. A file that fails is reported
+/// and the rest carry on.
+async fn run_tree_transfer(
+    state: &SftpState,
+    tree: &TreeTransfer,
+    cancel_token: &CancellationToken,
+    on_progress: &Channel<TransferEvent>,
+) -> Result<(), String> {
+    let options = &tree.options;
+    let filter = TreeFilter::new(&options.include, &options.exclude)?;
+    let connection = sftp::get_connection(state, &tree.device_ip).await?;
+    let sftp = connection.sftp().await?;
+
+    let entries = match tree.direction {
+        TransferDirection::Download => {
+            walk_remote(&sftp, &tree.remote_root, &filter, options.symlinks).await?
+        }
+        TransferDirection::Upload => {
+            let root = PathBuf::from(&tree.local_root);
+            let symlinks = options.symlinks;
+            tokio::task::spawn_blocking(move || walk_local(&root, &filter, symlinks))
+                .await
+                .map_err(|e| format!("Failed to walk {}: {}", tree.local_root, e))??
+        }
+    };
+
+    let destination = |relative: &str| match tree.direction {
+        TransferDirection::Download => join_local(&tree.local_root, relative),
+        TransferDirection::Upload => join_remote(&tree.remote_root, relative),
+    };
+
+    match tree.direction {
+        TransferDirection::Download => ensure_local_root(&tree.local_root).await?,
+        TransferDirection::Upload => ensure_remote_root(&sftp, &tree.remote_root).await?,
+    }
+
+    // Directories and links first; walk order puts parents before children
+    let mut files = Vec::new();
+    for entry in entries {
+        if cancel_token.is_cancelled() {
+            return Err("Transfer cancelled".to_string());
+        }
+        let path = destination(&entry.relative);
+        match entry.kind {
+            EntryKind::Dir => match tree.direction {
+                TransferDirection::Download => ensure_local_dir(&path).await?,
+                TransferDirection::Upload => ensure_remote_dir(&sftp, &path).await?,
+            },
+            EntryKind::Symlink { target } => match tree.direction {
+                TransferDirection::Download => {
+                    make_local_symlink(&path, &target, options.overwrite).await?
+                }
+                TransferDirection::Upload => {
+                    make_remote_symlink(&sftp, &path, &target, options.overwrite).await?
+                }
+            },
+            EntryKind::File { size, modified } => files.push((entry.relative, size, modified)),
+        }
+    }
+    // The per-file transfers take their own channels from the pool
+    drop(sftp);
+
+    let bytes_total = files.iter().map(|(_, size, _)| size).sum();
+    let mut progress = TreeProgress::new(on_progress, files.len() as u64, bytes_total);
+
+    for (relative, size, modified) in files {
+        if cancel_token.is_cancelled() {
+            return Err("Transfer cancelled".to_string());
+        }
+
+        let path = destination(&relative);
+        let landing = match tree.direction {
+            TransferDirection::Download => local_destination(&path, modified, options.overwrite),
+            TransferDirection::Upload => {
+                let sftp = connection.sftp().await?;
+                remote_destination(&sftp, &path, modified, options.overwrite).await
+            }
+        };
+
+        let result = match landing {
+            Ok(None) => {
+                let _ = on_progress.send(TransferEvent::Skipped {
+                    filename: relative.clone(),
+                    reason: "Already exists".to_string(),
+                });
+                Ok(())
+            }
+            Ok(Some(landing)) => {
+                let (local_path, remote_path) = match tree.direction {
+                    TransferDirection::Download => {
+                        (landing, join_remote(&tree.remote_root, &relative))
+                    }
+                    TransferDirection::Upload => (join_local(&tree.local_root, &relative), landing),
+                };
+                let transfer = FileTransfer {
+                    device_ip: tree.device_ip.clone(),
+                    direction: tree.direction.clone(),
+                    local_path,
+                    remote_path,
+                    verify_checksum: options.verify_checksum,
+                };
+                let result = progress
+                    .transfer(state, &transfer, &relative, cancel_token)
+                    .await;
+                if result.is_err() && cancel_token.is_cancelled() {
+                    sftp::discard_partial(state, &transfer).await;
+                }
+                result
+            }
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() && cancel_token.is_cancelled() {
+            return Err("Transfer cancelled".to_string());
+        }
+        progress.file_done(relative, size, result);
+    }
+
+    progress.finish()
+}
+
+/// Sends `Complete` or `Error` for a finished multi-file transfer.
+pub(crate) fn report_outcome(
+    on_progress: &Channel<TransferEvent>,
+    filename: String,
+    result: Result<(), String>,
+) -> Result<(), String> {
+    match result {
+        Ok(()) => {
+            let _ = on_progress.send(TransferEvent::Complete { filename });
+            Ok(())
+        }
+        Err(e) => {
+            let _ = on_progress.send(TransferEvent::Error {
+                filename,
+                message: e.clone(),
+            });
+            Err(e)
+        }
+    }
+}
+
+/// Runs `tree` under `transfer_id` so `sftp_cancel_transfer` can stop it,
+/// and reports how it ended.
+async fn track_tree_transfer(
+    state: &SftpState,
+    transfer_id: String,
+    tree: TreeTransfer,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    let cancel_token = state
+        .begin_transfer(&transfer_id, tree.direction.clone())
+        .await;
+    let result = run_tree_transfer(state, &tree, &cancel_token, &on_progress).await;
+    state.end_transfer(&transfer_id).await;
+
+    let root = match tree.direction {
+        TransferDirection::Download => &tree.remote_root,
+        TransferDirection::Upload => &tree.local_root,
+    };
+    let filename = Path::new(root)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.clone());
+    report_outcome(&on_progress, filename, result)
+}
+
+// ----- Tauri Commands -----
+
+#[tauri::command]
+pub async fn sftp_download_dir(
+    state: State<'_, SftpState>,
+    device_ip: String,
+    remote_path: String,
+    local_path: String,
+    transfer_id: String,
+    options: Option<TreeOptions>,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    let tree = TreeTransfer {
+        device_ip,
+        direction: TransferDirection::Download,
+        local_root: local_path,
+        remote_root: remote_path,
+        options: options.unwrap_or_default(),
+    };
+    track_tree_transfer(&state, transfer_id, tree, on_progress).await
+}
+
+#[tauri::command]
+pub async fn sftp_upload_dir(
+    state: State<'_, SftpState>,
+    device_ip: String,
+    local_path: String,
+    remote_path: String,
+    transfer_id: String,
+    options: Option<TreeOptions>,
+    on_progress: Channel<TransferEvent>,
+) -> Result<(), String> {
+    let tree = TreeTransfer {
+        device_ip,
+        direction: TransferDirection::Upload,
+        local_root: local_path,
+        remote_root: remote_path,
+        options: options.unwrap_or_default(),
+    };
+    track_tree_transfer(&state, transfer_id, tree, on_progress).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sftp::tests::{connect, state_with};
+    use crate::test_server;
+    use std::os::unix::fs::symlink;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> TreeFilter {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        TreeFilter::new(&strings(include), &strings(exclude)).unwrap()
+    }
+
+    fn relatives(entries: &[TreeEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.relative.as_str()).collect()
+    }
+
+    fn path_of(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_names() {
+        let filter = filter(&["*.log"], &["target"]);
+        assert!(filter.included("a.log"));
+        assert!(filter.included("deep/down/a.log"));
+        assert!(!filter.included("a.log.txt"));
+        assert!(filter.excluded("target"));
+        assert!(filter.excluded("crates/app/target"));
+        assert!(!filter.excluded("targets"));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_match_paths() {
+        let filter = filter(&["logs/*.log"], &["build/**"]);
+        assert!(filter.included("logs/a.log"));
+        // `*` doesn't cross a `/`
+        assert!(!filter.included("logs/old/a.log"));
+        assert!(!filter.included("a.log"));
+        assert!(!filter.included("other/logs/a.log"));
+        assert!(filter.excluded("build/out/a.o"));
+        assert!(!filter.excluded("src/build/a.o"));
+    }
+
+    #[test]
+    fn no_includes_means_everything() {
+        let filter = filter(&[], &[]);
+        assert!(filter.included("anything/at/all"));
+        assert!(!filter.excluded("anything/at/all"));
+    }
+
+    #[test]
+    fn bad_patterns_are_reported() {
+        let error = TreeFilter::new(&["[".to_string()], &[]).err().unwrap();
+        assert!(error.starts_with("Invalid pattern `[`"), "{}", error);
+    }
+
+    #[test]
+    fn numbered_paths() {
+        assert_eq!(numbered_path("dir/a.txt", 1), "dir/a (1).txt");
+        assert_eq!(numbered_path("dir/a.tar.gz", 2), "dir/a (2).tar.gz");
+        assert_eq!(numbered_path("dir/.bashrc", 1), "dir/.bashrc (1)");
+        assert_eq!(numbered_path(".env.local", 1), ".env (1).local");
+        assert_eq!(numbered_path("dir/README", 3), "dir/README (3)");
+        assert_eq!(numbered_path("my.dir/README", 1), "my.dir/README (1)");
+    }
+
+    #[test]
+    fn plain_names() {
+        assert!(is_plain_name("a"));
+        assert!(is_plain_name(".hidden"));
+        assert!(is_plain_name("a.txt"));
+        for name in ["", ".", "..", "a/b", "/a", "a/"] {
+            assert!(!is_plain_name(name), "{:?} is plain", name);
+        }
+    }
+
+    #[test]
+    fn local_destination_follows_the_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = path_of(&dir.path().join("new.txt"));
+        let existing = dir.path().join("a.txt");
+        fs::write(&existing, "old").unwrap();
+        let existing_mtime = local_mtime(&fs::metadata(&existing).unwrap()).unwrap();
+        let existing = path_of(&existing);
+
+        for policy in [
+            OverwritePolicy::Skip,
+            OverwritePolicy::Overwrite,
+            OverwritePolicy::NewerOnly,
+            OverwritePolicy::Rename,
+        ] {
+            assert_eq!(
+                local_destination(&missing, None, policy).unwrap(),
+                Some(missing.clone())
+            );
+        }
+
+        let land = |modified, policy| local_destination(&existing, modified, policy).unwrap();
+        assert_eq!(land(None, OverwritePolicy::Skip), None);
+        assert_eq!(
+            land(None, OverwritePolicy::Overwrite),
+            Some(existing.clone())
+        );
+        assert_eq!(land(Some(existing_mtime), OverwritePolicy::NewerOnly), None);
+        assert_eq!(
+            land(Some(existing_mtime + 1), OverwritePolicy::NewerOnly),
+            Some(existing.clone())
+        );
+
+        let first = path_of(&dir.path().join("a (1).txt"));
+        assert_eq!(land(None, OverwritePolicy::Rename), Some(first.clone()));
+        fs::write(&first, "taken").unwrap();
+        let second = path_of(&dir.path().join("a (2).txt"));
+        assert_eq!(land(None, OverwritePolicy::Rename), Some(second));
+    }
+
+    #[test]
+    fn followed_link_loops_end() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/file.txt"), "x").unwrap();
+        symlink("..", root.join("a/b/up")).unwrap();
+        symlink(root, root.join("a/root")).unwrap();
+        symlink("a", root.join("same")).unwrap();
+
+        let entries = walk_local(root, &filter(&[], &[]), SymlinkPolicy::Follow).unwrap();
+        assert_eq!(relatives(&entries), ["a", "a/b", "a/b/file.txt"]);
+    }
+
+    #[test]
+    fn links_are_skipped_or_kept_as_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("file.txt"), "x").unwrap();
+        symlink("file.txt", root.join("link")).unwrap();
+
+        let skipped = walk_local(root, &filter(&[], &[]), SymlinkPolicy::Skip).unwrap();
+        assert_eq!(relatives(&skipped), ["file.txt"]);
+        let kept = walk_local(root, &filter(&[], &[]), SymlinkPolicy::Preserve).unwrap();
+        assert_eq!(relatives(&kept), ["file.txt", "link"]);
+        assert!(matches!(&kept[1].kind, EntryKind::Symlink { target } if target == "file.txt"));
+    }
+
+    #[tokio::test]
+    async fn local_dirs_are_not_made_through_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let link = dir.path().join("link");
+        symlink(&outside, &link).unwrap();
+
+        let error = ensure_local_dir(&path_of(&link)).await.unwrap_err();
+        assert!(error.contains("is a link"), "{}", error);
+        // The root is the user's pick, so a link there is fine
+        ensure_local_root(&path_of(&link)).await.unwrap();
+    }
+
+    /// Runs a directory transfer as the commands do, against `server`.
+    async fn run_tree(
+        server: &test_server::TestServer,
+        direction: TransferDirection,
+        local_root: &Path,
+        remote_root: &Path,
+    ) -> Result<(), String> {
+        let state = state_with("127.0.0.1", connect(server).await).await;
+        let tree = TreeTransfer {
+            device_ip: "127.0.0.1".to_string(),
+            direction,
+            local_root: path_of(local_root),
+            remote_root: path_of(remote_root),
+            options: TreeOptions::default(),
+        };
+        track_tree_transfer(&state, "tree".to_string(), tree, Channel::new(|_| Ok(()))).await
+    }
+
+    #[tokio::test]
+    async fn a_tree_survives_a_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("src/nested")).unwrap();
+        fs::create_dir_all(source.join("empty")).unwrap();
+        fs::write(source.join("README"), "readme").unwrap();
+        fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(source.join("src/nested/data.bin"), vec![7u8; 100_000]).unwrap();
+        let server = test_server::start().await;
+
+        let remote = dir.path().join("remote");
+        run_tree(&server, TransferDirection::Upload, &source, &remote)
+            .await
+            .unwrap();
+        let back = dir.path().join("back");
+        run_tree(&server, TransferDirection::Download, &back, &remote)
+            .await
+            .unwrap();
+
+        let filter = filter(&[], &[]);
+        let sent = walk_local(&source, &filter, SymlinkPolicy::Skip).unwrap();
+        let returned = walk_local(&back, &filter, SymlinkPolicy::Skip).unwrap();
+        assert_eq!(relatives(&returned), relatives(&sent));
+        for entry in &sent {
+            if let EntryKind::File { .. } = entry.kind {
+                assert_eq!(
+                    fs::read(back.join(&entry.relative)).unwrap(),
+                    fs::read(source.join(&entry.relative)).unwrap(),
+                    "{} differs",
+                    entry.relative
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn uploads_do_not_write_through_remote_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir_all(source.join("sub")).unwrap();
+        fs::write(source.join("sub/file.txt"), "x").unwrap();
+        let outside = dir.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        let remote = dir.path().join("remote");
+        fs::create_dir(&remote).unwrap();
+        symlink(&outside, remote.join("sub")).unwrap();
+        let server = test_server::start().await;
+
+        let error = run_tree(&server, TransferDirection::Upload, &source, &remote)
+            .await
+            .unwrap_err();
+        assert!(error.contains("is a link"), "{}", error);
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,10 @@
+/// Wakes a sleeping machine on this computer's LAN. Magic packets don't
+/// cross the tailnet; for a box on a remote LAN, ask a node there with
+/// node-rpc's `PowerService.Wake` instead.
+#[tauri::command]
+pub async fn wake_on_lan(mac: String, broadcast: Option<String>) -> Result<(), String> {
+    let address = wol::parse_mac(&mac)?;
+    let target = wol::parse_target(broadcast.as_deref().unwrap_or(""))?;
+    wol::send(address, target)
+        .map_err(|e| format!("Failed to send magic packet to {}: {}", target, e))
+}
@@ -7,3 +7,4 @@ export { default as TransferQueue } from './TransferQueue.svelte';
 export { default as ContextMenu } from './ContextMenu.svelte';
 export { default as QuickLook } from './QuickLook.svelte';
 export { default as DualPane } from './DualPane.svelte';
+export { default as RemoteEditor } from './RemoteEditor.svelte';
@@ -4,11 +4,78 @@ pub mod node {
     tonic::include_proto!("node");
 }
 use node::node_monitor_client::NodeMonitorClient;
-use node::CpuRequest;
+use node::power_service_client::PowerServiceClient;
+use node::{
+    CpuRequest, PowerAction, PowerCancelRequest, PowerRequest, PowerStatus, PowerStatusRequest,
+    WakeRequest,
+};
+
+const ADDRESS: &str = "http://127.0.0.1:50051";
+
+const USAGE: &str = "usage:
+  client                                  stream CPU usage
+  client wake <mac> [broadcast[:port]]    send a Wake-on-LAN packet from here
+  client wake-via-node <mac> [broadcast]  ask the node to send it on its LAN
+  client power reboot|shutdown|suspend [delay-seconds]
+  client power cancel|status";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut client = NodeMonitorClient::connect("http://127.0.0.1:50051").await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => stream_cpu().await,
+        ["wake", mac, rest @ ..] if rest.len() <= 1 => {
+            let broadcast = rest.first().copied().unwrap_or("");
+            let target = wol::parse_target(broadcast)?;
+            wol::send(wol::parse_mac(mac)?, target)?;
+            println!("Sent Wake-on-LAN for {mac} to {target}");
+            Ok(())
+        }
+        ["wake-via-node", mac, rest @ ..] if rest.len() <= 1 => {
+            let mut client = PowerServiceClient::connect(ADDRESS).await?;
+            let broadcast = rest.first().copied().unwrap_or("").to_string();
+            client
+                .wake(WakeRequest {
+                    mac: mac.to_string(),
+                    broadcast,
+                })
+                .await?;
+            println!("Node sent Wake-on-LAN for {mac}");
+            Ok(())
+        }
+        ["power", command, rest @ ..] if rest.len() <= 1 => {
+            let mut client = PowerServiceClient::connect(ADDRESS).await?;
+            let status = match *command {
+                "cancel" => client.cancel(PowerCancelRequest {}).await?,
+                "status" => client.status(PowerStatusRequest {}).await?,
+                action => {
+                    let action = match action {
+                        "reboot" => PowerAction::Reboot,
+                        "shutdown" => PowerAction::Shutdown,
+                        "suspend" => PowerAction::Suspend,
+                        _ => return Err(USAGE.into()),
+                    };
+                    let delay_s = match rest.first() {
+                        Some(delay) => delay.parse()?,
+                        None => 0,
+                    };
+                    let request = PowerRequest {
+                        action: action as i32,
+                        delay_s,
+                    };
+                    client.schedule(request).await?
+                }
+            };
+            print_status(&status.into_inner());
+            Ok(())
+        }
+        _ => Err(USAGE.into()),
+    }
+}
+
+async fn stream_cpu() -> Result<(), Box<dyn std::error::Error>> {
+    let mut client = NodeMonitorClient::connect(ADDRESS).await?;
     let mut stream = client
         .stream_cpu(Request::new(CpuRequest { refresh_ms: 1000 }))
         .await?
@@ -23,3 +90,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+fn print_status(status: &PowerStatus) {
+    let name = |action: PowerAction| {
+        let name = action.as_str_name().trim_start_matches("POWER_ACTION_");
+        name.to_lowercase()
+    };
+    let mut allowed: Vec<String> = status.allowed().map(name).collect();
+    if status.wake_allowed {
+        allowed.push("wake".to_string());
+    }
+    match status.pending() {
+        PowerAction::None => println!("Nothing scheduled"),
+        action => {
+            let now_ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            println!(
+                "{} in {}s",
+                name(action),
+                status.at_ms.saturating_sub(now_ms).div_ceil(1000)
+            );
+        }
+    }
+    println!(
+        "Allowed: {} (minimum delay {}s)",
+        if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") },
+        status.min_delay_s
+    );
+}
@@ -0,0 +1,45 @@
+use std::fs;
+
+/// Aggregate CPU time counters from the first line of `/proc/stat`, in
+/// clock ticks since boot.
+#[derive(Clone, Copy)]
+pub struct CpuTimes {
+    user: u64,
+    system: u64,
+    total: u64,
+}
+
+impl CpuTimes {
+    pub fn read() -> Option<Self> {
+        let stat = fs::read_to_string("/proc/stat").ok()?;
+        Self::parse(stat.lines().next()?)
+    }
+
+    /// Parses `cpu  user nice system idle iowait irq softirq steal ...`.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        if fields.next()? != "cpu" {
+            return None;
+        }
+        let values: Vec<u64> = fields.map_while(|f| f.parse().ok()).collect();
+        if values.len() < 4 {
+            return None;
+        }
+        let field = |i: usize| values.get(i).copied().unwrap_or(0);
+        // guest and guest_nice (8, 9) are already counted in user and nice
+        let total = values.iter().take(8).sum();
+        Some(Self {
+            user: field(0) + field(1),
+            system: field(2) + field(5) + field(6),
+            total,
+        })
+    }
+
+    /// User and system usage in percent between `earlier` and `self`.
+    pub fn usage_since(&self, earlier: &CpuTimes) -> Option<(f32, f32)> {
+        let total = self.total.checked_sub(earlier.total).filter(|&t| t > 0)? as f32;
+        let user = self.user.saturating_sub(earlier.user) as f32;
+        let system = self.system.saturating_sub(earlier.system) as f32;
+        Some((user / total * 100.0, system / total * 100.0))
+    }
+}
@@ -0,0 +1,583 @@
+use std::fs::{self, Metadata};
+use std::io;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use nix::libc;
+use sha2::{Digest, Sha256};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::logs;
+use crate::node::file_service_server::FileService;
+use crate::node::upload_request::Kind as UploadKind;
+use crate::node::{
+    ChecksumReply, ChecksumRequest, DownloadRequest, FileChunk, FileInfo, FileKind, ListReply,
+    ListRequest, ListRootsReply, ListRootsRequest, LogLevel, StatRequest, UploadReply,
+    UploadRequest, UploadStart,
+};
+
+/// Colon-separated directories the service may touch, like `PATH`.
+const ROOTS_VAR: &str = "NODE_RPC_FILE_ROOTS";
+const DEFAULT_CHUNK: usize = 64 * 1024;
+const MAX_CHUNK: usize = 1024 * 1024;
+const DEFAULT_MODE: u32 = 0o644;
+
+pub struct Files {
+    /// Canonical root directories; empty refuses every request.
+    roots: Arc<Vec<PathBuf>>,
+}
+
+impl Default for Files {
+    /// Roots from `NODE_RPC_FILE_ROOTS`. Without it the service is present
+    /// but refuses everything, so file access is always an explicit choice.
+    fn default() -> Self {
+        let roots = std::env::var_os(ROOTS_VAR)
+            .map(|value| std::env::split_paths(&value).collect())
+            .unwrap_or_default();
+        Self::with_roots(roots)
+    }
+}
+
+impl Files {
+    /// Confines the service to `roots`. Missing roots are skipped with a
+    /// warning so one unmounted disk doesn't take the others down.
+    pub fn with_roots(roots: Vec<PathBuf>) -> Self {
+        let roots = roots
+            .into_iter()
+            .filter(|root| !root.as_os_str().is_empty())
+            .filter_map(|root| match root.canonicalize() {
+                Ok(canonical) if canonical.is_dir() => Some(canonical),
+                Ok(_) => {
+                    let message = format!("File root {} is not a directory", root.display());
+                    logs::record(LogLevel::Warning, message);
+                    None
+                }
+                Err(e) => {
+                    logs::record(LogLevel::Warning, format!("File root {}: {e}", root.display()));
+                    None
+                }
+            })
+            .collect();
+        Self {
+            roots: Arc::new(roots),
+        }
+    }
+
+    fn check_inside(&self, canonical: &Path, requested: &str) -> Result<(), Status> {
+        if self.roots.iter().any(|root| canonical.starts_with(root)) {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(format!(
+                "{requested} is outside the configured file roots"
+            )))
+        }
+    }
+
+    /// `path` with symlinks resolved, if it exists inside a root.
+    fn resolve(&self, path: &str) -> Result<PathBuf, Status> {
+        let requested = absolute(path)?;
+        let canonical = requested.canonicalize().map_err(|e| io_status(e, path))?;
+        self.check_inside(&canonical, path)?;
+        Ok(canonical)
+    }
+
+    /// Where a file that may not exist yet would be written: its parent must
+    /// exist inside a root, and the file itself must not be a symlink.
+    fn resolve_new(&self, path: &str) -> Result<PathBuf, Status> {
+        let requested = absolute(path)?;
+        let (Some(parent), Some(name)) = (requested.parent(), requested.file_name()) else {
+            return Err(Status::invalid_argument(format!("{path} is not a file path")));
+        };
+        let parent = parent.canonicalize().map_err(|e| io_status(e, path))?;
+        self.check_inside(&parent, path)?;
+        let target = parent.join(name);
+        // A link, dangling or not, could point anywhere. The open refuses
+        // them too, in case one appears after this check.
+        match fs::symlink_metadata(&target) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(Status::invalid_argument(format!("{path} is a symlink")));
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(io_status(e, path)),
+        }
+        Ok(target)
+    }
+}
+
+#[tonic::async_trait]
+impl FileService for Files {
+    type DownloadStream = ReceiverStream<Result<FileChunk, Status>>;
+
+    async fn list_roots(
+        &self,
+        _req: Request<ListRootsRequest>,
+    ) -> Result<Response<ListRootsReply>, Status> {
+        let roots = self
+            .roots
+            .iter()
+            .map(|root| root.to_string_lossy().into_owned())
+            .collect();
+        Ok(Response::new(ListRootsReply { roots }))
+    }
+
+    async fn list(&self, req: Request<ListRequest>) -> Result<Response<ListReply>, Status> {
+        let path = req.into_inner().path;
+        let dir = self.resolve(&path)?;
+        let entries = tokio::task::spawn_blocking(move || -> io::Result<Vec<FileInfo>> {
+            let mut entries = Vec::new();
+            for entry in fs::read_dir(&dir)? {
+                let entry = entry?;
+                // Entries can vanish between listing and stat
+                if let Ok(metadata) = entry.metadata() {
+                    entries.push(file_info(&entry.path(), &metadata));
+                }
+            }
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            Ok(entries)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(|e| io_status(e, &path))?;
+        Ok(Response::new(ListReply { entries }))
+    }
+
+    async fn stat(&self, req: Request<StatRequest>) -> Result<Response<FileInfo>, Status> {
+        let path = req.into_inner().path;
+        let resolved = self.resolve(&path)?;
+        let metadata = tokio::fs::metadata(&resolved)
+            .await
+            .map_err(|e| io_status(e, &path))?;
+        Ok(Response::new(file_info(&resolved, &metadata)))
+    }
+
+    async fn checksum(
+        &self,
+        req: Request<ChecksumRequest>,
+    ) -> Result<Response<ChecksumReply>, Status> {
+        let path = req.into_inner().path;
+        let resolved = self.resolve(&path)?;
+        let reply = tokio::task::spawn_blocking(move || -> io::Result<ChecksumReply> {
+            let mut file = fs::File::open(&resolved)?;
+            let mut hasher = Sha256::new();
+            let size = io::copy(&mut file, &mut hasher)?;
+            Ok(ChecksumReply {
+                sha256: hex::encode(hasher.finalize()),
+                size,
+            })
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(|e| io_status(e, &path))?;
+        Ok(Response::new(reply))
+    }
+
+    async fn download(
+        &self,
+        req: Request<DownloadRequest>,
+    ) -> Result<Response<Self::DownloadStream>, Status> {
+        let DownloadRequest {
+            path,
+            offset,
+            chunk_size,
+        } = req.into_inner();
+        let resolved = self.resolve(&path)?;
+        let mut file = File::open(&resolved).await.map_err(|e| io_status(e, &path))?;
+        if !file.metadata().await.map_err(|e| io_status(e, &path))?.is_file() {
+            return Err(Status::invalid_argument(format!("{path} is not a regular file")));
+        }
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| io_status(e, &path))?;
+        let chunk_size = match chunk_size as usize {
+            0 => DEFAULT_CHUNK,
+            n => n.min(MAX_CHUNK),
+        };
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut offset = offset;
+            let mut buf = vec![0u8; chunk_size];
+            loop {
+                let chunk = match file.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => Ok(FileChunk {
+                        offset,
+                        data: buf[..n].to_vec(),
+                    }),
+                    Err(e) => Err(io_status(e, &path)),
+                };
+                let failed = chunk.is_err();
+                if let Ok(chunk) = &chunk {
+                    offset += chunk.data.len() as u64;
+                }
+                if tx.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn upload(
+        &self,
+        req: Request<Streaming<UploadRequest>>,
+    ) -> Result<Response<UploadReply>, Status> {
+        let mut input = req.into_inner();
+        let UploadStart {
+            path,
+            offset,
+            overwrite,
+            mode,
+        } = match input.message().await? {
+            Some(UploadRequest {
+                kind: Some(UploadKind::Start(start)),
+            }) => start,
+            _ => return Err(Status::invalid_argument("first message must be `start`")),
+        };
+        let target = self.resolve_new(&path)?;
+
+        // `mode` only applies on create, so an explicit one is set again below
+        let explicit_mode = (mode != 0).then_some(mode & 0o7777);
+        // The checks run on the opened file, so nothing can be swapped in
+        // between checking and writing
+        let mut file = OpenOptions::new()
+            .write(true)
+            // A resume needs the file to already be there
+            .create(offset == 0)
+            .create_new(offset == 0 && !overwrite)
+            // Cut to `offset` below instead, so a resume keeps what arrived
+            .truncate(false)
+            .mode(explicit_mode.unwrap_or(DEFAULT_MODE))
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&target)
+            .await
+            .map_err(|e| match e.raw_os_error() {
+                Some(libc::ELOOP) => Status::invalid_argument(format!("{path} is a symlink")),
+                Some(libc::EISDIR) => {
+                    Status::invalid_argument(format!("{path} is not a regular file"))
+                }
+                _ if e.kind() == io::ErrorKind::NotFound && offset > 0 => {
+                    Status::not_found(format!("{path} does not exist to resume"))
+                }
+                _ => io_status(e, &path),
+            })?;
+        let metadata = file.metadata().await.map_err(|e| io_status(e, &path))?;
+        if !metadata.is_file() {
+            return Err(Status::invalid_argument(format!("{path} is not a regular file")));
+        }
+        if offset > metadata.len() {
+            return Err(Status::out_of_range(format!(
+                "resume offset {offset} is past the end of {path} ({} bytes)",
+                metadata.len()
+            )));
+        }
+        file.set_len(offset).await.map_err(|e| io_status(e, &path))?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|e| io_status(e, &path))?;
+
+        let mut size = offset;
+        while let Some(message) = input.message().await? {
+            match message.kind {
+                Some(UploadKind::Data(data)) => {
+                    file.write_all(&data).await.map_err(|e| io_status(e, &path))?;
+                    size += data.len() as u64;
+                }
+                Some(UploadKind::Start(_)) => {
+                    return Err(Status::invalid_argument("`start` sent twice"));
+                }
+                None => {}
+            }
+        }
+        file.flush().await.map_err(|e| io_status(e, &path))?;
+        file.sync_all().await.map_err(|e| io_status(e, &path))?;
+        if let Some(mode) = explicit_mode {
+            file.set_permissions(fs::Permissions::from_mode(mode))
+                .await
+                .map_err(|e| io_status(e, &path))?;
+        }
+
+        logs::record(LogLevel::Info, format!("Received {path} ({size} bytes)"));
+        Ok(Response::new(UploadReply { size }))
+    }
+}
+
+fn absolute(path: &str) -> Result<PathBuf, Status> {
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        return Err(Status::invalid_argument(format!(
+            "{} is not an absolute path",
+            path.display()
+        )));
+    }
+    // `..` is resolved by canonicalize, but refusing it up front keeps
+    // not-yet-existing upload targets honest too.
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(Status::invalid_argument(format!(
+            "{} must not contain `..`",
+            path.display()
+        )));
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Describes `path` from metadata that was not made to follow symlinks.
+fn file_info(path: &Path, metadata: &Metadata) -> FileInfo {
+    let file_type = metadata.file_type();
+    let kind = if file_type.is_symlink() {
+        FileKind::Symlink
+    } else if file_type.is_dir() {
+        FileKind::Directory
+    } else if file_type.is_file() {
+        FileKind::File
+    } else {
+        FileKind::Other
+    };
+    let symlink_target = if file_type.is_symlink() {
+        fs::read_link(path)
+            .map(|target| target.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let modified_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64);
+    FileInfo {
+        path: path.to_string_lossy().into_owned(),
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned()),
+        kind: kind as i32,
+        size: metadata.size(),
+        modified_ms,
+        mode: metadata.permissions().mode() & 0o7777,
+        symlink_target,
+    }
+}
+
+fn io_status(e: io::Error, path: &str) -> Status {
+    let message = format!("{path}: {e}");
+    match e.kind() {
+        io::ErrorKind::NotFound => Status::not_found(message),
+        io::ErrorKind::PermissionDenied => Status::permission_denied(message),
+        io::ErrorKind::AlreadyExists => Status::already_exists(message),
+        io::ErrorKind::InvalidInput => Status::invalid_argument(message),
+        _ => Status::internal(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::file_service_client::FileServiceClient;
+    use crate::node::file_service_server::FileServiceServer;
+    use std::os::unix::fs::symlink;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::Code;
+    use tonic::transport::{Channel, Server};
+
+    async fn serve(files: Files) -> FileServiceClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::builder()
+            .add_service(FileServiceServer::new(files))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+        FileServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    /// A service confined to a fresh directory, and that directory's
+    /// canonical path.
+    fn rooted() -> (tempfile::TempDir, PathBuf, Files) {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().canonicalize().unwrap();
+        let files = Files::with_roots(vec![root.path().to_path_buf()]);
+        (root, path, files)
+    }
+
+    fn upload_messages(path: &Path, offset: u64, data: &[&[u8]]) -> Vec<UploadRequest> {
+        let start = UploadRequest {
+            kind: Some(UploadKind::Start(UploadStart {
+                path: path.to_str().unwrap().to_string(),
+                offset,
+                overwrite: false,
+                mode: 0,
+            })),
+        };
+        let data = data.iter().map(|chunk| UploadRequest {
+            kind: Some(UploadKind::Data(chunk.to_vec())),
+        });
+        std::iter::once(start).chain(data).collect()
+    }
+
+    async fn download(
+        client: &mut FileServiceClient<Channel>,
+        path: &Path,
+        offset: u64,
+    ) -> Result<Vec<FileChunk>, Status> {
+        let request = DownloadRequest {
+            path: path.to_str().unwrap().to_string(),
+            offset,
+            chunk_size: 4,
+        };
+        let mut stream = client.download(request).await?.into_inner();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.message().await? {
+            chunks.push(chunk);
+        }
+        Ok(chunks)
+    }
+
+    #[test]
+    fn resolve_stays_inside_the_roots() {
+        let (_root, root_path, files) = rooted();
+        let outside = tempfile::tempdir().unwrap();
+        fs::create_dir(root_path.join("sub")).unwrap();
+        fs::write(outside.path().join("secret"), "x").unwrap();
+
+        let sub = root_path.join("sub");
+        assert_eq!(files.resolve(sub.to_str().unwrap()).unwrap(), sub);
+
+        let code = |path: &str| files.resolve(path).unwrap_err().code();
+        let escape = format!(
+            "{}/sub/../../{}",
+            root_path.display(),
+            outside.path().display()
+        );
+        assert_eq!(code(&escape), Code::InvalidArgument);
+        assert_eq!(code("sub"), Code::InvalidArgument);
+        let secret = outside.path().join("secret");
+        assert_eq!(code(secret.to_str().unwrap()), Code::PermissionDenied);
+        assert_eq!(code("/"), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn resolve_refuses_links_out_of_the_root() {
+        let (_root, root_path, files) = rooted();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret"), "x").unwrap();
+        symlink(outside.path(), root_path.join("out")).unwrap();
+        fs::write(root_path.join("inside"), "x").unwrap();
+        symlink("inside", root_path.join("in")).unwrap();
+
+        let through = root_path.join("out/secret");
+        let err = files.resolve(through.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        let link = root_path.join("in");
+        assert_eq!(
+            files.resolve(link.to_str().unwrap()).unwrap(),
+            root_path.join("inside")
+        );
+    }
+
+    #[tokio::test]
+    async fn refuses_everything_without_roots() {
+        // As in the shell tests, NODE_RPC_FILE_ROOTS is assumed unset
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("file"), "x").unwrap();
+        let mut client = serve(Files::default()).await;
+        let file = root.path().join("file");
+        let path = file.to_str().unwrap().to_string();
+
+        let roots = client.list_roots(ListRootsRequest {}).await.unwrap();
+        assert!(roots.into_inner().roots.is_empty());
+        let status = client
+            .stat(StatRequest { path: path.clone() })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let status = download(&mut client, &file, 0).await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let new = root.path().join("new");
+        let messages = upload_messages(&new, 0, &[b"data"]);
+        let status = client
+            .upload(tokio_stream::iter(messages))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(!new.exists());
+    }
+
+    #[tokio::test]
+    async fn download_resumes_from_an_offset() {
+        let (_root, root_path, files) = rooted();
+        let file = root_path.join("file");
+        fs::write(&file, "0123456789").unwrap();
+        let mut client = serve(files).await;
+
+        let chunks = download(&mut client, &file, 3).await.unwrap();
+        let offsets: Vec<u64> = chunks.iter().map(|c| c.offset).collect();
+        assert_eq!(offsets, [3, 7]);
+        let data: Vec<u8> = chunks.into_iter().flat_map(|c| c.data).collect();
+        assert_eq!(data, b"3456789");
+        assert!(download(&mut client, &file, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn upload_resumes_from_an_offset() {
+        let (_root, root_path, files) = rooted();
+        let file = root_path.join("file");
+        let client = serve(files).await;
+        let upload = |offset, data: &[&[u8]]| {
+            let messages = upload_messages(&file, offset, data);
+            let mut client = client.clone();
+            async move { client.upload(tokio_stream::iter(messages)).await }
+        };
+
+        let status = upload(4, &[b"data"]).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+
+        // Whatever arrived past the offset is replaced
+        fs::write(&file, "0123xx").unwrap();
+        let reply = upload(4, &[b"45", b"67"]).await.unwrap().into_inner();
+        assert_eq!(reply.size, 8);
+        assert_eq!(fs::read(&file).unwrap(), b"01234567");
+
+        let status = upload(9, &[b"9"]).await.unwrap_err();
+        assert_eq!(status.code(), Code::OutOfRange);
+        assert_eq!(fs::read(&file).unwrap(), b"01234567");
+
+        let status = upload(0, &[b"new"]).await.unwrap_err();
+        assert_eq!(status.code(), Code::AlreadyExists);
+    }
+
+    #[test]
+    fn resolve_new_refuses_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let files = Files::with_roots(vec![root.path().to_path_buf()]);
+        let root_path = root.path().canonicalize().unwrap();
+
+        let dangling = root_path.join("dangling");
+        symlink(outside.path().join("missing"), &dangling).unwrap();
+        let err = files.resolve_new(dangling.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        let new_file = root_path.join("new.txt");
+        assert_eq!(
+            files.resolve_new(new_file.to_str().unwrap()).unwrap(),
+            new_file
+        );
+
+        let outside_file = outside.path().join("new.txt");
+        let err = files
+            .resolve_new(outside_file.to_str().unwrap())
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::VecDeque;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::node::log_service_server::LogService;
+use crate::node::{LogEntry, LogLevel, LogsRequest};
+
+/// Entries of node-rpc's own log kept for clients that connect later.
+const OWN_HISTORY: usize = 1000;
+
+#[derive(Default)]
+pub struct Logs;
+
+#[tonic::async_trait]
+impl LogService for Logs {
+    type StreamLogsStream = ReceiverStream<Result<LogEntry, Status>>;
+
+    async fn stream_logs(
+        &self,
+        req: Request<LogsRequest>,
+    ) -> Result<Response<Self::StreamLogsStream>, Status> {
+        let LogsRequest { unit, backlog } = req.into_inner();
+        let (tx, rx) = mpsc::channel(64);
+        if unit.is_empty() {
+            follow_own(backlog as usize, tx);
+        } else {
+            follow_journal(&unit, backlog, tx)?;
+        }
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+struct OwnLog {
+    history: Mutex<VecDeque<LogEntry>>,
+    live: broadcast::Sender<LogEntry>,
+}
+
+fn own_log() -> &'static OwnLog {
+    static LOG: OnceLock<OwnLog> = OnceLock::new();
+    LOG.get_or_init(|| OwnLog {
+        history: Mutex::new(VecDeque::with_capacity(OWN_HISTORY)),
+        live: broadcast::channel(256).0,
+    })
+}
+
+/// Prints `message` like before and makes it available to `StreamLogs`
+/// clients following node-rpc itself.
+pub fn record(level: LogLevel, message: impl Into<String>) {
+    let message = message.into();
+    match level {
+        LogLevel::Warning | LogLevel::Error => eprintln!("{message}"),
+        LogLevel::Debug | LogLevel::Info => println!("{message}"),
+    }
+
+    let entry = LogEntry {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        level: level.into(),
+        source: "node-rpc".to_string(),
+        message,
+    };
+    let log = own_log();
+    // Sending under the lock keeps followers from seeing an entry twice or
+    // missing one between their history snapshot and subscription.
+    let mut history = log.history.lock().expect("log history poisoned");
+    if history.len() >= OWN_HISTORY {
+        history.pop_front();
+    }
+    history.push_back(entry.clone());
+    let _ = log.live.send(entry);
+}
+
+fn follow_own(backlog: usize, tx: mpsc::Sender<Result<LogEntry, Status>>) {
+    let log = own_log();
+    let (past, mut live) = {
+        let history = log.history.lock().expect("log history poisoned");
+        let skip = history.len().saturating_sub(backlog);
+        let past: Vec<LogEntry> = history.iter().skip(skip).cloned().collect();
+        (past, log.live.subscribe())
+    };
+
+    tokio::spawn(async move {
+        for entry in past {
+            if tx.send(Ok(entry)).await.is_err() {
+                return;
+            }
+        }
+        loop {
+            let entry = match live.recv().await {
+                Ok(entry) => entry,
+                Err(broadcast::error::RecvError::Lagged(missed)) => LogEntry {
+                    level: LogLevel::Warning.into(),
+                    source: "node-rpc".to_string(),
+                    message: format!("{missed} log entries dropped for this client"),
+                    ..Default::default()
+                },
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            if tx.send(Ok(entry)).await.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+fn follow_journal(
+    unit: &str,
+    backlog: u32,
+    tx: mpsc::Sender<Result<LogEntry, Status>>,
+) -> Result<(), Status> {
+    let mut child = Command::new("journalctl")
+        .arg("--follow")
+        .arg("--output=json")
+        .arg(format!("--lines={backlog}"))
+        .arg(format!("--unit={unit}"))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        // Dropping the task when the client goes away stops journalctl too
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| Status::unavailable(format!("failed to run journalctl: {e}")))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => {
+                    let Some(entry) = parse_journal_entry(&line) else {
+                        continue;
+                    };
+                    if tx.send(Ok(entry)).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = tx.send(Err(Status::internal(e.to_string()))).await;
+                    return;
+                }
+            }
+        }
+        let status = match child.wait().await {
+            Ok(status) if status.success() => Status::unavailable("journalctl exited"),
+            Ok(status) => Status::internal(format!("journalctl failed ({status})")),
+            Err(e) => Status::internal(e.to_string()),
+        };
+        let _ = tx.send(Err(status)).await;
+    });
+    Ok(())
+}
+
+/// Converts one line of `journalctl --output=json`.
+fn parse_journal_entry(line: &str) -> Option<LogEntry> {
+    let json: Value = serde_json::from_str(line).ok()?;
+    let field = |name: &str| json.get(name).and_then(Value::as_str);
+
+    // MESSAGE is an array of bytes when it isn't valid UTF-8
+    let message = match json.get("MESSAGE")? {
+        Value::String(s) => s.clone(),
+        Value::Array(bytes) => {
+            let bytes: Vec<u8> = bytes.iter().filter_map(|b| b.as_u64()).map(|b| b as u8).collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => return None,
+    };
+    let level = match field("PRIORITY").and_then(|p| p.parse::<u8>().ok()) {
+        Some(0..=3) => LogLevel::Error,
+        Some(4) => LogLevel::Warning,
+        Some(7) => LogLevel::Debug,
+        _ => LogLevel::Info,
+    };
+    let timestamp_ms = field("__REALTIME_TIMESTAMP")
+        .and_then(|us| us.parse::<u64>().ok())
+        .map(|us| us / 1000)
+        .unwrap_or_default();
+    let source = field("SYSLOG_IDENTIFIER")
+        .or_else(|| field("_SYSTEMD_UNIT"))
+        .or_else(|| field("_COMM"))
+        .unwrap_or_default()
+        .to_string();
+
+    Some(LogEntry {
+        timestamp_ms,
+        level: level.into(),
+        source,
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/fixtures/journal/",
+                $name
+            ))
+        };
+    }
+
+    fn level(entry: &LogEntry) -> LogLevel {
+        LogLevel::try_from(entry.level).unwrap()
+    }
+
+    #[test]
+    fn parses_a_full_entry() {
+        let entry = parse_journal_entry(fixture!("full.json")).unwrap();
+        assert_eq!(entry.timestamp_ms, 1_718_000_000_123);
+        assert_eq!(level(&entry), LogLevel::Warning);
+        assert_eq!(entry.source, "sshd");
+        assert_eq!(
+            entry.message,
+            "Invalid user admin from 192.0.2.7 port 50122"
+        );
+    }
+
+    #[test]
+    fn missing_fields_fall_back() {
+        let entry = parse_journal_entry(fixture!("unit_only.json")).unwrap();
+        assert_eq!(entry.source, "nginx.service");
+        assert_eq!(level(&entry), LogLevel::Info);
+
+        let entry = parse_journal_entry(fixture!("bare.json")).unwrap();
+        assert_eq!(entry.timestamp_ms, 0);
+        assert_eq!(level(&entry), LogLevel::Info);
+        assert_eq!(entry.source, "");
+        assert_eq!(entry.message, "no metadata at all");
+    }
+
+    #[test]
+    fn entries_without_a_message_are_skipped() {
+        assert!(parse_journal_entry(fixture!("no_message.json")).is_none());
+        assert!(parse_journal_entry(fixture!("null_message.json")).is_none());
+        assert!(parse_journal_entry("-- No entries --").is_none());
+    }
+
+    #[test]
+    fn binary_messages_are_decoded_lossily() {
+        let entry = parse_journal_entry(fixture!("binary.json")).unwrap();
+        assert_eq!(entry.message, "copied \u{fffd}\u{fffd} ok");
+        assert_eq!(entry.source, "backup");
+        assert_eq!(level(&entry), LogLevel::Error);
+    }
+
+    #[test]
+    fn priorities_map_to_levels() {
+        let expected = [
+            ("0", LogLevel::Error),
+            ("2", LogLevel::Error),
+            ("3", LogLevel::Error),
+            ("4", LogLevel::Warning),
+            ("5", LogLevel::Info),
+            ("6", LogLevel::Info),
+            ("7", LogLevel::Debug),
+            ("9", LogLevel::Info),
+            ("high", LogLevel::Info),
+        ];
+        for (priority, want) in expected {
+            let line = format!(r#"{{"PRIORITY":"{priority}","MESSAGE":"m"}}"#);
+            let entry = parse_journal_entry(&line).unwrap();
+            assert_eq!(level(&entry), want, "PRIORITY {priority}");
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use std::time::{Duration, Instant};
+
+use sysinfo::{NetworkData, Networks};
+use tokio::net::{TcpStream, lookup_host};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::node::network_service_server::NetworkService;
+use crate::node::{
+    InterfaceStats, InterfacesReply, InterfacesRequest, ProbeKind, ProbeReply, ProbeRequest,
+    ProbeResult,
+};
+use crate::refresh_interval;
+
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Default)]
+pub struct Network;
+
+#[tonic::async_trait]
+impl NetworkService for Network {
+    type StreamInterfacesStream = ReceiverStream<Result<InterfacesReply, Status>>;
+    type StreamProbesStream = ReceiverStream<Result<ProbeReply, Status>>;
+
+    async fn stream_interfaces(
+        &self,
+        req: Request<InterfacesRequest>,
+    ) -> Result<Response<Self::StreamInterfacesStream>, Status> {
+        let refresh_ms = req.into_inner().refresh_ms;
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut networks = Networks::new_with_refreshed_list();
+            let mut last_refresh = Instant::now();
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                networks.refresh(true);
+                let elapsed = last_refresh.elapsed().as_secs_f64().max(0.001);
+                last_refresh = Instant::now();
+
+                let mut interfaces: Vec<InterfaceStats> = networks
+                    .list()
+                    .iter()
+                    .map(|(name, data)| interface_stats(name, data, elapsed))
+                    .collect();
+                interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+
+                if tx.send(Ok(InterfacesReply { interfaces })).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn stream_probes(
+        &self,
+        req: Request<ProbeRequest>,
+    ) -> Result<Response<Self::StreamProbesStream>, Status> {
+        let ProbeRequest {
+            refresh_ms,
+            timeout_ms,
+            tcp_targets,
+            dns_names,
+        } = req.into_inner();
+        if tcp_targets.is_empty() && dns_names.is_empty() {
+            return Err(Status::invalid_argument("no probe targets given"));
+        }
+        let timeout = if timeout_ms == 0 {
+            DEFAULT_PROBE_TIMEOUT
+        } else {
+            Duration::from_millis(timeout_ms)
+        };
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                let results = run_probes(&tcp_targets, &dns_names, timeout).await;
+                if tx.send(Ok(ProbeReply { results })).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn interface_stats(name: &str, data: &NetworkData, elapsed_secs: f64) -> InterfaceStats {
+    InterfaceStats {
+        name: name.to_string(),
+        mac_address: data.mac_address().to_string(),
+        ip_addresses: data
+            .ip_networks()
+            .iter()
+            .map(|net| format!("{}/{}", net.addr, net.prefix))
+            .collect(),
+        mtu: data.mtu(),
+        rx_bytes_per_sec: data.received() as f64 / elapsed_secs,
+        tx_bytes_per_sec: data.transmitted() as f64 / elapsed_secs,
+        rx_packets_per_sec: data.packets_received() as f64 / elapsed_secs,
+        tx_packets_per_sec: data.packets_transmitted() as f64 / elapsed_secs,
+        rx_errors: data.errors_on_received(),
+        tx_errors: data.errors_on_transmitted(),
+        total_rx_bytes: data.total_received(),
+        total_tx_bytes: data.total_transmitted(),
+        total_rx_errors: data.total_errors_on_received(),
+        total_tx_errors: data.total_errors_on_transmitted(),
+    }
+}
+
+/// Runs every probe concurrently and returns the results in request order:
+/// TCP targets first, then DNS names.
+async fn run_probes(
+    tcp_targets: &[String],
+    dns_names: &[String],
+    timeout: Duration,
+) -> Vec<ProbeResult> {
+    let mut set = JoinSet::new();
+    for (i, target) in tcp_targets.iter().cloned().enumerate() {
+        set.spawn(async move { (i, probe_tcp(target, timeout).await) });
+    }
+    for (i, name) in dns_names.iter().cloned().enumerate() {
+        let index = tcp_targets.len() + i;
+        set.spawn(async move { (index, probe_dns(name, timeout).await) });
+    }
+
+    let mut results: Vec<(usize, ProbeResult)> = set.join_all().await;
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+async fn probe_tcp(target: String, timeout: Duration) -> ProbeResult {
+    let mut result = ProbeResult {
+        kind: ProbeKind::Tcp.into(),
+        target,
+        ..Default::default()
+    };
+
+    // Resolve first so the reported latency is the handshake alone.
+    let lookup = tokio::time::timeout(timeout, lookup_host(result.target.clone())).await;
+    let addr = match lookup {
+        Ok(Ok(mut addrs)) => match addrs.next() {
+            Some(addr) => addr,
+            None => {
+                result.error = "no addresses found".to_string();
+                return result;
+            }
+        },
+        Ok(Err(e)) => {
+            result.error = format!("resolve failed: {e}");
+            return result;
+        }
+        Err(_) => {
+            result.error = "resolve timed out".to_string();
+            return result;
+        }
+    };
+    result.addresses.push(addr.to_string());
+
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => {
+            result.ok = true;
+            result.latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+        }
+        Ok(Err(e)) => result.error = e.to_string(),
+        Err(_) => result.error = "connect timed out".to_string(),
+    }
+    result
+}
+
+async fn probe_dns(name: String, timeout: Duration) -> ProbeResult {
+    let mut result = ProbeResult {
+        kind: ProbeKind::Dns.into(),
+        target: name,
+        ..Default::default()
+    };
+
+    // lookup_host wants a port; it is not used for resolution.
+    let query = format!("{}:0", result.target);
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, lookup_host(query)).await {
+        Ok(Ok(addrs)) => {
+            result.latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+            result.addresses = addrs.map(|addr| addr.ip().to_string()).collect();
+            result.ok = !result.addresses.is_empty();
+            if !result.ok {
+                result.error = "no addresses found".to_string();
+            }
+        }
+        Ok(Err(e)) => result.error = e.to_string(),
+        Err(_) => result.error = "resolve timed out".to_string(),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn loopback_stats() {
+        let networks = Networks::new_with_refreshed_list();
+        let (name, data) = networks
+            .list()
+            .iter()
+            .find(|(name, _)| name.as_str() == "lo")
+            .expect("no loopback interface");
+        let stats = interface_stats(name, data, 2.0);
+        assert_eq!(stats.name, "lo");
+        assert!(
+            stats.ip_addresses.iter().any(|ip| ip == "127.0.0.1/8"),
+            "{:?}",
+            stats.ip_addresses
+        );
+        assert_eq!(stats.rx_bytes_per_sec, data.received() as f64 / 2.0);
+        assert_eq!(
+            stats.tx_packets_per_sec,
+            data.packets_transmitted() as f64 / 2.0
+        );
+        assert_eq!(stats.total_rx_bytes, data.total_received());
+    }
+
+    #[tokio::test]
+    async fn tcp_probes_report_the_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().to_string();
+        let closed = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+
+        let result = probe_tcp(open.clone(), DEFAULT_PROBE_TIMEOUT).await;
+        assert!(result.ok, "{}", result.error);
+        assert_eq!(result.addresses, [open]);
+        assert!(result.latency_ms > 0.0);
+
+        let result = probe_tcp(closed.clone(), DEFAULT_PROBE_TIMEOUT).await;
+        assert!(!result.ok);
+        assert_eq!(result.addresses, [closed]);
+        assert!(result.error.contains("refused"), "{}", result.error);
+    }
+
+    #[tokio::test]
+    async fn failed_lookups_are_reported() {
+        // No port, so it fails before reaching a resolver
+        let result = probe_tcp("localhost".to_string(), DEFAULT_PROBE_TIMEOUT).await;
+        assert!(!result.ok);
+        assert!(
+            result.error.starts_with("resolve failed: "),
+            "{}",
+            result.error
+        );
+        assert!(result.addresses.is_empty());
+
+        let result = probe_dns("not a hostname!".to_string(), DEFAULT_PROBE_TIMEOUT).await;
+        assert!(!result.ok);
+        assert!(!result.error.is_empty());
+        assert_eq!(ProbeKind::try_from(result.kind), Ok(ProbeKind::Dns));
+    }
+
+    #[tokio::test]
+    async fn slow_handshakes_time_out() {
+        // Once the backlog of a listener that never accepts is full, further
+        // handshakes go unanswered
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let listener = socket.listen(1).unwrap();
+        let target = listener.local_addr().unwrap().to_string();
+        // Each probe that gets through takes a place in it
+        let mut result = probe_tcp(target.clone(), Duration::from_millis(200)).await;
+        for _ in 0..16 {
+            if !result.ok {
+                break;
+            }
+            result = probe_tcp(target.clone(), Duration::from_millis(200)).await;
+        }
+        assert_eq!(result.error, "connect timed out");
+        assert!(!result.ok);
+    }
+
+    #[tokio::test]
+    async fn results_keep_the_request_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().to_string();
+        let tcp = [open.clone(), "localhost".to_string()];
+        let dns = ["localhost".to_string()];
+
+        let results = run_probes(&tcp, &dns, DEFAULT_PROBE_TIMEOUT).await;
+        let targets: Vec<&str> = results.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, [open.as_str(), "localhost", "localhost"]);
+        let ok: Vec<bool> = results.iter().map(|r| r.ok).collect();
+        assert_eq!(ok, [true, false, true]);
+        assert!(results[2].addresses.iter().any(|a| a == "127.0.0.1"));
+    }
+}
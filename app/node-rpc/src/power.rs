@@ -0,0 +1,338 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tonic::{Request, Response, Status};
+
+use crate::logs;
+use crate::node::power_service_server::PowerService;
+use crate::node::{
+    LogLevel, PowerAction, PowerCancelRequest, PowerRequest, PowerStatus, PowerStatusRequest,
+    WakeReply, WakeRequest,
+};
+
+/// Comma-separated actions the service may perform; `wake` allows `Wake`.
+const ALLOWED_VAR: &str = "NODE_RPC_POWER_ACTIONS";
+/// Nothing runs sooner than this, so a mistaken request can be cancelled.
+const MIN_DELAY: Duration = Duration::from_secs(10);
+
+pub struct Power {
+    allowed: Vec<PowerAction>,
+    /// Whether the node may send Wake-on-LAN packets, which go wherever the
+    /// caller says.
+    wake_allowed: bool,
+    pending: Arc<Mutex<Option<Pending>>>,
+    /// What carries the actions out; `systemctl` outside of tests.
+    systemctl: String,
+}
+
+struct Pending {
+    action: PowerAction,
+    at: SystemTime,
+    task: JoinHandle<()>,
+}
+
+impl Default for Power {
+    /// Allow-list from `NODE_RPC_POWER_ACTIONS`; empty unless set.
+    fn default() -> Self {
+        let allowed = std::env::var(ALLOWED_VAR).unwrap_or_default();
+        let names: Vec<&str> = allowed
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        let wake_allowed = names.contains(&"wake");
+        let allowed = names
+            .into_iter()
+            .filter(|&name| name != "wake")
+            .filter_map(|name| match parse_action(name) {
+                Some(action) => Some(action),
+                None => {
+                    logs::record(LogLevel::Warning, format!("Unknown power action `{name}`"));
+                    None
+                }
+            })
+            .collect();
+        Self::with_allowed(allowed, wake_allowed)
+    }
+}
+
+impl Power {
+    pub fn with_allowed(allowed: Vec<PowerAction>, wake_allowed: bool) -> Self {
+        Self {
+            allowed,
+            wake_allowed,
+            pending: Arc::new(Mutex::new(None)),
+            systemctl: "systemctl".to_string(),
+        }
+    }
+
+    fn snapshot(&self) -> PowerStatus {
+        let pending = self.pending.lock().unwrap();
+        let (action, at_ms) = match pending.as_ref() {
+            Some(pending) => (pending.action, millis(pending.at)),
+            None => (PowerAction::None, 0),
+        };
+        PowerStatus {
+            pending: action as i32,
+            at_ms,
+            allowed: self.allowed.iter().map(|&action| action as i32).collect(),
+            min_delay_s: MIN_DELAY.as_secs() as u32,
+            wake_allowed: self.wake_allowed,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl PowerService for Power {
+    async fn schedule(&self, req: Request<PowerRequest>) -> Result<Response<PowerStatus>, Status> {
+        let req = req.into_inner();
+        let action = req.action();
+        if action == PowerAction::None {
+            return Err(Status::invalid_argument("no power action given"));
+        }
+        if !self.allowed.contains(&action) {
+            return Err(Status::permission_denied(format!(
+                "{} is not in {ALLOWED_VAR} on this node",
+                action_name(action)
+            )));
+        }
+        let delay = Duration::from_secs(req.delay_s.into()).max(MIN_DELAY);
+
+        {
+            let mut pending = self.pending.lock().unwrap();
+            if let Some(existing) = pending.as_ref() {
+                return Err(Status::failed_precondition(format!(
+                    "{} is already scheduled; cancel it first",
+                    action_name(existing.action)
+                )));
+            }
+            let slot = Arc::clone(&self.pending);
+            let systemctl = self.systemctl.clone();
+            let task = tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                // Past the cancel window: from here on it can't be taken back
+                slot.lock().unwrap().take();
+                run(&systemctl, action).await;
+            });
+            *pending = Some(Pending {
+                action,
+                at: SystemTime::now() + delay,
+                task,
+            });
+        }
+
+        logs::record(
+            LogLevel::Warning,
+            format!("{} scheduled in {}s", action_name(action), delay.as_secs()),
+        );
+        Ok(Response::new(self.snapshot()))
+    }
+
+    async fn cancel(
+        &self,
+        _req: Request<PowerCancelRequest>,
+    ) -> Result<Response<PowerStatus>, Status> {
+        let Some(pending) = self.pending.lock().unwrap().take() else {
+            return Err(Status::failed_precondition("nothing is scheduled"));
+        };
+        pending.task.abort();
+        logs::record(
+            LogLevel::Warning,
+            format!("{} cancelled", action_name(pending.action)),
+        );
+        Ok(Response::new(self.snapshot()))
+    }
+
+    async fn status(
+        &self,
+        _req: Request<PowerStatusRequest>,
+    ) -> Result<Response<PowerStatus>, Status> {
+        Ok(Response::new(self.snapshot()))
+    }
+
+    async fn wake(&self, req: Request<WakeRequest>) -> Result<Response<WakeReply>, Status> {
+        if !self.wake_allowed {
+            return Err(Status::permission_denied(format!(
+                "wake is not in {ALLOWED_VAR} on this node"
+            )));
+        }
+        let WakeRequest { mac, broadcast } = req.into_inner();
+        let address = wol::parse_mac(&mac).map_err(Status::invalid_argument)?;
+        let target = wol::parse_target(&broadcast).map_err(Status::invalid_argument)?;
+        wol::send(address, target)
+            .map_err(|e| Status::internal(format!("sending to {target}: {e}")))?;
+        logs::record(LogLevel::Info, format!("Sent Wake-on-LAN for {mac} to {target}"));
+        Ok(Response::new(WakeReply {}))
+    }
+}
+
+async fn run(systemctl: &str, action: PowerAction) {
+    let verb = match action {
+        PowerAction::Reboot => "reboot",
+        PowerAction::Shutdown => "poweroff",
+        PowerAction::Suspend => "suspend",
+        PowerAction::None => return,
+    };
+    logs::record(LogLevel::Warning, format!("Running systemctl {verb}"));
+    match Command::new(systemctl).arg(verb).output().await {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            logs::record(LogLevel::Error, format!("systemctl {verb} failed: {}", stderr.trim()));
+        }
+        Err(e) => logs::record(LogLevel::Error, format!("Failed to run systemctl {verb}: {e}")),
+    }
+}
+
+fn parse_action(name: &str) -> Option<PowerAction> {
+    match name {
+        "reboot" => Some(PowerAction::Reboot),
+        "shutdown" => Some(PowerAction::Shutdown),
+        "suspend" => Some(PowerAction::Suspend),
+        _ => None,
+    }
+}
+
+fn action_name(action: PowerAction) -> &'static str {
+    match action {
+        PowerAction::None => "nothing",
+        PowerAction::Reboot => "reboot",
+        PowerAction::Shutdown => "shutdown",
+        PowerAction::Suspend => "suspend",
+    }
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use tonic::Code;
+
+    fn schedule_request(action: PowerAction, delay_s: u32) -> Request<PowerRequest> {
+        Request::new(PowerRequest {
+            action: action as i32,
+            delay_s,
+        })
+    }
+
+    /// A `systemctl` that notes each verb in `ran` beside it.
+    fn fake_systemctl(dir: &Path) -> String {
+        let path = dir.join("systemctl");
+        std::fs::write(
+            &path,
+            "#!/bin/sh\necho \"$1\" >> \"$(dirname \"$0\")/ran\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn power(dir: &Path) -> Power {
+        Power {
+            systemctl: fake_systemctl(dir),
+            ..Power::with_allowed(vec![PowerAction::Reboot], false)
+        }
+    }
+
+    /// Lets the scheduled task and its process run, in real time.
+    async fn settle() {
+        for _ in 0..50 {
+            tokio::task::yield_now().await;
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn actions_off_the_allow_list_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let power = power(dir.path());
+
+        let refused = power
+            .schedule(schedule_request(PowerAction::Shutdown, 60))
+            .await
+            .unwrap_err();
+        assert_eq!(refused.code(), Code::PermissionDenied);
+        assert!(refused.message().contains(ALLOWED_VAR));
+        let nothing = power
+            .schedule(schedule_request(PowerAction::None, 60))
+            .await
+            .unwrap_err();
+        assert_eq!(nothing.code(), Code::InvalidArgument);
+        let wake = power
+            .wake(Request::new(WakeRequest {
+                mac: "aa:bb:cc:dd:ee:ff".to_string(),
+                broadcast: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(wake.code(), Code::PermissionDenied);
+        assert!(power.pending.lock().unwrap().is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn short_delays_are_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let power = power(dir.path());
+
+        let before = millis(SystemTime::now());
+        let status = power
+            .schedule(schedule_request(PowerAction::Reboot, 0))
+            .await
+            .unwrap()
+            .into_inner();
+        let at = status.at_ms - before;
+        assert!((10_000..11_000).contains(&at), "due in {at}ms");
+        assert_eq!(status.pending(), PowerAction::Reboot);
+
+        // Still cancellable just before the minimum delay is up
+        tokio::time::sleep(MIN_DELAY - Duration::from_millis(100)).await;
+        power
+            .cancel(Request::new(PowerCancelRequest {}))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelling_in_the_window_stops_the_action() {
+        let dir = tempfile::tempdir().unwrap();
+        let ran = dir.path().join("ran");
+        let power = power(dir.path());
+
+        power
+            .schedule(schedule_request(PowerAction::Reboot, 30))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        let status = power
+            .cancel(Request::new(PowerCancelRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.pending(), PowerAction::None);
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        settle().await;
+        assert!(!ran.exists(), "the cancelled action ran");
+
+        // Left alone, the same schedule does run
+        power
+            .schedule(schedule_request(PowerAction::Reboot, 30))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(31)).await;
+        settle().await;
+        assert_eq!(std::fs::read_to_string(&ran).unwrap(), "reboot\n");
+        let late = power
+            .cancel(Request::new(PowerCancelRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(late.code(), Code::FailedPrecondition);
+    }
+}
@@ -0,0 +1,114 @@
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::logs;
+use crate::node::process_service_server::ProcessService;
+use crate::node::{
+    LogLevel, ProcessInfo, ProcessesReply, ProcessesRequest, Signal, SignalReply, SignalRequest,
+};
+use crate::refresh_interval;
+
+#[derive(Default)]
+pub struct Processes;
+
+#[tonic::async_trait]
+impl ProcessService for Processes {
+    type StreamProcessesStream = ReceiverStream<Result<ProcessesReply, Status>>;
+
+    async fn stream_processes(
+        &self,
+        req: Request<ProcessesRequest>,
+    ) -> Result<Response<Self::StreamProcessesStream>, Status> {
+        let refresh_ms = req.into_inner().refresh_ms;
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut system = System::new();
+            let users = Users::new_with_refreshed_list();
+            let refresh_kind = ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_memory()
+                .with_cmd(UpdateKind::OnlyIfNotSet)
+                .with_user(UpdateKind::OnlyIfNotSet);
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                system.refresh_memory();
+                system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh_kind);
+
+                let processes = system
+                    .processes()
+                    .values()
+                    .map(|p| ProcessInfo {
+                        pid: p.pid().as_u32(),
+                        parent_pid: p.parent().map(|pid| pid.as_u32()),
+                        name: p.name().to_string_lossy().into_owned(),
+                        command: p
+                            .cmd()
+                            .iter()
+                            .map(|arg| arg.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        user: p
+                            .user_id()
+                            .and_then(|uid| users.get_user_by_id(uid))
+                            .map(|u| u.name().to_string())
+                            .unwrap_or_default(),
+                        cpu_usage: p.cpu_usage(),
+                        memory_bytes: p.memory(),
+                        status: p.status().to_string(),
+                        run_time_secs: p.run_time(),
+                    })
+                    .collect();
+
+                let reply = ProcessesReply {
+                    processes,
+                    total_memory: system.total_memory(),
+                };
+                if tx.send(Ok(reply)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn send_signal(
+        &self,
+        req: Request<SignalRequest>,
+    ) -> Result<Response<SignalReply>, Status> {
+        let req = req.into_inner();
+        let signal = match req.signal() {
+            Signal::Term => sysinfo::Signal::Term,
+            Signal::Kill => sysinfo::Signal::Kill,
+            Signal::Int => sysinfo::Signal::Interrupt,
+            Signal::Hup => sysinfo::Signal::Hangup,
+            Signal::Stop => sysinfo::Signal::Stop,
+            Signal::Cont => sysinfo::Signal::Continue,
+        };
+
+        let pid = Pid::from_u32(req.pid);
+        let mut system = System::new();
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        let process = system
+            .process(pid)
+            .ok_or_else(|| Status::not_found(format!("no process with pid {}", req.pid)))?;
+
+        match process.kill_with(signal) {
+            Some(true) => {
+                logs::record(LogLevel::Info, format!("Sent {signal} to pid {}", req.pid));
+                Ok(Response::new(SignalReply {}))
+            }
+            Some(false) => Err(Status::permission_denied(format!(
+                "failed to send {signal} to pid {}",
+                req.pid
+            ))),
+            None => Err(Status::unimplemented(format!(
+                "{signal} is not supported on this platform"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sysinfo::Components;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::node::sensor_service_server::SensorService;
+use crate::node::{FanReading, SensorsReply, SensorsRequest, TemperatureReading};
+use crate::refresh_interval;
+
+const HWMON_ROOT: &str = "/sys/class/hwmon";
+
+pub struct Sensors {
+    hwmon_root: PathBuf,
+}
+
+impl Default for Sensors {
+    fn default() -> Self {
+        Self::with_hwmon_root(HWMON_ROOT)
+    }
+}
+
+impl Sensors {
+    /// Reads fans from `root` instead of `/sys/class/hwmon`, so a fake sysfs
+    /// tree can stand in for real hardware.
+    pub fn with_hwmon_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            hwmon_root: root.into(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SensorService for Sensors {
+    type StreamSensorsStream = ReceiverStream<Result<SensorsReply, Status>>;
+
+    async fn stream_sensors(
+        &self,
+        req: Request<SensorsRequest>,
+    ) -> Result<Response<Self::StreamSensorsStream>, Status> {
+        let refresh_ms = req.into_inner().refresh_ms;
+        let hwmon_root = self.hwmon_root.clone();
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut components = Components::new_with_refreshed_list();
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                components.refresh(true);
+
+                let temperatures = components
+                    .list()
+                    .iter()
+                    .map(|c| TemperatureReading {
+                        label: c.label().to_string(),
+                        celsius: c.temperature(),
+                        max_celsius: c.max(),
+                        critical_celsius: c.critical(),
+                    })
+                    .collect();
+                let fans = read_fans(&hwmon_root);
+
+                if tx
+                    .send(Ok(SensorsReply { temperatures, fans }))
+                    .await
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Collects `fan<N>_input` readings from every `hwmon*` chip under `root`.
+///
+/// Layout follows the kernel hwmon sysfs ABI: each chip directory has a `name`
+/// file, and each fan has `fan<N>_input` (RPM) plus optional `fan<N>_label`,
+/// `fan<N>_min` and `fan<N>_max`. Missing or unreadable files are skipped, so
+/// a machine without fans yields an empty list.
+pub fn read_fans(root: &Path) -> Vec<FanReading> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+
+    let mut chips: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+    chips.sort();
+
+    let mut fans = Vec::new();
+    for chip_dir in chips {
+        let chip = read_trimmed(&chip_dir.join("name")).unwrap_or_else(|| {
+            chip_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+
+        let Ok(files) = fs::read_dir(&chip_dir) else {
+            continue;
+        };
+        let mut indices: Vec<u32> = files
+            .flatten()
+            .filter_map(|f| {
+                let name = f.file_name();
+                let name = name.to_str()?;
+                name.strip_prefix("fan")?
+                    .strip_suffix("_input")?
+                    .parse()
+                    .ok()
+            })
+            .collect();
+        indices.sort_unstable();
+
+        for n in indices {
+            let Some(rpm) = read_u64(&chip_dir.join(format!("fan{n}_input"))) else {
+                continue;
+            };
+            fans.push(FanReading {
+                chip: chip.clone(),
+                label: read_trimmed(&chip_dir.join(format!("fan{n}_label")))
+                    .unwrap_or_else(|| format!("fan{n}")),
+                rpm,
+                min_rpm: read_u64(&chip_dir.join(format!("fan{n}_min"))),
+                max_rpm: read_u64(&chip_dir.join(format!("fan{n}_max"))),
+            });
+        }
+    }
+    fans
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_stream::StreamExt;
+
+    /// A fake `/sys/class/hwmon` with two chips.
+    fn hwmon_tree() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+
+        write("hwmon1/name", "nct6775\n");
+        write("hwmon1/fan1_input", "1234\n");
+        write("hwmon1/fan1_label", "CPU Fan\n");
+        write("hwmon1/fan1_min", "300\n");
+        write("hwmon1/fan1_max", "2500\n");
+        // No label: named after its index
+        write("hwmon1/fan2_input", "870\n");
+        // Unreadable and unparsable readings are skipped
+        fs::create_dir_all(root.path().join("hwmon1/fan3_input")).unwrap();
+        write("hwmon1/fan4_input", "n/a\n");
+        write("hwmon1/fan10_input", "0\n");
+        write("hwmon1/temp1_input", "42000\n");
+
+        // No name file: named after its directory
+        write("hwmon0/fan1_input", "2000\n");
+        write("hwmon0/fan1_label", "\n");
+        root
+    }
+
+    fn fan(chip: &str, label: &str, rpm: u64, min: Option<u64>, max: Option<u64>) -> FanReading {
+        FanReading {
+            chip: chip.to_string(),
+            label: label.to_string(),
+            rpm,
+            min_rpm: min,
+            max_rpm: max,
+        }
+    }
+
+    #[test]
+    fn fans_from_a_hwmon_tree() {
+        let root = hwmon_tree();
+        assert_eq!(
+            read_fans(root.path()),
+            vec![
+                fan("hwmon0", "fan1", 2000, None, None),
+                fan("nct6775", "CPU Fan", 1234, Some(300), Some(2500)),
+                fan("nct6775", "fan2", 870, None, None),
+                fan("nct6775", "fan10", 0, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_hwmon_root_has_no_fans() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(read_fans(&root.path().join("missing")).is_empty());
+    }
+
+    #[tokio::test]
+    async fn stream_reads_fans_from_its_root() {
+        let root = hwmon_tree();
+        let sensors = Sensors::with_hwmon_root(root.path());
+        let request = Request::new(SensorsRequest { refresh_ms: 100 });
+        let mut stream = sensors.stream_sensors(request).await.unwrap().into_inner();
+
+        let reply = stream.next().await.unwrap().unwrap();
+        assert_eq!(reply.fans, read_fans(root.path()));
+    }
+
+    #[tokio::test]
+    async fn zero_refresh_is_clamped() {
+        let root = hwmon_tree();
+        let sensors = Sensors::with_hwmon_root(root.path());
+        let request = Request::new(SensorsRequest { refresh_ms: 0 });
+        let mut stream = sensors.stream_sensors(request).await.unwrap().into_inner();
+
+        let start = std::time::Instant::now();
+        stream.next().await.unwrap().unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert!(start.elapsed() >= crate::MIN_REFRESH);
+    }
+}
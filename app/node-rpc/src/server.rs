@@ -1,14 +1,65 @@
+use std::net::SocketAddr;
 use std::time::Duration;
-use sysinfo::System;
+use sysinfo::{Disks, System};
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tonic::{transport::Server, Request, Response, Status};
 
+mod cpu_times;
+mod files;
+mod logs;
+mod network;
+mod power;
+mod processes;
+mod sensors;
+mod shell;
+mod smart;
+mod storage;
+
 pub mod node {
     tonic::include_proto!("node");
 }
+use cpu_times::CpuTimes;
+use files::Files;
+use logs::Logs;
+use network::Network;
+use node::file_service_server::FileServiceServer;
+use node::log_service_server::LogServiceServer;
+use node::network_service_server::NetworkServiceServer;
 use node::node_monitor_server::{NodeMonitor, NodeMonitorServer};
-use node::{CpuReply, CpuRequest};
+use node::power_service_server::PowerServiceServer;
+use node::process_service_server::ProcessServiceServer;
+use node::sensor_service_server::SensorServiceServer;
+use node::shell_service_server::ShellServiceServer;
+use node::storage_service_server::StorageServiceServer;
+use node::{CpuReply, CpuRequest, LogLevel, SummaryReply, SummaryRequest};
+use power::Power;
+use processes::Processes;
+use sensors::Sensors;
+use shell::Shell;
+use storage::Storage;
+
+/// Shortest period a stream refreshes at. A `refresh_ms` of 0 would panic
+/// `tokio::time::interval`, and anything close to it just spins.
+const MIN_REFRESH: Duration = Duration::from_millis(100);
+
+/// Ticks every `refresh_ms`, but no faster than [`MIN_REFRESH`].
+fn refresh_interval(refresh_ms: u64) -> tokio::time::Interval {
+    tokio::time::interval(Duration::from_millis(refresh_ms).max(MIN_REFRESH))
+}
+
+const ADDR_VAR: &str = "NODE_RPC_ADDR";
+const DEFAULT_ADDR: &str = "127.0.0.1:50051";
+
+/// Address to serve on, from `NODE_RPC_ADDR`. The default is loopback only:
+/// nothing here is authenticated, shell and power included, so clients on
+/// other machines should come in through an SSH tunnel
+/// (`ssh -L 50051:127.0.0.1:50051 node`) rather than a wider bind.
+fn listen_addr() -> Result<SocketAddr, String> {
+    let addr = std::env::var(ADDR_VAR).unwrap_or_else(|_| DEFAULT_ADDR.to_string());
+    addr.parse()
+        .map_err(|e| format!("{ADDR_VAR}={addr:?} is not a socket address: {e}"))
+}
 
 #[derive(Default)]
 struct Monitor;
@@ -28,14 +79,73 @@ impl NodeMonitor for Monitor {
             let mut system = System::new();
             system.refresh_cpu_usage();
             let cpu_count = system.cpus().len() as u64;
-            let mut interval = tokio::time::interval(Duration::from_millis(refresh_ms));
+            let mut times = CpuTimes::read();
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                system.refresh_cpu_usage();
+                let core_usage: Vec<f32> = system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect();
+                let total_usage: f32 = core_usage.iter().sum::<f32>() / cpu_count as f32;
+
+                let now = CpuTimes::read();
+                let split = now.zip(times).and_then(|(now, then)| now.usage_since(&then));
+                times = now;
+
+                let reply = CpuReply {
+                    cpu_count,
+                    total_usage,
+                    core_usage,
+                    user_usage: split.map(|(user, _)| user),
+                    system_usage: split.map(|(_, system)| system),
+                };
+                if tx.send(Ok(reply)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type StreamSummaryStream = ReceiverStream<Result<SummaryReply, Status>>;
+
+    async fn stream_summary(
+        &self,
+        req: Request<SummaryRequest>,
+    ) -> Result<Response<Self::StreamSummaryStream>, Status> {
+        let refresh_ms = req.into_inner().refresh_ms;
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut system = System::new();
+            let mut disks = Disks::new_with_refreshed_list();
+            let hostname = System::host_name().unwrap_or_default();
+            let mut interval = refresh_interval(refresh_ms);
             loop {
                 interval.tick().await;
                 system.refresh_cpu_usage();
-                let total_usage: f32 =
-                    system.cpus().iter().map(|cpu| cpu.cpu_usage()).sum::<f32>()
-                        / cpu_count as f32;
-                if tx.send(Ok(CpuReply { cpu_count, total_usage })).await.is_err() {
+                system.refresh_memory();
+                disks.refresh(true);
+
+                let max_disk_usage = disks
+                    .list()
+                    .iter()
+                    .filter(|d| d.total_space() > 0)
+                    .map(|d| {
+                        let used = d.total_space().saturating_sub(d.available_space());
+                        used as f32 / d.total_space() as f32 * 100.0
+                    })
+                    .fold(0.0, f32::max);
+                let reply = SummaryReply {
+                    hostname: hostname.clone(),
+                    cpu_usage: system.global_cpu_usage(),
+                    used_memory: system.used_memory(),
+                    total_memory: system.total_memory(),
+                    load_average: System::load_average().one,
+                    uptime_secs: System::uptime(),
+                    max_disk_usage,
+                };
+                if tx.send(Ok(reply)).await.is_err() {
                     break;
                 }
             }
@@ -47,10 +157,19 @@ impl NodeMonitor for Monitor {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("NodeMonitor listening on 127.0.0.1:50051");
+    let addr = listen_addr()?;
+    logs::record(LogLevel::Info, format!("NodeMonitor listening on {addr}"));
     Server::builder()
         .add_service(NodeMonitorServer::new(Monitor))
-        .serve("127.0.0.1:50051".parse()?)
+        .add_service(StorageServiceServer::new(Storage))
+        .add_service(NetworkServiceServer::new(Network))
+        .add_service(SensorServiceServer::new(Sensors::default()))
+        .add_service(ProcessServiceServer::new(Processes))
+        .add_service(LogServiceServer::new(Logs))
+        .add_service(ShellServiceServer::new(Shell::default()))
+        .add_service(FileServiceServer::new(Files::default()))
+        .add_service(PowerServiceServer::new(Power::default()))
+        .serve(addr)
         .await?;
     Ok(())
 }
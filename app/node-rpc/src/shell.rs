@@ -0,0 +1,211 @@
+use std::io::{Read, Write};
+
+use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::logs;
+use crate::node::shell_input::Kind as InputKind;
+use crate::node::shell_output::Kind as OutputKind;
+use crate::node::shell_service_server::ShellService;
+use crate::node::{LogLevel, ShellInput, ShellOutput, ShellStart};
+
+/// Set to `1` to serve shells; anything else refuses them.
+const ENABLED_VAR: &str = "NODE_RPC_SHELL";
+
+pub struct Shell {
+    enabled: bool,
+}
+
+impl Default for Shell {
+    /// Enabled by `NODE_RPC_SHELL=1`. A shell runs as whoever runs node-rpc,
+    /// so it is off unless asked for, like file access and power actions.
+    fn default() -> Self {
+        Self::with_enabled(std::env::var(ENABLED_VAR).is_ok_and(|value| value == "1"))
+    }
+}
+
+impl Shell {
+    pub fn with_enabled(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+#[tonic::async_trait]
+impl ShellService for Shell {
+    type OpenStream = ReceiverStream<Result<ShellOutput, Status>>;
+
+    async fn open(
+        &self,
+        req: Request<Streaming<ShellInput>>,
+    ) -> Result<Response<Self::OpenStream>, Status> {
+        if !self.enabled {
+            return Err(Status::permission_denied(format!(
+                "shells are disabled on this node; set {ENABLED_VAR}=1 to allow them"
+            )));
+        }
+        let mut input = req.into_inner();
+        let ShellStart { rows, cols, command } = match input.message().await? {
+            Some(ShellInput {
+                kind: Some(InputKind::Start(start)),
+            }) => start,
+            _ => return Err(Status::invalid_argument("first message must be `start`")),
+        };
+
+        let pair = native_pty_system()
+            .openpty(pty_size(rows, cols))
+            .map_err(|e| Status::internal(format!("openpty: {e}")))?;
+
+        let (mut cmd, label) = if command.is_empty() {
+            let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string());
+            (CommandBuilder::new(&shell), shell)
+        } else {
+            let mut c = CommandBuilder::new("sh");
+            c.args(["-c", &command]);
+            (c, command)
+        };
+        cmd.env("TERM", "xterm-256color");
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| Status::internal(format!("spawn shell: {e}")))?;
+        drop(pair.slave);
+        let mut killer = child.clone_killer();
+        let master = pair.master;
+        let mut writer = master
+            .take_writer()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let mut reader = master
+            .try_clone_reader()
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        logs::record(LogLevel::Info, format!("Shell opened: {label}"));
+        let (tx, rx) = mpsc::channel(64);
+
+        // PTY reads block, so output gets a thread of its own
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let output = ShellOutput {
+                            kind: Some(OutputKind::Data(buf[..n].to_vec())),
+                        };
+                        if tx.blocking_send(Ok(output)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+            let code = match child.wait() {
+                Ok(status) => status.exit_code() as i32,
+                Err(_) => -1,
+            };
+            logs::record(LogLevel::Info, format!("Shell exited with code {code}: {label}"));
+            let _ = tx.blocking_send(Ok(ShellOutput {
+                kind: Some(OutputKind::ExitCode(code)),
+            }));
+        });
+
+        tokio::spawn(async move {
+            while let Ok(Some(message)) = input.message().await {
+                match message.kind {
+                    Some(InputKind::Data(data)) => {
+                        // A shell that isn't reading can fill the PTY buffer
+                        let written = tokio::task::block_in_place(|| {
+                            writer.write_all(&data).and_then(|_| writer.flush())
+                        });
+                        if written.is_err() {
+                            break;
+                        }
+                    }
+                    Some(InputKind::Resize(size)) => {
+                        let _ = master.resize(pty_size(size.rows, size.cols));
+                    }
+                    Some(InputKind::Start(_)) | None => {}
+                }
+            }
+            // The client hung up (or the shell is gone): make sure it exits
+            let _ = killer.kill();
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn pty_size(rows: u32, cols: u32) -> PtySize {
+    PtySize {
+        rows: rows.clamp(1, u16::MAX as u32) as u16,
+        cols: cols.clamp(1, u16::MAX as u32) as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::shell_service_client::ShellServiceClient;
+    use crate::node::shell_service_server::ShellServiceServer;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::Code;
+    use tonic::transport::{Channel, Server};
+
+    async fn serve(shell: Shell) -> ShellServiceClient<Channel> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::builder()
+            .add_service(ShellServiceServer::new(shell))
+            .serve_with_incoming(TcpListenerStream::new(listener));
+        tokio::spawn(server);
+        ShellServiceClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap()
+    }
+
+    /// Input that starts `command` and stays open, since hanging up kills
+    /// the shell. Keep the sender alive for as long as the shell should run.
+    fn start(command: &str) -> (mpsc::Sender<ShellInput>, ReceiverStream<ShellInput>) {
+        let (tx, rx) = mpsc::channel(4);
+        let start = ShellInput {
+            kind: Some(InputKind::Start(ShellStart {
+                rows: 24,
+                cols: 80,
+                command: command.to_string(),
+            })),
+        };
+        tx.try_send(start).unwrap();
+        (tx, ReceiverStream::new(rx))
+    }
+
+    #[tokio::test]
+    async fn refuses_shells_by_default() {
+        let mut client = serve(Shell::default()).await;
+        let (_tx, input) = start("true");
+        let status = client.open(input).await.unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains(ENABLED_VAR));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn runs_the_command_when_enabled() {
+        let mut client = serve(Shell::with_enabled(true)).await;
+        let (_tx, input) = start("echo hello");
+        let mut output = client.open(input).await.unwrap().into_inner();
+
+        let mut data = Vec::new();
+        let code = loop {
+            match output.message().await.unwrap().unwrap().kind {
+                Some(OutputKind::Data(chunk)) => data.extend(chunk),
+                Some(OutputKind::ExitCode(code)) => break code,
+                None => {}
+            }
+        };
+        assert_eq!(code, 0);
+        assert!(String::from_utf8_lossy(&data).contains("hello"));
+    }
+}
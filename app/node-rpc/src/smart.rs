@@ -0,0 +1,315 @@
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::node::{SmartAttribute, SmartHealth};
+
+// Subset of `smartctl --json -a` output we care about. Every field is optional
+// because ATA, NVMe and USB bridges each report a different shape.
+#[derive(Deserialize)]
+struct SmartctlOutput {
+    #[serde(default)]
+    device: SmartctlDevice,
+    #[serde(default)]
+    model_name: String,
+    smart_status: Option<SmartctlStatus>,
+    temperature: Option<SmartctlTemperature>,
+    power_on_time: Option<SmartctlPowerOn>,
+    ata_smart_attributes: Option<SmartctlAtaAttributes>,
+    nvme_smart_health_information_log: Option<SmartctlNvmeLog>,
+}
+
+#[derive(Deserialize, Default)]
+struct SmartctlDevice {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SmartctlStatus {
+    passed: bool,
+}
+
+#[derive(Deserialize)]
+struct SmartctlTemperature {
+    current: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlPowerOn {
+    #[serde(default)]
+    hours: u64,
+}
+
+#[derive(Deserialize)]
+struct SmartctlAtaAttributes {
+    #[serde(default)]
+    table: Vec<SmartctlAtaAttribute>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlAtaAttribute {
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    value: u32,
+    #[serde(default)]
+    worst: u32,
+    #[serde(default)]
+    thresh: u32,
+    #[serde(default)]
+    when_failed: String,
+    raw: Option<SmartctlRaw>,
+}
+
+#[derive(Deserialize)]
+struct SmartctlRaw {
+    #[serde(default)]
+    value: i64,
+}
+
+#[derive(Deserialize)]
+struct SmartctlNvmeLog {
+    #[serde(default)]
+    critical_warning: i64,
+    #[serde(default)]
+    available_spare: i64,
+    #[serde(default)]
+    available_spare_threshold: i64,
+    #[serde(default)]
+    percentage_used: i64,
+    #[serde(default)]
+    media_errors: i64,
+    #[serde(default)]
+    num_err_log_entries: i64,
+    #[serde(default)]
+    unsafe_shutdowns: i64,
+}
+
+/// Parses the JSON printed by `smartctl --json -a <device>`.
+///
+/// Returns `None` when the output is not JSON or the device does not report a
+/// SMART status at all (e.g. virtual disks, most USB sticks).
+pub fn parse(json: &[u8]) -> Option<SmartHealth> {
+    let out: SmartctlOutput = serde_json::from_slice(json).ok()?;
+    let status = out.smart_status?;
+
+    let mut attributes = Vec::new();
+
+    if let Some(ata) = out.ata_smart_attributes {
+        for attr in ata.table {
+            let failing =
+                !attr.when_failed.is_empty() || (attr.thresh > 0 && attr.value <= attr.thresh);
+            attributes.push(SmartAttribute {
+                id: attr.id,
+                name: attr.name,
+                value: attr.value,
+                worst: attr.worst,
+                threshold: attr.thresh,
+                raw: attr.raw.map(|r| r.value).unwrap_or(0),
+                failing,
+            });
+        }
+    }
+
+    if let Some(log) = out.nvme_smart_health_information_log {
+        let nvme = [
+            (
+                "Critical_Warning",
+                log.critical_warning,
+                log.critical_warning != 0,
+            ),
+            (
+                "Available_Spare",
+                log.available_spare,
+                log.available_spare < log.available_spare_threshold,
+            ),
+            (
+                "Percentage_Used",
+                log.percentage_used,
+                log.percentage_used >= 100,
+            ),
+            ("Media_Errors", log.media_errors, log.media_errors > 0),
+            ("Error_Log_Entries", log.num_err_log_entries, false),
+            ("Unsafe_Shutdowns", log.unsafe_shutdowns, false),
+        ];
+        for (name, raw, failing) in nvme {
+            attributes.push(SmartAttribute {
+                id: 0,
+                name: name.to_string(),
+                value: 0,
+                worst: 0,
+                threshold: 0,
+                raw,
+                failing,
+            });
+        }
+    }
+
+    Some(SmartHealth {
+        device: out.device.name,
+        model: out.model_name,
+        passed: status.passed,
+        temperature_celsius: out.temperature.and_then(|t| t.current),
+        power_on_hours: out.power_on_time.map(|p| p.hours).unwrap_or(0),
+        attributes,
+    })
+}
+
+/// Maps a partition device to the whole-disk device smartctl expects,
+/// e.g. `/dev/sda1` → `/dev/sda`, `/dev/nvme0n1p2` → `/dev/nvme0n1`,
+/// `/dev/disk3s1` → `/dev/disk3`. Returns `None` for non-`/dev` sources
+/// such as `tmpfs` or `overlay`.
+pub fn parent_device(name: &str) -> Option<String> {
+    let dev = name.strip_prefix("/dev/")?;
+    if dev.is_empty() {
+        return None;
+    }
+
+    let trimmed = dev.trim_end_matches(|c: char| c.is_ascii_digit());
+    let base = if dev.starts_with("nvme") || dev.starts_with("mmcblk") {
+        // Partitions are suffixed with `p<N>`; the whole disk ends in a digit.
+        match trimmed.strip_suffix('p') {
+            Some(disk) if trimmed.len() != dev.len() => disk,
+            _ => dev,
+        }
+    } else if dev.starts_with("disk") {
+        // macOS: disk<N>s<M>
+        match trimmed.strip_suffix('s') {
+            Some(disk) if trimmed.len() != dev.len() => disk,
+            _ => dev,
+        }
+    } else if ["sd", "hd", "vd", "xvd"].iter().any(|p| dev.starts_with(p)) {
+        trimmed
+    } else {
+        dev
+    };
+
+    Some(format!("/dev/{base}"))
+}
+
+/// Runs smartctl against `device`. smartctl uses a bitmask exit status that is
+/// non-zero for many healthy drives, so the exit code is ignored and only the
+/// JSON body decides.
+pub async fn query(device: &str) -> Option<SmartHealth> {
+    let output = Command::new("smartctl")
+        .args(["--json", "-a", device])
+        .output()
+        .await
+        .ok()?;
+    parse(&output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/smartctl/", $name))
+        };
+    }
+
+    fn attribute<'a>(health: &'a SmartHealth, name: &str) -> &'a SmartAttribute {
+        health
+            .attributes
+            .iter()
+            .find(|a| a.name == name)
+            .unwrap_or_else(|| panic!("no {name} attribute"))
+    }
+
+    #[test]
+    fn ata_drive() {
+        let health = parse(fixture!("ata.json")).unwrap();
+        assert_eq!(health.device, "/dev/sda");
+        assert_eq!(health.model, "WDC WD40EFRX-68N32N0");
+        assert!(health.passed);
+        assert_eq!(health.temperature_celsius, Some(34));
+        assert_eq!(health.power_on_hours, 28113);
+        assert_eq!(health.attributes.len(), 4);
+
+        let reallocated = attribute(&health, "Reallocated_Sector_Ct");
+        assert_eq!(
+            (reallocated.id, reallocated.value, reallocated.worst, reallocated.threshold),
+            (5, 200, 200, 140)
+        );
+        assert_eq!(attribute(&health, "Power_On_Hours").raw, 28113);
+        assert!(health.attributes.iter().all(|a| !a.failing));
+    }
+
+    #[test]
+    fn nvme_drive() {
+        let health = parse(fixture!("nvme.json")).unwrap();
+        assert_eq!(health.device, "/dev/nvme0n1");
+        assert_eq!(health.model, "Samsung SSD 980 PRO 1TB");
+        assert!(health.passed);
+        assert_eq!(health.temperature_celsius, Some(41));
+        assert_eq!(health.power_on_hours, 6031);
+
+        assert_eq!(attribute(&health, "Available_Spare").raw, 100);
+        assert_eq!(attribute(&health, "Percentage_Used").raw, 3);
+        assert_eq!(attribute(&health, "Error_Log_Entries").raw, 1184);
+        assert_eq!(attribute(&health, "Unsafe_Shutdowns").raw, 27);
+        assert!(health.attributes.iter().all(|a| a.id == 0 && !a.failing));
+    }
+
+    #[test]
+    fn failing_drive() {
+        let health = parse(fixture!("failing.json")).unwrap();
+        assert!(!health.passed);
+        assert_eq!(health.temperature_celsius, Some(44));
+
+        // Failed now, and below its threshold
+        let reallocated = attribute(&health, "Reallocated_Sector_Ct");
+        assert!(reallocated.failing);
+        assert_eq!(reallocated.raw, 61496);
+        // No threshold to fall below
+        assert!(!attribute(&health, "Reported_Uncorrect").failing);
+        assert!(!attribute(&health, "Current_Pending_Sector").failing);
+    }
+
+    #[test]
+    fn nvme_warnings_fail_their_attributes() {
+        let json = br#"{
+            "device": { "name": "/dev/nvme1n1" },
+            "smart_status": { "passed": false },
+            "nvme_smart_health_information_log": {
+                "critical_warning": 1,
+                "available_spare": 5,
+                "available_spare_threshold": 10,
+                "percentage_used": 104,
+                "media_errors": 2
+            }
+        }"#;
+        let health = parse(json).unwrap();
+        for name in [
+            "Critical_Warning",
+            "Available_Spare",
+            "Percentage_Used",
+            "Media_Errors",
+        ] {
+            assert!(attribute(&health, name).failing, "{name} should fail");
+        }
+        assert_eq!(health.temperature_celsius, None);
+    }
+
+    #[test]
+    fn malformed_or_statusless_output() {
+        assert!(parse(fixture!("malformed.json")).is_none());
+        assert!(parse(b"").is_none());
+        assert!(parse(b"smartctl: command not found").is_none());
+        // What a USB stick without SMART reports
+        let json = br#"{ "device": { "name": "/dev/sdd" }, "smartctl": { "exit_status": 1 } }"#;
+        assert!(parse(json).is_none());
+    }
+
+    #[test]
+    fn parent_devices() {
+        assert_eq!(parent_device("/dev/sda1").as_deref(), Some("/dev/sda"));
+        assert_eq!(parent_device("/dev/nvme0n1p2").as_deref(), Some("/dev/nvme0n1"));
+        assert_eq!(parent_device("/dev/nvme0n1").as_deref(), Some("/dev/nvme0n1"));
+        assert_eq!(parent_device("/dev/mmcblk0p1").as_deref(), Some("/dev/mmcblk0"));
+        assert_eq!(parent_device("/dev/disk3s1").as_deref(), Some("/dev/disk3"));
+        assert_eq!(parent_device("tmpfs"), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use nix::sys::statvfs::statvfs;
+use sysinfo::{Disk, Disks};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::node::storage_service_server::StorageService;
+use crate::node::{DiskInfo, SmartHealth, StorageReply, StorageRequest};
+use crate::refresh_interval;
+use crate::smart;
+
+// smartctl wakes sleeping drives and can take a second per device, so SMART
+// data is refreshed far less often than usage counters.
+const SMART_REFRESH: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub struct Storage;
+
+#[tonic::async_trait]
+impl StorageService for Storage {
+    type StreamStorageStream = ReceiverStream<Result<StorageReply, Status>>;
+
+    async fn stream_storage(
+        &self,
+        req: Request<StorageRequest>,
+    ) -> Result<Response<Self::StreamStorageStream>, Status> {
+        let StorageRequest {
+            refresh_ms,
+            include_smart,
+        } = req.into_inner();
+        let (tx, rx) = mpsc::channel(4);
+
+        tokio::spawn(async move {
+            let mut disks = Disks::new_with_refreshed_list();
+            let mut smart_cache: HashMap<String, (Instant, Option<SmartHealth>)> = HashMap::new();
+            let mut last_refresh = Instant::now();
+            let mut interval = refresh_interval(refresh_ms);
+            loop {
+                interval.tick().await;
+                disks.refresh(true);
+                let elapsed = last_refresh.elapsed().as_secs_f64().max(0.001);
+                last_refresh = Instant::now();
+
+                let mut infos = Vec::with_capacity(disks.list().len());
+                for disk in disks.list() {
+                    let mut info = disk_info(disk, elapsed);
+                    if include_smart {
+                        info.smart = smart_for(&info.name, &mut smart_cache).await;
+                    }
+                    infos.push(info);
+                }
+
+                if tx.send(Ok(StorageReply { disks: infos })).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+fn disk_info(disk: &Disk, elapsed_secs: f64) -> DiskInfo {
+    let mount_point = disk.mount_point();
+    // fsfilcnt_t is u32 on macOS, u64 on Linux.
+    #[allow(clippy::unnecessary_cast)]
+    let (total_inodes, free_inodes) = match statvfs(mount_point) {
+        Ok(stat) => (stat.files() as u64, stat.files_available() as u64),
+        Err(_) => (0, 0),
+    };
+    let usage = disk.usage();
+
+    DiskInfo {
+        name: disk.name().to_string_lossy().into_owned(),
+        mount_point: mount_point.to_string_lossy().into_owned(),
+        file_system: disk.file_system().to_string_lossy().into_owned(),
+        kind: disk.kind().to_string(),
+        removable: disk.is_removable(),
+        read_only: disk.is_read_only(),
+        total_bytes: disk.total_space(),
+        available_bytes: disk.available_space(),
+        total_inodes,
+        free_inodes,
+        read_bytes_per_sec: usage.read_bytes as f64 / elapsed_secs,
+        written_bytes_per_sec: usage.written_bytes as f64 / elapsed_secs,
+        smart: None,
+    }
+}
+
+async fn smart_for(
+    name: &str,
+    cache: &mut HashMap<String, (Instant, Option<SmartHealth>)>,
+) -> Option<SmartHealth> {
+    let device = smart::parent_device(name)?;
+    if let Some((at, health)) = cache.get(&device)
+        && at.elapsed() < SMART_REFRESH
+    {
+        return health.clone();
+    }
+    let health = smart::query(&device).await;
+    cache.insert(device, (Instant::now(), health.clone()));
+    health
+}
@@ -1,4 +1,8 @@
 fn main() {
-    tonic_prost_build::compile_protos("../node-rpc/protobufs/node.proto").unwrap();
+    // serde derives let `--record`/`--replay` store AppEvents as JSON
+    tonic_prost_build::configure()
+        .type_attribute(".node", "#[derive(serde::Serialize, serde::Deserialize)]")
+        .compile_protos(&["../node-rpc/protobufs/node.proto"], &["../node-rpc/protobufs"])
+        .unwrap();
     tonic_prost_build::compile_protos("../node-rpc/protobufs/greeter.proto").unwrap();
 }
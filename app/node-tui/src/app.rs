@@ -1,106 +1,400 @@
-use std::collections::VecDeque;
+use std::cell::RefCell;
 
-use ratatui::crossterm::event::{KeyCode, KeyEvent};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Margin, Position, Rect};
+use tokio::sync::mpsc;
 
-use crate::event::AppEvent;
+use color_eyre::eyre::{bail, Result};
 
-#[derive(PartialEq)]
-pub enum Panel {
-    Cpu,
-    Greeter,
-}
+use crate::config::Config;
+use crate::event::{AppEvent, Tagged};
+use crate::fleet::Fleet;
+use crate::grpc::{Connection, EventSender};
+use crate::grpc::node::Signal;
+use crate::keymap::{GlobalAction, Keymap};
+use crate::modal::{self, Modal};
+use crate::panel::{KeyResult, Panel};
+use crate::panels;
+use crate::theme::Theme;
 
 pub enum Action {
     Quit,
     Reconnect,
     SendGreeting(String),
+    SendSignal { pid: u32, signal: Signal },
+    /// Show the dashboard of `config.nodes[index]`.
+    OpenNode(usize),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    /// Tiles of every configured node.
+    Fleet,
+    /// The panel dashboard of the active node.
+    Detail,
+}
+
+/// A row of the dashboard: how many consecutive entries of `App::panels` it
+/// holds and its fixed height, if any.
+pub struct LayoutRow {
+    pub len: usize,
+    pub height: Option<u16>,
 }
 
 pub struct App {
-    pub active_panel: Panel,
+    pub view: View,
+    pub fleet: Fleet,
+    /// Index of the node the panels show.
+    pub active: usize,
+    /// Panels in focus order (row-major over `rows`).
+    pub panels: Vec<Box<dyn Panel>>,
+    /// Layout ids of `panels`, to rebuild them for another node.
+    panel_ids: Vec<String>,
+    pub rows: Vec<LayoutRow>,
+    pub focused: usize,
     pub connected: bool,
-    pub connection_error: Option<String>,
-    pub cpu_count: u64,
-    pub cpu_usage: f32,
-    pub cpu_history: VecDeque<u64>,
-    pub greeter_input: String,
-    pub greeter_response: Option<String>,
+    /// Why the node was last unreachable, kept until it reconnects, or the
+    /// last failure of a stream or request since then.
+    last_error: Option<String>,
+    pub modal: Option<Modal>,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub mouse: bool,
+    /// Where each panel was drawn last frame, border included, for routing
+    /// mouse events.
+    pub panel_areas: RefCell<Vec<Rect>>,
+    /// Streams of the active node; replaced on reconnect.
+    connection: Option<Connection>,
+    /// Counts connections to the dashboard's node, so events from one that
+    /// has been replaced are dropped rather than shown as the new one's.
+    generation: u64,
 }
 
 impl App {
-    pub fn new() -> Self {
-        Self {
-            active_panel: Panel::Cpu,
+    pub fn new(config: &Config) -> Result<Self> {
+        let theme = Theme::resolve(&config.theme, &config.themes)?;
+        let keymap = Keymap::new(&config.keys)?;
+        if config.nodes.is_empty() {
+            bail!("No nodes configured");
+        }
+
+        let mut panels = Vec::new();
+        let mut panel_ids = Vec::new();
+        let mut rows = Vec::new();
+        for row in &config.layout.rows {
+            if row.panels.is_empty() {
+                continue;
+            }
+            for id in &row.panels {
+                let Some(panel) = panels::create(id) else {
+                    let known: Vec<&str> = panels::ids().collect();
+                    bail!("Unknown panel `{id}` in layout (known: {})", known.join(", "));
+                };
+                panels.push(panel);
+                panel_ids.push(id.clone());
+            }
+            rows.push(LayoutRow {
+                len: row.panels.len(),
+                height: row.height,
+            });
+        }
+        if panels.is_empty() {
+            bail!("Layout has no panels");
+        }
+
+        Ok(Self {
+            view: if config.nodes.len() > 1 { View::Fleet } else { View::Detail },
+            fleet: Fleet::new(&config.nodes),
+            active: 0,
+            panels,
+            panel_ids,
+            rows,
+            focused: 0,
             connected: false,
-            connection_error: None,
-            cpu_count: 0,
-            cpu_usage: 0.0,
-            cpu_history: VecDeque::with_capacity(120),
-            greeter_input: String::from("world"),
-            greeter_response: None,
+            last_error: None,
+            modal: None,
+            theme,
+            keymap,
+            mouse: config.mouse,
+            panel_areas: RefCell::new(Vec::new()),
+            connection: None,
+            generation: 0,
+        })
+    }
+
+    /// gRPC endpoint of the active node.
+    pub fn endpoint(&self) -> String {
+        self.fleet.node(self.active).address.clone()
+    }
+
+    /// Tags events for the current connection to the active node.
+    pub fn events(&self, tx: &mpsc::Sender<Tagged>) -> EventSender {
+        EventSender::new(tx.clone(), Some(self.generation))
+    }
+
+    /// (Re)starts the data streams of every panel on the dashboard, stopping
+    /// the previous ones.
+    pub fn connect(&mut self, tx: &mpsc::Sender<Tagged>) {
+        self.generation += 1;
+        let mut connection = Connection::new(self.endpoint(), self.events(tx));
+        for panel in &self.panels {
+            panel.connect(&mut connection);
         }
+        self.connection = Some(connection);
     }
 
-    pub fn apply_event(&mut self, event: AppEvent) {
-        match event {
-            AppEvent::CpuUpdate {
-                cpu_count,
-                total_usage,
-            } => {
-                self.connected = true;
-                self.connection_error = None;
-                self.cpu_count = cpu_count;
-                self.cpu_usage = total_usage;
-                if self.cpu_history.len() >= 120 {
-                    self.cpu_history.pop_front();
-                }
-                self.cpu_history.push_back(total_usage as u64);
+    /// Switches to the dashboard of `node`. Returns true if its streams need
+    /// starting, i.e. unless it is already the active, connected node.
+    pub fn open_node(&mut self, node: usize) -> bool {
+        self.view = View::Detail;
+        if node == self.active && self.connection.is_some() {
+            return false;
+        }
+        self.active = node;
+        self.connection = None;
+        self.generation += 1;
+        self.connected = false;
+        self.last_error = None;
+        self.panels = self
+            .panel_ids
+            .iter()
+            .map(|id| panels::create(id).expect("layout validated in App::new"))
+            .collect();
+        true
+    }
+
+    /// Applies an event, unless it comes from a connection that has since
+    /// been replaced.
+    pub fn apply_event(&mut self, event: impl Into<Tagged>) {
+        let Tagged { generation, event } = event.into();
+        if generation.is_some_and(|generation| generation != self.generation) {
+            return;
+        }
+        match &event {
+            AppEvent::Disconnected(error) => {
+                self.connected = false;
+                self.last_error = Some(error.clone());
             }
-            AppEvent::GreeterResponse(msg) => {
-                self.greeter_response = Some(msg);
+            AppEvent::LogsFailed { unit, error } => {
+                let source = if unit.is_empty() { "node-rpc" } else { unit };
+                self.last_error = Some(format!("Log stream for {source}: {error}"));
             }
-            AppEvent::Disconnected(err) => {
-                self.connected = false;
-                self.connection_error = Some(err);
+            AppEvent::ShellFailed { error, .. } => {
+                self.last_error = Some(format!("Shell: {error}"));
+            }
+            AppEvent::SignalResult { pid, result: Err(error) } => {
+                self.last_error = Some(format!("Signal to pid {pid}: {error}"));
+            }
+            AppEvent::GreeterResponse(_)
+            | AppEvent::SignalResult { .. }
+            | AppEvent::NodeSummary { .. }
+            | AppEvent::NodeOffline { .. }
+            | AppEvent::ShellExited { .. } => {}
+            _ => {
+                if !self.connected {
+                    self.last_error = None;
+                }
+                self.connected = true;
             }
         }
+        self.fleet.apply_event(&event);
+        for panel in &mut self.panels {
+            panel.apply_event(&event);
+        }
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
-        // Global keys — work regardless of active panel
-        match key.code {
-            KeyCode::Esc => return Some(Action::Quit),
-            KeyCode::Tab => {
-                self.active_panel = match self.active_panel {
-                    Panel::Cpu => Panel::Greeter,
-                    Panel::Greeter => Panel::Cpu,
-                };
+        // An open popup swallows every key until closed
+        if let Some(modal) = self.modal.take() {
+            return self.handle_modal_key(modal, key);
+        }
+
+        if self.view == View::Fleet {
+            let result = self.fleet.handle_key(key);
+            if !matches!(result, KeyResult::Ignored) {
+                return self.resolve(result);
+            }
+            let fallbacks = [GlobalAction::Quit, GlobalAction::Help, GlobalAction::ErrorDetails];
+            return self
+                .keymap
+                .resolve(&key, &fallbacks)
+                .and_then(|action| self.global(action));
+        }
+
+        if self.panels[self.focused].captures_input() {
+            let result = self.panels[self.focused].handle_key(key);
+            return self.resolve(result);
+        }
+
+        // Keys that work regardless of the focused panel
+        let navigation = [GlobalAction::NextPanel, GlobalAction::PrevPanel];
+        match self.keymap.resolve(&key, &navigation) {
+            Some(GlobalAction::NextPanel) => {
+                self.focused = (self.focused + 1) % self.panels.len();
                 return None;
             }
-            KeyCode::Char('c') if self.active_panel == Panel::Cpu => {
-                return Some(Action::Reconnect);
+            Some(_) => {
+                self.focused = (self.focused + self.panels.len() - 1) % self.panels.len();
+                return None;
+            }
+            None => {}
+        }
+
+        let result = self.panels[self.focused].handle_key(key);
+        if !matches!(result, KeyResult::Ignored) {
+            return self.resolve(result);
+        }
+
+        // Fallbacks for keys the focused panel left alone
+        let fallbacks = [
+            GlobalAction::Quit,
+            GlobalAction::Reconnect,
+            GlobalAction::Fleet,
+            GlobalAction::Help,
+            GlobalAction::ErrorDetails,
+        ];
+        self.keymap
+            .resolve(&key, &fallbacks)
+            .and_then(|action| self.global(action))
+    }
+
+    /// Runs a global action that no panel claimed.
+    fn global(&mut self, action: GlobalAction) -> Option<Action> {
+        match action {
+            GlobalAction::Quit => return Some(Action::Quit),
+            GlobalAction::Reconnect => return Some(Action::Reconnect),
+            GlobalAction::Fleet => self.view = View::Fleet,
+            GlobalAction::Help => self.modal = Some(Modal::Help { scroll: 0 }),
+            GlobalAction::ErrorDetails => {
+                if let Some((title, message)) = self.error_details() {
+                    self.modal = Some(Modal::Error { title, message });
+                }
             }
-            _ => {}
+            GlobalAction::NextPanel | GlobalAction::PrevPanel => {}
+            GlobalAction::Confirm | GlobalAction::Cancel => {}
+        }
+        None
+    }
+
+    /// Title and full text of the error relevant to the current view: the
+    /// selected node's in the fleet, otherwise the active node's last one.
+    pub fn error_details(&self) -> Option<(String, String)> {
+        match self.view {
+            View::Fleet => self
+                .fleet
+                .selected_error()
+                .map(|(name, error)| (format!("{name} is offline"), error.to_string())),
+            View::Detail => self.last_error.as_ref().map(|error| {
+                let name = &self.fleet.node(self.active).name;
+                (format!("Error on {name}"), error.clone())
+            }),
         }
+    }
 
-        // Panel-specific keys
-        match self.active_panel {
-            Panel::Cpu => match key.code {
-                KeyCode::Char('q') => Some(Action::Quit),
-                _ => None,
+    fn handle_modal_key(&mut self, modal: Modal, key: KeyEvent) -> Option<Action> {
+        let answers = [GlobalAction::Confirm, GlobalAction::Cancel];
+        match modal {
+            Modal::Confirm { prompt, action } => match self.keymap.resolve(&key, &answers) {
+                Some(GlobalAction::Confirm) => return Some(action),
+                Some(_) => {}
+                None => self.modal = Some(Modal::Confirm { prompt, action }),
             },
-            Panel::Greeter => match key.code {
-                KeyCode::Enter => Some(Action::SendGreeting(self.greeter_input.clone())),
-                KeyCode::Backspace => {
-                    self.greeter_input.pop();
-                    None
+            Modal::Help { scroll } => {
+                let scroll = match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => scroll.saturating_sub(1),
+                    KeyCode::Down | KeyCode::Char('j') => scroll + 1,
+                    KeyCode::PageUp => scroll.saturating_sub(10),
+                    KeyCode::PageDown => scroll + 10,
+                    KeyCode::Home => 0,
+                    _ => {
+                        let closing = [GlobalAction::Cancel, GlobalAction::Confirm, GlobalAction::Help];
+                        if self.keymap.resolve(&key, &closing).is_some() {
+                            return None;
+                        }
+                        scroll
+                    }
+                };
+                self.modal = Some(self.help_scrolled(scroll));
+            }
+            Modal::Error { title, message } => {
+                let closing = [GlobalAction::Cancel, GlobalAction::Confirm, GlobalAction::ErrorDetails];
+                if self.keymap.resolve(&key, &closing).is_none() {
+                    self.modal = Some(Modal::Error { title, message });
+                }
+            }
+        }
+        None
+    }
+
+    /// The help popup scrolled to `scroll`, keeping at least its last line.
+    fn help_scrolled(&self, scroll: u16) -> Modal {
+        let last = modal::help_lines(self).len().saturating_sub(1) as u16;
+        Modal::Help {
+            scroll: scroll.min(last),
+        }
+    }
+
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<Action> {
+        if let Some(modal) = self.modal.take() {
+            // Confirmations need an explicit answer; anything else closes
+            // on a click and the help scrolls with the wheel.
+            self.modal = match (modal, mouse.kind) {
+                (Modal::Help { scroll }, MouseEventKind::ScrollUp) => {
+                    Some(self.help_scrolled(scroll.saturating_sub(3)))
                 }
-                KeyCode::Char(c) => {
-                    self.greeter_input.push(c);
-                    None
+                (Modal::Help { scroll }, MouseEventKind::ScrollDown) => {
+                    Some(self.help_scrolled(scroll + 3))
                 }
-                _ => None,
-            },
+                (Modal::Help { .. } | Modal::Error { .. }, MouseEventKind::Down(_)) => None,
+                (modal, _) => Some(modal),
+            };
+            return None;
+        }
+
+        if self.view == View::Fleet {
+            let result = self.fleet.handle_mouse(mouse);
+            return self.resolve(result);
+        }
+
+        let areas = self.panel_areas.borrow().clone();
+        let position = Position::new(mouse.column, mouse.row);
+        let contents = |area: Rect| area.inner(Margin::new(1, 1));
+
+        // Every panel sees movement so it can drop its hover state
+        if mouse.kind == MouseEventKind::Moved {
+            for (panel, area) in self.panels.iter_mut().zip(&areas) {
+                panel.handle_mouse(mouse, contents(*area));
+            }
+            return None;
+        }
+
+        let index = areas.iter().position(|area| area.contains(position))?;
+        if mouse.kind == MouseEventKind::Down(MouseButton::Left) {
+            self.focused = index;
+        }
+        let result = self.panels[index].handle_mouse(mouse, contents(areas[index]));
+        self.resolve(result)
+    }
+
+    /// Tells each panel the size it was just drawn at.
+    pub fn resize_panels(&mut self) {
+        if self.view != View::Detail {
+            return;
+        }
+        let areas = self.panel_areas.borrow();
+        for (panel, area) in self.panels.iter_mut().zip(areas.iter()) {
+            panel.resize(area.inner(Margin::new(1, 1)));
+        }
+    }
+
+    fn resolve(&mut self, result: KeyResult) -> Option<Action> {
+        match result {
+            KeyResult::Ignored | KeyResult::Handled => None,
+            KeyResult::Action(action) => Some(action),
+            KeyResult::Confirm { prompt, action } => {
+                self.modal = Some(Modal::Confirm { prompt, action });
+                None
+            }
         }
     }
 }
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::{bail, eyre, Result};
+
+const USAGE: &str = "\
+Usage: node-tui [OPTIONS]
+
+Options:
+  --record <FILE>   Append every received event to FILE as NDJSON
+  --replay <FILE>   Play back a recording instead of connecting to node-rpc
+  --speed <FACTOR>  Replay speed multiplier (default 1.0)
+  -h, --help        Print this help";
+
+pub struct Args {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub speed: f64,
+}
+
+impl Args {
+    /// Parses the process arguments, printing usage and exiting on `--help`.
+    pub fn parse() -> Result<Self> {
+        let mut args = Self {
+            record: None,
+            replay: None,
+            speed: 1.0,
+        };
+
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().ok_or_else(|| eyre!("{arg} needs a value\n\n{USAGE}"));
+            match arg.as_str() {
+                "--record" => args.record = Some(value()?.into()),
+                "--replay" => args.replay = Some(value()?.into()),
+                "--speed" => {
+                    let speed = value()?;
+                    args.speed = match speed.parse::<f64>() {
+                        Ok(s) if s > 0.0 && s.is_finite() => s,
+                        _ => bail!("--speed must be a positive number, got `{speed}`"),
+                    };
+                }
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => bail!("Unexpected argument `{arg}`\n\n{USAGE}"),
+            }
+        }
+        Ok(args)
+    }
+}
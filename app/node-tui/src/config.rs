@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde::Deserialize;
+
+use crate::keymap::KeySpec;
+use crate::theme::ThemeConfig;
+
+/// node-tui settings, read from `$XDG_CONFIG_HOME/node-tui/config.toml`
+/// (falling back to `~/.config/node-tui/config.toml`). Every section is
+/// optional; a missing file means all defaults.
+///
+/// ```toml
+/// theme = "latte"
+/// mouse = false
+///
+/// [[nodes]]
+/// name = "nas"
+/// address = "http://10.0.0.5:50051"
+/// tags = ["storage"]
+///
+/// [keys]
+/// quit = ["q", "Ctrl+c"]
+/// reconnect = "F5"
+///
+/// [themes.dusk]
+/// base = "mocha"
+/// bg = "#101018"
+/// ```
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Nodes shown in the fleet view. With more than one, node-tui starts
+    /// there instead of in the first node's dashboard.
+    pub nodes: Vec<NodeConfig>,
+    pub layout: LayoutConfig,
+    /// A built-in Catppuccin flavour (`mocha`, `macchiato`, `frappe`,
+    /// `latte`) or the name of a `[themes.*]` table.
+    pub theme: String,
+    pub themes: HashMap<String, ThemeConfig>,
+    /// Keys for global actions, replacing the defaults of each action listed.
+    pub keys: HashMap<String, KeySpec>,
+    /// Capture the mouse for clicking, scrolling and hover tooltips. Off
+    /// leaves the terminal's own text selection working.
+    pub mouse: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            nodes: vec![NodeConfig {
+                name: "localhost".to_string(),
+                address: "http://127.0.0.1:50051".to_string(),
+                tags: Vec::new(),
+            }],
+            layout: LayoutConfig::default(),
+            theme: "mocha".to_string(),
+            themes: HashMap::new(),
+            keys: HashMap::new(),
+            mouse: true,
+        }
+    }
+}
+
+/// A node-rpc server.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    pub name: String,
+    /// gRPC endpoint, e.g. `http://10.0.0.5:50051`.
+    pub address: String,
+    /// Free-form labels the fleet view can group by.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Panels arranged as rows from top to bottom, each row split evenly between
+/// its panels from left to right. Focus cycles in the same order.
+///
+/// ```toml
+/// [[layout.rows]]
+/// panels = ["cpu", "sensors"]
+///
+/// [[layout.rows]]
+/// panels = ["greeter"]
+/// height = 5
+///
+/// [[layout.rows]]
+/// panels = ["logs:sshd.service"]  # `logs` alone follows node-rpc's own log
+///
+/// [[layout.rows]]
+/// panels = ["shell"]  # or e.g. "shell:htop"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub rows: Vec<RowConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowConfig {
+    pub panels: Vec<String>,
+    /// Fixed height in lines. Without it the row shares the remaining space,
+    /// never shrinking below its tallest panel's minimum.
+    #[serde(default)]
+    pub height: Option<u16>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        let row = |panels: &[&str], height| RowConfig {
+            panels: panels.iter().map(|p| p.to_string()).collect(),
+            height,
+        };
+        Self {
+            rows: vec![
+                row(&["cpu"], None),
+                row(&["disks", "sensors"], None),
+                row(&["processes"], None),
+                row(&["greeter"], Some(5)),
+            ],
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("node-tui").join("config.toml"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).wrap_err_with(|| format!("Invalid config in {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(content: &str) -> String {
+        Config::parse(content).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn an_empty_file_means_the_defaults() {
+        let config = Config::parse("").unwrap();
+        assert_eq!(config.theme, "mocha");
+        assert!(config.mouse);
+        assert_eq!(config.nodes.len(), 1);
+        assert_eq!(config.nodes[0].address, "http://127.0.0.1:50051");
+        assert_eq!(config.layout.rows.len(), 4);
+    }
+
+    #[test]
+    fn parses_every_section() {
+        let config = Config::parse(
+            r##"
+            theme = "dusk"
+            mouse = false
+
+            [[nodes]]
+            name = "nas"
+            address = "http://10.0.0.5:50051"
+            tags = ["storage"]
+
+            [[nodes]]
+            name = "pi"
+            address = "http://10.0.0.6:50051"
+
+            [[layout.rows]]
+            panels = ["cpu", "logs:sshd.service"]
+            height = 8
+
+            [keys]
+            quit = ["q", "Ctrl+c"]
+            reconnect = "F5"
+
+            [themes.dusk]
+            base = "mocha"
+            bg = "#101018"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.theme, "dusk");
+        assert!(!config.mouse);
+        let names: Vec<&str> = config.nodes.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["nas", "pi"]);
+        assert_eq!(config.nodes[0].tags, ["storage"]);
+        assert!(config.nodes[1].tags.is_empty());
+        assert_eq!(config.layout.rows[0].panels, ["cpu", "logs:sshd.service"]);
+        assert_eq!(config.layout.rows[0].height, Some(8));
+        assert_eq!(config.keys.len(), 2);
+        assert_eq!(config.themes["dusk"].bg.as_deref(), Some("#101018"));
+    }
+
+    #[test]
+    fn invalid_config_is_explained() {
+        let message = error("colour = \"red\"\n");
+        assert!(message.contains("unknown field `colour`"), "{message}");
+
+        let message = error("[[nodes]]\nname = \"nas\"\n");
+        assert!(message.contains("missing field `address`"), "{message}");
+
+        let message = error("mouse = \"yes\"\n");
+        assert!(
+            message.contains("invalid type: string \"yes\", expected a boolean"),
+            "{message}"
+        );
+
+        let message = error("[[layout.rows]]\npanels = [\"cpu\"]\nheight = -1\n");
+        assert!(message.contains("height"), "{message}");
+
+        let message = error("theme = \n");
+        assert!(message.contains("line 1"), "{message}");
+    }
+}
@@ -1,5 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+use crate::grpc::node::{
+    CpuReply, DiskInfo, FanReading, LogEntry, ProcessInfo, SummaryReply, TemperatureReading,
+};
+
+#[derive(Serialize, Deserialize)]
 pub enum AppEvent {
-    CpuUpdate { cpu_count: u64, total_usage: f32 },
+    CpuUpdate(CpuReply),
+    StorageUpdate(Vec<DiskInfo>),
+    SensorsUpdate {
+        temperatures: Vec<TemperatureReading>,
+        fans: Vec<FanReading>,
+    },
+    ProcessesUpdate {
+        processes: Vec<ProcessInfo>,
+        total_memory: u64,
+    },
+    SignalResult {
+        pid: u32,
+        result: Result<(), String>,
+    },
     GreeterResponse(String),
+    /// Fleet view: latest summary of `config.nodes[node]`.
+    NodeSummary {
+        node: usize,
+        summary: SummaryReply,
+    },
+    /// Fleet view: `config.nodes[node]` is unreachable; retried periodically.
+    NodeOffline {
+        node: usize,
+        error: String,
+    },
+    /// A line from the log of `unit` (empty for node-rpc's own log).
+    Log {
+        unit: String,
+        entry: LogEntry,
+    },
+    /// The log stream for `unit` failed, e.g. journalctl is unavailable.
+    LogsFailed {
+        unit: String,
+        error: String,
+    },
+    /// Terminal output of the shell running `command` (empty for the login
+    /// shell).
+    ShellOutput {
+        command: String,
+        data: Vec<u8>,
+    },
+    ShellExited {
+        command: String,
+        code: i32,
+    },
+    /// The shell could not be started or its stream broke.
+    ShellFailed {
+        command: String,
+        error: String,
+    },
     Disconnected(String),
 }
+
+/// An event on its way to the app, with the generation of the dashboard
+/// connection it came from. Events without one, from the fleet view and
+/// replays, are never stale.
+pub struct Tagged {
+    pub generation: Option<u64>,
+    pub event: AppEvent,
+}
+
+impl From<AppEvent> for Tagged {
+    fn from(event: AppEvent) -> Self {
+        Self {
+            generation: None,
+            event,
+        }
+    }
+}
@@ -0,0 +1,854 @@
+use std::cell::{Cell as StdCell, RefCell};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, VecDeque};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, LineGauge, Paragraph, Sparkline},
+    Frame,
+};
+use tokio::sync::mpsc;
+
+use crate::app::Action;
+use crate::config::NodeConfig;
+use crate::event::{AppEvent, Tagged};
+use crate::grpc::{self, Connection, EventSender};
+use crate::grpc::node::SummaryReply;
+use crate::panel::{KeyHint, KeyResult};
+use crate::theme::Theme;
+use crate::ui::{self, human_bytes};
+
+const HINTS: &[KeyHint] = &[
+    KeyHint::new("Enter", "Open node"),
+    KeyHint::new("←↑↓→", "Move"),
+    KeyHint::new("s", "Sort"),
+    KeyHint::new("g", "Group"),
+];
+
+/// Tiles are at least this wide; the grid fits as many columns as it can.
+const TILE_WIDTH: u16 = 34;
+const TILE_HEIGHT: u16 = 6;
+const HISTORY_LEN: usize = 60;
+/// CPU, memory or disk usage at or above this raises a badge.
+const ALERT_PERCENT: f32 = 90.0;
+
+/// Ordered by how much attention a node needs.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Offline,
+    Alerting,
+    Online,
+    Connecting,
+}
+
+impl Status {
+    const ALL: [Status; 4] = [
+        Status::Offline,
+        Status::Alerting,
+        Status::Online,
+        Status::Connecting,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Status::Offline => "offline",
+            Status::Alerting => "alerting",
+            Status::Online => "online",
+            Status::Connecting => "connecting",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SortKey {
+    Name,
+    Status,
+    Load,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Status,
+            SortKey::Status => SortKey::Load,
+            SortKey::Load => SortKey::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Status => "status",
+            SortKey::Load => "load",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum GroupBy {
+    None,
+    Tag,
+    Status,
+}
+
+impl GroupBy {
+    fn next(self) -> Self {
+        match self {
+            GroupBy::None => GroupBy::Tag,
+            GroupBy::Tag => GroupBy::Status,
+            GroupBy::Status => GroupBy::None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            GroupBy::None => "none",
+            GroupBy::Tag => "tag",
+            GroupBy::Status => "status",
+        }
+    }
+}
+
+struct NodeState {
+    config: NodeConfig,
+    /// Last summary received; kept while offline so the tile isn't blank.
+    summary: Option<SummaryReply>,
+    /// Why the node is offline, until the next summary arrives.
+    error: Option<String>,
+    /// CPU usage in tenths of a percent, for the sparkline.
+    history: VecDeque<u64>,
+}
+
+impl NodeState {
+    fn status(&self) -> Status {
+        if self.error.is_some() {
+            Status::Offline
+        } else if self.summary.is_none() {
+            Status::Connecting
+        } else if self.alerts().is_empty() {
+            Status::Online
+        } else {
+            Status::Alerting
+        }
+    }
+
+    fn alerts(&self) -> Vec<&'static str> {
+        let Some(summary) = &self.summary else {
+            return Vec::new();
+        };
+        let mut alerts = Vec::new();
+        if summary.cpu_usage >= ALERT_PERCENT {
+            alerts.push("CPU");
+        }
+        if memory_percent(summary) >= ALERT_PERCENT {
+            alerts.push("MEM");
+        }
+        if summary.max_disk_usage >= ALERT_PERCENT {
+            alerts.push("DISK");
+        }
+        alerts
+    }
+}
+
+/// A section of the grid: an optional heading and its nodes in display order.
+struct Group {
+    title: Option<String>,
+    nodes: Vec<usize>,
+}
+
+/// One line of the grid, indexing into the flattened display order.
+enum GridLine {
+    Heading(String),
+    Tiles { start: usize, len: usize },
+}
+
+impl GridLine {
+    fn height(&self) -> u16 {
+        match self {
+            GridLine::Heading(_) => 1,
+            GridLine::Tiles { .. } => TILE_HEIGHT,
+        }
+    }
+
+    fn contains(&self, position: usize) -> bool {
+        matches!(self, GridLine::Tiles { start, len } if (*start..start + len).contains(&position))
+    }
+}
+
+/// Tiles every configured node with a summary stream of its own.
+pub struct Fleet {
+    nodes: Vec<NodeState>,
+    sort: SortKey,
+    group: GroupBy,
+    /// Position in the flattened display order; a node appears once per tag
+    /// when grouped by tag.
+    selected: usize,
+    /// Tiles per row at the last render, for up/down movement.
+    columns: StdCell<usize>,
+    /// First grid line shown, kept between frames so the grid doesn't jump.
+    offset: StdCell<usize>,
+    /// Tiles drawn last frame and their display positions, for mouse hits.
+    tiles: RefCell<Vec<(Rect, usize)>>,
+    /// Pointer position, for the sparkline tooltip.
+    hover: Option<Position>,
+    connections: Vec<Connection>,
+}
+
+impl Fleet {
+    pub fn new(nodes: &[NodeConfig]) -> Self {
+        Self {
+            nodes: nodes
+                .iter()
+                .map(|config| NodeState {
+                    config: config.clone(),
+                    summary: None,
+                    error: None,
+                    history: VecDeque::with_capacity(HISTORY_LEN),
+                })
+                .collect(),
+            sort: SortKey::Name,
+            group: GroupBy::None,
+            selected: 0,
+            columns: StdCell::new(1),
+            offset: StdCell::new(0),
+            tiles: RefCell::new(Vec::new()),
+            hover: None,
+            connections: Vec::new(),
+        }
+    }
+
+    pub fn node(&self, index: usize) -> &NodeConfig {
+        &self.nodes[index].config
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn online(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n.status(), Status::Online | Status::Alerting))
+            .count()
+    }
+
+    pub fn key_hints(&self) -> &[KeyHint] {
+        HINTS
+    }
+
+    /// Name and error of the selected node, if it is offline.
+    pub fn selected_error(&self) -> Option<(&str, &str)> {
+        let node = &self.nodes[*self.order().get(self.selected)?];
+        let error = node.error.as_deref()?;
+        Some((&node.config.name, error))
+    }
+
+    /// (Re)starts the summary stream of every node, replacing earlier ones.
+    pub fn connect(&mut self, tx: &mpsc::Sender<Tagged>) {
+        self.connections = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let tx = EventSender::new(tx.clone(), None);
+                let mut conn = Connection::new(node.config.address.clone(), tx);
+                grpc::spawn_summary_stream(&mut conn, index);
+                conn
+            })
+            .collect();
+    }
+
+    pub fn apply_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::NodeSummary { node, summary } => {
+                let Some(state) = self.nodes.get_mut(*node) else {
+                    return;
+                };
+                if state.history.len() >= HISTORY_LEN {
+                    state.history.pop_front();
+                }
+                state.history.push_back((summary.cpu_usage * 10.0).round() as u64);
+                state.summary = Some(summary.clone());
+                state.error = None;
+            }
+            AppEvent::NodeOffline { node, error } => {
+                if let Some(state) = self.nodes.get_mut(*node) {
+                    state.error = Some(error.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        let order = self.order();
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.selected = (self.selected + 1).min(order.len().saturating_sub(1))
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_vertically(false),
+            KeyCode::Down | KeyCode::Char('j') => self.move_vertically(true),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = order.len().saturating_sub(1),
+            KeyCode::Char('s') => self.regroup(|fleet| fleet.sort = fleet.sort.next()),
+            KeyCode::Char('g') => self.regroup(|fleet| fleet.group = fleet.group.next()),
+            KeyCode::Enter => {
+                return match order.get(self.selected) {
+                    Some(&node) => KeyResult::Action(Action::OpenNode(node)),
+                    None => KeyResult::Handled,
+                };
+            }
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    /// A click selects a tile and a second click opens it; the wheel moves
+    /// between tile rows.
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) -> KeyResult {
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Moved => self.hover = Some(position),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let hit = self
+                    .tiles
+                    .borrow()
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                    .map(|&(_, tile)| tile);
+                match hit {
+                    Some(tile) if tile == self.selected => {
+                        let node = self.order()[tile];
+                        return KeyResult::Action(Action::OpenNode(node));
+                    }
+                    Some(tile) => self.selected = tile,
+                    None => return KeyResult::Ignored,
+                }
+            }
+            MouseEventKind::ScrollDown => self.move_vertically(true),
+            MouseEventKind::ScrollUp => self.move_vertically(false),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    /// Applies a sort/group change while keeping the same node selected.
+    fn regroup(&mut self, change: impl FnOnce(&mut Self)) {
+        let node = self.order().get(self.selected).copied();
+        change(self);
+        let order = self.order();
+        self.selected = node
+            .and_then(|node| order.iter().position(|&n| n == node))
+            .unwrap_or(0);
+        self.offset.set(0);
+    }
+
+    fn move_vertically(&mut self, down: bool) {
+        let lines = self.grid_lines(&self.groups(), self.columns.get());
+        let tile_lines: Vec<(usize, usize)> = lines
+            .iter()
+            .filter_map(|line| match line {
+                GridLine::Tiles { start, len } => Some((*start, *len)),
+                GridLine::Heading(_) => None,
+            })
+            .collect();
+        let Some(current) = tile_lines
+            .iter()
+            .position(|&(start, len)| (start..start + len).contains(&self.selected))
+        else {
+            return;
+        };
+        let target = if down {
+            current + 1
+        } else if current > 0 {
+            current - 1
+        } else {
+            return;
+        };
+        if let Some(&(start, len)) = tile_lines.get(target) {
+            let column = self.selected - tile_lines[current].0;
+            self.selected = start + column.min(len - 1);
+        }
+    }
+
+    fn compare(&self, a: usize, b: usize) -> Ordering {
+        let (na, nb) = (&self.nodes[a], &self.nodes[b]);
+        let by_name = na.config.name.cmp(&nb.config.name);
+        match self.sort {
+            SortKey::Name => by_name,
+            SortKey::Status => na.status().cmp(&nb.status()).then(by_name),
+            SortKey::Load => {
+                let load = |n: &NodeState| n.summary.as_ref().map(|s| s.cpu_usage);
+                // Busiest first, nodes without data last
+                load(nb)
+                    .partial_cmp(&load(na))
+                    .unwrap_or(Ordering::Equal)
+                    .then(by_name)
+            }
+        }
+    }
+
+    fn groups(&self) -> Vec<Group> {
+        let mut sorted: Vec<usize> = (0..self.nodes.len()).collect();
+        sorted.sort_by(|&a, &b| self.compare(a, b));
+
+        match self.group {
+            GroupBy::None => vec![Group {
+                title: None,
+                nodes: sorted,
+            }],
+            GroupBy::Status => Status::ALL
+                .iter()
+                .map(|&status| Group {
+                    title: Some(status.label().to_string()),
+                    nodes: sorted
+                        .iter()
+                        .copied()
+                        .filter(|&n| self.nodes[n].status() == status)
+                        .collect(),
+                })
+                .filter(|g| !g.nodes.is_empty())
+                .collect(),
+            GroupBy::Tag => {
+                let tags: BTreeSet<&str> = self
+                    .nodes
+                    .iter()
+                    .flat_map(|n| n.config.tags.iter().map(String::as_str))
+                    .collect();
+                let mut groups: Vec<Group> = tags
+                    .into_iter()
+                    .map(|tag| Group {
+                        title: Some(tag.to_string()),
+                        nodes: sorted
+                            .iter()
+                            .copied()
+                            .filter(|&n| self.nodes[n].config.tags.iter().any(|t| t == tag))
+                            .collect(),
+                    })
+                    .collect();
+                let untagged: Vec<usize> = sorted
+                    .iter()
+                    .copied()
+                    .filter(|&n| self.nodes[n].config.tags.is_empty())
+                    .collect();
+                if !untagged.is_empty() {
+                    groups.push(Group {
+                        title: Some("untagged".to_string()),
+                        nodes: untagged,
+                    });
+                }
+                groups
+            }
+        }
+    }
+
+    /// Node indices in display order, matching `selected`.
+    fn order(&self) -> Vec<usize> {
+        self.groups().into_iter().flat_map(|g| g.nodes).collect()
+    }
+
+    fn grid_lines(&self, groups: &[Group], columns: usize) -> Vec<GridLine> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        for group in groups {
+            if let Some(title) = &group.title {
+                lines.push(GridLine::Heading(format!("{title} ({})", group.nodes.len())));
+            }
+            for chunk in group.nodes.chunks(columns) {
+                lines.push(GridLine::Tiles {
+                    start,
+                    len: chunk.len(),
+                });
+                start += chunk.len();
+            }
+        }
+        lines
+    }
+
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let [status_area, grid_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(" Sort: ", Style::default().fg(theme.lavender)),
+                Span::raw(self.sort.label()),
+                Span::styled("  Group: ", Style::default().fg(theme.lavender)),
+                Span::raw(self.group.label()),
+                Span::raw(format!("  |  {}/{} online", self.online(), self.nodes.len())),
+            ])),
+            status_area,
+        );
+
+        let columns = (grid_area.width / TILE_WIDTH).max(1) as usize;
+        self.columns.set(columns);
+        let groups = self.groups();
+        let order: Vec<usize> = groups.iter().flat_map(|g| g.nodes.iter().copied()).collect();
+        let lines = self.grid_lines(&groups, columns);
+
+        // Scroll just enough to keep the selected tile row (and its heading,
+        // when it is the first row of a group) in view.
+        let selected_line = lines
+            .iter()
+            .position(|line| line.contains(self.selected))
+            .unwrap_or(0);
+        let mut offset = self.offset.get().min(selected_line);
+        if offset == selected_line
+            && offset > 0
+            && matches!(lines[offset - 1], GridLine::Heading(_))
+        {
+            offset -= 1;
+        }
+        while offset < selected_line
+            && lines[offset..=selected_line].iter().map(GridLine::height).sum::<u16>()
+                > grid_area.height
+        {
+            offset += 1;
+        }
+        self.offset.set(offset);
+
+        let mut tiles = self.tiles.borrow_mut();
+        tiles.clear();
+        let mut tooltip = None;
+        let mut y = grid_area.y;
+        for line in &lines[offset..] {
+            let height = line.height();
+            if y + height > grid_area.bottom() {
+                break;
+            }
+            let line_area = Rect::new(grid_area.x, y, grid_area.width, height);
+            match line {
+                GridLine::Heading(title) => frame.render_widget(
+                    Paragraph::new(format!(" ▸ {title}")).style(
+                        Style::default()
+                            .fg(theme.lavender)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    line_area,
+                ),
+                GridLine::Tiles { start, len } => {
+                    let cells =
+                        Layout::horizontal(vec![Constraint::Fill(1); columns]).split(line_area);
+                    for (position, cell) in (*start..start + len).zip(cells.iter()) {
+                        let node = &self.nodes[order[position]];
+                        let spark_area =
+                            render_tile(frame, *cell, node, position == self.selected, theme);
+                        tiles.push((*cell, position));
+                        if let Some(hover) = self.hover
+                            && spark_area.contains(hover)
+                        {
+                            tooltip = sparkline_tooltip(node, spark_area, hover, theme)
+                                .map(|lines| (lines, hover));
+                        }
+                    }
+                }
+            }
+            y += height;
+        }
+
+        // Drawn last so neighbouring tiles don't cover it
+        if let Some((lines, hover)) = tooltip {
+            ui::draw_tooltip(frame, theme, lines, hover, grid_area);
+        }
+    }
+}
+
+/// Exact CPU usage and age of the sparkline sample under the pointer.
+fn sparkline_tooltip(
+    node: &NodeState,
+    spark_area: Rect,
+    hover: Position,
+    theme: &Theme,
+) -> Option<Vec<Line<'static>>> {
+    let skip = node.history.len().saturating_sub(spark_area.width as usize);
+    let index = skip + (hover.x - spark_area.x) as usize;
+    let value = *node.history.get(index)?;
+    let age = grpc::SUMMARY_REFRESH * (node.history.len() - 1 - index) as u32;
+    Some(vec![
+        Line::styled(node.config.name.clone(), Style::default().fg(theme.lavender)),
+        Line::from(format!("CPU {:.1}%", value as f64 / 10.0)),
+        Line::from(match age.as_secs() {
+            0 => "latest".to_string(),
+            secs => format!("{secs}s ago"),
+        }),
+    ])
+}
+
+/// Draws one node tile and returns where its sparkline went.
+fn render_tile(
+    frame: &mut Frame,
+    area: Rect,
+    node: &NodeState,
+    selected: bool,
+    theme: &Theme,
+) -> Rect {
+    let status = node.status();
+    let status_color = match status {
+        Status::Offline => theme.red,
+        Status::Alerting => theme.yellow,
+        Status::Online => theme.green,
+        Status::Connecting => theme.fg,
+    };
+
+    let badges = match status {
+        Status::Offline => vec!["OFFLINE"],
+        _ => node.alerts(),
+    };
+    let badge_spans: Vec<Span> = badges
+        .into_iter()
+        .map(|badge| {
+            Span::styled(
+                format!(" {badge} "),
+                Style::default()
+                    .bg(theme.red)
+                    .fg(theme.bg)
+                    .add_modifier(Modifier::BOLD),
+            )
+        })
+        .collect();
+
+    let block = Block::bordered()
+        .title(format!(" {} ", node.config.name))
+        .title(
+            Line::from(Span::styled(
+                format!(" ● {} ", status.label()),
+                Style::default().fg(status_color),
+            ))
+            .right_aligned(),
+        )
+        .title_bottom(Line::from(badge_spans).right_aligned())
+        .border_style(Style::default().fg(if selected { theme.blue } else { theme.surface0 }))
+        .style(Style::default().bg(theme.bg).fg(theme.fg));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [info_area, cpu_area, mem_area, spark_area] =
+        Layout::vertical([Constraint::Length(1); 4]).areas(inner);
+
+    let info = match (&node.error, &node.summary) {
+        (Some(error), _) => Line::styled(format!(" {error}"), Style::default().fg(theme.red)),
+        (None, Some(s)) => Line::from(format!(
+            " {}  up {}  load {:.2}",
+            s.hostname,
+            format_uptime(s.uptime_secs),
+            s.load_average
+        )),
+        (None, None) => Line::from(" connecting…"),
+    };
+    frame.render_widget(Paragraph::new(info), info_area);
+
+    let (cpu, memory, memory_label) = match &node.summary {
+        Some(s) => (
+            s.cpu_usage,
+            memory_percent(s),
+            format!(
+                "{}/{}",
+                human_bytes(s.used_memory as f64),
+                human_bytes(s.total_memory as f64)
+            ),
+        ),
+        None => (0.0, 0.0, String::new()),
+    };
+    let gauge = |label: String, percent: f32| {
+        LineGauge::default()
+            .ratio((percent as f64 / 100.0).clamp(0.0, 1.0))
+            .label(label)
+            .filled_style(Style::default().fg(usage_color(theme, percent)))
+            .unfilled_style(Style::default().fg(theme.surface0))
+    };
+    frame.render_widget(gauge(format!(" CPU {cpu:>5.1}%"), cpu), cpu_area);
+    frame.render_widget(
+        gauge(format!(" MEM {memory:>5.1}% {memory_label}"), memory),
+        mem_area,
+    );
+
+    // Newest samples on the right
+    let history: Vec<u64> = node.history.iter().copied().collect();
+    let skip = history.len().saturating_sub(spark_area.width as usize);
+    frame.render_widget(
+        Sparkline::default()
+            .data(&history[skip..])
+            .max(1000)
+            .style(Style::default().fg(theme.green)),
+        spark_area,
+    );
+    spark_area
+}
+
+fn memory_percent(summary: &SummaryReply) -> f32 {
+    if summary.total_memory == 0 {
+        0.0
+    } else {
+        summary.used_memory as f32 / summary.total_memory as f32 * 100.0
+    }
+}
+
+fn usage_color(theme: &Theme, percent: f32) -> Color {
+    if percent >= ALERT_PERCENT {
+        theme.red
+    } else if percent >= 75.0 {
+        theme.yellow
+    } else {
+        theme.green
+    }
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{days}d{hours}h")
+    } else if hours > 0 {
+        format!("{hours}h{minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, tags: &[&str]) -> NodeConfig {
+        NodeConfig {
+            name: name.to_string(),
+            address: format!("http://{name}:50051"),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn summary(cpu_usage: f32) -> SummaryReply {
+        SummaryReply {
+            cpu_usage,
+            total_memory: 1 << 30,
+            ..Default::default()
+        }
+    }
+
+    /// beta is online at 50% CPU, alpha alerting at 95%, gamma offline and
+    /// delta still connecting.
+    fn fleet() -> Fleet {
+        let mut fleet = Fleet::new(&[
+            node("gamma", &["db"]),
+            node("alpha", &["web", "db"]),
+            node("delta", &[]),
+            node("beta", &["web"]),
+        ]);
+        fleet.apply_event(&AppEvent::NodeSummary {
+            node: 1,
+            summary: summary(95.0),
+        });
+        fleet.apply_event(&AppEvent::NodeSummary {
+            node: 3,
+            summary: summary(50.0),
+        });
+        fleet.apply_event(&AppEvent::NodeOffline {
+            node: 0,
+            error: "connection refused".to_string(),
+        });
+        fleet
+    }
+
+    fn press(fleet: &mut Fleet, c: char) {
+        fleet.handle_key(KeyEvent::from(KeyCode::Char(c)));
+    }
+
+    fn names(fleet: &Fleet) -> Vec<&str> {
+        fleet
+            .order()
+            .into_iter()
+            .map(|n| fleet.node(n).name.as_str())
+            .collect()
+    }
+
+    fn headings(fleet: &Fleet) -> Vec<(String, Vec<&str>)> {
+        fleet
+            .groups()
+            .into_iter()
+            .map(|g| {
+                let nodes = g
+                    .nodes
+                    .iter()
+                    .map(|&n| fleet.node(n).name.as_str())
+                    .collect();
+                (g.title.unwrap_or_default(), nodes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_name_status_and_load() {
+        let mut fleet = fleet();
+        assert_eq!(names(&fleet), ["alpha", "beta", "delta", "gamma"]);
+        press(&mut fleet, 's');
+        assert_eq!(names(&fleet), ["gamma", "alpha", "beta", "delta"]);
+        press(&mut fleet, 's');
+        // Busiest first, nodes without a summary last by name
+        assert_eq!(names(&fleet), ["alpha", "beta", "delta", "gamma"]);
+        fleet.apply_event(&AppEvent::NodeSummary {
+            node: 3,
+            summary: summary(99.0),
+        });
+        assert_eq!(names(&fleet), ["beta", "alpha", "delta", "gamma"]);
+        press(&mut fleet, 's');
+        assert_eq!(names(&fleet), ["alpha", "beta", "delta", "gamma"]);
+    }
+
+    #[test]
+    fn groups_by_tag_with_untagged_last() {
+        let mut fleet = fleet();
+        press(&mut fleet, 'g');
+        assert_eq!(
+            headings(&fleet),
+            [
+                ("db".to_string(), vec!["alpha", "gamma"]),
+                ("web".to_string(), vec!["alpha", "beta"]),
+                ("untagged".to_string(), vec!["delta"]),
+            ]
+        );
+        assert_eq!(names(&fleet).len(), 5);
+    }
+
+    #[test]
+    fn groups_by_status_skipping_empty_ones() {
+        let mut fleet = fleet();
+        fleet.apply_event(&AppEvent::NodeSummary {
+            node: 2,
+            summary: summary(10.0),
+        });
+        press(&mut fleet, 'g');
+        press(&mut fleet, 'g');
+        assert_eq!(
+            headings(&fleet),
+            [
+                ("offline".to_string(), vec!["gamma"]),
+                ("alerting".to_string(), vec!["alpha"]),
+                ("online".to_string(), vec!["beta", "delta"]),
+            ]
+        );
+        press(&mut fleet, 'g');
+        assert_eq!(
+            headings(&fleet),
+            [(String::new(), vec!["alpha", "beta", "delta", "gamma"])]
+        );
+    }
+
+    #[test]
+    fn regrouping_keeps_the_selected_node() {
+        let mut fleet = fleet();
+        press(&mut fleet, 'l');
+        press(&mut fleet, 'l');
+        assert_eq!(names(&fleet)[fleet.selected], "delta");
+        press(&mut fleet, 's');
+        assert_eq!(names(&fleet)[fleet.selected], "delta");
+        press(&mut fleet, 'g');
+        assert_eq!(names(&fleet)[fleet.selected], "delta");
+        assert!(matches!(
+            fleet.handle_key(KeyEvent::from(KeyCode::Enter)),
+            KeyResult::Action(Action::OpenNode(2))
+        ));
+    }
+}
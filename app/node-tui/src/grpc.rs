@@ -1,7 +1,13 @@
+use std::future::Future;
+use std::time::Duration;
+
 use tokio::sync::mpsc;
-use tonic::Request;
+use tokio::task::JoinSet;
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Streaming};
 
-use crate::event::AppEvent;
+use crate::event::{AppEvent, Tagged};
 
 pub mod node {
     tonic::include_proto!("node");
@@ -11,14 +17,169 @@ pub mod greeter {
     tonic::include_proto!("greeter");
 }
 
-use greeter::greeter_client::GreeterClient;
 use greeter::HelloRequest;
+use greeter::greeter_client::GreeterClient;
+use node::log_service_client::LogServiceClient;
 use node::node_monitor_client::NodeMonitorClient;
-use node::CpuRequest;
+use node::process_service_client::ProcessServiceClient;
+use node::sensor_service_client::SensorServiceClient;
+use node::shell_input::Kind as ShellInputKind;
+use node::shell_output::Kind as ShellOutputKind;
+use node::shell_service_client::ShellServiceClient;
+use node::storage_service_client::StorageServiceClient;
+use node::{
+    CpuRequest, LogsRequest, ProcessesRequest, SensorsRequest, ShellInput, ShellStart, Signal,
+    SignalRequest, StorageRequest, SummaryRequest,
+};
 
-pub fn spawn_cpu_stream(tx: mpsc::Sender<AppEvent>) {
-    tokio::spawn(async move {
-        let mut client = match NodeMonitorClient::connect("http://127.0.0.1:50051").await {
+/// Interval between fleet summaries of a node.
+pub const SUMMARY_REFRESH: Duration = Duration::from_secs(2);
+/// How long the fleet view waits before retrying an unreachable node.
+const SUMMARY_RETRY: Duration = Duration::from_secs(5);
+
+/// Sends events into the app's channel tagged with a connection generation,
+/// so ones still queued when that connection is replaced can be dropped.
+#[derive(Clone)]
+pub struct EventSender {
+    tx: mpsc::Sender<Tagged>,
+    generation: Option<u64>,
+}
+
+impl EventSender {
+    pub fn new(tx: mpsc::Sender<Tagged>, generation: Option<u64>) -> Self {
+        Self { tx, generation }
+    }
+
+    pub async fn send(&self, event: AppEvent) -> Result<(), mpsc::error::SendError<Tagged>> {
+        self.tx
+            .send(Tagged {
+                generation: self.generation,
+                event,
+            })
+            .await
+    }
+}
+
+/// The streams feeding the UI from one node. Dropping it aborts them all, so
+/// replacing it on reconnect never leaves duplicate streams behind.
+pub struct Connection {
+    pub endpoint: String,
+    pub tx: EventSender,
+    tasks: JoinSet<()>,
+}
+
+impl Connection {
+    pub fn new(endpoint: String, tx: EventSender) -> Self {
+        Self {
+            endpoint,
+            tx,
+            tasks: JoinSet::new(),
+        }
+    }
+
+    fn spawn(&mut self, task: impl Future<Output = ()> + Send + 'static) {
+        self.tasks.spawn(task);
+    }
+}
+
+/// What opening a stream can fail with: connecting or the call itself.
+type OpenError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Starts the stream `open` makes of `request` on the connection's endpoint
+/// and forwards every reply to the app as `map` turns it into an event.
+/// Failing to open it, or it ending, reports the node as disconnected.
+fn spawn_stream<R, T, F>(
+    conn: &mut Connection,
+    request: R,
+    open: impl FnOnce(String, R) -> F + Send + 'static,
+    map: impl Fn(T) -> AppEvent + Send + 'static,
+) where
+    R: Send + 'static,
+    T: Send + 'static,
+    F: Future<Output = Result<Streaming<T>, OpenError>> + Send,
+{
+    let (endpoint, tx) = (conn.endpoint.clone(), conn.tx.clone());
+    conn.spawn(async move {
+        let mut stream = match open(endpoint, request).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = tx.send(AppEvent::Disconnected(e.to_string())).await;
+                return;
+            }
+        };
+
+        while let Ok(Some(reply)) = stream.message().await {
+            if tx.send(map(reply)).await.is_err() {
+                return;
+            }
+        }
+
+        let _ = tx.send(AppEvent::Disconnected("Stream ended".into())).await;
+    });
+}
+
+pub fn spawn_cpu_stream(conn: &mut Connection) {
+    spawn_stream(
+        conn,
+        CpuRequest { refresh_ms: 500 },
+        |endpoint, request| async move {
+            let mut client = NodeMonitorClient::connect(endpoint).await?;
+            Ok(client.stream_cpu(request).await?.into_inner())
+        },
+        AppEvent::CpuUpdate,
+    );
+}
+
+pub fn spawn_storage_stream(conn: &mut Connection) {
+    spawn_stream(
+        conn,
+        StorageRequest {
+            refresh_ms: 2000,
+            include_smart: true,
+        },
+        |endpoint, request| async move {
+            let mut client = StorageServiceClient::connect(endpoint).await?;
+            Ok(client.stream_storage(request).await?.into_inner())
+        },
+        |reply| AppEvent::StorageUpdate(reply.disks),
+    );
+}
+
+pub fn spawn_sensors_stream(conn: &mut Connection) {
+    spawn_stream(
+        conn,
+        SensorsRequest { refresh_ms: 2000 },
+        |endpoint, request| async move {
+            let mut client = SensorServiceClient::connect(endpoint).await?;
+            Ok(client.stream_sensors(request).await?.into_inner())
+        },
+        |reply| AppEvent::SensorsUpdate {
+            temperatures: reply.temperatures,
+            fans: reply.fans,
+        },
+    );
+}
+
+pub fn spawn_process_stream(conn: &mut Connection) {
+    spawn_stream(
+        conn,
+        ProcessesRequest { refresh_ms: 2000 },
+        |endpoint, request| async move {
+            let mut client = ProcessServiceClient::connect(endpoint).await?;
+            Ok(client.stream_processes(request).await?.into_inner())
+        },
+        |reply| AppEvent::ProcessesUpdate {
+            processes: reply.processes,
+            total_memory: reply.total_memory,
+        },
+    );
+}
+
+/// Follows the log of `unit`, starting with up to `backlog` past entries.
+pub fn spawn_logs_stream(conn: &mut Connection, unit: String, backlog: u32) {
+    let (endpoint, tx) = (conn.endpoint.clone(), conn.tx.clone());
+    conn.spawn(async move {
+        let mut client = match LogServiceClient::connect(endpoint).await {
             Ok(c) => c,
             Err(e) => {
                 let _ = tx.send(AppEvent::Disconnected(e.to_string())).await;
@@ -26,37 +187,169 @@ pub fn spawn_cpu_stream(tx: mpsc::Sender<AppEvent>) {
             }
         };
 
-        let mut stream = match client
-            .stream_cpu(Request::new(CpuRequest { refresh_ms: 500 }))
-            .await
-        {
+        let request = LogsRequest {
+            unit: unit.clone(),
+            backlog,
+        };
+        let mut stream = match client.stream_logs(Request::new(request)).await {
             Ok(resp) => resp.into_inner(),
+            Err(e) => {
+                let error = e.message().to_string();
+                let _ = tx.send(AppEvent::LogsFailed { unit, error }).await;
+                return;
+            }
+        };
+
+        loop {
+            let event = match stream.message().await {
+                Ok(Some(entry)) => AppEvent::Log {
+                    unit: unit.clone(),
+                    entry,
+                },
+                Ok(None) => break,
+                Err(e) => AppEvent::LogsFailed {
+                    unit: unit.clone(),
+                    error: e.message().to_string(),
+                },
+            };
+            let failed = matches!(event, AppEvent::LogsFailed { .. });
+            if tx.send(event).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+}
+
+/// Opens a shell on a `rows`x`cols` PTY running `command` (the login shell
+/// when empty). Everything sent on `input` after that is forwarded as is.
+pub fn spawn_shell(
+    conn: &mut Connection,
+    command: String,
+    (rows, cols): (u16, u16),
+    input: mpsc::Receiver<ShellInput>,
+) {
+    let (endpoint, tx) = (conn.endpoint.clone(), conn.tx.clone());
+    conn.spawn(async move {
+        let mut client = match ShellServiceClient::connect(endpoint).await {
+            Ok(c) => c,
             Err(e) => {
                 let _ = tx.send(AppEvent::Disconnected(e.to_string())).await;
                 return;
             }
         };
 
-        while let Ok(Some(reply)) = stream.message().await {
+        let start = ShellInput {
+            kind: Some(ShellInputKind::Start(ShellStart {
+                rows: rows.into(),
+                cols: cols.into(),
+                command: command.clone(),
+            })),
+        };
+        let outbound = tokio_stream::once(start).chain(ReceiverStream::new(input));
+        let mut stream = match client.open(Request::new(outbound)).await {
+            Ok(resp) => resp.into_inner(),
+            Err(e) => {
+                let error = e.message().to_string();
+                let _ = tx.send(AppEvent::ShellFailed { command, error }).await;
+                return;
+            }
+        };
+
+        loop {
+            let event = match stream.message().await {
+                Ok(Some(output)) => match output.kind {
+                    Some(ShellOutputKind::Data(data)) => AppEvent::ShellOutput {
+                        command: command.clone(),
+                        data,
+                    },
+                    Some(ShellOutputKind::ExitCode(code)) => AppEvent::ShellExited {
+                        command: command.clone(),
+                        code,
+                    },
+                    None => continue,
+                },
+                Ok(None) => break,
+                Err(e) => AppEvent::ShellFailed {
+                    command: command.clone(),
+                    error: e.message().to_string(),
+                },
+            };
+            let last = !matches!(event, AppEvent::ShellOutput { .. });
+            if tx.send(event).await.is_err() || last {
+                break;
+            }
+        }
+    });
+}
+
+/// Summary of node `node` for the fleet view. Unlike the detail streams this
+/// one keeps retrying, so a node that comes back shows up on its own.
+pub fn spawn_summary_stream(conn: &mut Connection, node: usize) {
+    let (endpoint, tx) = (conn.endpoint.clone(), conn.tx.clone());
+    conn.spawn(async move {
+        loop {
+            let error = match NodeMonitorClient::connect(endpoint.clone()).await {
+                Ok(mut client) => match client
+                    .stream_summary(Request::new(SummaryRequest {
+                        refresh_ms: SUMMARY_REFRESH.as_millis() as u64,
+                    }))
+                    .await
+                {
+                    Ok(resp) => {
+                        let mut stream = resp.into_inner();
+                        loop {
+                            match stream.message().await {
+                                Ok(Some(summary)) => {
+                                    if tx
+                                        .send(AppEvent::NodeSummary { node, summary })
+                                        .await
+                                        .is_err()
+                                    {
+                                        return;
+                                    }
+                                }
+                                Ok(None) => break "Stream ended".to_string(),
+                                Err(e) => break e.message().to_string(),
+                            }
+                        }
+                    }
+                    Err(e) => e.message().to_string(),
+                },
+                Err(e) => e.to_string(),
+            };
+
             if tx
-                .send(AppEvent::CpuUpdate {
-                    cpu_count: reply.cpu_count,
-                    total_usage: reply.total_usage,
-                })
+                .send(AppEvent::NodeOffline { node, error })
                 .await
                 .is_err()
             {
-                break;
+                return;
             }
+            tokio::time::sleep(SUMMARY_RETRY).await;
         }
+    });
+}
 
-        let _ = tx.send(AppEvent::Disconnected("Stream ended".into())).await;
+pub fn send_signal(endpoint: String, pid: u32, signal: Signal, tx: EventSender) {
+    tokio::spawn(async move {
+        let result = match ProcessServiceClient::connect(endpoint).await {
+            Ok(mut client) => client
+                .send_signal(Request::new(SignalRequest {
+                    pid,
+                    signal: signal.into(),
+                }))
+                .await
+                .map(|_| ())
+                .map_err(|e| e.message().to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let _ = tx.send(AppEvent::SignalResult { pid, result }).await;
     });
 }
 
-pub fn send_greeting(name: String, tx: mpsc::Sender<AppEvent>) {
+pub fn send_greeting(endpoint: String, name: String, tx: EventSender) {
     tokio::spawn(async move {
-        let mut client = match GreeterClient::connect("http://127.0.0.1:50051").await {
+        let mut client = match GreeterClient::connect(endpoint).await {
             Ok(c) => c,
             Err(e) => {
                 let _ = tx
@@ -66,10 +359,7 @@ pub fn send_greeting(name: String, tx: mpsc::Sender<AppEvent>) {
             }
         };
 
-        match client
-            .say_hello(Request::new(HelloRequest { name }))
-            .await
-        {
+        match client.say_hello(Request::new(HelloRequest { name })).await {
             Ok(resp) => {
                 let _ = tx
                     .send(AppEvent::GreeterResponse(resp.into_inner().message))
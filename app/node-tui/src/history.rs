@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How far a tier's oldest point may sit after the start of a range and
+/// still count as reaching it, at least; otherwise one bucket. A tier pruned
+/// to its span starts a sample or half a bucket after `end - span`.
+const REACH_SLACK: Duration = Duration::from_secs(2);
+
+/// One point in time with a value per series. Missing values are NaN.
+pub struct Sample {
+    pub at: Instant,
+    pub values: Vec<f64>,
+}
+
+/// A time series buffer that keeps recent samples at full resolution and
+/// older ones averaged into coarser buckets, so a day of history stays a few
+/// thousand points no matter how fast samples arrive.
+pub struct History {
+    tiers: Vec<Tier>,
+}
+
+struct Tier {
+    /// Bucket width; zero keeps every sample as is.
+    step: Duration,
+    /// How far back this tier reaches.
+    span: Duration,
+    points: VecDeque<Sample>,
+    pending: Option<Bucket>,
+}
+
+struct Bucket {
+    start: Instant,
+    sums: Vec<f64>,
+    counts: Vec<u32>,
+}
+
+impl Bucket {
+    fn new(start: Instant, width: usize) -> Self {
+        Self {
+            start,
+            sums: vec![0.0; width],
+            counts: vec![0; width],
+        }
+    }
+
+    fn add(&mut self, values: &[f64]) {
+        for (i, value) in values.iter().enumerate().take(self.sums.len()) {
+            if !value.is_nan() {
+                self.sums[i] += value;
+                self.counts[i] += 1;
+            }
+        }
+    }
+
+    fn average(&self, step: Duration) -> Sample {
+        Sample {
+            at: self.start + step / 2,
+            values: self
+                .sums
+                .iter()
+                .zip(&self.counts)
+                .map(|(sum, &count)| if count == 0 { f64::NAN } else { sum / count as f64 })
+                .collect(),
+        }
+    }
+}
+
+impl History {
+    /// Raw samples for 5 minutes, 5 s averages for an hour and 2 min averages
+    /// for a day.
+    pub fn new() -> Self {
+        let tier = |step, span| Tier {
+            step: Duration::from_secs(step),
+            span: Duration::from_secs(span),
+            points: VecDeque::new(),
+            pending: None,
+        };
+        Self {
+            tiers: vec![tier(0, 5 * 60), tier(5, 60 * 60), tier(120, 24 * 60 * 60)],
+        }
+    }
+
+    pub fn push(&mut self, at: Instant, values: Vec<f64>) {
+        for tier in &mut self.tiers {
+            if tier.step.is_zero() {
+                tier.points.push_back(Sample {
+                    at,
+                    values: values.clone(),
+                });
+            } else {
+                // A change in series count (e.g. a reconnect to another node)
+                // starts a fresh bucket rather than mixing the two.
+                let flush = tier.pending.as_ref().is_some_and(|bucket| {
+                    at.duration_since(bucket.start) >= tier.step
+                        || bucket.sums.len() != values.len()
+                });
+                if flush && let Some(bucket) = tier.pending.take() {
+                    tier.points.push_back(bucket.average(tier.step));
+                }
+                tier.pending
+                    .get_or_insert_with(|| Bucket::new(at, values.len()))
+                    .add(&values);
+            }
+
+            while tier
+                .points
+                .front()
+                .is_some_and(|p| at.saturating_duration_since(p.at) > tier.span)
+            {
+                tier.points.pop_front();
+            }
+        }
+    }
+
+    /// Samples in `(end - window, end]` from the finest tier that still has
+    /// points from `end - window`, which for a range scrubbed into the past may
+    /// be a coarser one than its width needs. If none reaches that far, the
+    /// one reaching furthest back.
+    pub fn range(&self, window: Duration, end: Instant) -> impl Iterator<Item = &Sample> {
+        let start = end.checked_sub(window);
+        let oldest = |t: &Tier| t.points.front().map(|p| p.at);
+        let tier = self
+            .tiers
+            .iter()
+            .find(|t| {
+                let slack = t.step.max(REACH_SLACK);
+                start
+                    .zip(oldest(t))
+                    .is_some_and(|(start, at)| at <= start + slack)
+            })
+            .or_else(|| {
+                self.tiers
+                    .iter()
+                    .filter(|t| !t.points.is_empty())
+                    .min_by_key(|t| oldest(t))
+            })
+            .unwrap_or(&self.tiers[0]);
+        tier.points
+            .iter()
+            .filter(move |p| p.at <= end && start.is_none_or(|start| p.at > start))
+    }
+
+    /// The newest sample at or before `at`.
+    pub fn at(&self, at: Instant) -> Option<&Sample> {
+        self.tiers
+            .iter()
+            .find_map(|t| t.points.iter().rev().find(|p| p.at <= at))
+    }
+
+    pub fn oldest(&self) -> Option<Instant> {
+        self.tiers
+            .iter()
+            .filter_map(|t| t.points.front().map(|p| p.at))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// A history fed one sample a second for `secs` seconds, each the
+    /// number of seconds in, and when the last one was taken.
+    fn filled(secs: u32) -> (History, Instant) {
+        let start = Instant::now();
+        let mut history = History::new();
+        for i in 0..secs {
+            history.push(start + SECOND * i, vec![i as f64]);
+        }
+        (history, start + SECOND * (secs - 1))
+    }
+
+    /// The gap between the first two samples of a range, to tell which tier
+    /// it came from.
+    fn spacing(samples: &[&Sample]) -> Duration {
+        samples[1].at.duration_since(samples[0].at)
+    }
+
+    #[test]
+    fn buckets_average_their_samples() {
+        let start = Instant::now();
+        let mut history = History::new();
+        for (i, total) in [10.0, 20.0, 30.0, 40.0, 50.0, 99.0].into_iter().enumerate() {
+            let other = if i == 0 { 7.0 } else { f64::NAN };
+            history.push(start + SECOND * i as u32, vec![total, other, f64::NAN]);
+        }
+
+        let tier = &history.tiers[1];
+        assert_eq!(tier.points.len(), 1);
+        let bucket = &tier.points[0];
+        assert_eq!(bucket.at, start + Duration::from_millis(2500));
+        assert_eq!(bucket.values[0], 30.0);
+        // Missing values don't drag the average down, and none at all stays NaN
+        assert_eq!(bucket.values[1], 7.0);
+        assert!(bucket.values[2].is_nan());
+        assert_eq!(history.tiers[0].points.len(), 6);
+    }
+
+    #[test]
+    fn old_points_are_pruned_per_tier() {
+        let (history, last) = filled(2 * 60 * 60);
+
+        for tier in &history.tiers {
+            let oldest = tier.points.front().unwrap().at;
+            assert!(last.duration_since(oldest) <= tier.span);
+        }
+        assert_eq!(history.tiers[0].points.len(), 5 * 60 + 1);
+        assert_eq!(history.oldest(), Some(history.tiers[2].points[0].at));
+    }
+
+    #[test]
+    fn a_new_series_count_starts_a_new_bucket() {
+        let start = Instant::now();
+        let mut history = History::new();
+        history.push(start, vec![1.0, 2.0]);
+        history.push(start + SECOND, vec![3.0, 4.0, 5.0]);
+
+        let tier = &history.tiers[1];
+        assert_eq!(tier.points.len(), 1);
+        assert_eq!(tier.points[0].values, [1.0, 2.0]);
+        assert_eq!(tier.pending.as_ref().unwrap().sums, [3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn ranges_come_from_the_finest_tier_that_reaches() {
+        let (history, last) = filled(2 * 60 * 60);
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        let range = |window, end| history.range(window, end).collect::<Vec<_>>();
+
+        // Recent minutes at full resolution, a tier's whole span included
+        let recent = range(minutes(1), last);
+        assert_eq!(recent.len(), 60);
+        assert_eq!(spacing(&recent), SECOND);
+        assert_eq!(spacing(&range(minutes(5), last)), SECOND);
+
+        // Scrubbed back past the raw samples, the 5 s averages stand in
+        let scrubbed = range(minutes(5), last - minutes(30));
+        assert_eq!(spacing(&scrubbed), 5 * SECOND);
+        assert!(scrubbed.len() >= 59);
+        assert!(scrubbed.iter().all(|s| s.at <= last - minutes(30)));
+
+        assert_eq!(spacing(&range(minutes(60), last)), 5 * SECOND);
+        assert_eq!(spacing(&range(minutes(24 * 60), last)), 120 * SECOND);
+    }
+
+    #[test]
+    fn a_short_history_is_shown_in_full() {
+        let (history, last) = filled(3 * 60);
+
+        // No tier reaches back an hour; the raw one goes furthest
+        let samples: Vec<_> = history.range(Duration::from_secs(60 * 60), last).collect();
+        assert_eq!(samples.len(), 3 * 60);
+        assert_eq!(spacing(&samples), SECOND);
+        assert_eq!(History::new().range(SECOND, last).count(), 0);
+    }
+}
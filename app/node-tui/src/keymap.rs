@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use color_eyre::eyre::{bail, Result};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Actions bound through the `[keys]` config table. Panel-specific keys are
+/// not remappable; they take precedence over these when a panel uses them.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GlobalAction {
+    Quit,
+    NextPanel,
+    PrevPanel,
+    Reconnect,
+    Fleet,
+    Confirm,
+    Cancel,
+    Help,
+    ErrorDetails,
+}
+
+impl GlobalAction {
+    pub const ALL: [GlobalAction; 9] = [
+        GlobalAction::Quit,
+        GlobalAction::NextPanel,
+        GlobalAction::PrevPanel,
+        GlobalAction::Reconnect,
+        GlobalAction::Fleet,
+        GlobalAction::Confirm,
+        GlobalAction::Cancel,
+        GlobalAction::Help,
+        GlobalAction::ErrorDetails,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GlobalAction::Quit => "quit",
+            GlobalAction::NextPanel => "next_panel",
+            GlobalAction::PrevPanel => "prev_panel",
+            GlobalAction::Reconnect => "reconnect",
+            GlobalAction::Fleet => "fleet",
+            GlobalAction::Confirm => "confirm",
+            GlobalAction::Cancel => "cancel",
+            GlobalAction::Help => "help",
+            GlobalAction::ErrorDetails => "error_details",
+        }
+    }
+
+    /// What the action does, for the help popup.
+    pub fn description(self) -> &'static str {
+        match self {
+            GlobalAction::Quit => "Quit",
+            GlobalAction::NextPanel => "Focus next panel",
+            GlobalAction::PrevPanel => "Focus previous panel",
+            GlobalAction::Reconnect => "Reconnect to the node",
+            GlobalAction::Fleet => "Back to the fleet view",
+            GlobalAction::Confirm => "Confirm a popup",
+            GlobalAction::Cancel => "Cancel / close a popup",
+            GlobalAction::Help => "Show this help",
+            GlobalAction::ErrorDetails => "Show the last error in full",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            GlobalAction::Quit => &["q", "Esc"],
+            GlobalAction::NextPanel => &["Tab"],
+            GlobalAction::PrevPanel => &["Shift+Tab"],
+            GlobalAction::Reconnect => &["c"],
+            GlobalAction::Fleet => &["f"],
+            GlobalAction::Confirm => &["y", "Enter"],
+            GlobalAction::Cancel => &["n", "Esc"],
+            GlobalAction::Help => &["?"],
+            GlobalAction::ErrorDetails => &["e"],
+        }
+    }
+}
+
+/// A key plus modifiers, written in config as e.g. `"q"`, `"Esc"`, `"Ctrl+c"`,
+/// `"Shift+Tab"` or `"F5"`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = spec.split('+').collect();
+        // A lone "+" or a trailing "Ctrl++" means the plus key itself
+        let key = match parts.pop() {
+            Some("") if spec == "+" || spec.ends_with("++") => {
+                parts.pop();
+                "+"
+            }
+            Some(key) => key,
+            None => bail!("empty key binding"),
+        };
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown modifier `{modifier}` in `{spec}`"),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" | "return" => KeyCode::Enter,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=24) => KeyCode::F(n),
+                    _ => bail!("unknown key `{key}` in `{spec}`"),
+                },
+            },
+        };
+
+        // Terminals report Shift+Tab as BackTab, and shifted letters as the
+        // uppercase char, so fold those into one canonical form.
+        let (code, modifiers) = match code {
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+                (KeyCode::BackTab, modifiers - KeyModifiers::SHIFT)
+            }
+            KeyCode::BackTab => (KeyCode::BackTab, modifiers - KeyModifiers::SHIFT),
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => (
+                KeyCode::Char(c.to_ascii_uppercase()),
+                modifiers - KeyModifiers::SHIFT,
+            ),
+            code => (code, modifiers),
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        // Shift is already folded into the char / BackTab code
+        let modifiers = match key.code {
+            KeyCode::Char(_) | KeyCode::BackTab => key.modifiers - KeyModifiers::SHIFT,
+            _ => key.modifiers,
+        };
+        key.code == self.code && modifiers == self.modifiers
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => write!(f, "S-Tab"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => write!(f, "{code}"),
+        }
+    }
+}
+
+/// One or several keys for an action in the `[keys]` table.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Resolves key presses to global actions.
+pub struct Keymap {
+    bindings: HashMap<GlobalAction, Vec<KeyBinding>>,
+}
+
+impl Keymap {
+    /// Builds the keymap from the defaults, replacing the keys of every action
+    /// named in `overrides`.
+    pub fn new(overrides: &HashMap<String, KeySpec>) -> Result<Self> {
+        let mut bindings = HashMap::new();
+        for action in GlobalAction::ALL {
+            let keys = action
+                .default_keys()
+                .iter()
+                .map(|spec| KeyBinding::parse(spec))
+                .collect::<Result<Vec<_>>>()?;
+            bindings.insert(action, keys);
+        }
+
+        for (name, spec) in overrides {
+            let Some(action) = GlobalAction::from_name(name) else {
+                let known: Vec<&str> = GlobalAction::ALL.iter().map(|a| a.name()).collect();
+                bail!("Unknown action `{name}` in [keys] (known: {})", known.join(", "));
+            };
+            let specs = match spec {
+                KeySpec::One(s) => std::slice::from_ref(s),
+                KeySpec::Many(v) => v.as_slice(),
+            };
+            let keys = specs
+                .iter()
+                .map(|s| KeyBinding::parse(s))
+                .collect::<Result<Vec<_>>>()?;
+            bindings.insert(action, keys);
+        }
+
+        Ok(Self { bindings })
+    }
+
+    /// The action bound to `key`, checking only `candidates` so the same key
+    /// can mean different things in different contexts (Esc both quits and
+    /// cancels a popup).
+    pub fn resolve(&self, key: &KeyEvent, candidates: &[GlobalAction]) -> Option<GlobalAction> {
+        candidates.iter().copied().find(|action| {
+            self.bindings
+                .get(action)
+                .is_some_and(|keys| keys.iter().any(|b| b.matches(key)))
+        })
+    }
+
+    /// Bound keys for `action` joined for the help line, e.g. `q/Esc`.
+    pub fn label(&self, action: GlobalAction) -> String {
+        self.bindings
+            .get(&action)
+            .map(|keys| {
+                keys.iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&HashMap::new()).expect("default key bindings are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONE: KeyModifiers = KeyModifiers::NONE;
+    const CTRL: KeyModifiers = KeyModifiers::CONTROL;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn parse(spec: &str) -> (KeyCode, KeyModifiers) {
+        let binding = KeyBinding::parse(spec).unwrap();
+        (binding.code, binding.modifiers)
+    }
+
+    fn with_keys(toml: &str) -> Result<Keymap> {
+        Keymap::new(&toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn parses_keys_and_modifiers() {
+        assert_eq!(parse("q"), (KeyCode::Char('q'), NONE));
+        assert_eq!(parse("esc"), (KeyCode::Esc, NONE));
+        assert_eq!(parse("PageDown"), (KeyCode::PageDown, NONE));
+        assert_eq!(parse("Space"), (KeyCode::Char(' '), NONE));
+        assert_eq!(parse("F12"), (KeyCode::F(12), NONE));
+        assert_eq!(parse("+"), (KeyCode::Char('+'), NONE));
+        assert_eq!(parse("Ctrl++"), (KeyCode::Char('+'), CTRL));
+        assert_eq!(
+            parse("control+Alt+x"),
+            (KeyCode::Char('x'), CTRL | KeyModifiers::ALT)
+        );
+    }
+
+    #[test]
+    fn shift_folds_into_the_key() {
+        assert_eq!(parse("Shift+Tab"), (KeyCode::BackTab, NONE));
+        assert_eq!(parse("BackTab"), (KeyCode::BackTab, NONE));
+        assert_eq!(parse("Shift+k"), (KeyCode::Char('K'), NONE));
+
+        // Terminals may or may not report Shift alongside the uppercase char
+        let upper = KeyBinding::parse("K").unwrap();
+        assert!(upper.matches(&key(KeyCode::Char('K'), NONE)));
+        assert!(upper.matches(&key(KeyCode::Char('K'), KeyModifiers::SHIFT)));
+        assert!(!upper.matches(&key(KeyCode::Char('k'), NONE)));
+
+        let ctrl = KeyBinding::parse("Ctrl+c").unwrap();
+        assert!(ctrl.matches(&key(KeyCode::Char('c'), CTRL)));
+        assert!(!ctrl.matches(&key(KeyCode::Char('c'), NONE)));
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for spec in ["", "Ctrl+", "Escc", "F0", "F25"] {
+            assert!(KeyBinding::parse(spec).is_err(), "{spec:?}");
+        }
+        let err = KeyBinding::parse("Meta+q").unwrap_err();
+        assert_eq!(err.to_string(), "unknown modifier `Meta` in `Meta+q`");
+    }
+
+    #[test]
+    fn labels_read_as_the_help_line_shows_them() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.label(GlobalAction::Quit), "q/Esc");
+        assert_eq!(keymap.label(GlobalAction::PrevPanel), "S-Tab");
+        let binding = KeyBinding::parse("Ctrl+Alt+PageUp").unwrap();
+        assert_eq!(binding.to_string(), "Ctrl+Alt+PgUp");
+    }
+
+    #[test]
+    fn overrides_replace_the_defaults() {
+        let keymap = with_keys("quit = \"Ctrl+q\"\nhelp = [\"F1\", \"h\"]").unwrap();
+        let quit = [GlobalAction::Quit];
+        assert_eq!(
+            keymap.resolve(&key(KeyCode::Char('q'), CTRL), &quit),
+            Some(GlobalAction::Quit)
+        );
+        assert_eq!(keymap.resolve(&key(KeyCode::Char('q'), NONE), &quit), None);
+        assert_eq!(keymap.resolve(&key(KeyCode::Esc, NONE), &quit), None);
+        assert_eq!(keymap.label(GlobalAction::Help), "F1/h");
+        // Actions left out keep their defaults
+        assert_eq!(keymap.label(GlobalAction::Fleet), "f");
+    }
+
+    #[test]
+    fn rejects_bad_overrides() {
+        let err = with_keys("explode = \"x\"").err().unwrap();
+        assert!(err.to_string().starts_with("Unknown action `explode`"));
+        assert!(with_keys("quit = [\"q\", \"Nope\"]").is_err());
+    }
+
+    #[test]
+    fn candidates_settle_shared_keys() {
+        let esc = key(KeyCode::Esc, NONE);
+        let keymap = Keymap::default();
+        // Esc quits from the dashboard but cancels a popup
+        assert_eq!(
+            keymap.resolve(&esc, &[GlobalAction::Quit, GlobalAction::Help]),
+            Some(GlobalAction::Quit)
+        );
+        assert_eq!(
+            keymap.resolve(&esc, &[GlobalAction::Confirm, GlobalAction::Cancel]),
+            Some(GlobalAction::Cancel)
+        );
+
+        // A key bound to two candidates goes to the first one listed
+        let x = key(KeyCode::Char('x'), NONE);
+        let keymap = with_keys("confirm = \"x\"\ncancel = \"x\"").unwrap();
+        let popup = [GlobalAction::Confirm, GlobalAction::Cancel];
+        assert_eq!(keymap.resolve(&x, &popup), Some(GlobalAction::Confirm));
+        let reversed = [GlobalAction::Cancel, GlobalAction::Confirm];
+        assert_eq!(keymap.resolve(&x, &reversed), Some(GlobalAction::Cancel));
+        assert_eq!(keymap.resolve(&x, &[GlobalAction::Quit]), None);
+    }
+}
@@ -1,49 +1,149 @@
+use std::io::stdout;
 use std::time::Duration;
 
 use color_eyre::Result;
-use ratatui::crossterm::event::{Event, KeyEventKind};
+use ratatui::crossterm::event::{
+    DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
+    EventStream, KeyEventKind,
+};
+use ratatui::crossterm::execute;
 use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::StreamExt;
 
 mod app;
+mod cli;
+mod config;
 mod event;
+mod fleet;
 mod grpc;
+mod history;
+mod keymap;
+mod modal;
+mod panel;
+mod panels;
+mod record;
+mod theme;
 mod ui;
 
-use app::{Action, App};
-use event::AppEvent;
+use app::{Action, App, View};
+use cli::Args;
+use config::Config;
+use event::Tagged;
+use record::Recorder;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
+    let args = Args::parse()?;
+    let config = Config::load()?;
+    let mut app = App::new(&config)?;
+    let replay = args.replay.as_deref().map(record::load).transpose()?;
+    let mut recorder = args.record.as_deref().map(Recorder::create).transpose()?;
+    let replaying = replay.is_some();
     let mut terminal = ratatui::init();
 
-    let (tx, mut rx) = mpsc::channel::<AppEvent>(32);
-    let mut app = App::new();
+    let (tx, mut rx) = mpsc::channel::<Tagged>(32);
 
-    grpc::spawn_cpu_stream(tx.clone());
+    match replay {
+        Some(entries) => record::spawn_replay(entries, args.speed, tx.clone()),
+        None => {
+            app.fleet.connect(&tx);
+            if app.view == View::Detail {
+                app.connect(&tx);
+            }
+        }
+    }
+
+    execute!(stdout(), EnableFocusChange)?;
+    if app.mouse {
+        execute!(stdout(), EnableMouseCapture)?;
+    }
+    let mut terminal_events = EventStream::new();
+    // Keeps time-relative views (chart axes) moving and paces redraws while
+    // the terminal is unfocused.
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+    tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut terminal_focused = true;
+    let mut dirty = true;
 
     loop {
-        terminal.draw(|frame| ui::draw(frame, &app))?;
-
-        if ratatui::crossterm::event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = ratatui::crossterm::event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    if let Some(action) = app.handle_key(key) {
-                        match action {
-                            Action::Quit => break,
-                            Action::Reconnect => grpc::spawn_cpu_stream(tx.clone()),
-                            Action::SendGreeting(name) => grpc::send_greeting(name, tx.clone()),
-                        }
+        if dirty {
+            terminal.draw(|frame| ui::draw(frame, &app))?;
+            app.resize_panels();
+            dirty = false;
+        }
+
+        tokio::select! {
+            event = terminal_events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    dirty = true;
+                    if let Some(action) = app.handle_key(key)
+                        && !perform(&mut app, action, &tx, replaying)
+                    {
+                        break;
+                    }
+                }
+                Some(Ok(Event::Mouse(mouse))) => {
+                    dirty = true;
+                    if let Some(action) = app.handle_mouse(mouse)
+                        && !perform(&mut app, action, &tx, replaying)
+                    {
+                        break;
                     }
                 }
+                Some(Ok(Event::Resize(..))) => dirty = true,
+                Some(Ok(Event::FocusGained)) => {
+                    terminal_focused = true;
+                    dirty = true;
+                }
+                Some(Ok(Event::FocusLost)) => terminal_focused = false,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            },
+            Some(ev) = rx.recv() => {
+                // Apply whatever else is already queued before drawing once
+                let mut next = Some(ev);
+                while let Some(ev) = next {
+                    if let Some(recorder) = &mut recorder {
+                        recorder.record(&ev.event)?;
+                    }
+                    app.apply_event(ev);
+                    next = rx.try_recv().ok();
+                }
+                // Unfocused, data only reaches the screen on the next tick
+                dirty = terminal_focused;
             }
-        }
-
-        while let Ok(ev) = rx.try_recv() {
-            app.apply_event(ev);
+            _ = tick.tick() => dirty = true,
         }
     }
 
+    if app.mouse {
+        execute!(stdout(), DisableMouseCapture)?;
+    }
+    execute!(stdout(), DisableFocusChange)?;
     ratatui::restore();
     Ok(())
 }
+
+/// Carries out an action from a key press or click. Returns false to quit.
+fn perform(app: &mut App, action: Action, tx: &mpsc::Sender<Tagged>, replaying: bool) -> bool {
+    match action {
+        Action::Quit => return false,
+        Action::OpenNode(node) => {
+            let needs_streams = app.open_node(node);
+            if needs_streams && !replaying {
+                app.connect(tx);
+            }
+        }
+        // A replay has no node to talk to
+        _ if replaying => {}
+        Action::Reconnect => app.connect(tx),
+        Action::SendGreeting(name) => grpc::send_greeting(app.endpoint(), name, app.events(tx)),
+        Action::SendSignal { pid, signal } => {
+            grpc::send_signal(app.endpoint(), pid, signal, app.events(tx))
+        }
+    }
+    true
+}
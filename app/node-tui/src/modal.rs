@@ -0,0 +1,159 @@
+use ratatui::{
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::app::{Action, App};
+use crate::keymap::GlobalAction;
+use crate::panel::KeyHint;
+use crate::theme::Theme;
+
+const MOUSE_HINTS: &[KeyHint] = &[
+    KeyHint::new("Click", "Focus panel / select row or tile"),
+    KeyHint::new("Click again", "Open the selected fleet tile"),
+    KeyHint::new("Wheel", "Scroll lists and logs, zoom the CPU chart"),
+    KeyHint::new("Hover", "Exact values on charts and sparklines"),
+];
+
+/// A popup drawn over everything else that takes every key until closed.
+pub enum Modal {
+    /// A yes/no question guarding an action, e.g. killing a process.
+    Confirm { prompt: String, action: Action },
+    /// Every binding: global keys, the fleet view's and each panel's.
+    Help { scroll: u16 },
+    /// The full text of an error that is only shown truncated inline.
+    Error { title: String, message: String },
+}
+
+/// Lines of the help popup, grouped by where the keys apply.
+pub fn help_lines(app: &App) -> Vec<Line<'static>> {
+    let theme = &app.theme;
+    let heading = |title: &str| {
+        Line::styled(
+            title.to_string(),
+            Style::default()
+                .fg(theme.lavender)
+                .add_modifier(Modifier::BOLD),
+        )
+    };
+    let binding = |key: String, description: &str| {
+        Line::from(vec![
+            Span::styled(format!("  {key:<16}"), Style::default().fg(theme.yellow)),
+            Span::raw(description.to_string()),
+        ])
+    };
+    let hints = |lines: &mut Vec<Line<'static>>, title: &str, hints: &[KeyHint]| {
+        if hints.is_empty() {
+            return;
+        }
+        lines.push(Line::from(""));
+        lines.push(heading(title));
+        for hint in hints {
+            lines.push(binding(hint.key.to_string(), hint.description));
+        }
+    };
+
+    let mut lines = vec![heading("Global")];
+    for action in GlobalAction::ALL {
+        lines.push(binding(app.keymap.label(action), action.description()));
+    }
+    hints(&mut lines, "Fleet", app.fleet.key_hints());
+
+    let mut seen = Vec::new();
+    for panel in &app.panels {
+        if !seen.contains(&panel.title()) {
+            seen.push(panel.title());
+            hints(&mut lines, panel.title(), panel.key_hints());
+        }
+    }
+    if app.mouse {
+        hints(&mut lines, "Mouse", MOUSE_HINTS);
+    }
+    lines
+}
+
+pub fn draw(frame: &mut Frame, app: &App, modal: &Modal, area: Rect) {
+    let theme = &app.theme;
+    let keymap = &app.keymap;
+    let key = |action| {
+        Span::styled(format!("[{}]", keymap.label(action)), Style::default().fg(theme.yellow))
+    };
+
+    match modal {
+        Modal::Confirm { prompt, .. } => {
+            let width = (prompt.chars().count() as u16 + 4).clamp(30, area.width);
+            let text = vec![
+                Line::from(prompt.as_str()),
+                Line::from(""),
+                Line::from(vec![
+                    key(GlobalAction::Confirm),
+                    Span::raw(" Yes  "),
+                    key(GlobalAction::Cancel),
+                    Span::raw(" No"),
+                ]),
+            ];
+            let popup = area.centered(Constraint::Length(width), Constraint::Length(5));
+            draw_popup(frame, theme, " Confirm ", text, popup, theme.yellow);
+        }
+        Modal::Help { scroll } => {
+            let lines = help_lines(app);
+            let width = 64.min(area.width);
+            let height = (lines.len() as u16 + 3).min(area.height);
+            let popup = area.centered(Constraint::Length(width), Constraint::Length(height));
+            let block = Block::bordered()
+                .title(" Help ")
+                .title_bottom(Line::from(vec![
+                    Span::raw(" "),
+                    Span::styled("[↑/↓]", Style::default().fg(theme.yellow)),
+                    Span::raw(" Scroll  "),
+                    key(GlobalAction::Cancel),
+                    Span::raw(" Close "),
+                ]))
+                .border_style(Style::default().fg(theme.lavender))
+                .style(Style::default().bg(theme.surface0).fg(theme.fg));
+            frame.render_widget(Clear, popup);
+            frame.render_widget(Paragraph::new(lines).block(block).scroll((*scroll, 0)), popup);
+        }
+        Modal::Error { title, message } => {
+            let width = 72.min(area.width);
+            // Wrapped height, roughly; the text wraps on words
+            let text_width = width.saturating_sub(2).max(1) as usize;
+            let rows: usize = message
+                .lines()
+                .map(|line| line.chars().count().div_ceil(text_width).max(1))
+                .sum();
+            let height = (rows as u16 + 4).min(area.height);
+
+            let mut text: Vec<Line> = message.lines().map(|l| Line::from(l).left_aligned()).collect();
+            text.push(Line::from(""));
+            text.push(Line::from(vec![key(GlobalAction::Cancel), Span::raw(" Close")]));
+            let popup = area.centered(Constraint::Length(width), Constraint::Length(height));
+            draw_popup(frame, theme, &format!(" {title} "), text, popup, theme.red);
+        }
+    }
+}
+
+fn draw_popup(
+    frame: &mut Frame,
+    theme: &Theme,
+    title: &str,
+    text: Vec<Line>,
+    popup: Rect,
+    border: Color,
+) {
+    let block = Block::bordered()
+        .title(title.to_string())
+        .border_style(Style::default().fg(border))
+        .style(Style::default().bg(theme.surface0).fg(theme.fg));
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(block)
+            .centered()
+            .wrap(Wrap { trim: true }),
+        popup,
+    );
+}
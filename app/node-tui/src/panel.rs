@@ -0,0 +1,80 @@
+use ratatui::crossterm::event::{KeyEvent, MouseEvent};
+use ratatui::{Frame, layout::Rect};
+
+use crate::app::Action;
+use crate::event::AppEvent;
+use crate::grpc::Connection;
+use crate::theme::Theme;
+
+/// What a panel did with a key press or mouse event.
+pub enum KeyResult {
+    /// The panel does not use this key; global bindings get a chance at it.
+    Ignored,
+    /// The panel used the key and only changed its own state.
+    Handled,
+    /// The panel used the key and wants the main loop to do something.
+    Action(Action),
+    /// Like `Action`, but only after the user confirms `prompt` in a popup.
+    Confirm { prompt: String, action: Action },
+}
+
+/// A `[key] description` pair shown in the help line while a panel is focused.
+pub struct KeyHint {
+    pub key: &'static str,
+    pub description: &'static str,
+}
+
+impl KeyHint {
+    pub const fn new(key: &'static str, description: &'static str) -> Self {
+        Self { key, description }
+    }
+}
+
+/// A self-contained dashboard panel.
+///
+/// `App` owns a list of boxed panels built from the layout config. It draws
+/// the border and title around each one, routes key presses to the focused
+/// panel, mouse events to the panel under the pointer and broadcasts every
+/// `AppEvent` to all of them.
+pub trait Panel {
+    fn title(&self) -> &str;
+
+    fn key_hints(&self) -> &[KeyHint] {
+        &[]
+    }
+
+    /// Smallest useful height including the border.
+    fn min_height(&self) -> u16 {
+        3
+    }
+
+    /// Draws the panel contents inside the border `App` already rendered.
+    fn render(&self, frame: &mut Frame, area: Rect, focused: bool, theme: &Theme);
+
+    /// While true the panel sees every key first, including Esc and Tab, e.g.
+    /// while the user is typing into a filter field.
+    fn captures_input(&self) -> bool {
+        false
+    }
+
+    fn handle_key(&mut self, _key: KeyEvent) -> KeyResult {
+        KeyResult::Ignored
+    }
+
+    /// A click or scroll over the panel, or any pointer movement (so hover
+    /// state can be cleared once the pointer leaves). `area` is where the
+    /// contents were last rendered. A click has already focused the panel.
+    fn handle_mouse(&mut self, _mouse: MouseEvent, _area: Rect) -> KeyResult {
+        KeyResult::Ignored
+    }
+
+    /// The contents area after a frame was drawn, for panels whose state
+    /// depends on their size. Called after every frame; it may be empty.
+    fn resize(&mut self, _area: Rect) {}
+
+    fn apply_event(&mut self, event: &AppEvent);
+
+    /// Starts whatever gRPC streams feed this panel on `conn`. Called at
+    /// startup, on every reconnect and when switching nodes.
+    fn connect(&self, _conn: &mut Connection) {}
+}
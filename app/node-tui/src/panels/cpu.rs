@@ -0,0 +1,387 @@
+use std::time::{Duration, Instant};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Position, Rect},
+    style::Style,
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{Axis, Chart, Dataset, Gauge, GraphType, LegendPosition, Paragraph},
+    Frame,
+};
+
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::CpuReply;
+use crate::history::History;
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+use crate::ui;
+
+const HINTS: &[KeyHint] = &[
+    KeyHint::new("z/Z", "Zoom"),
+    KeyHint::new("v", "Usage/Cores"),
+    KeyHint::new("Space", "Pause"),
+    KeyHint::new("←/→", "Scrub"),
+];
+
+const ZOOM_LEVELS: [(Duration, &str); 4] = [
+    (Duration::from_secs(60), "1m"),
+    (Duration::from_secs(5 * 60), "5m"),
+    (Duration::from_secs(60 * 60), "1h"),
+    (Duration::from_secs(24 * 60 * 60), "24h"),
+];
+
+/// Columns left of the plot: the widest y label ("100%") and the axis line.
+const Y_AXIS_WIDTH: u16 = 5;
+
+// Layout of each history sample
+const TOTAL: usize = 0;
+const USER: usize = 1;
+const SYSTEM: usize = 2;
+const FIRST_CORE: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+    /// Total next to its user/system split.
+    Usage,
+    /// One line per logical CPU.
+    Cores,
+}
+
+pub struct CpuPanel {
+    cpu_count: u64,
+    cpu_usage: f32,
+    history: History,
+    zoom: usize,
+    view: View,
+    /// While paused the chart stays anchored here instead of following now.
+    paused_at: Option<Instant>,
+    /// How far the right edge of the chart is scrubbed back from the anchor.
+    scrub: Duration,
+    /// Pointer position while it is over the panel, for the value tooltip.
+    hover: Option<Position>,
+    error: Option<String>,
+}
+
+impl CpuPanel {
+    pub fn new() -> Self {
+        Self {
+            cpu_count: 0,
+            cpu_usage: 0.0,
+            history: History::new(),
+            zoom: 0,
+            view: View::Usage,
+            paused_at: None,
+            scrub: Duration::ZERO,
+            hover: None,
+            error: None,
+        }
+    }
+
+    fn window(&self) -> Duration {
+        ZOOM_LEVELS[self.zoom].0
+    }
+
+    /// Right edge of the chart.
+    fn end(&self) -> Instant {
+        let anchor = self.paused_at.unwrap_or_else(Instant::now);
+        anchor.checked_sub(self.scrub).unwrap_or(anchor)
+    }
+
+    fn scrub_by(&mut self, back: bool) {
+        let anchor = *self.paused_at.get_or_insert_with(Instant::now);
+        let step = self.window() / 10;
+        self.scrub = if back {
+            let oldest = self.history.oldest().unwrap_or(anchor);
+            (self.scrub + step).min(anchor.saturating_duration_since(oldest))
+        } else {
+            self.scrub.saturating_sub(step)
+        };
+    }
+
+    /// Exact values of the sample under the pointer, if it is over the plot.
+    fn tooltip(&self, chart_area: Rect, hover: Position, theme: &Theme) -> Option<Vec<Line<'static>>> {
+        let plot_x = chart_area.x + Y_AXIS_WIDTH;
+        let plot_width = chart_area.width.checked_sub(Y_AXIS_WIDTH)?;
+        if hover.x < plot_x || plot_width == 0 {
+            return None;
+        }
+        let fraction = (hover.x - plot_x) as f64 / plot_width as f64;
+        let back = self.window().mul_f64((1.0 - fraction).clamp(0.0, 1.0));
+        let end = self.end();
+        let sample = self.history.at(end.checked_sub(back)?)?;
+        // Nothing recorded near the pointer, e.g. before the first sample
+        if end.saturating_duration_since(sample.at) > back + self.window() / plot_width as u32 {
+            return None;
+        }
+
+        let anchor = self.paused_at.unwrap_or(end);
+        let when = format_ago(anchor.saturating_duration_since(sample.at));
+        let mut lines = vec![Line::styled(when, Style::default().fg(theme.lavender))];
+        let value = |name: String, index: usize| {
+            let value = *sample.values.get(index)?;
+            (!value.is_nan()).then(|| Line::from(format!("{name:<7}{value:>6.1}%")))
+        };
+        lines.extend(value("total".to_string(), TOTAL));
+        match self.view {
+            View::Usage => {
+                lines.extend(value("user".to_string(), USER));
+                lines.extend(value("system".to_string(), SYSTEM));
+            }
+            View::Cores => {
+                for core in 0..sample.values.len().saturating_sub(FIRST_CORE) {
+                    lines.extend(value(format!("cpu{core}"), FIRST_CORE + core));
+                }
+            }
+        }
+        Some(lines)
+    }
+
+    fn stats_line(&self, theme: &Theme) -> Line<'_> {
+        let zoom = Span::styled(
+            format!("  [{}]", ZOOM_LEVELS[self.zoom].1),
+            Style::default().fg(theme.lavender),
+        );
+        let error_hint = match &self.error {
+            Some(e) => format!("  ({e})"),
+            None => String::new(),
+        };
+
+        let Some(anchor) = self.paused_at else {
+            let split = self
+                .history
+                .at(Instant::now())
+                .map(|s| split_text(&s.values))
+                .unwrap_or_default();
+            return Line::from(vec![
+                Span::raw(format!(
+                    " CPUs: {}  |  Usage: {:.1}%{split}",
+                    self.cpu_count, self.cpu_usage
+                )),
+                zoom,
+                Span::raw(error_hint),
+            ]);
+        };
+
+        let end = self.end();
+        let when = format!(" PAUSED @ {}", format_ago(anchor.duration_since(end)));
+        let values = match self.history.at(end) {
+            Some(sample) => format!(
+                "  |  Usage: {:.1}%{}",
+                sample.values[TOTAL],
+                split_text(&sample.values)
+            ),
+            None => "  |  (no data)".to_string(),
+        };
+        Line::from(vec![
+            Span::styled(when, Style::default().fg(theme.yellow)),
+            Span::raw(values),
+            zoom,
+        ])
+    }
+}
+
+impl Panel for CpuPanel {
+    fn title(&self) -> &str {
+        "CPU Monitor"
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        HINTS
+    }
+
+    fn min_height(&self) -> u16 {
+        10
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, _focused: bool, theme: &Theme) {
+        let [stats_area, chart_area, gauge_area] = Layout::vertical([
+            Constraint::Length(1), // Stats line
+            Constraint::Min(4),    // Chart
+            Constraint::Length(1), // Gauge
+        ])
+        .areas(area);
+
+        frame.render_widget(
+            Paragraph::new(self.stats_line(theme)).style(Style::default().bg(theme.bg).fg(theme.fg)),
+            stats_area,
+        );
+
+        // Chart, x in seconds relative to its right edge
+        let window = self.window();
+        let end = self.end();
+        let samples: Vec<_> = self.history.range(window, end).collect();
+        let series = |index: usize| -> Vec<(f64, f64)> {
+            samples
+                .iter()
+                .filter_map(|s| {
+                    let value = *s.values.get(index)?;
+                    let x = -end.saturating_duration_since(s.at).as_secs_f64();
+                    (!value.is_nan()).then_some((x, value))
+                })
+                .collect()
+        };
+
+        let lines = match self.view {
+            View::Usage => vec![
+                ("total".to_string(), theme.green, series(TOTAL)),
+                ("user".to_string(), theme.blue, series(USER)),
+                ("system".to_string(), theme.red, series(SYSTEM)),
+            ],
+            View::Cores => {
+                let palette = [theme.blue, theme.green, theme.yellow, theme.lavender, theme.red];
+                (0..self.cpu_count as usize)
+                    .map(|core| {
+                        let color = palette[core % palette.len()];
+                        (format!("cpu{core}"), color, series(FIRST_CORE + core))
+                    })
+                    .collect()
+            }
+        };
+        let datasets = lines
+            .iter()
+            .filter(|(_, _, data)| !data.is_empty())
+            .map(|(name, color, data)| {
+                Dataset::default()
+                    .name(name.as_str())
+                    .marker(Marker::Braille)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(*color))
+                    .data(data)
+            })
+            .collect();
+
+        let anchor = self.paused_at.unwrap_or(end);
+        let offset = anchor.saturating_duration_since(end);
+        let x_labels = vec![
+            Span::raw(format_ago(offset + window)),
+            Span::raw(format_ago(offset + window / 2)),
+            Span::raw(format_ago(offset)),
+        ];
+        let chart = Chart::new(datasets)
+            .style(Style::default().bg(theme.bg).fg(theme.fg))
+            .x_axis(
+                Axis::default()
+                    .bounds([-window.as_secs_f64(), 0.0])
+                    .labels(x_labels)
+                    .style(Style::default().fg(theme.fg)),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([0.0, 100.0])
+                    .labels(["0%", "50%", "100%"])
+                    .style(Style::default().fg(theme.fg)),
+            )
+            .hidden_legend_constraints((Constraint::Ratio(1, 4), Constraint::Ratio(3, 4)))
+            .legend_position(match self.view {
+                View::Usage => Some(LegendPosition::TopLeft),
+                View::Cores => None,
+            });
+        frame.render_widget(chart, chart_area);
+
+        if let Some(hover) = self.hover
+            && chart_area.contains(hover)
+            && let Some(lines) = self.tooltip(chart_area, hover, theme)
+        {
+            ui::draw_tooltip(frame, theme, lines, hover, chart_area);
+        }
+
+        // Gauge
+        let ratio = (self.cpu_usage as f64 / 100.0).clamp(0.0, 1.0);
+        let gauge = Gauge::default()
+            .ratio(ratio)
+            .gauge_style(Style::default().fg(theme.green).bg(theme.surface0))
+            .label(format!("{:.1}%", self.cpu_usage));
+        frame.render_widget(gauge, gauge_area);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        match key.code {
+            KeyCode::Char('z') => self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1),
+            KeyCode::Char('Z') => self.zoom = self.zoom.saturating_sub(1),
+            KeyCode::Char('v') => {
+                self.view = match self.view {
+                    View::Usage => View::Cores,
+                    View::Cores => View::Usage,
+                }
+            }
+            KeyCode::Char(' ') => {
+                self.paused_at = match self.paused_at {
+                    Some(_) => None,
+                    None => Some(Instant::now()),
+                };
+                self.scrub = Duration::ZERO;
+            }
+            KeyCode::Left => self.scrub_by(true),
+            KeyCode::Right => self.scrub_by(false),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, area: Rect) -> KeyResult {
+        match mouse.kind {
+            MouseEventKind::Moved => {
+                let position = Position::new(mouse.column, mouse.row);
+                self.hover = area.contains(position).then_some(position);
+            }
+            MouseEventKind::ScrollUp => self.zoom = self.zoom.saturating_sub(1),
+            MouseEventKind::ScrollDown => self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::CpuUpdate(reply) => {
+                self.error = None;
+                self.cpu_count = reply.cpu_count;
+                self.cpu_usage = reply.total_usage;
+                self.history.push(Instant::now(), sample_values(reply));
+            }
+            AppEvent::Disconnected(err) => {
+                self.error = Some(err.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        grpc::spawn_cpu_stream(conn);
+    }
+}
+
+fn sample_values(reply: &CpuReply) -> Vec<f64> {
+    let optional = |v: Option<f32>| v.map_or(f64::NAN, f64::from);
+    let mut values = vec![
+        f64::from(reply.total_usage),
+        optional(reply.user_usage),
+        optional(reply.system_usage),
+    ];
+    values.extend(reply.core_usage.iter().map(|&v| f64::from(v)));
+    values
+}
+
+fn split_text(values: &[f64]) -> String {
+    match (values.get(USER), values.get(SYSTEM)) {
+        (Some(user), Some(system)) if !user.is_nan() && !system.is_nan() => {
+            format!(" (user {user:.1}%, sys {system:.1}%)")
+        }
+        _ => String::new(),
+    }
+}
+
+/// `now`, `-45s`, `-5m`, `-1h30m` style axis label.
+fn format_ago(ago: Duration) -> String {
+    let secs = ago.as_secs();
+    match secs {
+        0 => "now".to_string(),
+        s if s < 60 => format!("-{s}s"),
+        s if s < 3600 && s % 60 == 0 => format!("-{}m", s / 60),
+        s if s < 3600 => format!("-{}m{}s", s / 60, s % 60),
+        s if s % 3600 == 0 => format!("-{}h", s / 3600),
+        s => format!("-{}h{}m", s / 3600, s % 3600 / 60),
+    }
+}
@@ -0,0 +1,223 @@
+use std::cell::Cell as StdCell;
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::DiskInfo;
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+use crate::ui::human_bytes;
+
+const HINTS: &[KeyHint] = &[KeyHint::new("j/k", "Select disk")];
+
+pub struct DisksPanel {
+    disks: Vec<DiskInfo>,
+    selected: usize,
+    /// Scroll offset kept between frames, to map clicks to rows.
+    offset: StdCell<usize>,
+}
+
+impl DisksPanel {
+    pub fn new() -> Self {
+        Self {
+            disks: Vec::new(),
+            selected: 0,
+            offset: StdCell::new(0),
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.disks.len().saturating_sub(1));
+    }
+}
+
+impl Panel for DisksPanel {
+    fn title(&self) -> &str {
+        "Disks"
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        HINTS
+    }
+
+    fn min_height(&self) -> u16 {
+        6
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, _focused: bool, theme: &Theme) {
+        let [table_area, detail_area] = Layout::vertical([
+            Constraint::Min(2),    // Table
+            Constraint::Length(1), // SMART details of selected disk
+        ])
+        .areas(area);
+
+        let header = Row::new([
+            "Mount", "Device", "FS", "Used", "Size", "Use%", "Inodes", "Read/s", "Write/s", "Health",
+        ])
+        .style(Style::default().fg(theme.lavender).add_modifier(Modifier::BOLD));
+
+        let rows = self.disks.iter().map(|disk| {
+            let used = disk.total_bytes.saturating_sub(disk.available_bytes);
+            let use_pct = percent(used, disk.total_bytes);
+            let inode_pct = percent(
+                disk.total_inodes.saturating_sub(disk.free_inodes),
+                disk.total_inodes,
+            );
+            let health = match &disk.smart {
+                Some(smart) if smart.passed => Span::styled("PASS", Style::default().fg(theme.green)),
+                Some(_) => Span::styled("FAIL", Style::default().fg(theme.red)),
+                None => Span::raw("-"),
+            };
+            Row::new(vec![
+                Cell::from(disk.mount_point.clone()),
+                Cell::from(disk.name.clone()),
+                Cell::from(disk.file_system.clone()),
+                Cell::from(human_bytes(used as f64)),
+                Cell::from(human_bytes(disk.total_bytes as f64)),
+                Cell::from(Span::styled(
+                    format!("{use_pct:.0}%"),
+                    Style::default().fg(usage_color(theme, use_pct)),
+                )),
+                Cell::from(Span::styled(
+                    format!("{inode_pct:.0}%"),
+                    Style::default().fg(usage_color(theme, inode_pct)),
+                )),
+                Cell::from(human_bytes(disk.read_bytes_per_sec)),
+                Cell::from(human_bytes(disk.written_bytes_per_sec)),
+                Cell::from(health),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Fill(2),
+                Constraint::Fill(2),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(5),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(6),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().bg(theme.surface0))
+        .style(Style::default().bg(theme.bg).fg(theme.fg));
+
+        let mut state = TableState::default()
+            .with_offset(self.offset.get())
+            .with_selected((!self.disks.is_empty()).then_some(self.selected));
+        frame.render_stateful_widget(table, table_area, &mut state);
+        self.offset.set(state.offset());
+
+        let detail = match self.disks.get(self.selected) {
+            Some(disk) => smart_summary(disk, theme),
+            None => Line::from(" (waiting for storage data)"),
+        };
+        frame.render_widget(Paragraph::new(detail), detail_area);
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = self.selected.saturating_sub(1);
+                KeyResult::Handled
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.selected + 1 < self.disks.len() {
+                    self.selected += 1;
+                }
+                KeyResult::Handled
+            }
+            _ => KeyResult::Ignored,
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, area: Rect) -> KeyResult {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.select(self.selected.saturating_sub(1)),
+            MouseEventKind::ScrollDown => self.select(self.selected + 1),
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Rows start below the header line
+                if let Some(row) = mouse.row.checked_sub(area.y + 1)
+                    && self.offset.get() + (row as usize) < self.disks.len()
+                {
+                    self.selected = self.offset.get() + row as usize;
+                }
+            }
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        if let AppEvent::StorageUpdate(disks) = event {
+            self.selected = self.selected.min(disks.len().saturating_sub(1));
+            self.disks = disks.clone();
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        grpc::spawn_storage_stream(conn);
+    }
+}
+
+fn smart_summary<'a>(disk: &'a DiskInfo, theme: &Theme) -> Line<'a> {
+    let Some(smart) = &disk.smart else {
+        return Line::from(" SMART: not available");
+    };
+
+    let mut spans = vec![
+        Span::styled(" SMART: ", Style::default().fg(theme.lavender)),
+        Span::raw(format!("{} ", smart.model)),
+    ];
+    if let Some(temp) = smart.temperature_celsius {
+        spans.push(Span::raw(format!(" {temp}°C ")));
+    }
+    spans.push(Span::raw(format!(" {}h on ", smart.power_on_hours)));
+
+    let failing: Vec<&str> = smart
+        .attributes
+        .iter()
+        .filter(|a| a.failing)
+        .map(|a| a.name.as_str())
+        .collect();
+    if failing.is_empty() {
+        spans.push(Span::styled(" all attributes OK", Style::default().fg(theme.green)));
+    } else {
+        spans.push(Span::styled(
+            format!(" failing: {}", failing.join(", ")),
+            Style::default().fg(theme.red),
+        ));
+    }
+    Line::from(spans)
+}
+
+fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        used as f64 / total as f64 * 100.0
+    }
+}
+
+fn usage_color(theme: &Theme, pct: f64) -> Color {
+    if pct >= 90.0 {
+        theme.red
+    } else if pct >= 75.0 {
+        theme.yellow
+    } else {
+        theme.green
+    }
+}
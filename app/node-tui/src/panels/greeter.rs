@@ -0,0 +1,99 @@
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::app::Action;
+use crate::event::AppEvent;
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+
+const HINTS: &[KeyHint] = &[KeyHint::new("Enter", "Send greeting")];
+
+pub struct GreeterPanel {
+    input: String,
+    response: Option<String>,
+}
+
+impl GreeterPanel {
+    pub fn new() -> Self {
+        Self {
+            input: String::from("world"),
+            response: None,
+        }
+    }
+}
+
+impl Panel for GreeterPanel {
+    fn title(&self) -> &str {
+        "Greeter"
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        HINTS
+    }
+
+    fn min_height(&self) -> u16 {
+        4
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, focused: bool, theme: &Theme) {
+        let [input_area, response_area] = Layout::vertical([
+            Constraint::Length(1), // Input
+            Constraint::Length(1), // Response
+        ])
+        .areas(area);
+
+        // Input line
+        let cursor = if focused { "_" } else { "" };
+        let input = Paragraph::new(Line::from(vec![
+            Span::styled(" Name: ", Style::default().fg(theme.lavender)),
+            Span::raw(&self.input),
+            Span::styled(
+                cursor,
+                Style::default()
+                    .fg(theme.yellow)
+                    .add_modifier(Modifier::SLOW_BLINK),
+            ),
+        ]));
+        frame.render_widget(input, input_area);
+
+        // Response line
+        let response_text = match &self.response {
+            Some(r) => r.as_str(),
+            None => "(none yet)",
+        };
+        let response = Paragraph::new(Line::from(vec![
+            Span::styled(" Response: ", Style::default().fg(theme.lavender)),
+            Span::raw(response_text),
+        ]));
+        frame.render_widget(response, response_area);
+    }
+
+    // Text input: every printable key belongs to the name field, so global
+    // single-letter bindings never fire while this panel is focused.
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        match key.code {
+            KeyCode::Enter => KeyResult::Action(Action::SendGreeting(self.input.clone())),
+            KeyCode::Backspace => {
+                self.input.pop();
+                KeyResult::Handled
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                KeyResult::Handled
+            }
+            _ => KeyResult::Ignored,
+        }
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        if let AppEvent::GreeterResponse(msg) = event {
+            self.response = Some(msg.clone());
+        }
+    }
+}
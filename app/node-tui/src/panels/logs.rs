@@ -0,0 +1,482 @@
+use std::cell::Cell as StdCell;
+use std::collections::VecDeque;
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use regex::Regex;
+
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::{LogEntry, LogLevel};
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+
+const HINTS: &[KeyHint] = &[
+    KeyHint::new("↑/↓/PgUp/PgDn", "Scroll"),
+    KeyHint::new("End", "Follow"),
+    KeyHint::new("l", "Level"),
+    KeyHint::new("/", "Search"),
+    KeyHint::new("n/N", "Older/newer match"),
+];
+
+const SEARCH_HINTS: &[KeyHint] = &[
+    KeyHint::new("Enter", "Search"),
+    KeyHint::new("Esc", "Cancel"),
+];
+
+/// Oldest entries are dropped beyond this, however chatty the unit.
+const MAX_ENTRIES: usize = 5000;
+/// Past entries requested when the stream starts.
+const BACKLOG: u32 = 500;
+/// Lines scrolled per mouse wheel notch.
+const WHEEL_STEP: isize = 3;
+
+/// Follows a journald unit, or node-rpc's own log when `unit` is empty.
+/// Configured in the layout as `logs` or `logs:<unit>`.
+pub struct LogsPanel {
+    unit: String,
+    title: String,
+    entries: VecDeque<LogEntry>,
+    min_level: LogLevel,
+    /// Keep the newest entry in view as entries arrive.
+    follow: bool,
+    /// Visible entries between the bottom of the view and the newest one,
+    /// while not following.
+    scroll: usize,
+    search: Option<Regex>,
+    search_input: String,
+    editing_search: bool,
+    search_error: Option<String>,
+    error: Option<String>,
+    /// Rows shown at the last render, for paging.
+    height: StdCell<usize>,
+}
+
+impl LogsPanel {
+    pub fn new(unit: &str) -> Self {
+        let source = if unit.is_empty() { "node-rpc" } else { unit };
+        Self {
+            unit: unit.to_string(),
+            title: format!("Logs: {source}"),
+            entries: VecDeque::new(),
+            min_level: LogLevel::Debug,
+            follow: true,
+            scroll: 0,
+            search: None,
+            search_input: String::new(),
+            editing_search: false,
+            search_error: None,
+            error: None,
+            height: StdCell::new(1),
+        }
+    }
+
+    fn visible(&self, entry: &LogEntry) -> bool {
+        entry.level() >= self.min_level
+    }
+
+    /// Entries passing the level filter, oldest first.
+    fn filtered(&self) -> Vec<&LogEntry> {
+        self.entries.iter().filter(|e| self.visible(e)).collect()
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        let max = self.filtered().len().saturating_sub(1);
+        let scroll = if self.follow { 0 } else { self.scroll };
+        self.scroll = scroll.saturating_add_signed(delta).min(max);
+        self.follow = self.scroll == 0;
+    }
+
+    /// Puts the nearest older (or newer) search match at the bottom of the
+    /// view.
+    fn jump_to_match(&mut self, older: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let filtered = self.filtered();
+        let bottom = filtered.len().saturating_sub(1 + if self.follow { 0 } else { self.scroll });
+        let found = if older {
+            (0..bottom).rev().find(|&i| search.is_match(&filtered[i].message))
+        } else {
+            (bottom + 1..filtered.len()).find(|&i| search.is_match(&filtered[i].message))
+        };
+        if let Some(index) = found {
+            self.scroll = filtered.len() - 1 - index;
+            self.follow = false;
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> KeyResult {
+        match key.code {
+            KeyCode::Enter => {
+                self.editing_search = false;
+                if self.search_input.is_empty() {
+                    self.search = None;
+                    self.search_error = None;
+                } else {
+                    match Regex::new(&self.search_input) {
+                        Ok(regex) => {
+                            self.search = Some(regex);
+                            self.search_error = None;
+                        }
+                        Err(e) => self.search_error = Some(e.to_string()),
+                    }
+                }
+            }
+            KeyCode::Esc => self.editing_search = false,
+            KeyCode::Backspace => {
+                self.search_input.pop();
+            }
+            KeyCode::Char(c) => self.search_input.push(c),
+            _ => {}
+        }
+        KeyResult::Handled
+    }
+
+    fn entry_line<'a>(&self, entry: &'a LogEntry, theme: &Theme) -> Line<'a> {
+        let level = entry.level();
+        let (label, color) = match level {
+            LogLevel::Error => ("ERR ", theme.red),
+            LogLevel::Warning => ("WARN", theme.yellow),
+            LogLevel::Info => ("INFO", theme.green),
+            LogLevel::Debug => ("DBG ", theme.lavender),
+        };
+        let mut spans = vec![
+            Span::raw(format!(" {} ", format_time(entry.timestamp_ms))),
+            Span::styled(label, Style::default().fg(color)),
+            Span::styled(format!(" {}: ", entry.source), Style::default().fg(theme.lavender)),
+        ];
+        let message_color = match level {
+            LogLevel::Error => theme.red,
+            LogLevel::Warning => theme.yellow,
+            _ => theme.fg,
+        };
+        spans.extend(highlight(
+            &entry.message,
+            self.search.as_ref(),
+            message_color,
+            theme,
+        ));
+        Line::from(spans)
+    }
+}
+
+impl Panel for LogsPanel {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        if self.editing_search {
+            SEARCH_HINTS
+        } else {
+            HINTS
+        }
+    }
+
+    fn min_height(&self) -> u16 {
+        6
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, focused: bool, theme: &Theme) {
+        let [log_area, footer_area] = Layout::vertical([
+            Constraint::Min(1),    // Entries
+            Constraint::Length(1), // Status / search line
+        ])
+        .areas(area);
+        let height = log_area.height as usize;
+        self.height.set(height.max(1));
+
+        let filtered = self.filtered();
+        let end = filtered.len() - if self.follow { 0 } else { self.scroll.min(filtered.len()) };
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = filtered[start..end]
+            .iter()
+            .map(|entry| self.entry_line(entry, theme))
+            .collect();
+        frame.render_widget(Paragraph::new(lines), log_area);
+
+        let footer = if self.editing_search {
+            let cursor = if focused { "_" } else { "" };
+            Line::from(vec![
+                Span::styled(" Search: ", Style::default().fg(theme.lavender)),
+                Span::raw(self.search_input.clone()),
+                Span::styled(cursor, Style::default().fg(theme.yellow)),
+            ])
+        } else if let Some(error) = self.error.as_ref().or(self.search_error.as_ref()) {
+            Line::styled(format!(" {error}"), Style::default().fg(theme.red))
+        } else {
+            let mode = if self.follow {
+                Span::styled(" FOLLOW ", Style::default().fg(theme.green))
+            } else {
+                Span::styled(
+                    format!(" -{} ", self.scroll),
+                    Style::default().fg(theme.yellow),
+                )
+            };
+            let mut spans = vec![
+                mode,
+                Span::raw(format!(
+                    " level ≥ {}  |  {} of {} entries",
+                    level_name(self.min_level),
+                    filtered.len(),
+                    self.entries.len()
+                )),
+            ];
+            if let Some(search) = &self.search {
+                spans.push(Span::styled(
+                    format!("  /{}/", search.as_str()),
+                    Style::default().fg(theme.yellow),
+                ));
+            }
+            Line::from(spans)
+        };
+        frame.render_widget(Paragraph::new(footer), footer_area);
+    }
+
+    fn captures_input(&self) -> bool {
+        self.editing_search
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        if self.editing_search {
+            return self.handle_search_key(key);
+        }
+
+        let page = self.height.get() as isize;
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_by(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_by(-1),
+            KeyCode::PageUp => self.scroll_by(page),
+            KeyCode::PageDown => self.scroll_by(-page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_by(isize::MAX),
+            KeyCode::End | KeyCode::Char('G') => {
+                self.follow = true;
+                self.scroll = 0;
+            }
+            KeyCode::Char('l') => {
+                self.min_level = match self.min_level {
+                    LogLevel::Debug => LogLevel::Info,
+                    LogLevel::Info => LogLevel::Warning,
+                    LogLevel::Warning => LogLevel::Error,
+                    LogLevel::Error => LogLevel::Debug,
+                };
+                self.scroll = self.scroll.min(self.filtered().len().saturating_sub(1));
+            }
+            KeyCode::Char('/') => {
+                self.editing_search = true;
+                self.search_input = self
+                    .search
+                    .as_ref()
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default();
+            }
+            KeyCode::Char('n') => self.jump_to_match(true),
+            KeyCode::Char('N') => self.jump_to_match(false),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, _area: Rect) -> KeyResult {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.scroll_by(WHEEL_STEP),
+            MouseEventKind::ScrollDown => self.scroll_by(-WHEEL_STEP),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::Log { unit, entry } if *unit == self.unit => {
+                self.error = None;
+                if self.entries.len() >= MAX_ENTRIES {
+                    self.entries.pop_front();
+                }
+                // Keep a scrolled-back view on the same entries
+                if !self.follow && self.visible(entry) {
+                    self.scroll += 1;
+                }
+                self.entries.push_back(entry.clone());
+            }
+            AppEvent::LogsFailed { unit, error } if *unit == self.unit => {
+                self.error = Some(error.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        grpc::spawn_logs_stream(conn, self.unit.clone(), BACKLOG);
+    }
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Debug => "debug",
+        LogLevel::Info => "info",
+        LogLevel::Warning => "warning",
+        LogLevel::Error => "error",
+    }
+}
+
+/// `message` split into spans with every `search` match highlighted.
+fn highlight<'a>(
+    message: &'a str,
+    search: Option<&Regex>,
+    color: Color,
+    theme: &Theme,
+) -> Vec<Span<'a>> {
+    let normal = Style::default().fg(color);
+    let Some(search) = search else {
+        return vec![Span::styled(message, normal)];
+    };
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for found in search.find_iter(message).filter(|m| !m.is_empty()) {
+        if found.start() > last {
+            spans.push(Span::styled(&message[last..found.start()], normal));
+        }
+        spans.push(Span::styled(
+            found.as_str(),
+            Style::default().bg(theme.yellow).fg(theme.bg),
+        ));
+        last = found.end();
+    }
+    if last < message.len() {
+        spans.push(Span::styled(&message[last..], normal));
+    }
+    spans
+}
+
+/// `HH:MM:SS` in UTC.
+fn format_time(timestamp_ms: u64) -> String {
+    let secs = timestamp_ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend};
+
+    use super::*;
+
+    fn log(panel: &mut LogsPanel, level: LogLevel, message: &str) {
+        panel.apply_event(&AppEvent::Log {
+            unit: "nginx".to_string(),
+            entry: LogEntry {
+                level: level.into(),
+                message: message.to_string(),
+                ..Default::default()
+            },
+        });
+    }
+
+    fn press(panel: &mut LogsPanel, code: KeyCode) {
+        panel.handle_key(KeyEvent::from(code));
+    }
+
+    fn messages(panel: &LogsPanel) -> Vec<&str> {
+        panel
+            .filtered()
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect()
+    }
+
+    /// The panel as text, one string per row.
+    fn render(panel: &LogsPanel, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let area = Rect::new(0, 0, width, height);
+        terminal
+            .draw(|frame| panel.render(frame, area, true, &Theme::MOCHA))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_past_the_bound() {
+        let mut panel = LogsPanel::new("nginx");
+        for i in 0..MAX_ENTRIES + 10 {
+            log(&mut panel, LogLevel::Info, &i.to_string());
+        }
+        log(&mut panel, LogLevel::Info, "last");
+        assert_eq!(panel.entries.len(), MAX_ENTRIES);
+        assert_eq!(panel.entries.front().unwrap().message, "11");
+        assert_eq!(panel.entries.back().unwrap().message, "last");
+
+        // Another unit's entries are not this panel's
+        panel.apply_event(&AppEvent::Log {
+            unit: "sshd".to_string(),
+            entry: LogEntry::default(),
+        });
+        assert_eq!(panel.entries.back().unwrap().message, "last");
+    }
+
+    #[test]
+    fn the_level_filter_cycles() {
+        let mut panel = LogsPanel::new("nginx");
+        log(&mut panel, LogLevel::Debug, "d");
+        log(&mut panel, LogLevel::Info, "i");
+        log(&mut panel, LogLevel::Warning, "w");
+        log(&mut panel, LogLevel::Error, "e");
+
+        assert_eq!(messages(&panel), ["d", "i", "w", "e"]);
+        press(&mut panel, KeyCode::Char('l'));
+        assert_eq!(messages(&panel), ["i", "w", "e"]);
+        press(&mut panel, KeyCode::Char('l'));
+        assert_eq!(messages(&panel), ["w", "e"]);
+        press(&mut panel, KeyCode::Char('l'));
+        assert_eq!(messages(&panel), ["e"]);
+        assert!(
+            render(&panel, 60, 6)
+                .last()
+                .unwrap()
+                .contains("level ≥ error  |  1 of 4 entries")
+        );
+        press(&mut panel, KeyCode::Char('l'));
+        assert_eq!(messages(&panel), ["d", "i", "w", "e"]);
+    }
+
+    #[test]
+    fn an_invalid_search_is_reported_not_applied() {
+        let mut panel = LogsPanel::new("nginx");
+        log(&mut panel, LogLevel::Info, "GET /index.html");
+        log(&mut panel, LogLevel::Info, "GET /(broken");
+
+        press(&mut panel, KeyCode::Char('/'));
+        for c in "index".chars() {
+            press(&mut panel, KeyCode::Char(c));
+        }
+        press(&mut panel, KeyCode::Enter);
+        assert_eq!(panel.search.as_ref().unwrap().as_str(), "index");
+
+        press(&mut panel, KeyCode::Char('/'));
+        press(&mut panel, KeyCode::Backspace);
+        press(&mut panel, KeyCode::Char('('));
+        press(&mut panel, KeyCode::Enter);
+        assert!(panel.search_error.is_some());
+        assert_eq!(panel.search.as_ref().unwrap().as_str(), "index");
+
+        // Neither drawing nor jumping trips over it
+        render(&panel, 60, 6);
+        press(&mut panel, KeyCode::Char('n'));
+        press(&mut panel, KeyCode::Char('N'));
+        render(&panel, 60, 6);
+    }
+}
@@ -0,0 +1,44 @@
+mod cpu;
+mod disks;
+mod greeter;
+mod logs;
+mod processes;
+mod sensors;
+mod shell;
+
+use crate::panel::Panel;
+use cpu::CpuPanel;
+use disks::DisksPanel;
+use greeter::GreeterPanel;
+use logs::LogsPanel;
+use processes::ProcessesPanel;
+use sensors::SensorsPanel;
+use shell::ShellPanel;
+
+/// Builds a panel from the argument after `:` in its layout id (empty when
+/// there is none), e.g. the unit in `logs:sshd.service`.
+type Factory = fn(&str) -> Box<dyn Panel>;
+
+/// Every panel the layout config can refer to, keyed by its config id.
+/// Adding a panel means writing its module and adding one line here.
+const REGISTRY: &[(&str, Factory)] = &[
+    ("cpu", |_| Box::new(CpuPanel::new())),
+    ("disks", |_| Box::new(DisksPanel::new())),
+    ("sensors", |_| Box::new(SensorsPanel::new())),
+    ("processes", |_| Box::new(ProcessesPanel::new())),
+    ("greeter", |_| Box::new(GreeterPanel::new())),
+    ("logs", |unit| Box::new(LogsPanel::new(unit))),
+    ("shell", |command| Box::new(ShellPanel::new(command))),
+];
+
+pub fn create(id: &str) -> Option<Box<dyn Panel>> {
+    let (id, arg) = id.split_once(':').unwrap_or((id, ""));
+    REGISTRY
+        .iter()
+        .find(|(name, _)| *name == id)
+        .map(|(_, factory)| factory(arg))
+}
+
+pub fn ids() -> impl Iterator<Item = &'static str> {
+    REGISTRY.iter().map(|(name, _)| *name)
+}
@@ -0,0 +1,589 @@
+use std::cell::Cell as StdCell;
+use std::collections::{HashMap, HashSet};
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Cell, Paragraph, Row, Table, TableState},
+    Frame,
+};
+
+use crate::app::Action;
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::{ProcessInfo, Signal};
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+use crate::ui::human_bytes;
+
+const HINTS: &[KeyHint] = &[
+    KeyHint::new("↑/↓", "Select"),
+    KeyHint::new("s", "Sort"),
+    KeyHint::new("r", "Reverse"),
+    KeyHint::new("t", "Tree"),
+    KeyHint::new("/", "Filter"),
+    KeyHint::new("k/K", "TERM/KILL"),
+];
+
+const FILTER_HINTS: &[KeyHint] = &[
+    KeyHint::new("Enter", "Apply filter"),
+    KeyHint::new("Esc", "Clear filter"),
+];
+
+const PAGE: usize = 10;
+/// Rows moved per mouse wheel notch.
+const WHEEL_STEP: isize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortKey {
+    Cpu,
+    Memory,
+    Pid,
+    Name,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Cpu => SortKey::Memory,
+            SortKey::Memory => SortKey::Pid,
+            SortKey::Pid => SortKey::Name,
+            SortKey::Name => SortKey::Cpu,
+        }
+    }
+
+    /// Resource columns read best biggest-first, identifiers smallest-first.
+    fn default_descending(self) -> bool {
+        matches!(self, SortKey::Cpu | SortKey::Memory)
+    }
+
+    fn compare(self, a: &ProcessInfo, b: &ProcessInfo) -> std::cmp::Ordering {
+        match self {
+            SortKey::Cpu => a.cpu_usage.total_cmp(&b.cpu_usage),
+            SortKey::Memory => a.memory_bytes.cmp(&b.memory_bytes),
+            SortKey::Pid => a.pid.cmp(&b.pid),
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        }
+        .then(a.pid.cmp(&b.pid))
+    }
+}
+
+/// One visible table row: the process and its depth in tree view.
+struct ViewRow<'a> {
+    process: &'a ProcessInfo,
+    depth: usize,
+}
+
+pub struct ProcessesPanel {
+    processes: Vec<ProcessInfo>,
+    total_memory: u64,
+    sort: SortKey,
+    descending: bool,
+    tree: bool,
+    filter: String,
+    editing_filter: bool,
+    /// Selection follows the pid, so it stays on the same process when the
+    /// list is re-sorted by a refresh.
+    selected_pid: Option<u32>,
+    /// Scroll offset kept between frames so the table doesn't jump.
+    offset: StdCell<usize>,
+    /// Outcome of the last signal: a message, red if it failed.
+    status: Option<Result<String, String>>,
+}
+
+impl ProcessesPanel {
+    pub fn new() -> Self {
+        Self {
+            processes: Vec::new(),
+            total_memory: 0,
+            sort: SortKey::Cpu,
+            descending: true,
+            tree: false,
+            filter: String::new(),
+            editing_filter: false,
+            selected_pid: None,
+            offset: StdCell::new(0),
+            status: None,
+        }
+    }
+
+    fn view(&self) -> Vec<ViewRow<'_>> {
+        let needle = self.filter.to_lowercase();
+        let mut matching: Vec<&ProcessInfo> = self
+            .processes
+            .iter()
+            .filter(|p| {
+                needle.is_empty()
+                    || p.name.to_lowercase().contains(&needle)
+                    || p.command.to_lowercase().contains(&needle)
+                    || p.user.to_lowercase().contains(&needle)
+                    || p.pid.to_string() == needle
+            })
+            .collect();
+        matching.sort_by(|a, b| {
+            let ord = self.sort.compare(a, b);
+            if self.descending { ord.reverse() } else { ord }
+        });
+
+        if !self.tree {
+            return matching
+                .into_iter()
+                .map(|process| ViewRow { process, depth: 0 })
+                .collect();
+        }
+
+        // Children keep the sort order among siblings. Processes whose parent
+        // is filtered out or gone become roots.
+        let present: HashSet<u32> = matching.iter().map(|p| p.pid).collect();
+        let mut children: HashMap<u32, Vec<&ProcessInfo>> = HashMap::new();
+        let mut roots = Vec::new();
+        for process in matching {
+            match process.parent_pid {
+                Some(ppid) if ppid != process.pid && present.contains(&ppid) => {
+                    children.entry(ppid).or_default().push(process)
+                }
+                _ => roots.push(process),
+            }
+        }
+
+        let mut rows = Vec::with_capacity(self.processes.len());
+        let mut stack: Vec<(&ProcessInfo, usize)> =
+            roots.into_iter().rev().map(|p| (p, 0)).collect();
+        while let Some((process, depth)) = stack.pop() {
+            rows.push(ViewRow { process, depth });
+            if let Some(kids) = children.get(&process.pid) {
+                stack.extend(kids.iter().rev().map(|kid| (*kid, depth + 1)));
+            }
+        }
+        rows
+    }
+
+    fn selected_index(&self, view: &[ViewRow]) -> Option<usize> {
+        if view.is_empty() {
+            return None;
+        }
+        let index = self
+            .selected_pid
+            .and_then(|pid| view.iter().position(|row| row.process.pid == pid))
+            .unwrap_or(0);
+        Some(index)
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let view = self.view();
+        let Some(current) = self.selected_index(&view) else {
+            return;
+        };
+        let last = view.len() - 1;
+        let target = current.saturating_add_signed(delta).min(last);
+        self.selected_pid = Some(view[target].process.pid);
+    }
+
+    fn signal_selected(&self, signal: Signal) -> KeyResult {
+        let view = self.view();
+        let Some(index) = self.selected_index(&view) else {
+            return KeyResult::Handled;
+        };
+        let process = view[index].process;
+        KeyResult::Confirm {
+            prompt: format!(
+                "Send {} to {} (pid {})?",
+                signal_name(signal),
+                process.name,
+                process.pid
+            ),
+            action: Action::SendSignal {
+                pid: process.pid,
+                signal,
+            },
+        }
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) -> KeyResult {
+        match key.code {
+            KeyCode::Enter => self.editing_filter = false,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.editing_filter = false;
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            KeyCode::Char(c) => self.filter.push(c),
+            _ => {}
+        }
+        KeyResult::Handled
+    }
+}
+
+impl Panel for ProcessesPanel {
+    fn title(&self) -> &str {
+        "Processes"
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        if self.editing_filter {
+            FILTER_HINTS
+        } else {
+            HINTS
+        }
+    }
+
+    fn min_height(&self) -> u16 {
+        8
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, focused: bool, theme: &Theme) {
+        let [table_area, footer_area] = Layout::vertical([
+            Constraint::Min(2),    // Table
+            Constraint::Length(1), // Filter / status line
+        ])
+        .areas(area);
+
+        let arrow = if self.descending { "▼" } else { "▲" };
+        let header_label = |key: SortKey, label: &str| {
+            if self.sort == key {
+                format!("{label}{arrow}")
+            } else {
+                label.to_string()
+            }
+        };
+        let header = Row::new(vec![
+            header_label(SortKey::Pid, "PID"),
+            "USER".to_string(),
+            header_label(SortKey::Cpu, "CPU%"),
+            header_label(SortKey::Memory, "MEM"),
+            "MEM%".to_string(),
+            "STATE".to_string(),
+            "TIME".to_string(),
+            header_label(SortKey::Name, "COMMAND"),
+        ])
+        .style(Style::default().fg(theme.lavender).add_modifier(Modifier::BOLD));
+
+        let view = self.view();
+        let rows = view.iter().map(|row| {
+            let p = row.process;
+            let mem_pct = if self.total_memory == 0 {
+                0.0
+            } else {
+                p.memory_bytes as f64 / self.total_memory as f64 * 100.0
+            };
+            let cpu_color = if p.cpu_usage >= 80.0 {
+                theme.red
+            } else if p.cpu_usage >= 30.0 {
+                theme.yellow
+            } else {
+                theme.fg
+            };
+            let prefix = if row.depth == 0 {
+                String::new()
+            } else {
+                format!("{}└ ", "  ".repeat(row.depth - 1))
+            };
+            let command = if p.command.is_empty() {
+                p.name.clone()
+            } else {
+                p.command.clone()
+            };
+            Row::new(vec![
+                Cell::from(p.pid.to_string()),
+                Cell::from(p.user.clone()),
+                Cell::from(Span::styled(
+                    format!("{:.1}", p.cpu_usage),
+                    Style::default().fg(cpu_color),
+                )),
+                Cell::from(human_bytes(p.memory_bytes as f64)),
+                Cell::from(format!("{mem_pct:.1}")),
+                Cell::from(p.status.clone()),
+                Cell::from(format_duration(p.run_time_secs)),
+                Cell::from(format!("{prefix}{command}")),
+            ])
+        });
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(7),
+                Constraint::Length(9),
+                Constraint::Length(6),
+                Constraint::Length(8),
+                Constraint::Length(5),
+                Constraint::Length(8),
+                Constraint::Length(9),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::default().bg(theme.surface0).add_modifier(Modifier::BOLD))
+        .style(Style::default().bg(theme.bg).fg(theme.fg));
+
+        let mut state = TableState::default()
+            .with_offset(self.offset.get())
+            .with_selected(self.selected_index(&view));
+        frame.render_stateful_widget(table, table_area, &mut state);
+        self.offset.set(state.offset());
+
+        let footer = if self.editing_filter || !self.filter.is_empty() {
+            let cursor = if self.editing_filter && focused { "_" } else { "" };
+            Line::from(vec![
+                Span::styled(" Filter: ", Style::default().fg(theme.lavender)),
+                Span::raw(self.filter.clone()),
+                Span::styled(cursor, Style::default().fg(theme.yellow)),
+                Span::raw(format!("  ({} of {})", view.len(), self.processes.len())),
+            ])
+        } else if let Some(status) = &self.status {
+            match status {
+                Ok(message) => Line::styled(message.clone(), Style::default().fg(theme.green)),
+                Err(message) => Line::styled(message.clone(), Style::default().fg(theme.red)),
+            }
+        } else {
+            Line::from(format!(" {} processes", self.processes.len()))
+        };
+        frame.render_widget(Paragraph::new(footer), footer_area);
+    }
+
+    fn captures_input(&self) -> bool {
+        self.editing_filter
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        if self.editing_filter {
+            return self.handle_filter_key(key);
+        }
+
+        match key.code {
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE as isize),
+            KeyCode::Home => self.move_selection(isize::MIN),
+            KeyCode::End => self.move_selection(isize::MAX),
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.descending = self.sort.default_descending();
+            }
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Char('t') => self.tree = !self.tree,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('k') => return self.signal_selected(Signal::Term),
+            KeyCode::Char('K') => return self.signal_selected(Signal::Kill),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, area: Rect) -> KeyResult {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.move_selection(-WHEEL_STEP),
+            MouseEventKind::ScrollDown => self.move_selection(WHEEL_STEP),
+            MouseEventKind::Down(MouseButton::Left) => {
+                // Rows start below the header line
+                let Some(row) = mouse.row.checked_sub(area.y + 1) else {
+                    return KeyResult::Handled;
+                };
+                let view = self.view();
+                if let Some(clicked) = view.get(self.offset.get() + row as usize) {
+                    self.selected_pid = Some(clicked.process.pid);
+                }
+            }
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::ProcessesUpdate {
+                processes,
+                total_memory,
+            } => {
+                self.processes = processes.clone();
+                self.total_memory = *total_memory;
+                // Keep the selection on a live process
+                let view = self.view();
+                if let Some(index) = self.selected_index(&view) {
+                    self.selected_pid = Some(view[index].process.pid);
+                }
+            }
+            AppEvent::SignalResult { pid, result } => {
+                self.status = Some(match result {
+                    Ok(()) => Ok(format!(" Signal sent to pid {pid}")),
+                    Err(e) => Err(format!(" Signal to pid {pid} failed: {e}")),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        grpc::spawn_process_stream(conn);
+    }
+}
+
+fn signal_name(signal: Signal) -> &'static str {
+    match signal {
+        Signal::Term => "SIGTERM",
+        Signal::Kill => "SIGKILL",
+        Signal::Int => "SIGINT",
+        Signal::Hup => "SIGHUP",
+        Signal::Stop => "SIGSTOP",
+        Signal::Cont => "SIGCONT",
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h >= 24 {
+        format!("{}d{:02}h", h / 24, h % 24)
+    } else {
+        format!("{h:02}:{m:02}:{s:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn process(pid: u32, name: &str, cpu_usage: f32, memory_bytes: u64) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            command: format!("/usr/bin/{name}"),
+            user: "root".to_string(),
+            cpu_usage,
+            memory_bytes,
+            ..Default::default()
+        }
+    }
+
+    /// A panel holding three processes, sorted by CPU: 30, 10, 20.
+    fn panel() -> ProcessesPanel {
+        let mut panel = ProcessesPanel::new();
+        panel.apply_event(&AppEvent::ProcessesUpdate {
+            processes: vec![
+                process(10, "sshd", 5.0, 300),
+                process(20, "Xorg", 1.0, 200),
+                process(30, "cargo", 90.0, 100),
+            ],
+            total_memory: 1000,
+        });
+        panel
+    }
+
+    fn pids(panel: &ProcessesPanel) -> Vec<u32> {
+        panel.view().iter().map(|row| row.process.pid).collect()
+    }
+
+    #[test]
+    fn arrows_move_the_selection() {
+        let mut panel = panel();
+        assert_eq!(panel.selected_pid, Some(30));
+
+        assert!(matches!(
+            panel.handle_key(key(KeyCode::Down)),
+            KeyResult::Handled
+        ));
+        assert_eq!(panel.selected_pid, Some(10));
+        panel.handle_key(key(KeyCode::End));
+        assert_eq!(panel.selected_pid, Some(20));
+        panel.handle_key(key(KeyCode::Down));
+        assert_eq!(panel.selected_pid, Some(20));
+        panel.handle_key(key(KeyCode::Up));
+        assert_eq!(panel.selected_pid, Some(10));
+        panel.handle_key(key(KeyCode::Home));
+        assert_eq!(panel.selected_pid, Some(30));
+    }
+
+    #[test]
+    fn s_cycles_the_sort_and_r_reverses_it() {
+        let mut panel = panel();
+        assert_eq!(pids(&panel), [30, 10, 20]);
+
+        panel.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(pids(&panel), [10, 20, 30], "memory, biggest first");
+        panel.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(pids(&panel), [10, 20, 30], "pid, smallest first");
+        panel.handle_key(key(KeyCode::Char('r')));
+        assert_eq!(pids(&panel), [30, 20, 10]);
+        panel.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(pids(&panel), [30, 10, 20], "name, ignoring case");
+        panel.handle_key(key(KeyCode::Char('s')));
+        assert_eq!(pids(&panel), [30, 10, 20], "back to cpu");
+    }
+
+    #[test]
+    fn slash_edits_the_filter() {
+        let mut panel = panel();
+        panel.handle_key(key(KeyCode::Char('/')));
+        assert!(panel.captures_input());
+
+        // Keys that are commands elsewhere are typed into the filter
+        for c in "ssx".chars() {
+            panel.handle_key(key(KeyCode::Char(c)));
+        }
+        panel.handle_key(key(KeyCode::Backspace));
+        assert_eq!(pids(&panel), [10]);
+        panel.handle_key(key(KeyCode::Enter));
+        assert!(!panel.captures_input());
+        assert_eq!(panel.filter, "ss");
+        assert_eq!(pids(&panel), [10]);
+
+        panel.handle_key(key(KeyCode::Char('/')));
+        panel.handle_key(key(KeyCode::Esc));
+        assert!(!panel.captures_input());
+        assert_eq!(pids(&panel), [30, 10, 20]);
+    }
+
+    #[test]
+    fn k_asks_before_signalling_the_selection() {
+        let mut panel = panel();
+        panel.handle_key(key(KeyCode::Down));
+
+        match panel.handle_key(key(KeyCode::Char('k'))) {
+            KeyResult::Confirm {
+                prompt,
+                action: Action::SendSignal { pid, signal },
+            } => {
+                assert_eq!(prompt, "Send SIGTERM to sshd (pid 10)?");
+                assert_eq!((pid, signal), (10, Signal::Term));
+            }
+            _ => panic!("expected a signal confirmation"),
+        }
+        assert!(matches!(
+            panel.handle_key(key(KeyCode::Char('K'))),
+            KeyResult::Confirm {
+                action: Action::SendSignal {
+                    pid: 10,
+                    signal: Signal::Kill
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn nothing_to_signal_without_processes() {
+        let mut panel = ProcessesPanel::new();
+        assert!(matches!(
+            panel.handle_key(key(KeyCode::Char('k'))),
+            KeyResult::Handled
+        ));
+    }
+
+    #[test]
+    fn unknown_keys_are_ignored() {
+        let mut panel = panel();
+        assert!(matches!(
+            panel.handle_key(key(KeyCode::Char('z'))),
+            KeyResult::Ignored
+        ));
+        assert_eq!(panel.selected_pid, Some(30));
+    }
+}
@@ -0,0 +1,116 @@
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::{FanReading, TemperatureReading};
+use crate::panel::Panel;
+use crate::theme::Theme;
+
+pub struct SensorsPanel {
+    temperatures: Vec<TemperatureReading>,
+    fans: Vec<FanReading>,
+}
+
+impl SensorsPanel {
+    pub fn new() -> Self {
+        Self {
+            temperatures: Vec::new(),
+            fans: Vec::new(),
+        }
+    }
+}
+
+impl Panel for SensorsPanel {
+    fn title(&self) -> &str {
+        "Sensors"
+    }
+
+    fn min_height(&self) -> u16 {
+        4
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, _focused: bool, theme: &Theme) {
+        let [temps_area, fans_area] =
+            Layout::horizontal([Constraint::Fill(2), Constraint::Fill(1)]).areas(area);
+
+        let temps: Vec<Line> = if self.temperatures.is_empty() {
+            vec![Line::from(" (no temperature sensors)")]
+        } else {
+            self.temperatures.iter().map(|t| temperature_line(t, theme)).collect()
+        };
+        frame.render_widget(Paragraph::new(temps), temps_area);
+
+        let fans: Vec<Line> = if self.fans.is_empty() {
+            vec![Line::from(" (no fans)")]
+        } else {
+            self.fans.iter().map(|f| fan_line(f, theme)).collect()
+        };
+        frame.render_widget(Paragraph::new(fans), fans_area);
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        if let AppEvent::SensorsUpdate { temperatures, fans } = event {
+            self.temperatures = temperatures.clone();
+            self.fans = fans.clone();
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        grpc::spawn_sensors_stream(conn);
+    }
+}
+
+fn temperature_line<'a>(reading: &'a TemperatureReading, theme: &Theme) -> Line<'a> {
+    let value = match reading.celsius {
+        Some(c) => format!("{c:.1}°C"),
+        None => "n/a".to_string(),
+    };
+    let critical = match reading.critical_celsius {
+        Some(c) => format!(" / crit {c:.0}°C"),
+        None => String::new(),
+    };
+    Line::from(vec![
+        Span::styled(format!(" {}: ", reading.label), Style::default().fg(theme.lavender)),
+        Span::styled(
+            value,
+            Style::default().fg(temperature_color(theme, reading.celsius, reading.critical_celsius)),
+        ),
+        Span::raw(critical),
+    ])
+}
+
+/// Green well below the critical threshold, yellow within 15% of it, red at or
+/// above it. Sensors without a reported threshold keep the default colour.
+fn temperature_color(theme: &Theme, celsius: Option<f32>, critical: Option<f32>) -> Color {
+    match (celsius, critical) {
+        (Some(t), Some(crit)) if crit > 0.0 => {
+            let ratio = t / crit;
+            if ratio >= 1.0 {
+                theme.red
+            } else if ratio >= 0.85 {
+                theme.yellow
+            } else {
+                theme.green
+            }
+        }
+        _ => theme.fg,
+    }
+}
+
+fn fan_line<'a>(fan: &'a FanReading, theme: &Theme) -> Line<'a> {
+    let stalled = fan.min_rpm.is_some_and(|min| min > 0 && fan.rpm < min);
+    let color = if stalled { theme.red } else { theme.green };
+    Line::from(vec![
+        Span::styled(
+            format!(" {}/{}: ", fan.chip, fan.label),
+            Style::default().fg(theme.lavender),
+        ),
+        Span::styled(format!("{} RPM", fan.rpm), Style::default().fg(color)),
+    ])
+}
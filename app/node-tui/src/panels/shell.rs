@@ -0,0 +1,378 @@
+use std::cell::RefCell;
+
+use ratatui::{
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind},
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::Paragraph,
+    Frame,
+};
+use tokio::sync::mpsc;
+
+use crate::event::AppEvent;
+use crate::grpc::{self, Connection};
+use crate::grpc::node::shell_input::Kind;
+use crate::grpc::node::{ShellInput, ShellResize};
+use crate::panel::{KeyHint, KeyResult, Panel};
+use crate::theme::Theme;
+
+const HINTS: &[KeyHint] = &[
+    KeyHint::new("Enter", "Type into shell"),
+    KeyHint::new("PgUp/PgDn", "Scrollback"),
+];
+
+const ATTACHED_HINTS: &[KeyHint] = &[KeyHint::new("Ctrl+]", "Stop typing")];
+
+const SCROLLBACK: usize = 1000;
+/// Lines scrolled per mouse wheel notch.
+const WHEEL_STEP: usize = 3;
+
+/// A terminal on the node: a PTY shell streamed over `ShellService.Open`
+/// and rendered through a VT parser. Configured in the layout as `shell`, or
+/// `shell:<command>` to run something else.
+///
+/// While attached every key goes to the shell, Tab and Esc included, so
+/// Ctrl+] is the way back to the dashboard.
+pub struct ShellPanel {
+    command: String,
+    title: String,
+    parser: RefCell<vt100::Parser>,
+    /// Input half of the current session; replaced on every connect.
+    input: RefCell<Option<mpsc::Sender<ShellInput>>>,
+    attached: bool,
+    exit: Option<String>,
+}
+
+impl ShellPanel {
+    pub fn new(command: &str) -> Self {
+        let title = if command.is_empty() {
+            "Shell".to_string()
+        } else {
+            format!("Shell: {command}")
+        };
+        Self {
+            command: command.to_string(),
+            title,
+            parser: RefCell::new(vt100::Parser::new(24, 80, SCROLLBACK)),
+            input: RefCell::new(None),
+            attached: false,
+            exit: None,
+        }
+    }
+
+    fn send(&self, kind: Kind) {
+        if let Some(input) = self.input.borrow().as_ref() {
+            // A full queue means the node is not keeping up; drop the input
+            // rather than stall the UI.
+            let _ = input.try_send(ShellInput { kind: Some(kind) });
+        }
+    }
+
+    fn scroll(&self, up: bool, lines: usize) {
+        let mut parser = self.parser.borrow_mut();
+        let screen = parser.screen_mut();
+        let offset = if up {
+            screen.scrollback() + lines
+        } else {
+            screen.scrollback().saturating_sub(lines)
+        };
+        screen.set_scrollback(offset);
+    }
+}
+
+impl Panel for ShellPanel {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn key_hints(&self) -> &[KeyHint] {
+        if self.attached {
+            ATTACHED_HINTS
+        } else {
+            HINTS
+        }
+    }
+
+    fn min_height(&self) -> u16 {
+        8
+    }
+
+    fn render(&self, frame: &mut Frame, area: Rect, focused: bool, theme: &Theme) {
+        let [screen_area, status_area] = Layout::vertical([
+            Constraint::Min(1),    // Terminal
+            Constraint::Length(1), // Status line
+        ])
+        .areas(area);
+
+        let parser = self.parser.borrow();
+        let screen = parser.screen();
+        let buffer = frame.buffer_mut();
+        for row in 0..screen_area.height {
+            for col in 0..screen_area.width {
+                let Some(cell) = screen.cell(row, col) else {
+                    continue;
+                };
+                if cell.is_wide_continuation() {
+                    continue;
+                }
+                let symbol = if cell.has_contents() { cell.contents() } else { " " };
+                buffer[(screen_area.x + col, screen_area.y + row)]
+                    .set_symbol(symbol)
+                    .set_style(cell_style(cell, theme));
+            }
+        }
+
+        let scrolled = screen.scrollback();
+        if focused && self.attached && scrolled == 0 && !screen.hide_cursor() {
+            let (row, col) = screen.cursor_position();
+            frame.set_cursor_position(Position::new(
+                screen_area.x + col.min(screen_area.width.saturating_sub(1)),
+                screen_area.y + row.min(screen_area.height.saturating_sub(1)),
+            ));
+        }
+
+        let status = if let Some(exit) = &self.exit {
+            Line::styled(format!(" {exit}"), Style::default().fg(theme.red))
+        } else if scrolled > 0 {
+            Line::styled(
+                format!(" scrollback -{scrolled}"),
+                Style::default().fg(theme.yellow),
+            )
+        } else if self.attached {
+            Line::styled(" typing into shell", Style::default().fg(theme.green))
+        } else {
+            Line::styled(" detached", Style::default().fg(theme.lavender))
+        };
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+
+    fn captures_input(&self) -> bool {
+        self.attached
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        if !self.attached {
+            match key.code {
+                KeyCode::Enter | KeyCode::Char('i') => self.attached = true,
+                KeyCode::PageUp => self.scroll(true, self.parser.borrow().screen().size().0 as usize),
+                KeyCode::PageDown => self.scroll(false, self.parser.borrow().screen().size().0 as usize),
+                _ => return KeyResult::Ignored,
+            }
+            return KeyResult::Handled;
+        }
+
+        if key.code == KeyCode::Char(']') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.attached = false;
+            return KeyResult::Handled;
+        }
+        let application_cursor = self.parser.borrow().screen().application_cursor();
+        if let Some(bytes) = key_bytes(key, application_cursor) {
+            self.parser.borrow_mut().screen_mut().set_scrollback(0);
+            self.send(Kind::Data(bytes));
+        }
+        KeyResult::Handled
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, _area: Rect) -> KeyResult {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.scroll(true, WHEEL_STEP),
+            MouseEventKind::ScrollDown => self.scroll(false, WHEEL_STEP),
+            _ => return KeyResult::Ignored,
+        }
+        KeyResult::Handled
+    }
+
+    /// The PTY follows the panel, less its status line. A collapsed panel
+    /// keeps the last size rather than shrinking the shell to nothing.
+    fn resize(&mut self, area: Rect) {
+        let (rows, cols) = (area.height.saturating_sub(1), area.width);
+        let parser = self.parser.get_mut();
+        if rows == 0 || cols == 0 || parser.screen().size() == (rows, cols) {
+            return;
+        }
+        parser.screen_mut().set_size(rows, cols);
+        self.send(Kind::Resize(ShellResize {
+            rows: rows.into(),
+            cols: cols.into(),
+        }));
+    }
+
+    fn apply_event(&mut self, event: &AppEvent) {
+        match event {
+            AppEvent::ShellOutput { command, data } if *command == self.command => {
+                self.exit = None;
+                self.parser.get_mut().process(data);
+            }
+            AppEvent::ShellExited { command, code } if *command == self.command => {
+                self.exit = Some(format!("exited with code {code}; reconnect to start again"));
+                self.attached = false;
+            }
+            AppEvent::ShellFailed { command, error } if *command == self.command => {
+                self.exit = Some(error.clone());
+                self.attached = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn connect(&self, conn: &mut Connection) {
+        let (tx, rx) = mpsc::channel(64);
+        *self.input.borrow_mut() = Some(tx);
+        // Fresh screen for the fresh shell
+        let size = self.parser.borrow().screen().size();
+        *self.parser.borrow_mut() = vt100::Parser::new(size.0, size.1, SCROLLBACK);
+        grpc::spawn_shell(conn, self.command.clone(), size, rx);
+    }
+}
+
+fn cell_style(cell: &vt100::Cell, theme: &Theme) -> Style {
+    let color = |color, default| match color {
+        vt100::Color::Default => default,
+        vt100::Color::Idx(i) => Color::Indexed(i),
+        vt100::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    };
+    let (mut fg, mut bg) = (color(cell.fgcolor(), theme.fg), color(cell.bgcolor(), theme.bg));
+    if cell.inverse() {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    let mut style = Style::default().fg(fg).bg(bg);
+    if cell.bold() {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if cell.dim() {
+        style = style.add_modifier(Modifier::DIM);
+    }
+    if cell.italic() {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if cell.underline() {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    style
+}
+
+/// What an xterm sends for `key`.
+fn key_bytes(key: KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let cursor = |code: u8| {
+        if application_cursor {
+            vec![0x1b, b'O', code]
+        } else {
+            vec![0x1b, b'[', code]
+        }
+    };
+    let mut bytes = match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            match c.to_ascii_lowercase() {
+                c @ 'a'..='z' => vec![c as u8 - b'a' + 1],
+                ' ' | '@' | '2' => vec![0],
+                '[' | '3' => vec![0x1b],
+                '\\' | '4' => vec![0x1c],
+                ']' | '5' => vec![0x1d],
+                '^' | '6' => vec![0x1e],
+                '_' | '7' | '/' => vec![0x1f],
+                _ => return None,
+            }
+        }
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor(b'A'),
+        KeyCode::Down => cursor(b'B'),
+        KeyCode::Right => cursor(b'C'),
+        KeyCode::Left => cursor(b'D'),
+        KeyCode::Home => cursor(b'H'),
+        KeyCode::End => cursor(b'F'),
+        KeyCode::Insert => b"\x1b[2~".to_vec(),
+        KeyCode::Delete => b"\x1b[3~".to_vec(),
+        KeyCode::PageUp => b"\x1b[5~".to_vec(),
+        KeyCode::PageDown => b"\x1b[6~".to_vec(),
+        KeyCode::F(n @ 1..=4) => vec![0x1b, b'O', b'P' + n - 1],
+        KeyCode::F(n) => {
+            let code = match n {
+                5 => 15,
+                6..=10 => n + 11,
+                11..=12 => n + 12,
+                _ => return None,
+            };
+            format!("\x1b[{code}~").into_bytes()
+        }
+        _ => return None,
+    };
+    if key.modifiers.contains(KeyModifiers::ALT) {
+        bytes.insert(0, 0x1b);
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use super::*;
+
+    /// A panel connected to a channel that stands in for the node.
+    fn panel() -> (ShellPanel, mpsc::Receiver<ShellInput>) {
+        let panel = ShellPanel::new("");
+        let (tx, rx) = mpsc::channel(8);
+        *panel.input.borrow_mut() = Some(tx);
+        (panel, rx)
+    }
+
+    fn resizes(rx: &mut mpsc::Receiver<ShellInput>) -> Vec<(u32, u32)> {
+        let mut sizes = Vec::new();
+        while let Ok(input) = rx.try_recv() {
+            if let Some(Kind::Resize(size)) = input.kind {
+                sizes.push((size.rows, size.cols));
+            }
+        }
+        sizes
+    }
+
+    #[test]
+    fn resize_is_sent_once_per_size() {
+        let (mut panel, mut rx) = panel();
+        panel.resize(Rect::new(0, 0, 100, 31));
+        panel.resize(Rect::new(5, 5, 100, 31));
+        assert_eq!(resizes(&mut rx), [(30, 100)]);
+        assert_eq!(panel.parser.borrow().screen().size(), (30, 100));
+
+        panel.resize(Rect::new(0, 0, 60, 11));
+        assert_eq!(resizes(&mut rx), [(10, 60)]);
+    }
+
+    #[test]
+    fn collapsed_panel_keeps_its_size() {
+        let (mut panel, mut rx) = panel();
+        let collapsed = [
+            Rect::new(0, 0, 0, 20),
+            Rect::new(0, 0, 40, 1),
+            Rect::default(),
+        ];
+        for area in collapsed {
+            panel.resize(area);
+        }
+        assert_eq!(resizes(&mut rx), []);
+        assert_eq!(panel.parser.borrow().screen().size(), (24, 80));
+    }
+
+    #[test]
+    fn renders_into_an_empty_area() {
+        let (mut panel, _rx) = panel();
+        panel.attached = true;
+        let mut terminal = Terminal::new(TestBackend::new(10, 5)).unwrap();
+        let collapsed = [
+            Rect::new(0, 0, 0, 5),
+            Rect::new(0, 0, 10, 1),
+            Rect::default(),
+        ];
+        for area in collapsed {
+            terminal
+                .draw(|frame| panel.render(frame, area, true, &Theme::MOCHA))
+                .unwrap();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use color_eyre::eyre::{Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::event::{AppEvent, Tagged};
+
+/// One line of a recording: an event and when it arrived, in milliseconds
+/// since the recording started.
+#[derive(Serialize, Deserialize)]
+pub struct Entry<E> {
+    pub at_ms: u64,
+    pub event: E,
+}
+
+/// Writes received events to an NDJSON file, one flushed line per event so a
+/// crash loses nothing.
+pub struct Recorder {
+    out: LineWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to create recording {}", path.display()))?;
+        Ok(Self {
+            out: LineWriter::new(file),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: &AppEvent) -> Result<()> {
+        let entry = Entry {
+            at_ms: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+        serde_json::to_writer(&mut self.out, &entry)?;
+        self.out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Reads a whole recording up front so a malformed file fails before the
+/// terminal is taken over.
+pub fn load(path: &Path) -> Result<Vec<Entry<AppEvent>>> {
+    let file = File::open(path)
+        .wrap_err_with(|| format!("Failed to open recording {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .wrap_err_with(|| format!("{}:{}: invalid event", path.display(), index + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Feeds `entries` into the event channel with their recorded spacing divided
+/// by `speed`, in place of the gRPC streams.
+pub fn spawn_replay(entries: Vec<Entry<AppEvent>>, speed: f64, tx: mpsc::Sender<Tagged>) {
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now();
+        for entry in entries {
+            let offset = Duration::from_millis(entry.at_ms).div_f64(speed);
+            tokio::time::sleep_until(start + offset).await;
+            if tx.send(entry.event.into()).await.is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use color_eyre::eyre::{bail, eyre, Result};
+use ratatui::style::Color;
+use serde::Deserialize;
+
+/// The colours every panel draws with. Field names follow the Catppuccin
+/// roles they were first picked from.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub bg: Color,
+    pub fg: Color,
+    pub green: Color,
+    pub red: Color,
+    pub blue: Color,
+    pub yellow: Color,
+    pub lavender: Color,
+    pub surface0: Color,
+}
+
+impl Theme {
+    pub const MOCHA: Theme = Theme {
+        bg: Color::Rgb(30, 30, 46),
+        fg: Color::Rgb(205, 214, 244),
+        green: Color::Rgb(166, 227, 161),
+        red: Color::Rgb(243, 139, 168),
+        blue: Color::Rgb(137, 180, 250),
+        yellow: Color::Rgb(249, 226, 175),
+        lavender: Color::Rgb(180, 190, 254),
+        surface0: Color::Rgb(49, 50, 68),
+    };
+
+    pub const MACCHIATO: Theme = Theme {
+        bg: Color::Rgb(36, 39, 58),
+        fg: Color::Rgb(202, 211, 245),
+        green: Color::Rgb(166, 218, 149),
+        red: Color::Rgb(237, 135, 150),
+        blue: Color::Rgb(138, 173, 244),
+        yellow: Color::Rgb(238, 212, 159),
+        lavender: Color::Rgb(183, 189, 248),
+        surface0: Color::Rgb(54, 58, 79),
+    };
+
+    pub const FRAPPE: Theme = Theme {
+        bg: Color::Rgb(48, 52, 70),
+        fg: Color::Rgb(198, 208, 245),
+        green: Color::Rgb(166, 209, 137),
+        red: Color::Rgb(231, 130, 132),
+        blue: Color::Rgb(140, 170, 238),
+        yellow: Color::Rgb(229, 200, 144),
+        lavender: Color::Rgb(186, 187, 241),
+        surface0: Color::Rgb(65, 69, 89),
+    };
+
+    pub const LATTE: Theme = Theme {
+        bg: Color::Rgb(239, 241, 245),
+        fg: Color::Rgb(76, 79, 105),
+        green: Color::Rgb(64, 160, 43),
+        red: Color::Rgb(210, 15, 57),
+        blue: Color::Rgb(30, 102, 245),
+        yellow: Color::Rgb(223, 142, 29),
+        lavender: Color::Rgb(114, 135, 253),
+        surface0: Color::Rgb(204, 208, 218),
+    };
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "mocha" => Some(Self::MOCHA),
+            "macchiato" => Some(Self::MACCHIATO),
+            "frappe" => Some(Self::FRAPPE),
+            "latte" => Some(Self::LATTE),
+            _ => None,
+        }
+    }
+
+    /// Looks `name` up among the user's `[themes.*]` tables first, then the
+    /// built-in Catppuccin flavours.
+    pub fn resolve(name: &str, custom: &HashMap<String, ThemeConfig>) -> Result<Theme> {
+        if let Some(config) = custom.get(name) {
+            return config.build().map_err(|e| eyre!("Theme `{name}`: {e}"));
+        }
+        Self::builtin(name).ok_or_else(|| eyre!("Unknown theme `{name}`"))
+    }
+}
+
+/// A user-defined palette. Unset colours come from `base` (a built-in
+/// flavour, `mocha` by default). Colours accept anything ratatui parses:
+/// `"#1e1e2e"`, `"red"`, `"10"` (ANSI index).
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub base: Option<String>,
+    pub bg: Option<String>,
+    pub fg: Option<String>,
+    pub green: Option<String>,
+    pub red: Option<String>,
+    pub blue: Option<String>,
+    pub yellow: Option<String>,
+    pub lavender: Option<String>,
+    pub surface0: Option<String>,
+}
+
+impl ThemeConfig {
+    pub fn build(&self) -> Result<Theme> {
+        let base = self.base.as_deref().unwrap_or("mocha");
+        let Some(mut theme) = Theme::builtin(base) else {
+            bail!("unknown base theme `{base}`");
+        };
+
+        let overrides = [
+            (&self.bg, &mut theme.bg),
+            (&self.fg, &mut theme.fg),
+            (&self.green, &mut theme.green),
+            (&self.red, &mut theme.red),
+            (&self.blue, &mut theme.blue),
+            (&self.yellow, &mut theme.yellow),
+            (&self.lavender, &mut theme.lavender),
+            (&self.surface0, &mut theme.surface0),
+        ];
+        for (value, slot) in overrides {
+            if let Some(value) = value {
+                *slot = Color::from_str(value).map_err(|_| eyre!("invalid colour `{value}`"))?;
+            }
+        }
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn themes(toml: &str) -> HashMap<String, ThemeConfig> {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn user_themes_override_their_base() {
+        let custom = themes(
+            r##"
+            [dusk]
+            base = "latte"
+            bg = "#101018"
+            red = "magenta"
+            blue = "12"
+            "##,
+        );
+        let theme = Theme::resolve("dusk", &custom).unwrap();
+        assert_eq!(theme.bg, Color::Rgb(16, 16, 24));
+        assert_eq!(theme.red, Color::Magenta);
+        assert_eq!(theme.blue, Color::Indexed(12));
+        assert_eq!(theme.fg, Theme::LATTE.fg);
+        assert_eq!(theme.surface0, Theme::LATTE.surface0);
+    }
+
+    #[test]
+    fn user_themes_default_to_mocha_and_shadow_builtins() {
+        let custom = themes("[latte]\nfg = \"white\"\n");
+        let theme = Theme::resolve("latte", &custom).unwrap();
+        assert_eq!(theme.fg, Color::White);
+        assert_eq!(theme.bg, Theme::MOCHA.bg);
+        assert_eq!(
+            Theme::resolve("frappe", &custom).unwrap().bg,
+            Theme::FRAPPE.bg
+        );
+    }
+
+    #[test]
+    fn errors_name_the_theme_and_value() {
+        let custom = themes(
+            r##"
+            [bad-colour]
+            green = "#12345g"
+
+            [bad-base]
+            base = "espresso"
+            "##,
+        );
+        let error = |name| Theme::resolve(name, &custom).err().unwrap().to_string();
+        assert_eq!(
+            error("bad-colour"),
+            "Theme `bad-colour`: invalid colour `#12345g`"
+        );
+        assert_eq!(
+            error("bad-base"),
+            "Theme `bad-base`: unknown base theme `espresso`"
+        );
+        assert_eq!(error("missing"), "Unknown theme `missing`");
+    }
+
+    #[test]
+    fn unknown_theme_fields_are_refused() {
+        let result = toml::from_str::<HashMap<String, ThemeConfig>>("[dusk]\ngreen2 = \"red\"\n");
+        let message = result.err().unwrap().to_string();
+        assert!(message.contains("unknown field `green2`"), "{message}");
+    }
+}
@@ -1,37 +1,54 @@
 use ratatui::{
-    layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Layout, Position, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Gauge, Paragraph, Sparkline},
+    widgets::{Block, Clear, Paragraph},
     Frame,
 };
 
-use crate::app::{App, Panel};
+use crate::app::{App, View};
+use crate::keymap::GlobalAction;
+use crate::modal;
+use crate::panel::Panel;
+use crate::theme::Theme;
 
-// Catppuccin Mocha palette
-const BG: Color = Color::Rgb(30, 30, 46);
-const FG: Color = Color::Rgb(205, 214, 244);
-const GREEN: Color = Color::Rgb(166, 227, 161);
-const RED: Color = Color::Rgb(243, 139, 168);
-const BLUE: Color = Color::Rgb(137, 180, 250);
-const YELLOW: Color = Color::Rgb(249, 226, 175);
-const LAVENDER: Color = Color::Rgb(180, 190, 254);
-const SURFACE0: Color = Color::Rgb(49, 50, 68);
+/// Global actions listed in the help line after the focused panel's hints.
+const GLOBAL_HINTS: &[(GlobalAction, &str)] = &[
+    (GlobalAction::NextPanel, "Switch panel"),
+    (GlobalAction::Reconnect, "Reconnect"),
+    (GlobalAction::Fleet, "Fleet"),
+    (GlobalAction::Help, "Help"),
+    (GlobalAction::Quit, "Quit"),
+];
+
+const FLEET_GLOBAL_HINTS: &[(GlobalAction, &str)] =
+    &[(GlobalAction::Help, "Help"), (GlobalAction::Quit, "Quit")];
 
 pub fn draw(frame: &mut Frame, app: &App) {
     let size = frame.area();
+    let theme = &app.theme;
 
-    let status = if app.connected {
-        Span::styled("[Connected]", Style::default().fg(GREEN))
-    } else {
-        Span::styled("[Disconnected]", Style::default().fg(RED))
+    let status = match app.view {
+        View::Fleet => Span::styled(
+            format!("Fleet [{}/{} online]", app.fleet.online(), app.fleet.len()),
+            Style::default().fg(theme.fg),
+        ),
+        View::Detail => {
+            let (label, color) = if app.connected {
+                ("Connected", theme.green)
+            } else {
+                ("Disconnected", theme.red)
+            };
+            let name = &app.fleet.node(app.active).name;
+            Span::styled(format!("{name} [{label}]"), Style::default().fg(color))
+        }
     };
 
     let title_line = Line::from(vec![
         Span::styled(
             " node-tui ",
             Style::default()
-                .fg(LAVENDER)
+                .fg(theme.lavender)
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw("─── "),
@@ -39,124 +56,375 @@ pub fn draw(frame: &mut Frame, app: &App) {
         Span::raw(" "),
     ]);
 
-    let help_line = Line::from(vec![
-        Span::styled(" [c]", Style::default().fg(YELLOW)),
-        Span::raw(" Reconnect  "),
-        Span::styled("[Tab]", Style::default().fg(YELLOW)),
-        Span::raw(" Switch panel  "),
-        Span::styled("[Enter]", Style::default().fg(YELLOW)),
-        Span::raw(" Send greeting  "),
-        Span::styled("[q]", Style::default().fg(YELLOW)),
-        Span::raw(" Quit "),
-    ]);
+    let (hints, global_hints) = match app.view {
+        View::Fleet => (app.fleet.key_hints(), FLEET_GLOBAL_HINTS),
+        View::Detail => {
+            let focused = &app.panels[app.focused];
+            let globals = if focused.captures_input() { &[][..] } else { GLOBAL_HINTS };
+            (focused.key_hints(), globals)
+        }
+    };
+    let hints = hints
+        .iter()
+        .map(|hint| (hint.key.to_string(), hint.description));
+    // Only offered while there is something to show
+    let error_hint = app
+        .error_details()
+        .map(|_| (GlobalAction::ErrorDetails, "Error details"));
+    let global_hints = error_hint
+        .iter()
+        .chain(global_hints)
+        .map(|&(action, description)| (app.keymap.label(action), description));
+    let help_line = help_line(theme, hints.chain(global_hints));
 
     let outer_block = Block::bordered()
         .title_top(title_line)
         .title_bottom(help_line)
-        .border_style(Style::default().fg(LAVENDER))
-        .style(Style::default().bg(BG).fg(FG));
+        .border_style(Style::default().fg(theme.lavender))
+        .style(Style::default().bg(theme.bg).fg(theme.fg));
 
     let inner = outer_block.inner(size);
     frame.render_widget(outer_block, size);
 
-    let [cpu_area, greeter_area] = Layout::vertical([
-        Constraint::Min(6),    // CPU panel
-        Constraint::Length(5), // Greeter panel
-    ])
-    .areas(inner);
+    match app.view {
+        View::Fleet => app.fleet.render(frame, inner, theme),
+        View::Detail => draw_panels(frame, app, inner),
+    }
 
-    draw_cpu_panel(frame, app, cpu_area);
-    draw_greeter_panel(frame, app, greeter_area);
+    if let Some(modal) = &app.modal {
+        modal::draw(frame, app, modal, size);
+    }
 }
 
-fn draw_cpu_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let is_active = app.active_panel == Panel::Cpu;
-    let border_color = if is_active { BLUE } else { SURFACE0 };
+fn draw_panels(frame: &mut Frame, app: &App, inner: Rect) {
+    let theme = &app.theme;
+    let mut start = 0;
+    let mut row_constraints = Vec::with_capacity(app.rows.len());
+    for row in &app.rows {
+        let panels = &app.panels[start..start + row.len];
+        start += row.len;
+        row_constraints.push(match row.height {
+            Some(height) => Constraint::Length(height),
+            None => Constraint::Min(panels.iter().map(|p| p.min_height()).max().unwrap_or(3)),
+        });
+    }
+    let row_areas = Layout::vertical(row_constraints).split(inner);
+
+    let mut areas = app.panel_areas.borrow_mut();
+    areas.clear();
+    for (row, row_area) in app.rows.iter().zip(row_areas.iter()) {
+        let cell_areas = Layout::horizontal(vec![Constraint::Fill(1); row.len]).split(*row_area);
+        areas.extend(cell_areas.iter());
+    }
+    for (index, area) in areas.iter().enumerate() {
+        let focused = index == app.focused;
+        draw_panel(frame, theme, app.panels[index].as_ref(), *area, focused);
+    }
+}
+
+fn draw_panel(frame: &mut Frame, theme: &Theme, panel: &dyn Panel, area: Rect, focused: bool) {
+    let border_color = if focused { theme.blue } else { theme.surface0 };
 
     let block = Block::bordered()
-        .title(" CPU Monitor ")
+        .title(format!(" {} ", panel.title()))
         .border_style(Style::default().fg(border_color))
-        .style(Style::default().bg(BG).fg(FG));
+        .style(Style::default().bg(theme.bg).fg(theme.fg));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
+    panel.render(frame, inner, focused, theme);
+}
 
-    let [stats_area, spark_area, gauge_area] = Layout::vertical([
-        Constraint::Length(1), // Stats line
-        Constraint::Min(1),   // Sparkline
-        Constraint::Length(1), // Gauge
-    ])
-    .areas(inner);
-
-    // Stats line
-    let error_hint = match &app.connection_error {
-        Some(e) => format!("  ({e})"),
-        None => String::new(),
-    };
-    let stats = Paragraph::new(format!(
-        " CPUs: {}  |  Usage: {:.1}%{}",
-        app.cpu_count, app.cpu_usage, error_hint
-    ))
-    .style(Style::default().bg(BG).fg(FG));
-    frame.render_widget(stats, stats_area);
-
-    // Sparkline
-    let data: Vec<u64> = app.cpu_history.iter().copied().collect();
-    let sparkline = Sparkline::default()
-        .data(&data)
-        .max(100)
-        .style(Style::default().fg(GREEN).bg(BG));
-    frame.render_widget(sparkline, spark_area);
-
-    // Gauge
-    let ratio = (app.cpu_usage as f64 / 100.0).clamp(0.0, 1.0);
-    let gauge = Gauge::default()
-        .ratio(ratio)
-        .gauge_style(Style::default().fg(GREEN).bg(SURFACE0))
-        .label(format!("{:.1}%", app.cpu_usage));
-    frame.render_widget(gauge, gauge_area);
+fn help_line<'a>(theme: &Theme, hints: impl Iterator<Item = (String, &'a str)>) -> Line<'a> {
+    let mut spans = vec![Span::raw(" ")];
+    for (key, description) in hints {
+        spans.push(Span::styled(format!("[{key}]"), Style::default().fg(theme.yellow)));
+        spans.push(Span::raw(format!(" {description}  ")));
+    }
+    Line::from(spans)
 }
 
-fn draw_greeter_panel(frame: &mut Frame, app: &App, area: Rect) {
-    let is_active = app.active_panel == Panel::Greeter;
-    let border_color = if is_active { BLUE } else { SURFACE0 };
+/// A small box of `lines` beside the pointer at `anchor`, kept inside
+/// `bounds`.
+pub fn draw_tooltip(frame: &mut Frame, theme: &Theme, lines: Vec<Line>, anchor: Position, bounds: Rect) {
+    let width = (lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 2).min(bounds.width);
+    let height = (lines.len() as u16 + 2).min(bounds.height);
+    // Below and right of the pointer, flipped when that would leave `bounds`
+    let x = if anchor.x + 2 + width <= bounds.right() {
+        anchor.x + 2
+    } else {
+        anchor.x.saturating_sub(width + 1).max(bounds.x)
+    };
+    let y = if anchor.y + 1 + height <= bounds.bottom() {
+        anchor.y + 1
+    } else {
+        anchor.y.saturating_sub(height).max(bounds.y)
+    };
+    let area = Rect::new(x, y, width, height);
 
     let block = Block::bordered()
-        .title(" Greeter ")
-        .border_style(Style::default().fg(border_color))
-        .style(Style::default().bg(BG).fg(FG));
+        .border_style(Style::default().fg(theme.lavender))
+        .style(Style::default().bg(theme.surface0).fg(theme.fg));
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    let inner = block.inner(area);
-    frame.render_widget(block, area);
+pub fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{value:.0}{}", UNITS[unit])
+    } else {
+        format!("{value:.1}{}", UNITS[unit])
+    }
+}
 
-    let [input_area, response_area] = Layout::vertical([
-        Constraint::Length(1), // Input
-        Constraint::Length(1), // Response
-    ])
-    .areas(inner);
-
-    // Input line
-    let cursor = if is_active { "_" } else { "" };
-    let input = Paragraph::new(Line::from(vec![
-        Span::styled(" Name: ", Style::default().fg(LAVENDER)),
-        Span::raw(&app.greeter_input),
-        Span::styled(
-            cursor,
-            Style::default()
-                .fg(YELLOW)
-                .add_modifier(Modifier::SLOW_BLINK),
-        ),
-    ]));
-    frame.render_widget(input, input_area);
+#[cfg(test)]
+mod tests {
+    use ratatui::{Terminal, backend::TestBackend};
+    use tokio::sync::mpsc;
 
-    // Response line
-    let response_text = match &app.greeter_response {
-        Some(r) => r.as_str(),
-        None => "(none yet)",
-    };
-    let response = Paragraph::new(Line::from(vec![
-        Span::styled(" Response: ", Style::default().fg(LAVENDER)),
-        Span::raw(response_text),
-    ]));
-    frame.render_widget(response, response_area);
+    use super::*;
+    use crate::config::{Config, LayoutConfig, NodeConfig, RowConfig};
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+    use crate::app::Action;
+    use crate::event::AppEvent;
+    use crate::grpc::node::{ProcessInfo, Signal, SummaryReply};
+    use crate::modal::Modal;
+
+    fn node(name: &str) -> NodeConfig {
+        NodeConfig {
+            name: name.to_string(),
+            address: format!("http://{name}:50051"),
+            tags: Vec::new(),
+        }
+    }
+
+    fn app(nodes: Vec<NodeConfig>, rows: &[&[&str]]) -> App {
+        let config = Config {
+            nodes,
+            layout: LayoutConfig {
+                rows: rows
+                    .iter()
+                    .map(|panels| RowConfig {
+                        panels: panels.iter().map(|p| p.to_string()).collect(),
+                        height: None,
+                    })
+                    .collect(),
+            },
+            ..Config::default()
+        };
+        App::new(&config).unwrap()
+    }
+
+    /// The screen as text, one string per row.
+    fn render(app: &App, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    fn fleet() -> App {
+        let mut app = app(vec![node("alpha"), node("beta")], &[&["greeter"]]);
+        app.apply_event(AppEvent::NodeSummary {
+            node: 0,
+            summary: SummaryReply {
+                hostname: "alpha".to_string(),
+                cpu_usage: 12.5,
+                used_memory: 2 << 30,
+                total_memory: 8 << 30,
+                load_average: 0.5,
+                uptime_secs: 90_000,
+                max_disk_usage: 40.0,
+            },
+        });
+        app.apply_event(AppEvent::NodeOffline {
+            node: 1,
+            error: "connection refused".to_string(),
+        });
+        app
+    }
+
+    fn detail() -> App {
+        let process = |pid, name: &str, cpu_usage, memory_bytes, run_time_secs| ProcessInfo {
+            pid,
+            name: name.to_string(),
+            user: "root".to_string(),
+            cpu_usage,
+            memory_bytes,
+            run_time_secs,
+            ..Default::default()
+        };
+        let mut app = app(vec![node("alpha")], &[&["processes"]]);
+        app.apply_event(AppEvent::ProcessesUpdate {
+            processes: vec![
+                process(1, "init", 0.5, 4 << 20, 3700),
+                ProcessInfo {
+                    parent_pid: Some(1),
+                    ..process(42, "sshd", 2.0, 8 << 20, 60)
+                },
+            ],
+            total_memory: 1 << 30,
+        });
+        app
+    }
+
+    #[test]
+    fn fleet_view() {
+        let expected = [
+            "┌ node-tui ─── Fleet [1/2 online] ─────────────────────────────────────┐",
+            "│ Sort: name  Group: none  |  1/2 online                               │",
+            "│┌ alpha ──────────────── ● online ┐┌ beta ──────────────── ● offline ┐│",
+            "││ alpha  up 1d1h  load 0.50       ││ connection refused              ││",
+            "││ CPU  12.5% ─────────────────────││ CPU   0.0% ─────────────────────││",
+            "││ MEM  25.0% 2.0G/8.0G ───────────││ MEM   0.0%  ────────────────────││",
+            "││▁                                ││                                 ││",
+            "│└─────────────────────────────────┘└──────────────────────── OFFLINE ┘│",
+            "└ [Enter] Open node  [←↑↓→] Move  [s] Sort  [g] Group  [?] Help  [q/Esc┘",
+        ];
+        assert_eq!(render(&fleet(), 72, 9), expected);
+    }
+
+    #[test]
+    fn detail_view() {
+        let expected = [
+            "┌ node-tui ─── alpha [Connected] ──────────────────────────────┐",
+            "│┌ Processes ─────────────────────────────────────────────────┐│",
+            "││PID     USER      CPU%▼  MEM      MEM%  STATE    TIME      C││",
+            "││42      root      2.0    8.0M     0.8            00:01:00  s││",
+            "││1       root      0.5    4.0M     0.4            01:01:40  i││",
+            "││                                                            ││",
+            "││ 2 processes                                                ││",
+            "│└────────────────────────────────────────────────────────────┘│",
+            "└ [↑/↓] Select  [s] Sort  [r] Reverse  [t] Tree  [/] Filter  [k┘",
+        ];
+        assert_eq!(render(&detail(), 64, 9), expected);
+    }
+
+    #[test]
+    fn help_modal() {
+        let mut app = detail();
+        app.modal = Some(Modal::Help { scroll: 0 });
+        let expected = [
+            "┌ Help ────────────────────────────────────────────────────────┐",
+            "│Global                                                        │",
+            "│  q/Esc           Quit                                        │",
+            "│  Tab             Focus next panel                            │",
+            "│  S-Tab           Focus previous panel                        │",
+            "│  c               Reconnect to the node                       │",
+            "│  f               Back to the fleet view                      │",
+            "│  y/Enter         Confirm a popup                             │",
+            "└ [↑/↓] Scroll  [n/Esc] Close ─────────────────────────────────┘",
+        ];
+        assert_eq!(render(&app, 64, 9), expected);
+    }
+
+    #[test]
+    fn error_modal() {
+        let mut app = detail();
+        app.apply_event(AppEvent::Disconnected(
+            "transport error: connection refused by alpha while streaming processes".to_string(),
+        ));
+        let (title, message) = app.error_details().unwrap();
+        app.modal = Some(Modal::Error { title, message });
+        let expected = [
+            "┌ node-tui ─── alpha [Disconnected] ───────────────────────────┐",
+            "│┌ Processes ─────────────────────────────────────────────────┐│",
+            "┌ Error on alpha ──────────────────────────────────────────────┐",
+            "│transport error: connection refused by alpha while streaming  │",
+            "│processes                                                     │",
+            "│                                                              │",
+            "│                         [n/Esc] Close                        │",
+            "└──────────────────────────────────────────────────────────────┘",
+            "└ [↑/↓] Select  [s] Sort  [r] Reverse  [t] Tree  [/] Filter  [k┘",
+        ];
+        assert_eq!(render(&app, 64, 9), expected);
+    }
+
+    #[tokio::test]
+    async fn events_from_a_replaced_connection_are_dropped() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut app = detail();
+        let stale = app.events(&tx);
+        assert!(app.open_node(0));
+        let current = app.events(&tx);
+
+        stale
+            .send(AppEvent::Disconnected("old".to_string()))
+            .await
+            .unwrap();
+        app.apply_event(rx.recv().await.unwrap());
+        assert_eq!(app.error_details(), None);
+
+        current
+            .send(AppEvent::Disconnected("new".to_string()))
+            .await
+            .unwrap();
+        app.apply_event(rx.recv().await.unwrap());
+        assert_eq!(
+            app.error_details().map(|(_, message)| message).as_deref(),
+            Some("new")
+        );
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Action> {
+        app.handle_key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn confirming_a_signal_sends_it() {
+        let mut app = detail();
+        assert!(press(&mut app, KeyCode::Char('k')).is_none());
+        assert!(matches!(app.modal, Some(Modal::Confirm { .. })));
+        assert!(matches!(
+            press(&mut app, KeyCode::Char('y')),
+            Some(Action::SendSignal {
+                pid: 42,
+                signal: Signal::Term
+            })
+        ));
+        assert!(app.modal.is_none());
+    }
+
+    #[test]
+    fn declining_a_signal_sends_nothing() {
+        for answer in [KeyCode::Char('n'), KeyCode::Esc] {
+            let mut app = detail();
+            press(&mut app, KeyCode::Char('K'));
+            assert!(matches!(app.modal, Some(Modal::Confirm { .. })));
+            assert!(press(&mut app, answer).is_none());
+            assert!(app.modal.is_none());
+        }
+    }
+
+    #[test]
+    fn tree_toggle() {
+        let mut app = detail();
+        let rows = |app: &App| render(app, 80, 9)[3..5].to_vec();
+        assert!(press(&mut app, KeyCode::Char('t')).is_none());
+        assert_eq!(
+            rows(&app),
+            [
+                "││1       root      0.5    4.0M     0.4            01:01:40  init             ││",
+                "││42      root      2.0    8.0M     0.8            00:01:00  └ sshd           ││",
+            ]
+        );
+        press(&mut app, KeyCode::Char('t'));
+        assert_eq!(
+            rows(&app),
+            [
+                "││42      root      2.0    8.0M     0.8            00:01:00  sshd             ││",
+                "││1       root      0.5    4.0M     0.4            01:01:40  init             ││",
+            ]
+        );
+    }
 }
@@ -0,0 +1,87 @@
+//! Wake-on-LAN magic packets, shared by node-rpc's `PowerService.Wake` and
+//! the Control Center so the two can't drift apart.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+/// The discard port, which is what most WoL senders use.
+const DEFAULT_PORT: u16 = 9;
+
+/// Six 0xFF bytes followed by the MAC address sixteen times.
+pub fn magic_packet(mac: [u8; 6]) -> [u8; 102] {
+    let mut packet = [0xFF; 102];
+    for copy in packet[6..].chunks_exact_mut(6) {
+        copy.copy_from_slice(&mac);
+    }
+    packet
+}
+
+/// Accepts `aa:bb:cc:dd:ee:ff`, `aa-bb-cc-dd-ee-ff` or `aabbccddeeff`.
+pub fn parse_mac(text: &str) -> Result<[u8; 6], String> {
+    let digits: String = text.chars().filter(|c| !matches!(c, ':' | '-')).collect();
+    if digits.len() != 12 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("`{text}` is not a MAC address"));
+    }
+    let mut mac = [0; 6];
+    for (i, octet) in mac.iter_mut().enumerate() {
+        *octet = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Ok(mac)
+}
+
+/// `address` or `address:port`; empty is the limited broadcast address.
+pub fn parse_target(text: &str) -> Result<SocketAddr, String> {
+    if text.is_empty() {
+        return Ok(SocketAddrV4::new(Ipv4Addr::BROADCAST, DEFAULT_PORT).into());
+    }
+    if let Ok(address) = text.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    text.parse::<Ipv4Addr>()
+        .map(|ip| SocketAddrV4::new(ip, DEFAULT_PORT).into())
+        .map_err(|_| format!("`{text}` is not an address or address:port"))
+}
+
+pub fn send(mac: [u8; 6], target: SocketAddr) -> io::Result<()> {
+    let socket = if target.is_ipv4() {
+        UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?
+    } else {
+        UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?
+    };
+    socket.set_broadcast(true)?;
+    socket.send_to(&magic_packet(mac), target)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn magic_packet_is_sync_stream_then_sixteen_macs() {
+        let mac = [0x00, 0x11, 0x22, 0xAA, 0xBB, 0xFF];
+        let mut expected = vec![0xFF; 6];
+        for _ in 0..16 {
+            expected.extend_from_slice(&mac);
+        }
+        assert_eq!(magic_packet(mac).as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn mac_formats() {
+        let mac = [0xAA, 0xBB, 0xCC, 0x01, 0x02, 0x03];
+        assert_eq!(parse_mac("aa:bb:cc:01:02:03"), Ok(mac));
+        assert_eq!(parse_mac("AA-BB-CC-01-02-03"), Ok(mac));
+        assert_eq!(parse_mac("aabbcc010203"), Ok(mac));
+        assert!(parse_mac("aa:bb:cc:01:02").is_err());
+        assert!(parse_mac("gg:bb:cc:01:02:03").is_err());
+    }
+
+    #[test]
+    fn targets() {
+        assert_eq!(parse_target("").unwrap().to_string(), "255.255.255.255:9");
+        assert_eq!(parse_target("192.168.1.255").unwrap().to_string(), "192.168.1.255:9");
+        assert_eq!(parse_target("10.0.0.255:7").unwrap().to_string(), "10.0.0.255:7");
+        assert!(parse_target("lan").is_err());
+    }
+}